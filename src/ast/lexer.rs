@@ -30,6 +30,8 @@ pub enum Token {
     Identifier(String),
     NewLine,
     Assign,
+    //x += 1, x <<= 2 and so on, with the operator applied before assigning
+    CompoundAssign(Operator),
     True,
     False,
     None,
@@ -46,6 +48,12 @@ pub enum Token {
     ElifKeyword,
     ElseKeyword,
     DefKeyword,
+    PubKeyword,
+    ImportKeyword,
+    TypeKeyword,
+    ExternKeyword,
+    ConstKeyword,
+    ImplKeyword,
     OpenParen,
     CloseParen,
     OpenArrayBracket,
@@ -88,6 +96,12 @@ impl PartialToken {
                 "else" => Token::ElseKeyword,
                 "for" => Token::ForKeyword,
                 "def" => Token::DefKeyword,
+                "pub" => Token::PubKeyword,
+                "import" => Token::ImportKeyword,
+                "type" => Token::TypeKeyword,
+                "extern" => Token::ExternKeyword,
+                "const" => Token::ConstKeyword,
+                "impl" => Token::ImplKeyword,
                 "raise" => Token::RaiseKeyword,
                 "return" => Token::ReturnKeyword,
                 "in" => Token::InKeyword,
@@ -128,6 +142,14 @@ impl PartialToken {
                 "==" => Token::Operator(Operator::Equals),
                 "->" => Token::ArrowRight,
                 "=" => Token::Assign,
+                "+=" => Token::CompoundAssign(Operator::Plus),
+                "-=" => Token::CompoundAssign(Operator::Minus),
+                "*=" => Token::CompoundAssign(Operator::Multiply),
+                "/=" => Token::CompoundAssign(Operator::Divide),
+                "%=" => Token::CompoundAssign(Operator::Mod),
+                "^=" => Token::CompoundAssign(Operator::Xor),
+                "<<=" => Token::CompoundAssign(Operator::BitShiftLeft),
+                ">>=" => Token::CompoundAssign(Operator::BitShiftRight),
                 "!=" => Token::Operator(Operator::NotEquals),
                 "(" => Token::OpenParen,
                 ")" => Token::CloseParen,
@@ -286,6 +308,9 @@ impl Tokenizer {
     fn match_partial(&mut self, query: &str) -> (bool, usize) {
         let mut matched_chars = 0;
         let chars: Vec<char> = query.chars().collect();
+        if self.index + chars.len() > self.chars.len() {
+            return (false, 0);
+        }
         for i in 0..query.len() {
             if self.cur_offset(i as isize) != chars[i] {
                 return (false, 0);
@@ -308,6 +333,7 @@ impl Tokenizer {
 
     pub fn tokenize(mut self) -> Result<Vec<Token>, String> {
         let operators = &[
+            "<<=", ">>=", "+=", "-=", "*=", "/=", "%=", "^=",
             "+",  "->", "-", "*", "%", "/", "<<", ">>", "<=", ">=", ">", "<", "!=", "==", "=", "^", "(",
             ")",
         ];
@@ -471,6 +497,29 @@ mod tests {
         };
     }
 
+    #[test]
+    fn tokenizer_compound_assignments() -> Result<(), String> {
+        let result = tokenize("x += 1 -= *= /= %= ^= <<= >>= <= >=")?;
+        assert_eq!(
+            result,
+            [
+                Token::Identifier("x".into()),
+                Token::CompoundAssign(Operator::Plus),
+                Token::LiteralInteger(1),
+                Token::CompoundAssign(Operator::Minus),
+                Token::CompoundAssign(Operator::Multiply),
+                Token::CompoundAssign(Operator::Divide),
+                Token::CompoundAssign(Operator::Mod),
+                Token::CompoundAssign(Operator::Xor),
+                Token::CompoundAssign(Operator::BitShiftLeft),
+                Token::CompoundAssign(Operator::BitShiftRight),
+                Token::Operator(Operator::LessEquals),
+                Token::Operator(Operator::GreaterEquals),
+            ]
+        );
+        Ok(())
+    }
+
     #[test]
     fn tokenizer_many_operators() -> Result<(), String> {
         let result = tokenize("10 + - / * << >> != == -12")?;
@@ -792,6 +841,74 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn pub_def_function() -> Result<(), String> {
+        let result = tokenize("pub def function():")?;
+        assert_eq!(
+            result,
+            [
+                Token::PubKeyword,
+                Token::DefKeyword,
+                Token::Identifier("function".into()),
+                Token::OpenParen,
+                Token::CloseParen,
+                Token::Colon,
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn type_alias() -> Result<(), String> {
+        let result = tokenize("type Ints = array<i32>\n")?;
+        assert_eq!(
+            result,
+            [
+                Token::TypeKeyword,
+                Token::Identifier("Ints".into()),
+                Token::Assign,
+                Token::Identifier("array".into()),
+                Token::Operator(Operator::Less),
+                Token::Identifier("i32".into()),
+                Token::Operator(Operator::Greater),
+                Token::NewLine,
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn extern_keyword() -> Result<(), String> {
+        let result = tokenize("extern def log(x: str)")?;
+        assert_eq!(
+            result,
+            [
+                Token::ExternKeyword,
+                Token::DefKeyword,
+                Token::Identifier("log".into()),
+                Token::OpenParen,
+                Token::Identifier("x".into()),
+                Token::Colon,
+                Token::Identifier("str".into()),
+                Token::CloseParen,
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn import_keyword() -> Result<(), String> {
+        let result = tokenize("import other")?;
+        assert_eq!(
+            result,
+            [
+                Token::ImportKeyword,
+                Token::Identifier("other".into()),
+            ]
+        );
+        Ok(())
+    }
+
     #[test]
     fn return_keyword() -> Result<(), String> {
         let result = tokenize("return")?;
@@ -858,4 +975,15 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn cannot_use_dollar_inside_name() -> Result<(), String> {
+        let result = tokenize("x$0 = 1");
+
+        assert_eq!(
+            result.unwrap_err(),
+            "Unrecognized token $"
+        );
+        Ok(())
+    }
+
 }
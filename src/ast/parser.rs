@@ -11,11 +11,23 @@ pub enum Expr {
     Variable(String),
     FunctionCall(Box<Expr>, Vec<Expr>),
     IndexAccess(Box<Expr>, Box<Expr>),
+    //a[start:end]
+    SliceAccess(Box<Expr>, Box<Expr>, Box<Expr>),
     BinaryOperation(Box<Expr>, Operator, Box<Expr>),
     Parenthesized(Box<Expr>),
     UnaryExpression(Operator, Box<Expr>),
     MemberAccess(Box<Expr>, String),
     Array(Vec<Expr>), 
+    //sizeof<T>(), resolved at compile time
+    SizeOf(ASTType),
+    //cast<T>(expr), converts a number to another numeric type
+    Cast(ASTType, Box<Expr>),
+    //reinterpret<T>(expr), same bytes viewed as another type of the same size
+    Reinterpret(ASTType, Box<Expr>),
+    //only produced by return a, b for now
+    Tuple(Vec<Expr>),
+    //a < b < c is stored as operands [a, b, c] and operators [<, <], lowered to (a < b) and (b < c)
+    ComparisonChain(Vec<Expr>, Vec<Operator>),
     //maybe there could be a syntax to specify the type of the array
     //ex: instead of just x = [1,2,3] it could be x = [1, 2, 3] array<i32>
     //or like sum = array<i32>[].sum() would return 0
@@ -31,7 +43,17 @@ pub struct ASTIfStatement {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ASTType {
     Simple(String),
-    Generic(String, Vec<ASTType>)
+    Generic(String, Vec<ASTType>),
+    //a constant generic argument, like the length in array<i32, 4>
+    Const(u32),
+    //fn(i32, str) -> bool
+    Function(Vec<ASTType>, Box<ASTType>)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visibility {
+    Public,
+    Private
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -49,6 +71,11 @@ pub enum AST {
         path: Vec<String>,
         expression: Expr,
     },
+    CompoundAssign {
+        path: Vec<String>,
+        operator: Operator,
+        expression: Expr,
+    },
     Declare {
         var: TypeBoundName,
         expression: Expr,
@@ -69,17 +96,44 @@ pub enum AST {
     },
     StructDeclaration {
         struct_name: String,
+        type_parameters: Vec<String>,
         body: Vec<TypeBoundName>,
+        visibility: Visibility
     },
     DeclareFunction {
         function_name: String,
+        //def first<T>(xs: array<T>) -> T, empty for functions that are not generic
+        type_parameters: Vec<String>,
         parameters: Vec<TypeBoundName>,
         body: Vec<AST>,
+        return_type: Option<ASTType>,
+        visibility: Visibility
+    },
+    //function implemented outside of the program (builtin or host function), only the signature is known
+    DeclareExternFunction {
+        function_name: String,
+        parameters: Vec<TypeBoundName>,
         return_type: Option<ASTType>
     },
     Break,
     Return(Option<Expr>),
     Raise(Expr),
+    Import(String),
+    TypeAlias {
+        alias_name: String,
+        target: ASTType
+    },
+    //the initializer must be foldable at compile time
+    DeclareConst {
+        var: TypeBoundName,
+        expression: Expr,
+    },
+    //impl<T> box<T>: followed by the methods, which take the receiver as self
+    ImplBlock {
+        type_parameters: Vec<String>,
+        target: ASTType,
+        methods: Vec<AST>,
+    },
     Root(Vec<AST>)
 }
 
@@ -112,6 +166,18 @@ fn precedence(o: Operator) -> u32 {
     }
 }
 
+fn is_comparison(o: Operator) -> bool {
+    matches!(
+        o,
+        Operator::Less
+            | Operator::LessEquals
+            | Operator::Greater
+            | Operator::GreaterEquals
+            | Operator::Equals
+            | Operator::NotEquals
+    )
+}
+
 fn clean_parens(expr: Expr) -> Expr {
     match expr {
         Expr::Parenthesized(e) => clean_parens(*e),
@@ -121,6 +187,9 @@ fn clean_parens(expr: Expr) -> Expr {
             let right_clean = Box::new(clean_parens(*right));
             Expr::BinaryOperation(left_clean, op, right_clean)
         }
+        Expr::ComparisonChain(operands, ops) => {
+            Expr::ComparisonChain(operands.into_iter().map(clean_parens).collect(), ops)
+        }
         _ => expr,
     }
 }
@@ -128,6 +197,8 @@ fn clean_parens(expr: Expr) -> Expr {
 pub struct Parser {
     parsing_state: Vec<ParsingState>,
     tokens: Vec<Token>,
+    //errors found so far, the parser skips to the next line and keeps going
+    errors: Vec<ParsingError>,
 }
 
 struct ParsingState {
@@ -141,7 +212,26 @@ struct ParsingState {
 pub enum ParsingError {
     ExprError(String),
     TypeBoundMissingTypeSpecifier,
-    TypeBoundExpectedColonAfterFieldName
+    TypeBoundExpectedColonAfterFieldName,
+    //if, else, while and for need at least one indented statement
+    EmptyBlock(String),
+    //malformed type annotations, like array<i32, 99999999999>
+    InvalidType(String),
+    //malformed statements, like an if without a colon after the condition
+    SyntaxError(String)
+}
+
+impl std::fmt::Display for ParsingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParsingError::ExprError(message)
+            | ParsingError::EmptyBlock(message)
+            | ParsingError::InvalidType(message)
+            | ParsingError::SyntaxError(message) => write!(f, "{message}"),
+            ParsingError::TypeBoundMissingTypeSpecifier => write!(f, "Expected type name after colon"),
+            ParsingError::TypeBoundExpectedColonAfterFieldName => write!(f, "Expected colon after name"),
+        }
+    }
 }
 
 impl Parser {
@@ -154,6 +244,7 @@ impl Parser {
                 current_indent: 0,
             }],
             tokens: tokens,
+            errors: vec![],
         }
     }
 
@@ -171,6 +262,61 @@ impl Parser {
         self.parsing_state.pop().unwrap()
     }
 
+    //Discards the current parsing stack, records the error and skips the failed statement,
+    //so that parsing can continue on the next one.
+    fn recover_from_error(&mut self, error: ParsingError) {
+        self.pop_stack();
+        self.errors.push(error);
+        self.skip_statement();
+    }
+
+    //Skips the rest of the statement, including any block nested in it: stops at the newline
+    //before the next line that is not indented deeper than the statement.
+    fn skip_statement(&mut self) {
+        let indent = self.get_expected_indent();
+        while self.is_not_end() {
+            if self.cur_is_newline() && self.next_line_indentation().map_or(true, |next| next <= indent) {
+                return;
+            }
+            self.next();
+        }
+    }
+
+    //indentation of the next non-blank line after the current newline, None if the file ends first
+    fn next_line_indentation(&self) -> Option<usize> {
+        let mut indentation = 0;
+        let mut offset = 1;
+        loop {
+            match self.cur_offset_opt(offset)? {
+                Token::NewLine => indentation = 0,
+                Token::Indentation => indentation += 1,
+                _ => return Some(indentation),
+            }
+            offset += 1;
+        }
+    }
+
+    //a statement ends at a newline or EOF, anything else left on its line is reported and skipped
+    fn expect_statement_end(&mut self, statement: &str) {
+        if self.is_not_end() && !self.cur_is_newline() {
+            self.errors.push(ParsingError::SyntaxError(format!(
+                "Newline or EOF expected after {statement}, got {:?}", self.cur()
+            )));
+            self.skip_statement();
+        }
+    }
+
+    //consumes the expected token, or fails with the message
+    fn expect_token(&mut self, expected: Token, message: &str) -> Result<(), ParsingError> {
+        match self.cur_opt() {
+            Some(token) if *token == expected => {
+                self.next();
+                Ok(())
+            }
+            found => Err(ParsingError::SyntaxError(format!("{message}, got {found:?}"))),
+        }
+    }
+
     fn next(&mut self) {
         self.advance(1);
         //println!("Current reading {:?}", self.cur());
@@ -274,12 +420,12 @@ impl Parser {
         return &mut self.parsing_state.last_mut().unwrap().operator_stack;
     }
 
-    pub fn parse_assign(&mut self) -> Option<AST> {
+    pub fn parse_assign(&mut self) -> Result<Option<AST>, ParsingError> {
         let mut path = vec![];
         while let Token::Identifier(id) = self.cur().clone() {
             path.push(id.clone());
             if self.is_last() {
-                return None;
+                return Ok(None);
             } else {
                 self.next()
             }
@@ -288,25 +434,37 @@ impl Parser {
             }
         }
         if !self.can_go() {
-            return None;
+            return Ok(None);
         }
         if let Token::Assign = self.cur() {
             self.next();
-            let expr = self.parse_expr().expect("Expected expression after assign");
-            Some(AST::Assign {
+            let expr = self.parse_expr()?;
+            Ok(Some(AST::Assign {
                 path: path,
                 expression: expr.resulting_expr,
-            })
+            }))
+        } else if let Token::CompoundAssign(operator) = self.cur().clone() {
+            self.next();
+            let expr = self.parse_expr()?;
+            Ok(Some(AST::CompoundAssign {
+                path: path,
+                operator: operator,
+                expression: expr.resulting_expr,
+            }))
         } else {
-            None
+            Ok(None)
         }
     }
 
 
-    pub fn parse_assign_typed(&mut self) -> Option<AST> {
+    pub fn parse_assign_typed(&mut self) -> Result<Option<AST>, ParsingError> {
         
         let decl = self.parse_type_bound_name();
 
+        //a missing colon just means this is not a typed declaration, but a malformed type is an error
+        if let Err(error @ ParsingError::InvalidType(_)) = decl {
+            return Err(error);
+        }
         if let Ok(Some(typed_var_decl)) = decl {
             //no need to do .next here, parse_type_bound_name already does a .next()
             self.next();
@@ -314,98 +472,109 @@ impl Parser {
            // println!("{:?}", cur);
             if let Token::Assign = cur {
                 self.next();
-                let expr = self.parse_expr().expect("Expected expression after assign");
-                return Some(AST::Declare {
+                let expr = self.parse_expr()?;
+                return Ok(Some(AST::Declare {
                     var: typed_var_decl,
                     expression: expr.resulting_expr,
-                });
+                }));
             } else {
-                return None;
+                return Ok(None);
             }
         
         } else {
-            return None;
+            return Ok(None);
         }
     }
 
 
-    pub fn parse_if_statement(&mut self) -> Option<AST> {
-        if let Token::IfKeyword = self.cur().clone() {
-            self.next();
-            if !self.can_go() {
-                None
-            } else {
-                let expr = self.parse_expr().expect("Expected expr").resulting_expr;
-                if let Token::Colon = self.cur() {
-                    self.next();
-                } else {
-                    panic!("Expected colon after if expr");
-                }
-
-                if let Token::NewLine = self.cur() {
-                    self.next();
-                } else {
-                    panic!("Expected newline after colon");
-                }
+    //parses `<expr>:` and the newline after it, for if, elif and while
+    fn parse_condition_header(&mut self, construct: &str) -> Result<Expr, ParsingError> {
+        let expression = self.parse_expr()?.resulting_expr;
+        self.expect_token(Token::Colon, &format!("Expected colon after {construct} expr"))?;
+        self.expect_token(Token::NewLine, "Expected newline after colon")?;
+        Ok(expression)
+    }
 
-                self.increment_expected_indent();
-                let ast = self.parse_ast().unwrap();
-                let mut if_statement = AST::IfStatement {
-                    true_branch: ASTIfStatement {
-                        expression: expr,
-                        statements: ast,
-                    },
-                    elifs: vec![],
-                    final_else: None,
-                };
-                self.decrement_expected_indent();
+    pub fn parse_if_statement(&mut self) -> Result<Option<AST>, ParsingError> {
+        let Token::IfKeyword = self.cur().clone() else {
+            return Ok(None);
+        };
+        self.next();
+        if !self.can_go() {
+            return Ok(None);
+        }
+        let expr = self.parse_condition_header("if")?;
 
-                let cur_identation = self.get_expected_indent();
-                //lets try getting the else statement:
-                self.new_stack();
-                let identation_else = self.skip_whitespace_newline();
+        self.increment_expected_indent();
+        let ast = self.parse_nonempty_block("if");
+        let mut elifs = vec![];
+        self.decrement_expected_indent();
 
-                if self.can_go() && identation_else == cur_identation {
-                    if let Token::ElseKeyword = self.cur() {
-                        self.next();
-                        if let Token::Colon = self.cur() {
-                            self.next();
-                        } else {
-                            panic!("Expected colon after if expr");
-                        }
+        let cur_identation = self.get_expected_indent();
+        //elifs must be at the same indentation as the if, each one is committed once parsed
+        loop {
+            self.new_stack();
+            let identation_elif = self.skip_whitespace_newline();
+            if !(self.can_go() && identation_elif == cur_identation) {
+                self.pop_stack();
+                break;
+            }
+            let Token::ElifKeyword = self.cur() else {
+                self.pop_stack();
+                break;
+            };
+            self.next();
+            let expression = match self.parse_condition_header("elif") {
+                Ok(expression) => expression,
+                Err(e) => {
+                    self.pop_stack();
+                    return Err(e);
+                }
+            };
+
+            self.increment_expected_indent();
+            let statements = self.parse_nonempty_block("elif");
+            self.decrement_expected_indent();
+            elifs.push(ASTIfStatement { expression, statements });
+            let popped = self.pop_stack();
+            self.set_cur(&popped);
+        }
 
-                        if let Token::NewLine = self.cur() {
-                            self.next();
-                        } else {
-                            panic!("Expected newline after colon");
-                        }
+        //lets try getting the else statement:
+        let mut final_else = None;
+        self.new_stack();
+        let identation_else = self.skip_whitespace_newline();
 
-                        self.increment_expected_indent();
-                        let ast = self.parse_ast().unwrap();
-                        if_statement = match if_statement {
-                            AST::IfStatement {
-                                true_branch,
-                                elifs,
-                                final_else: _,
-                            } => AST::IfStatement {
-                                true_branch,
-                                elifs,
-                                final_else: Some(ast),
-                            },
-                            _ => panic!("Unrecognized ast on if else parsing"),
-                        };
-                        self.decrement_expected_indent();
-                    } else {
-                        self.pop_stack();
-                    }
-                } else {
+        if self.can_go() && identation_else == cur_identation {
+            if let Token::ElseKeyword = self.cur() {
+                self.next();
+                let header = self.expect_token(Token::Colon, "Expected colon after else")
+                    .and_then(|_| self.expect_token(Token::NewLine, "Expected newline after colon"));
+                if let Err(e) = header {
                     self.pop_stack();
+                    return Err(e);
                 }
-                return Some(if_statement);
+
+                self.increment_expected_indent();
+                final_else = Some(self.parse_nonempty_block("else"));
+                self.decrement_expected_indent();
+                let popped = self.pop_stack();
+                self.set_cur(&popped);
+            } else {
+                self.pop_stack();
             }
         } else {
-            None
+            self.pop_stack();
         }
+
+        Ok(Some(AST::IfStatement {
+            true_branch: ASTIfStatement {
+                expression: expr,
+                statements: ast,
+            },
+            elifs,
+            final_else,
+        }))
     }
 
 /*
@@ -433,146 +602,138 @@ impl Parser {
         }
     }
 */
-    pub fn parse_structdef(&mut self) -> Option<AST> {
+    //consumes an optional pub keyword before a declaration.
+    //declarations are private unless marked otherwise.
+    fn parse_visibility(&mut self) -> Visibility {
+        if let Token::PubKeyword = self.cur() {
+            self.next();
+            Visibility::Public
+        } else {
+            Visibility::Private
+        }
+    }
+
+    //<T, U> in struct, impl and function declarations, empty if there's no < at the current token
+    fn parse_type_parameters(&mut self) -> Result<Vec<String>, ParsingError> {
+        let mut type_parameters = vec![];
+        if let Token::Operator(Operator::Less) = self.cur() {
+            self.next();
+            while let Token::Identifier(type_parameter) = self.cur().clone() {
+                type_parameters.push(type_parameter);
+                self.next();
+                if let Token::Comma = self.cur() {
+                    self.next();
+                }
+            }
+            self.expect_token(Token::Operator(Operator::Greater), "Expected > after type parameters")?;
+        }
+        Ok(type_parameters)
+    }
+
+    pub fn parse_structdef(&mut self) -> Result<Option<AST>, ParsingError> {
+        let visibility = self.parse_visibility();
+        if !self.can_go() {
+            return Ok(None);
+        }
         if let Token::StructDef = self.cur().clone() {
             self.next();
             if !self.can_go() {
-                return None;
+                return Ok(None);
             } 
             if let Token::Identifier(name) = self.cur().clone() {
                 self.next();
-                if let Token::Colon = self.cur() {
-                    self.next();
-                } else {
-                    panic!("Expected colon after class decl identifier");
-                }
-                if let Token::NewLine = self.cur() {
-                    self.next();
-                } else {
-                    panic!("Expected newline after colon");
-                }
+                //struct box<T, U>:
+                let type_parameters = self.parse_type_parameters()?;
+                self.expect_token(Token::Colon, "Expected colon after struct name")?;
+                self.expect_token(Token::NewLine, "Expected newline after colon")?;
                 self.increment_expected_indent();
                
                 let mut fields = vec![];
 
+                //one field per line, the struct ends at the first line that is not indented like its fields
                 loop {
-                    self.skip_whitespace_newline();
-                    if !self.can_go() {
+                    self.new_stack();
+                    let identation = self.skip_whitespace_newline();
+                    if !self.is_not_end() || identation != self.get_expected_indent() {
+                        self.pop_stack();
                         break;
                     }
-                    if let Token::Identifier(_) = self.cur() {
-                        let parsed = self.parse_type_bound_name().unwrap().unwrap();
-                        fields.push(parsed);
-                        
-                        if !self.can_go() {
-                            break; 
-                        }
+                    let popped = self.pop_stack();
+                    self.set_cur(&popped);
 
-                        self.next();
-                        if let Token::NewLine = self.cur() {
-                            self.next();
-                            if !self.can_go() { break; }
-                            if let Token::NewLine = self.cur() {
-                                break;
-                            }
-                            continue;
-                        } else {
-                            break;
-                        }
-                    } else {
-                        break
+                    let field_start = self.cur().clone();
+                    let Some(parsed) = self.parse_type_bound_name()? else {
+                        return Err(ParsingError::SyntaxError(format!("Expected field declaration in struct {name}, got {field_start:?}")));
+                    };
+                    self.next();
+                    if self.can_go() {
+                        return Err(ParsingError::SyntaxError(format!(
+                            "Expected newline after field {} in struct {name}, got {:?}", parsed.name, self.cur()
+                        )));
                     }
+                    fields.push(parsed);
                 }
 
                 let def_classdecl = AST::StructDeclaration {
                     struct_name: name.clone(),
+                    type_parameters,
                     body: fields,
+                    visibility
                 };
                 
                 self.decrement_expected_indent();
 
-                return Some(def_classdecl);
+                return Ok(Some(def_classdecl));
             } else {
-                panic!("Unexpected token: expected identifier, got something else")
+                Err(ParsingError::SyntaxError(format!("Expected struct name after struct keyword, got {:?}", self.cur())))
             }
             
         } else {
-            None
+            Ok(None)
         }
     }
 
-    pub fn parse_while_statement(&mut self) -> Option<AST> {
+    pub fn parse_while_statement(&mut self) -> Result<Option<AST>, ParsingError> {
         if let Token::WhileKeyword = self.cur().clone() {
             self.next();
             if !self.can_go() {
-                None
+                Ok(None)
             } else {
-                let expr = self.parse_expr().expect("Expected expr").resulting_expr;
-                if let Token::Colon = self.cur() {
-                    self.next();
-                } else {
-                    panic!("Expected colon after while expr");
-                }
-
-                if let Token::NewLine = self.cur() {
-                    self.next();
-                } else {
-                    panic!("Expected newline after colon");
-                }
+                let expr = self.parse_condition_header("while")?;
 
                 self.increment_expected_indent();
-                let ast = self.parse_ast().unwrap();
+                let ast = self.parse_nonempty_block("while");
                 let while_statement = AST::WhileStatement {
                     expression: expr,
                     body: ast,
                 };
                 self.decrement_expected_indent();
 
-                return Some(while_statement);
+                return Ok(Some(while_statement));
             }
         } else {
-            None
+            Ok(None)
         }
     }
 
-    pub fn parse_for_statement(&mut self) -> Option<AST> {
+    pub fn parse_for_statement(&mut self) -> Result<Option<AST>, ParsingError> {
         if let Token::ForKeyword = self.cur().clone() {
             self.next();
             if !self.can_go() {
-                None
+                Ok(None)
             } else {
-                let variable_name: String;
-                if let Token::Identifier(name) = self.cur() {
-                    variable_name = name.clone();
-                    self.next();
-                } else {
-                    panic!("Expected identifier after for keyword")
-                }
-
-                if let Token::InKeyword = self.cur() {
-                    self.next();
-                } else {
-                    panic!("Expected in keyword after identifier in for keyword")
-                }
-
-                let expr = self
-                    .parse_expr()
-                    .expect("Expected expr after in keyword in for expression")
-                    .resulting_expr;
-                if let Token::Colon = self.cur() {
-                    self.next();
-                } else {
-                    panic!("Expected colon after for statement");
-                }
+                let Token::Identifier(variable_name) = self.cur().clone() else {
+                    return Err(ParsingError::SyntaxError(format!("Expected identifier after for keyword, got {:?}", self.cur())));
+                };
+                self.next();
+                self.expect_token(Token::InKeyword, "Expected in keyword after identifier in for statement")?;
 
-                if let Token::NewLine = self.cur() {
-                    self.next();
-                } else {
-                    panic!("Expected newline after colon");
-                }
+                let expr = self.parse_expr()?.resulting_expr;
+                self.expect_token(Token::Colon, "Expected colon after for statement")?;
+                self.expect_token(Token::NewLine, "Expected newline after colon")?;
 
                 self.increment_expected_indent();
-                let ast = self.parse_ast().unwrap();
+                let ast = self.parse_nonempty_block("for");
 
                 let for_statement = AST::ForStatement {
                     item_name: variable_name,
@@ -581,42 +742,104 @@ impl Parser {
                 };
                 self.decrement_expected_indent();
 
-                return Some(for_statement);
+                return Ok(Some(for_statement));
             }
         } else {
-            None
+            Ok(None)
         }
     }
 
-    pub fn parse_type_name(&mut self) -> Option<ASTType> {
+    pub fn parse_type_name(&mut self) -> Result<Option<ASTType>, ParsingError> {
 
         let Token::Identifier(type_name) = self.cur().clone() else { 
-            return None;
+            return Ok(None);
         };
         
-        if !self.can_go() {
-            return Some(ASTType::Simple(type_name.clone()));
-        }
+        //the type name can be the last token of the file
+        let Some(peek_next) = self.cur_offset_opt(1).cloned() else {
+            return Ok(Some(ASTType::Simple(type_name.clone())));
+        };
 
-        let peek_next = self.cur_offset(1).clone();
+        if type_name == "fn" {
+            if let Token::OpenParen = peek_next {
+                return self.parse_function_type().map(Some);
+            }
+        }
     
         let Token::Operator(Operator::Less) = peek_next else {
-            return Some(ASTType::Simple(type_name.clone()));
+            return Ok(Some(ASTType::Simple(type_name.clone())));
         };
         self.next(); //commits the peek_next
         self.next();
 
-        let Token::Identifier(generic_name) = self.cur().clone() else { 
-            panic!("For now we dont have proper error handling for mistakes in generic types, cur = {:?}", self.cur())
-         };
+        //generic args are comma separated, each one is a type name that can be generic itself
+        let mut generic_args = vec![];
+        loop {
+            if let Token::LiteralInteger(length) = self.cur().clone() {
+                let Ok(length) = u32::try_from(length) else {
+                    return Err(ParsingError::InvalidType(format!("Constant generic argument {length} does not fit in a u32")));
+                };
+                generic_args.push(ASTType::Const(length));
+                self.next();
+            } else {
+                let Some(generic_arg) = self.parse_type_name()? else {
+                    return Err(ParsingError::InvalidType(format!("Expected type name in generic type arguments of {type_name}, found {:?}", self.cur_opt())));
+                };
+                generic_args.push(generic_arg);
+                self.next();
+            }
+
+            self.split_shift_right();
+            match self.cur_opt() {
+                Some(Token::Comma) => self.next(),
+                Some(Token::Operator(Operator::Greater)) => break,
+                found => return Err(ParsingError::InvalidType(format!("Expected , or > in generic type arguments of {type_name}, found {found:?}"))),
+            }
+        }
+
+        return Ok(Some(ASTType::Generic(type_name.clone(), generic_args)));
+    }
+
+    //`>>` is lexed as a shift, but in array<array<i32>> it closes two generic argument lists.
+    //Splits it into two `>` so each list closes on its own
+    fn split_shift_right(&mut self) {
+        if let Some(Token::Operator(Operator::BitShiftRight)) = self.cur_opt() {
+            let index = self.parsing_state.last().unwrap().index;
+            self.tokens[index] = Token::Operator(Operator::Greater);
+            self.tokens.insert(index + 1, Token::Operator(Operator::Greater));
+        }
+    }
+
+    //parses fn(args) -> return, leaving the cursor on the last token of the return type like parse_type_name
+    fn parse_function_type(&mut self) -> Result<ASTType, ParsingError> {
+        self.next(); //fn
+        self.next(); //open paren
+
+        let mut arg_types = vec![];
+        while !matches!(self.cur_opt(), Some(Token::CloseParen)) {
+            let Some(arg_type) = self.parse_type_name()? else {
+                return Err(ParsingError::InvalidType(format!("Expected type name in function type arguments, found {:?}", self.cur_opt())));
+            };
+            arg_types.push(arg_type);
+            self.next();
+
+            match self.cur_opt() {
+                Some(Token::Comma) => self.next(),
+                Some(Token::CloseParen) => {}
+                found => return Err(ParsingError::InvalidType(format!("Expected , or ) in function type arguments, found {found:?}"))),
+            }
+        }
+        self.next(); //close paren
+
+        let Some(Token::ArrowRight) = self.cur_opt() else {
+            return Err(ParsingError::InvalidType(format!("Expected -> after function type arguments, found {:?}", self.cur_opt())));
+        };
         self.next();
 
-        if let Token::Operator(Operator::Greater) = self.cur().clone() { 
-            return Some(ASTType::Generic(type_name.clone(), 
-                vec![ ASTType::Simple(generic_name.clone())]));
-        } else {
-            panic!("For now we don't suport more than 1 generic argument (i'm lazy).")
+        let Some(return_type) = self.parse_type_name()? else {
+            return Err(ParsingError::InvalidType(format!("Expected return type in function type, found {:?}", self.cur_opt())));
         };
+        Ok(ASTType::Function(arg_types, Box::new(return_type)))
     }
 
     //Tries to parse a bound name with its type, for instance var: i32
@@ -634,7 +857,7 @@ impl Parser {
         };
         self.next();
 
-        let typename = self.parse_type_name();
+        let typename = self.parse_type_name()?;
 
         match typename {
             Some(x) => Ok(Some(TypeBoundName { name: name, name_type: x})),
@@ -642,30 +865,40 @@ impl Parser {
         }        
     }
 
-    pub fn parse_def_statement(&mut self) -> Option<AST> {
+    pub fn parse_def_statement(&mut self) -> Result<Option<AST>, ParsingError> {
+        let visibility = self.parse_visibility();
+        if !self.can_go() {
+            return Ok(None);
+        }
+        let is_extern = if let Token::ExternKeyword = self.cur() {
+            self.next();
+            true
+        } else {
+            false
+        };
         if let Token::DefKeyword = self.cur().clone() {
             self.next();
             if !self.can_go() {
-                None
+                Ok(None)
             } else {
-                let function_name: String;
-                if let Token::Identifier(name) = self.cur() {
-                    function_name = name.clone();
-                    self.next();
-                } else {
-                    panic!("Expected function identifier")
-                }
+                let Token::Identifier(function_name) = self.cur().clone() else {
+                    return Err(ParsingError::SyntaxError(format!("Expected function name after def, got {:?}", self.cur())));
+                };
+                self.next();
 
-                if let Token::OpenParen = self.cur() {
-                    self.next();
-                } else {
-                    panic!("Expected open paren function name")
+                let type_parameters = self.parse_type_parameters()?;
+                if is_extern && !type_parameters.is_empty() {
+                    return Err(ParsingError::SyntaxError(format!("Extern function {function_name} cannot have type parameters")));
                 }
+
+                self.expect_token(Token::OpenParen, &format!("Expected open paren after function name {function_name}"))?;
                 let mut params: Vec<TypeBoundName> = vec![];
 
                 while let Token::Identifier(_) = self.cur() {
 
-                    let param = self.parse_type_bound_name().unwrap().unwrap();
+                    let Some(param) = self.parse_type_bound_name()? else {
+                        return Err(ParsingError::SyntaxError(format!("Expected parameter type in function declaration {function_name}")));
+                    };
                   
                     params.push(param);
                     self.next();
@@ -676,45 +909,50 @@ impl Parser {
                     }
                 }
 
-                if let Token::CloseParen = self.cur() {
-                    self.next();
-                } else {
-                    panic!("Expected close paren after parameters in function declaration")
-                }
+                self.expect_token(Token::CloseParen, &format!("Expected close paren after parameters in function declaration {function_name}"))?;
                 
                 let mut return_type: Option<ASTType> = None;
 
-                if let Token::ArrowRight = self.cur() {
+                if let Some(Token::ArrowRight) = self.cur_opt() {
                     self.next();
                     
-                    return_type = self.parse_type_name();
+                    return_type = self.parse_type_name()?;
                     if return_type.is_none() {
-                        panic!("Expected type name after arrow right on function declaration")
+                        return Err(ParsingError::SyntaxError(format!("Expected return type after -> in function declaration {function_name}, got {:?}", self.cur_opt())));
                     }
                     self.next();
                 }
 
-                if let Token::Colon = self.cur() {
-                    self.next();
-                } else {
-                    panic!("Expected colon paren after parameters and return type in function declaration")
+                //extern functions have no body
+                if is_extern {
+                    return Ok(Some(AST::DeclareExternFunction {
+                        function_name,
+                        parameters: params,
+                        return_type
+                    }));
                 }
 
+                self.expect_token(Token::Colon, &format!("Expected colon after parameters and return type in function declaration {function_name}"))?;
+
                 self.increment_expected_indent();
-                let ast = self.parse_ast().unwrap();
+                let ast = self.parse_block();
 
                 let for_statement = AST::DeclareFunction {
                     function_name: function_name,
+                    type_parameters,
                     parameters: params,
                     body: ast,
-                    return_type: return_type
+                    return_type: return_type,
+                    visibility
                 };
                 self.decrement_expected_indent();
 
-                return Some(for_statement);
+                return Ok(Some(for_statement));
             }
+        } else if is_extern {
+            Err(ParsingError::SyntaxError(format!("Expected def after extern, got {:?}", self.cur())))
         } else {
-            None
+            Ok(None)
         }
     }
 
@@ -737,7 +975,32 @@ impl Parser {
         return identation_level;
     }
 
-    pub fn parse_ast(&mut self) -> Result<Vec<AST>, ParsingError> {
+    //Parses all statements, returning every error found instead of stopping at the first one
+    pub fn parse_ast(&mut self) -> Result<Vec<AST>, Vec<ParsingError>> {
+        let results = self.parse_block();
+        if self.errors.is_empty() {
+            Ok(results)
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+
+    //An empty body is reported but still returned, so that parsing continues after it.
+    //Must be called right after the newline that follows the colon.
+    fn parse_nonempty_block(&mut self, construct: &str) -> Vec<AST> {
+        let block = self.parse_block();
+        if block.is_empty() {
+            self.errors.push(ParsingError::EmptyBlock(format!(
+                "{construct} body must have at least one statement"
+            )));
+            //nothing was consumed, go back to the newline so the statement ends where a non-empty one would
+            self.advance(-1);
+        }
+        block
+    }
+
+    //Parses statements in the current indentation level, errors are accumulated in self.errors
+    fn parse_block(&mut self) -> Vec<AST> {
         let mut results = vec![];
 
         loop {
@@ -751,63 +1014,75 @@ impl Parser {
                 self.set_cur(&popped);
             } else {
                 self.pop_stack();
-                return Ok(results);
+                return results;
             }
 
             if !self.is_not_end() {
-                return Ok(results);
+                return results;
             }
 
             let mut parsed_successfully = false;
 
             if !parsed_successfully {
                 self.new_stack();
-                if let Some(assign_ast) = self.parse_structdef() {
-                    results.push(assign_ast);
-                    parsed_successfully = true;
-                    let popped = self.pop_stack();
-                    //correct indentation found: commit
-                    self.set_cur(&popped);
-                    assert!(
-                       !self.is_not_end() || self.cur_is_newline(),
-                       "Newline or EOF expected after struct, got {:?}", self.cur()
-                    );
-                } else {
-                    self.pop_stack();
-                }
-            }
-
-            if !parsed_successfully {
-                self.new_stack();
-                if let Some(assign_ast) = self.parse_assign() {
-                    results.push(assign_ast);
-                    parsed_successfully = true;
-                    let popped = self.pop_stack();
-                    //correct indentation found: commit
-                    self.set_cur(&popped);
-                    assert!(
-                        !self.is_not_end() || self.cur_is_newline(),
-                        "Newline or EOF expected after assign"
-                    );
-                } else {
-                    self.pop_stack();
+                match self.parse_structdef() {
+                    Ok(Some(struct_ast)) => {
+                        results.push(struct_ast);
+                        parsed_successfully = true;
+                        let popped = self.pop_stack();
+                        //correct indentation found: commit
+                        self.set_cur(&popped);
+                        self.expect_statement_end("struct");
+                    }
+                    Ok(None) => {
+                        self.pop_stack();
+                    }
+                    Err(e) => {
+                        self.recover_from_error(e);
+                        continue;
+                    }
                 }
             }
 
             if !parsed_successfully {
                 self.new_stack();
-                if let Some(assign_ast) = self.parse_assign_typed() {
-                    results.push(assign_ast);
-                    parsed_successfully = true;
-                    let popped = self.pop_stack();
-                    //correct indentation found: commit
-                    self.set_cur(&popped);
-                    assert!(
-                        !self.is_not_end() || self.cur_is_newline(),
-                        "Newline or EOF expected after assign"
-                    );
-                } else {
-                    self.pop_stack();
+                match self.parse_assign() {
+                    Ok(Some(assign_ast)) => {
+                        results.push(assign_ast);
+                        parsed_successfully = true;
+                        let popped = self.pop_stack();
+                        //correct indentation found: commit
+                        self.set_cur(&popped);
+                        self.expect_statement_end("assign");
+                    }
+                    Ok(None) => {
+                        self.pop_stack();
+                    }
+                    Err(e) => {
+                        self.recover_from_error(e);
+                        continue;
+                    }
+                }
+            }
+
+            if !parsed_successfully {
+                self.new_stack();
+                match self.parse_assign_typed() {
+                    Ok(Some(assign_ast)) => {
+                        results.push(assign_ast);
+                        parsed_successfully = true;
+                        let popped = self.pop_stack();
+                        //correct indentation found: commit
+                        self.set_cur(&popped);
+                        self.expect_statement_end("assign");
+                    }
+                    Ok(None) => {
+                        self.pop_stack();
+                    }
+                    Err(e) => {
+                        self.recover_from_error(e);
+                        continue;
+                    }
                 }
             }
 
@@ -815,21 +1090,22 @@ impl Parser {
                 self.new_stack();
                 let expr = self.parse_if_statement();
                 match expr {
-                    Some(ast_if) => {
+                    Ok(Some(ast_if)) => {
                         results.push(ast_if);
                         parsed_successfully = true;
                         let popped = self.pop_stack();
                         //correct indentation found: commit
                         self.set_cur(&popped);
-                        assert!(
-                            !self.is_not_end() || self.cur_is_newline(),
-                            "Newline or EOF expected after if block"
-                        );
+                        self.expect_statement_end("if block");
                     }
-                    None => {
+                    Ok(None) => {
                         parsed_successfully = false;
                         self.pop_stack();
                     }
+                    Err(e) => {
+                        self.recover_from_error(e);
+                        continue;
+                    }
                 }
             }
 
@@ -837,21 +1113,22 @@ impl Parser {
                 self.new_stack();
                 let expr = self.parse_while_statement();
                 match expr {
-                    Some(ast_if) => {
+                    Ok(Some(ast_if)) => {
                         results.push(ast_if);
                         parsed_successfully = true;
                         let popped = self.pop_stack();
                         //correct indentation found: commit
                         self.set_cur(&popped);
-                        assert!(
-                            !self.is_not_end() || self.cur_is_newline(),
-                            "Newline or EOF expected after if block"
-                        );
+                        self.expect_statement_end("while block");
                     }
-                    None => {
+                    Ok(None) => {
                         parsed_successfully = false;
                         self.pop_stack();
                     }
+                    Err(e) => {
+                        self.recover_from_error(e);
+                        continue;
+                    }
                 }
             }
 
@@ -859,21 +1136,22 @@ impl Parser {
                 self.new_stack();
                 let expr = self.parse_for_statement();
                 match expr {
-                    Some(ast_for) => {
+                    Ok(Some(ast_for)) => {
                         results.push(ast_for);
                         parsed_successfully = true;
                         let popped = self.pop_stack();
                         //correct indentation found: commit
                         self.set_cur(&popped);
-                        assert!(
-                            !self.is_not_end() || self.cur_is_newline(),
-                            "Newline or EOF expected after for block"
-                        );
+                        self.expect_statement_end("for block");
                     }
-                    None => {
+                    Ok(None) => {
                         parsed_successfully = false;
                         self.pop_stack();
                     }
+                    Err(e) => {
+                        self.recover_from_error(e);
+                        continue;
+                    }
                 }
             }
 
@@ -881,18 +1159,141 @@ impl Parser {
                 self.new_stack();
                 let expr = self.parse_def_statement();
                 match expr {
-                    Some(ast_for) => {
+                    Ok(Some(ast_for)) => {
                         results.push(ast_for);
                         parsed_successfully = true;
                         let popped = self.pop_stack();
                         //correct indentation found: commit
                         self.set_cur(&popped);
-                        assert!(
-                            !self.is_not_end() || self.cur_is_newline(),
-                            "Newline or EOF expected after for block"
-                        );
+                        self.expect_statement_end("function declaration");
+                    }
+                    Ok(None) => {
+                        parsed_successfully = false;
+                        self.pop_stack();
+                    }
+                    Err(e) => {
+                        self.recover_from_error(e);
+                        continue;
+                    }
+                }
+            }
+
+            if !parsed_successfully {
+                self.new_stack();
+                let tok = self.cur();
+                match tok {
+                    Token::TypeKeyword => {
+                        self.next();
+                        let Some(Token::Identifier(alias_name)) = self.cur_opt().cloned() else {
+                            let error = ParsingError::SyntaxError(format!("Expected alias name after type keyword, got {:?}", self.cur_opt()));
+                            self.recover_from_error(error);
+                            continue;
+                        };
+                        self.next();
+                        if let Err(e) = self.expect_token(Token::Assign, "Expected = after type alias name") {
+                            self.recover_from_error(e);
+                            continue;
+                        }
+                        let target = match self.parse_type_name() {
+                            Ok(Some(target)) => target,
+                            Ok(None) => {
+                                let error = ParsingError::SyntaxError(format!("Expected type name in type alias, got {:?}", self.cur_opt()));
+                                self.recover_from_error(error);
+                                continue;
+                            }
+                            Err(e) => {
+                                self.recover_from_error(e);
+                                continue;
+                            }
+                        };
+                        self.next();
+                        results.push(AST::TypeAlias { alias_name, target });
+                        parsed_successfully = true;
+                        let popped = self.pop_stack();
+                        //correct indentation found: commit
+                        self.set_cur(&popped);
+                        self.expect_statement_end("type alias");
+                    }
+                    Token::ConstKeyword => {
+                        self.next();
+                        match self.parse_assign_typed() {
+                            Ok(Some(AST::Declare { var, expression })) => {
+                                results.push(AST::DeclareConst { var, expression });
+                            }
+                            Ok(_) => {
+                                let error = ParsingError::SyntaxError(format!("Expected typed declaration after const keyword, got {:?}", self.cur_opt()));
+                                self.recover_from_error(error);
+                                continue;
+                            }
+                            Err(e) => {
+                                self.recover_from_error(e);
+                                continue;
+                            }
+                        }
+                        parsed_successfully = true;
+                        let popped = self.pop_stack();
+                        //correct indentation found: commit
+                        self.set_cur(&popped);
+                        self.expect_statement_end("const declaration");
+                    }
+                    Token::ImplKeyword => {
+                        self.next();
+                        let type_parameters = match self.parse_type_parameters() {
+                            Ok(type_parameters) => type_parameters,
+                            Err(e) => {
+                                self.recover_from_error(e);
+                                continue;
+                            }
+                        };
+                        let target = match self.parse_type_name() {
+                            Ok(Some(target)) => target,
+                            Ok(None) => {
+                                let error = ParsingError::SyntaxError(format!("Expected type name after impl, got {:?}", self.cur_opt()));
+                                self.recover_from_error(error);
+                                continue;
+                            }
+                            Err(e) => {
+                                self.recover_from_error(e);
+                                continue;
+                            }
+                        };
+                        self.next();
+                        if let Err(e) = self.expect_token(Token::Colon, "Expected colon after impl target type") {
+                            self.recover_from_error(e);
+                            continue;
+                        }
+                        self.increment_expected_indent();
+                        let methods = self.parse_nonempty_block("impl");
+                        self.decrement_expected_indent();
+                        if let Some(not_method) = methods.iter().find(|method| !matches!(method, AST::DeclareFunction { .. })) {
+                            let error = ParsingError::SyntaxError(format!("Only methods can be declared in impl blocks, got {:?}", not_method));
+                            self.recover_from_error(error);
+                            continue;
+                        }
+                        results.push(AST::ImplBlock { type_parameters, target, methods });
+                        parsed_successfully = true;
+                        let popped = self.pop_stack();
+                        //correct indentation found: commit
+                        self.set_cur(&popped);
+                        self.expect_statement_end("impl block");
+                    }
+                    Token::ImportKeyword => {
+                        self.next();
+                        if let Some(Token::Identifier(module_name)) = self.cur_opt() {
+                            results.push(AST::Import(module_name.clone()));
+                            self.next();
+                        } else {
+                            let error = ParsingError::SyntaxError(format!("Expected module name after import keyword, got {:?}", self.cur_opt()));
+                            self.recover_from_error(error);
+                            continue;
+                        }
+                        parsed_successfully = true;
+                        let popped = self.pop_stack();
+                        //correct indentation found: commit
+                        self.set_cur(&popped);
+                        self.expect_statement_end("import");
                     }
-                    None => {
+                    _ => {
                         parsed_successfully = false;
                         self.pop_stack();
                     }
@@ -910,11 +1311,7 @@ impl Parser {
                         let popped = self.pop_stack();
                         //correct indentation found: commit
                         self.set_cur(&popped);
-                        assert!(
-                            !self.is_not_end() || self.cur_is_newline(),
-                            "Newline or EOF expected after if block, got {:?}",
-                            self.cur_opt()
-                        );
+                        self.expect_statement_end("break");
                     }
                     _ => {
                         parsed_successfully = false;
@@ -930,8 +1327,36 @@ impl Parser {
                     Token::ReturnKeyword => {
                         self.next();
                         if self.can_go() {
-                            let expr = self.parse_expr()?;
-                            results.push(AST::Return(Some(expr.resulting_expr)));
+                            let expr = match self.parse_expr() {
+                                Ok(expr) => expr,
+                                Err(e) => {
+                                    self.recover_from_error(e);
+                                    continue;
+                                }
+                            };
+                            //return a, b returns a tuple
+                            let mut values = vec![expr.resulting_expr];
+                            let mut failed = false;
+                            while let Some(Token::Comma) = self.cur_opt() {
+                                self.next();
+                                match self.parse_expr() {
+                                    Ok(expr) => values.push(expr.resulting_expr),
+                                    Err(e) => {
+                                        self.recover_from_error(e);
+                                        failed = true;
+                                        break;
+                                    }
+                                }
+                            }
+                            if failed {
+                                continue;
+                            }
+                            let returned = if values.len() == 1 {
+                                values.pop().unwrap()
+                            } else {
+                                Expr::Tuple(values)
+                            };
+                            results.push(AST::Return(Some(returned)));
                         } else {
                             results.push(AST::Return(None));
                         }
@@ -939,11 +1364,7 @@ impl Parser {
                         let popped = self.pop_stack();
                         //correct indentation found: commit
                         self.set_cur(&popped);
-                        assert!(
-                            !self.is_not_end() || self.cur_is_newline(),
-                            "Newline or EOF expected after if block, got {:?}",
-                            self.cur_opt()
-                        );
+                        self.expect_statement_end("return");
                     }
                     _ => {
                         parsed_successfully = false;
@@ -959,20 +1380,23 @@ impl Parser {
                     Token::RaiseKeyword => {
                         self.next();
                         if self.can_go() {
-                            let expr = self.parse_expr()?;
+                            let expr = match self.parse_expr() {
+                                Ok(expr) => expr,
+                                Err(e) => {
+                                    self.recover_from_error(e);
+                                    continue;
+                                }
+                            };
                             results.push(AST::Raise(expr.resulting_expr));
                         } else {
-                            panic!("Must inform expression with raise keyword")
+                            self.recover_from_error(ParsingError::SyntaxError("Expected expression after raise keyword".into()));
+                            continue;
                         }
                         parsed_successfully = true;
                         let popped = self.pop_stack();
                         //correct indentation found: commit
                         self.set_cur(&popped);
-                        assert!(
-                            !self.is_not_end() || self.cur_is_newline(),
-                            "Newline or EOF expected after if block, got {:?}",
-                            self.cur_opt()
-                        );
+                        self.expect_statement_end("raise");
                     }
                     _ => {
                         parsed_successfully = false;
@@ -983,17 +1407,26 @@ impl Parser {
 
             if !parsed_successfully {
                 self.new_stack();
-                let expr = self.parse_expr()?;
+                let expr = match self.parse_expr() {
+                    Ok(expr) => expr,
+                    Err(e) => {
+                        self.recover_from_error(e);
+                        continue;
+                    }
+                };
+                if self.is_not_end() && !self.cur_is_newline() {
+                    let error = ParsingError::ExprError(format!(
+                        "Newline or EOF expected after standalone expr, instead found {:?}",
+                        self.cur()
+                    ));
+                    self.recover_from_error(error);
+                    continue;
+                }
                 results.push(AST::StandaloneExpr(expr.resulting_expr));
                 let popped = self.pop_stack();
                 //correct indentation found: commit
                 self.set_cur(&popped);
                 parsed_successfully = true;
-                assert!(
-                    !self.is_not_end() || self.cur_is_newline(),
-                    "Newline or EOF expected after standalone expr, instead found {:?}",
-                    self.cur()
-                );
             }
 
             if !parsed_successfully {
@@ -1015,7 +1448,7 @@ impl Parser {
             }
         }
 
-        return Ok(results);
+        return results;
     }
     /**
     *
@@ -1027,7 +1460,7 @@ impl Parser {
 
     fn index_access_helper(&mut self, expr_list_or_array: &Expr) -> Result<Expr, ParsingError> {
         if let Token::CloseParen = self.cur() {
-            panic!("Invalid syntax: must inform index value");
+            return Err(ParsingError::ExprError("Invalid syntax: must inform index value".into()));
         } else {
             self.new_stack();
             let list_of_exprs = self.parse_comma_sep_list_expr();
@@ -1035,16 +1468,37 @@ impl Parser {
             match list_of_exprs {
                 //try parse stuff
                 Ok(expressions) => {
-                    //commit the result
-                    let popped = self.pop_stack();
                     let mut resulting_exprs = expressions.resulting_expr_list;
                     if resulting_exprs.len() > 1 {
-                        panic!("Invalid syntax: must inform only one index");
+                        self.pop_stack();
+                        return Err(ParsingError::ExprError("Invalid syntax: must inform only one index".into()));
                     }
+                    let index = resulting_exprs.pop().unwrap();
 
+                    //a[start:end], both bounds are required
+                    if let Token::Colon = self.cur() {
+                        self.next();
+                        let end = match self.parse_expr() {
+                            Ok(end) => end.resulting_expr,
+                            Err(e) => {
+                                self.pop_stack();
+                                return Err(e);
+                            }
+                        };
+                        let popped = self.pop_stack();
+                        self.set_cur(&popped);
+                        return Ok(Expr::SliceAccess(
+                            Box::new(expr_list_or_array.clone()),
+                            Box::new(index),
+                            Box::new(end),
+                        ));
+                    }
+
+                    //commit the result
+                    let popped = self.pop_stack();
                     let fcall = Expr::IndexAccess(
                         Box::new(expr_list_or_array.clone()),
-                        Box::new(resulting_exprs.pop().unwrap()),
+                        Box::new(index),
                     );
 
                     self.set_cur(&popped);
@@ -1260,6 +1714,24 @@ impl Parser {
                             }
                         }
                     }
+                    Token::Identifier(identifier_str) if identifier_str == "sizeof"
+                        && matches!(self.cur_offset_opt(1), Some(Token::Operator(Operator::Less))) => {
+                        let sizeof = self.parse_sizeof()?;
+                        self.push_operand(sizeof);
+                        was_operand = true;
+                    }
+                    Token::Identifier(identifier_str) if identifier_str == "cast"
+                        && matches!(self.cur_offset_opt(1), Some(Token::Operator(Operator::Less))) => {
+                        let (typ, casted) = self.parse_type_conversion("cast")?;
+                        self.push_operand(Expr::Cast(typ, Box::new(casted)));
+                        was_operand = true;
+                    }
+                    Token::Identifier(identifier_str) if identifier_str == "reinterpret"
+                        && matches!(self.cur_offset_opt(1), Some(Token::Operator(Operator::Less))) => {
+                        let (typ, reinterpreted) = self.parse_type_conversion("reinterpret")?;
+                        self.push_operand(Expr::Reinterpret(typ, Box::new(reinterpreted)));
+                        was_operand = true;
+                    }
                     Token::Identifier(identifier_str) => {
                         self.push_operand(Expr::Variable(identifier_str.to_string()));
                         was_operand = true;
@@ -1396,6 +1868,31 @@ impl Parser {
                             );
                         }
                     }
+                    //a < b < c: the lhs is an unparenthesized comparison, so this becomes a chain.
+                    //(a < b) < c is still Parenthesized at this point and is not chained.
+                    if is_comparison(op) {
+                        match lhs_root {
+                            Expr::BinaryOperation(lhs_down, op_down, rhs_down) if is_comparison(op_down) => {
+                                bin_op = Expr::ComparisonChain(
+                                    vec![*lhs_down, *rhs_down, rhs_root],
+                                    vec![op_down, op],
+                                );
+                            }
+                            Expr::ComparisonChain(mut operands, mut ops) => {
+                                operands.push(rhs_root);
+                                ops.push(op);
+                                bin_op = Expr::ComparisonChain(operands, ops);
+                            }
+                            _ => {}
+                        }
+                    } else if let Expr::ComparisonChain(mut operands, ops) = lhs_root {
+                        //a < b < c * 2: the higher precedence operator binds to the last operand
+                        if precedence(op) > 1 {
+                            let last = operands.pop().unwrap();
+                            operands.push(Expr::BinaryOperation(Box::new(last), op, Box::new(rhs_root)));
+                            bin_op = Expr::ComparisonChain(operands, ops);
+                        }
+                    }
                     self.push_operand(bin_op);
                 }
             }
@@ -1438,10 +1935,61 @@ impl Parser {
         })
     }
 
+    //sizeof<type>(), leaves the cursor on the close paren
+    fn parse_sizeof(&mut self) -> Result<Expr, ParsingError> {
+        self.next(); //skip sizeof
+        self.next(); //skip <
+        let Some(typ) = self.parse_type_name()? else {
+            return Err(ParsingError::ExprError("Expected type name in sizeof".into()));
+        };
+        self.next();
+        let Token::Operator(Operator::Greater) = self.cur() else {
+            return Err(ParsingError::ExprError("Expected > after type name in sizeof".into()));
+        };
+        self.next();
+        let Token::OpenParen = self.cur() else {
+            return Err(ParsingError::ExprError("Expected ( after sizeof<type>".into()));
+        };
+        self.next();
+        let Token::CloseParen = self.cur() else {
+            return Err(ParsingError::ExprError("sizeof<type>() takes no arguments".into()));
+        };
+        Ok(Expr::SizeOf(typ))
+    }
+
+    //cast<type>(expr) or reinterpret<type>(expr), leaves the cursor on the close paren
+    fn parse_type_conversion(&mut self, intrinsic: &str) -> Result<(ASTType, Expr), ParsingError> {
+        self.next(); //skip cast/reinterpret
+        self.next(); //skip <
+        let Some(typ) = self.parse_type_name()? else {
+            return Err(ParsingError::ExprError(format!("Expected type name in {intrinsic}")));
+        };
+        self.next();
+        let Token::Operator(Operator::Greater) = self.cur() else {
+            return Err(ParsingError::ExprError(format!("Expected > after type name in {intrinsic}")));
+        };
+        self.next();
+        let Token::OpenParen = self.cur() else {
+            return Err(ParsingError::ExprError(format!("Expected ( after {intrinsic}<type>")));
+        };
+        self.new_stack();
+        self.next();
+        let converted = self.parse_expr()?.resulting_expr;
+        let popped = self.pop_stack();
+        self.set_cur(&popped);
+        let Token::CloseParen = self.cur() else {
+            return Err(ParsingError::ExprError(format!("{intrinsic}<type>(expr) takes a single expression")));
+        };
+        Ok((typ, converted))
+    }
+
     //expr, expr, ..., expr
     fn parse_comma_sep_list_expr(&mut self) -> Result<ParseListExpressionResult, ParsingError> {
         let mut expressions = vec![];
         loop {
+            if let Token::Comma = self.cur() {
+                return Err(ParsingError::ExprError(String::from("While parsing list of expressions: expected expression, found comma")));
+            }
             let parse_result = self.parse_expr();
 
             match parse_result {
@@ -1457,6 +2005,10 @@ impl Parser {
             if self.can_go() {
                 if let Token::Comma = self.cur() {
                     self.next();
+                    //a single trailing comma is allowed before the closing delimiter
+                    if let Token::CloseParen | Token::CloseArrayBracket = self.cur() {
+                        break;
+                    }
                     continue;
                 } else {
                     break;
@@ -1483,12 +2035,12 @@ struct ParseListExpressionResult {
 
 pub struct ParseExpressionResult {
     //remaining_tokens: Vec<&'a Token>,
-    resulting_expr: Expr,
+    pub resulting_expr: Expr,
 }
 
-pub fn parse_ast(tokens: Vec<Token>) -> Vec<AST> {
+pub fn parse_ast(tokens: Vec<Token>) -> Result<Vec<AST>, Vec<ParsingError>> {
     let mut parser = Parser::new(tokens);
-    return parser.parse_ast().unwrap();
+    return parser.parse_ast();
 }
 
 #[cfg(test)]
@@ -1526,7 +2078,7 @@ x = 'abc' + 'cde'
 y = x + str(True)",
         )
         .unwrap();
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
         let expected = vec![
             AST::Assign {
                 path: vec![String::from("x")],
@@ -1563,7 +2115,7 @@ while True:
         )
         .unwrap();
 
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
         let expected = vec![AST::WhileStatement {
             expression: Expr::BooleanValue(true),
             body: vec![
@@ -1588,7 +2140,7 @@ while True:
         )
         .unwrap();
 
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
         let expected = vec![AST::WhileStatement {
             expression: Expr::BinaryOperation(
                 Box::new(Expr::Variable("x".to_string())),
@@ -1630,7 +2182,7 @@ print(x)
         )
         .unwrap();
 
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
         let expected = vec![
             AST::IfStatement {
                 true_branch: ASTIfStatement {
@@ -1704,7 +2256,7 @@ print(x)
 
         let tokens = tokenize(source_replaced.as_str()).unwrap();
 
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
         let expected = vec![
             AST::IfStatement {
                 true_branch: ASTIfStatement {
@@ -1758,7 +2310,7 @@ if x == 0:
     x = x + 1",
         )
         .unwrap();
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
         let expected = vec![AST::IfStatement {
             true_branch: ASTIfStatement {
                 expression: Expr::BinaryOperation(
@@ -1781,6 +2333,37 @@ if x == 0:
         assert_eq!(expected, result);
     }
 
+    #[test]
+    fn if_elif_else_statement() {
+        let tokens = tokenize(
+            "
+if x:
+    y = 1
+elif z:
+    y = 2
+else:
+    y = 3",
+        )
+        .unwrap();
+        let result = parse_ast(tokens).unwrap();
+        let assign_y = |value| AST::Assign {
+            path: vec![String::from("y")],
+            expression: Expr::IntegerValue(value),
+        };
+        let expected = vec![AST::IfStatement {
+            true_branch: ASTIfStatement {
+                expression: Expr::Variable(String::from("x")),
+                statements: vec![assign_y(1)],
+            },
+            elifs: vec![ASTIfStatement {
+                expression: Expr::Variable(String::from("z")),
+                statements: vec![assign_y(2)],
+            }],
+            final_else: Some(vec![assign_y(3)]),
+        }];
+        assert_eq!(expected, result);
+    }
+
     #[test]
     fn if_statement_with_print_after() {
         let tokens = tokenize(
@@ -1789,7 +2372,7 @@ if x == 0:
 print(x)",
         )
         .unwrap();
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
         let expected = vec![
             AST::IfStatement {
                 true_branch: ASTIfStatement {
@@ -1826,7 +2409,7 @@ y = x + str(True)
 print(y)",
         )
         .unwrap();
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
         let expected = vec![
             AST::Assign {
                 path: vec![String::from("x")],
@@ -2270,6 +2853,36 @@ print(y)",
         assert_eq!(expected, result);
     }
 
+    #[test]
+    fn function_call_with_many_params_trailing_comma() {
+        let tokens = tokenize("some_identifier(1, 2, 3,)").unwrap();
+        let result = parse(tokens);
+        let expected = Expr::FunctionCall(
+            Box::new(Expr::Variable(String::from("some_identifier"))),
+            vec![
+                Expr::IntegerValue(1),
+                Expr::IntegerValue(2),
+                Expr::IntegerValue(3),
+            ],
+        );
+
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn function_call_double_comma_is_rejected() {
+        let tokens = tokenize("some_identifier(1,, 2)").unwrap();
+        let mut parser = Parser::new(tokens);
+        assert!(parser.parse_expr().is_err());
+    }
+
+    #[test]
+    fn function_call_leading_comma_is_rejected() {
+        let tokens = tokenize("some_identifier(, 1)").unwrap();
+        let mut parser = Parser::new(tokens);
+        assert!(parser.parse_expr().is_err());
+    }
+
     #[test]
     fn function_call_with_expression() {
         let tokens = tokenize("some_identifier(1 * 2)").unwrap();
@@ -2734,7 +3347,7 @@ print(y)",
     #[test]
     fn test_assign() {
         let tokens = tokenize("x = 1").unwrap();
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
         let expected = vec![AST::Assign {
             path: vec![String::from("x")],
             expression: Expr::IntegerValue(1),
@@ -2742,10 +3355,22 @@ print(y)",
         assert_eq!(expected, result);
     }
 
+    #[test]
+    fn test_compound_assign() {
+        let tokens = tokenize("x.y += 1").unwrap();
+        let result = parse_ast(tokens).unwrap();
+        let expected = vec![AST::CompoundAssign {
+            path: vec![String::from("x"), String::from("y")],
+            operator: Operator::Plus,
+            expression: Expr::IntegerValue(1),
+        }];
+        assert_eq!(expected, result);
+    }
+
     #[test]
     fn test_parse_ast_first_token_is_identifier() {
         let tokens = tokenize("x * 1").unwrap();
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
         let expected = vec![AST::StandaloneExpr(Expr::BinaryOperation(
             Box::new(Expr::Variable(String::from("x"))),
             Operator::Multiply,
@@ -2757,7 +3382,7 @@ print(y)",
     #[test]
     fn test_parse_assign_expr() {
         let tokens = tokenize("x = x * 1").unwrap();
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
         let expected = vec![AST::Assign {
             path: vec![String::from("x")],
             expression: Expr::BinaryOperation(
@@ -2772,7 +3397,7 @@ print(y)",
     #[test]
     fn test_parse_just_id_ast() {
         let tokens = tokenize("x").unwrap();
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
         let expected = vec![AST::StandaloneExpr(Expr::Variable(String::from("x")))];
         assert_eq!(expected, result);
     }
@@ -2814,7 +3439,7 @@ print(y)",
     #[test]
     fn assign_boolean_expr() {
         let tokens = tokenize("x = not (True and False) or (False)").unwrap();
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
         let expected = vec![AST::Assign {
             path: vec![String::from("x")],
             expression: Expr::BinaryOperation(
@@ -2837,7 +3462,7 @@ print(y)",
     #[test]
     fn assign_string_expr() {
         let tokens = tokenize("x = 'abc'").unwrap();
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
         let expected = vec![AST::Assign {
             path: vec![String::from("x")],
             expression: Expr::StringValue(String::from("abc")),
@@ -2849,7 +3474,7 @@ print(y)",
     #[test]
     fn declare_typed() {
         let tokens = tokenize("x: str = 'abc'").unwrap();
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
         let expected = vec![AST::Declare {
             var: TypeBoundName::simple("x", "str"),
             expression: Expr::StringValue(String::from("abc")),
@@ -2861,7 +3486,7 @@ print(y)",
     #[test]
     fn assign_string_concat_expr() {
         let tokens = tokenize("x = 'abc' + 'cde'").unwrap();
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
         let expected = vec![AST::Assign {
             path: vec![String::from("x")],
             expression: Expr::BinaryOperation(
@@ -2887,6 +3512,74 @@ print(y)",
         assert_eq!(expected, result);
     }
 
+    #[test]
+    fn array_of_ints_trailing_comma() {
+        let tokens = tokenize("[1,2,3,]").unwrap();
+        let result = parse(tokens);
+        let expected = Expr::Array(vec![
+            Expr::IntegerValue(1),
+            Expr::IntegerValue(2),
+            Expr::IntegerValue(3),
+        ]);
+
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn array_double_comma_is_rejected() {
+        let tokens = tokenize("[1,2,,3]").unwrap();
+        let mut parser = Parser::new(tokens);
+        assert!(parser.parse_expr().is_err());
+    }
+
+    #[test]
+    fn array_leading_comma_is_rejected() {
+        let tokens = tokenize("[,1,2]").unwrap();
+        let mut parser = Parser::new(tokens);
+        assert!(parser.parse_expr().is_err());
+    }
+
+    #[test]
+    fn sizeof_simple_type() {
+        let tokens = tokenize("sizeof<i32>()").unwrap();
+        let result = parse(tokens);
+        let expected = Expr::SizeOf(ASTType::Simple("i32".into()));
+
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn cast_of_expression() {
+        let tokens = tokenize("cast<f64>(x + 1) * 2").unwrap();
+        let result = parse(tokens);
+        let expected = Expr::BinaryOperation(
+            Box::new(Expr::Cast(
+                ASTType::Simple("f64".into()),
+                Box::new(Expr::BinaryOperation(
+                    Box::new(Expr::Variable("x".into())),
+                    Operator::Plus,
+                    Box::new(Expr::IntegerValue(1)),
+                )),
+            )),
+            Operator::Multiply,
+            Box::new(Expr::IntegerValue(2)),
+        );
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn sizeof_in_binop() {
+        let tokens = tokenize("sizeof<i32>() * 2").unwrap();
+        let result = parse(tokens);
+        let expected = Expr::BinaryOperation(
+            Box::new(Expr::SizeOf(ASTType::Simple("i32".into()))),
+            Operator::Multiply,
+            Expr::new_int(2),
+        );
+
+        assert_eq!(expected, result);
+    }
+
     #[test]
     fn array_of_strings() {
         let tokens = tokenize("[\"one\",\"two\",\"3\"]").unwrap();
@@ -2917,7 +3610,7 @@ print(y)",
     #[test]
     fn assign_array() {
         let tokens = tokenize("x = [1, 2]").unwrap();
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
         let expr = Expr::Array(vec![Expr::IntegerValue(1), Expr::IntegerValue(2)]);
         let expected = vec![AST::Assign {
             path: vec![String::from("x")],
@@ -2930,7 +3623,7 @@ print(y)",
     fn member_acessor() {
         let tokens = tokenize("obj.prop").unwrap();
         println!("{:?}", tokens);
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
         let expected = vec![AST::StandaloneExpr(Expr::MemberAccess(
             Box::new(Expr::Variable("obj".into())),
             "prop".into(),
@@ -2942,7 +3635,7 @@ print(y)",
     fn assign_member() {
         let tokens = tokenize("obj.prop = 1").unwrap();
         println!("{:?}", tokens);
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
         let expected = vec![AST::Assign {
             path: vec!["obj".into(), "prop".into()],
             expression: Expr::IntegerValue(1),
@@ -2954,7 +3647,7 @@ print(y)",
     fn member_compare() {
         let tokens = tokenize("self.current >= self.max").unwrap();
         println!("{:?}", tokens);
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
         let expected = vec![AST::StandaloneExpr(
             Expr::BinaryOperation(
                 Box::new(Expr::MemberAccess(
@@ -2980,7 +3673,7 @@ for item in list:
 ",
         )
         .unwrap();
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
         let expected = vec![AST::ForStatement {
             item_name: "item".into(),
             list_expression: Expr::Variable("list".into()),
@@ -3001,15 +3694,17 @@ def function(x: i32):
 ",
         )
         .unwrap();
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
         let expected = vec![AST::DeclareFunction {
             function_name: "function".into(),
+            type_parameters: vec![],
             parameters: vec![TypeBoundName::simple("x", "i32")],
             body: vec![AST::StandaloneExpr(Expr::FunctionCall(
                 Box::new(Expr::Variable("print".into())),
                 vec![Expr::Variable("x".into())],
             ))],
-            return_type: None
+            return_type: None,
+            visibility: Visibility::Private
         }];
         assert_eq!(expected, result);
     }
@@ -3023,15 +3718,17 @@ def function():
 ",
         )
         .unwrap();
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
         let expected = vec![AST::DeclareFunction {
             function_name: "function".into(),
+            type_parameters: vec![],
             parameters: vec![],
             body: vec![AST::StandaloneExpr(Expr::FunctionCall(
                 Box::new(Expr::Variable("print".into())),
                 vec![Expr::Variable("x".into())],
             ))],
-            return_type: None
+            return_type: None,
+            visibility: Visibility::Private
         }];
         assert_eq!(expected, result);
     }
@@ -3045,9 +3742,10 @@ def function(x: i32,y: u32,z: MyType):
 ",
         )
         .unwrap();
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
         let expected = vec![AST::DeclareFunction {
             function_name: "function".into(),
+            type_parameters: vec![],
             parameters: vec![
                 TypeBoundName::simple("x", "i32"),
                 TypeBoundName::simple("y", "u32"),
@@ -3056,26 +3754,160 @@ def function(x: i32,y: u32,z: MyType):
                 Box::new(Expr::Variable("print".into())),
                 vec![Expr::Variable("x".into())],
             ))],
-            return_type: None
+            return_type: None,
+            visibility: Visibility::Private
         }];
         assert_eq!(expected, result);
     }
 
     #[test]
-    fn return_nothing() {
+    fn function_decl_manyparams_trailing_comma() {
         let tokens = tokenize(
             "
-def function(x: i32):
-    return
+def function(x: i32, y: u32,):
+    print(x)
 ",
         )
         .unwrap();
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
         let expected = vec![AST::DeclareFunction {
             function_name: "function".into(),
-            parameters: vec![TypeBoundName::simple("x", "i32")],
-            body: vec![AST::Return(None)],
-            return_type: None
+            type_parameters: vec![],
+            parameters: vec![
+                TypeBoundName::simple("x", "i32"),
+                TypeBoundName::simple("y", "u32")],
+            body: vec![AST::StandaloneExpr(Expr::FunctionCall(
+                Box::new(Expr::Variable("print".into())),
+                vec![Expr::Variable("x".into())],
+            ))],
+            return_type: None,
+            visibility: Visibility::Private
+        }];
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn function_decl_double_comma_is_rejected() {
+        let tokens = tokenize(
+            "
+def function(x: i32,, y: u32):
+    print(x)
+",
+        )
+        .unwrap();
+        let errors = parse_ast(tokens).unwrap_err();
+        let messages = errors.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+        assert_eq!(messages, vec!["Expected close paren after parameters in function declaration function, got Some(Comma)"]);
+    }
+
+    #[test]
+    fn function_decl_leading_comma_is_rejected() {
+        let tokens = tokenize(
+            "
+def function(, x: i32):
+    print(x)
+",
+        )
+        .unwrap();
+        let errors = parse_ast(tokens).unwrap_err();
+        let messages = errors.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+        assert_eq!(messages, vec!["Expected close paren after parameters in function declaration function, got Some(Comma)"]);
+    }
+
+    #[test]
+    fn reports_all_syntax_errors() {
+        let tokens = tokenize(
+            "
+x = 1 2
+y = 2
+def function():
+    return 3 4
+    z = 4
+",
+        )
+        .unwrap();
+        let mut parser = Parser::new(tokens);
+        let errors = parser.parse_ast().unwrap_err();
+        let messages = errors.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+        assert_eq!(messages, vec![
+            "Unparsed operands: [IntegerValue(1), IntegerValue(2)]",
+            "Unparsed operands: [IntegerValue(3), IntegerValue(4)]"
+        ]);
+    }
+
+    #[test]
+    fn error_in_block_header_skips_its_body() {
+        let tokens = tokenize(
+            "
+def function(x: i32
+    if x == 1
+        return 1
+    return 2
+while True
+    x = 1
+raise
+y = 2
+",
+        )
+        .unwrap();
+        let mut parser = Parser::new(tokens);
+        let errors = parser.parse_ast().unwrap_err();
+        let messages = errors.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+        assert_eq!(messages, vec![
+            "Expected close paren after parameters in function declaration function, got Some(NewLine)",
+            "Expected colon after while expr, got Some(NewLine)",
+            "Expected expression after raise keyword"
+        ]);
+    }
+
+    #[test]
+    fn empty_if_body_is_an_error() {
+        let tokens = tokenize(
+            "
+def function():
+    if True:
+    x = 1
+",
+        )
+        .unwrap();
+        let mut parser = Parser::new(tokens);
+        let errors = parser.parse_ast().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(&errors[0], ParsingError::EmptyBlock(msg) if msg == "if body must have at least one statement"));
+    }
+
+    #[test]
+    fn array_length_that_does_not_fit_in_u32_is_an_error() {
+        let tokens = tokenize(
+            "
+def function():
+    x: array<i32, 99999999999> = [1]
+",
+        )
+        .unwrap();
+        let mut parser = Parser::new(tokens);
+        let errors = parser.parse_ast().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(&errors[0], ParsingError::InvalidType(msg) if msg == "Constant generic argument 99999999999 does not fit in a u32"));
+    }
+
+    #[test]
+    fn return_nothing() {
+        let tokens = tokenize(
+            "
+def function(x: i32):
+    return
+",
+        )
+        .unwrap();
+        let result = parse_ast(tokens).unwrap();
+        let expected = vec![AST::DeclareFunction {
+            function_name: "function".into(),
+            type_parameters: vec![],
+            parameters: vec![TypeBoundName::simple("x", "i32")],
+            body: vec![AST::Return(None)],
+            return_type: None,
+            visibility: Visibility::Private
         }];
         assert_eq!(expected, result);
     }
@@ -3089,16 +3921,18 @@ def function(x: i32) -> i32:
 ",
         )
         .unwrap();
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
         let expected = vec![AST::DeclareFunction {
             function_name: "function".into(),
+            type_parameters: vec![],
             parameters: vec![TypeBoundName::simple("x", "i32")],
             body: vec![AST::Return(Some(Expr::BinaryOperation(
                 Box::new(Expr::Variable("x".into())),
                 Operator::Plus,
                 Box::new(Expr::IntegerValue(1)),
             )))],
-            return_type: Some(ASTType::Simple("i32".into()))
+            return_type: Some(ASTType::Simple("i32".into())),
+            visibility: Visibility::Private
         }];
         assert_eq!(expected, result);
     }
@@ -3111,7 +3945,7 @@ some_var : List<i32> = [1, 2]
 ",
         )
         .unwrap();
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
 
         assert_eq!(result, vec![
             AST::Declare {
@@ -3122,6 +3956,113 @@ some_var : List<i32> = [1, 2]
 
     }
 
+    #[test]
+    fn fixed_size_array_type() {
+        let tokens = tokenize(
+            "
+some_var : array<i32, 2> = [1, 2]
+",
+        )
+        .unwrap();
+        let result = parse_ast(tokens).unwrap();
+
+        assert_eq!(result, vec![
+            AST::Declare {
+                var: TypeBoundName {
+                    name: "some_var".into(),
+                    name_type: ASTType::Generic("array".into(), vec![ASTType::Simple("i32".into()), ASTType::Const(2)])
+                },
+                expression: Expr::Array(vec![Expr::IntegerValue(1), Expr::IntegerValue(2)])
+            }
+        ]);
+    }
+
+    #[test]
+    fn nested_generic_type_closed_with_shift_right() {
+        let tokens = tokenize(
+            "
+some_var : array<array<i32>> = [[1]]
+size = sizeof<box<array<i64, 2>>>()
+",
+        )
+        .unwrap();
+        let result = parse_ast(tokens).unwrap();
+
+        let array_of_i32 = ASTType::Generic("array".into(), vec![ASTType::Simple("i32".into())]);
+        let array_of_i64 = ASTType::Generic("array".into(), vec![ASTType::Simple("i64".into()), ASTType::Const(2)]);
+        assert_eq!(result, vec![
+            AST::Declare {
+                var: TypeBoundName {
+                    name: "some_var".into(),
+                    name_type: ASTType::Generic("array".into(), vec![array_of_i32])
+                },
+                expression: Expr::Array(vec![Expr::Array(vec![Expr::IntegerValue(1)])])
+            },
+            AST::Assign {
+                path: vec!["size".into()],
+                expression: Expr::SizeOf(ASTType::Generic("box".into(), vec![array_of_i64]))
+            }
+        ]);
+    }
+
+    #[test]
+    fn malformed_generic_type_is_an_error() {
+        let tokens = tokenize(
+            "
+def function():
+    x: array<i32 i64> = [1]
+",
+        )
+        .unwrap();
+        let mut parser = Parser::new(tokens);
+        let errors = parser.parse_ast().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(&errors[0], ParsingError::InvalidType(msg)
+            if msg == "Expected , or > in generic type arguments of array, found Some(Identifier(\"i64\"))"));
+    }
+
+    #[test]
+    fn struct_followed_by_statement_without_blank_line() {
+        let tokens = tokenize(
+            "
+struct Point:
+    x: i32
+pub def origin() -> i32:
+    return 0
+",
+        )
+        .unwrap();
+        let result = parse_ast(tokens).unwrap();
+
+        assert_eq!(result.len(), 2);
+        assert!(matches!(&result[0], AST::StructDeclaration { body, .. } if body.len() == 1));
+        assert!(matches!(&result[1], AST::DeclareFunction { function_name, visibility: Visibility::Public, .. } if function_name == "origin"));
+    }
+
+    #[test]
+    fn struct_field_at_end_of_file() {
+        let tokens = tokenize("struct Point:\n    x: i32").unwrap();
+        let result = parse_ast(tokens).unwrap();
+
+        assert!(matches!(&result[0], AST::StructDeclaration { body, .. } if body == &vec![TypeBoundName::simple("x", "i32")]));
+    }
+
+    #[test]
+    fn struct_field_without_type_is_an_error() {
+        let tokens = tokenize(
+            "
+struct Point:
+    x: i32
+    y
+z = 1
+",
+        )
+        .unwrap();
+        let errors = parse_ast(tokens).unwrap_err();
+        let messages = errors.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+        assert_eq!(messages, vec!["Expected field declaration in struct Point, got Identifier(\"y\")"]);
+    }
+
     #[test]
     fn struct_definition_and_then_method() {
         let tokens = tokenize(
@@ -3135,18 +4076,21 @@ def my_function(arg1: i32, arg2: i32) -> i32:
 ",
         )
         .unwrap();
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
        
         assert_eq!(result, vec![
             AST::StructDeclaration { 
                 struct_name: "Struct1".into(), 
+                type_parameters: vec![],
                 body: vec![
                     TypeBoundName::simple("field1", "i32"),
                     TypeBoundName::simple("field2", "i64")
-                ]
+                ],
+                visibility: Visibility::Private
             }, 
             AST::DeclareFunction { 
                 function_name: "my_function".into(), 
+                type_parameters: vec![],
                 parameters: vec![
                     TypeBoundName::simple("arg1", "i32"),
                     TypeBoundName::simple("arg2", "i32")
@@ -3168,12 +4112,125 @@ def my_function(arg1: i32, arg2: i32) -> i32:
                         )
                     )
                 )], 
-                return_type: Some(ASTType::Simple("i32".into()))
+                return_type: Some(ASTType::Simple("i32".into())),
+                visibility: Visibility::Private
             }
         ]);
     }
 
 
+    #[test]
+    fn type_alias_then_declaration() {
+        let tokens = tokenize(
+            "
+type Ints = array<i32>
+x: Ints = [1]
+",
+        )
+        .unwrap();
+        let result = parse_ast(tokens).unwrap();
+        let expected = vec![
+            AST::TypeAlias {
+                alias_name: "Ints".into(),
+                target: ASTType::Generic("array".into(), vec![ASTType::Simple("i32".into())])
+            },
+            AST::Declare {
+                var: TypeBoundName::simple("x", "Ints"),
+                expression: Expr::Array(vec![Expr::IntegerValue(1)])
+            }
+        ];
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn const_declaration() {
+        let tokens = tokenize("const MAX: i32 = 100").unwrap();
+        let result = parse_ast(tokens).unwrap();
+        let expected = vec![AST::DeclareConst {
+            var: TypeBoundName::simple("MAX", "i32"),
+            expression: Expr::IntegerValue(100)
+        }];
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn return_multiple_values() {
+        let tokens = tokenize("return a, b + 1").unwrap();
+        let result = parse_ast(tokens).unwrap();
+        let expected = vec![AST::Return(Some(Expr::Tuple(vec![
+            Expr::Variable("a".into()),
+            Expr::BinaryOperation(
+                Box::new(Expr::Variable("b".into())),
+                Operator::Plus,
+                Box::new(Expr::IntegerValue(1))
+            )
+        ])))];
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn chained_comparison() {
+        let tokens = tokenize("1 < x <= 10").unwrap();
+        let result = parse_ast(tokens).unwrap();
+        let expected = vec![AST::StandaloneExpr(Expr::ComparisonChain(
+            vec![
+                Expr::IntegerValue(1),
+                Expr::Variable("x".into()),
+                Expr::IntegerValue(10)
+            ],
+            vec![Operator::Less, Operator::LessEquals]
+        ))];
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn parenthesized_comparison_is_not_chained() {
+        let tokens = tokenize("(1 < x) == y").unwrap();
+        let result = parse_ast(tokens).unwrap();
+        let expected = vec![AST::StandaloneExpr(Expr::BinaryOperation(
+            Box::new(Expr::BinaryOperation(
+                Box::new(Expr::IntegerValue(1)),
+                Operator::Less,
+                Box::new(Expr::Variable("x".into()))
+            )),
+            Operator::Equals,
+            Box::new(Expr::Variable("y".into()))
+        ))];
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn extern_function_declaration() {
+        let tokens = tokenize(
+            "
+extern def log(x: str) -> i32
+def main():
+    log(\"hi\")
+",
+        )
+        .unwrap();
+        let result = parse_ast(tokens).unwrap();
+        let expected = vec![
+            AST::DeclareExternFunction {
+                function_name: "log".into(),
+                parameters: vec![TypeBoundName::simple("x", "str")],
+                return_type: Some(ASTType::Simple("i32".into()))
+            },
+            AST::DeclareFunction {
+                function_name: "main".into(),
+                type_parameters: vec![],
+                parameters: vec![],
+                body: vec![AST::StandaloneExpr(Expr::FunctionCall(
+                    Box::new(Expr::Variable("log".into())),
+                    vec![Expr::StringValue("hi".into())]
+                ))],
+                return_type: None,
+                visibility: Visibility::Private
+            }
+        ];
+        assert_eq!(expected, result);
+    }
+
     #[test]
     fn struct_definition() {
         let tokens = tokenize(
@@ -3184,21 +4241,233 @@ struct SomeStruct:
 ",
         )
         .unwrap();
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
         let expected = vec![AST::StructDeclaration {
             struct_name: "SomeStruct".into(),
+            type_parameters: vec![],
             body: vec![
                 TypeBoundName::simple("field", "i32"),
                 TypeBoundName::simple("otherfield", "str")
-            ]
+            ],
+            visibility: Visibility::Private
         }];
         assert_eq!(expected, result);
     }
 
+    #[test]
+    fn impl_block_definition() {
+        let tokens = tokenize(
+            "
+impl<T> box<T>:
+    def get() -> T:
+        return self.first
+",
+        )
+        .unwrap();
+        let result = parse_ast(tokens).unwrap();
+        let expected = vec![AST::ImplBlock {
+            type_parameters: vec!["T".into()],
+            target: ASTType::Generic("box".into(), vec![ASTType::Simple("T".into())]),
+            methods: vec![AST::DeclareFunction {
+                function_name: "get".into(),
+                type_parameters: vec![],
+                parameters: vec![],
+                body: vec![AST::Return(Some(Expr::MemberAccess(
+                    Box::new(Expr::Variable("self".into())),
+                    "first".into()
+                )))],
+                return_type: Some(ASTType::Simple("T".into())),
+                visibility: Visibility::Private
+            }]
+        }];
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn generic_function_definition() {
+        let tokens = tokenize(
+            "
+def first<T, U>(xs: array<T>, other: U) -> T:
+    return xs[0]
+",
+        )
+        .unwrap();
+        let result = parse_ast(tokens).unwrap();
+        let expected = vec![AST::DeclareFunction {
+            function_name: "first".into(),
+            type_parameters: vec!["T".into(), "U".into()],
+            parameters: vec![
+                TypeBoundName {
+                    name: "xs".into(),
+                    name_type: ASTType::Generic("array".into(), vec![ASTType::Simple("T".into())])
+                },
+                TypeBoundName::simple("other", "U"),
+            ],
+            body: vec![AST::Return(Some(Expr::IndexAccess(
+                Box::new(Expr::Variable("xs".into())),
+                Box::new(Expr::IntegerValue(0))
+            )))],
+            return_type: Some(ASTType::Simple("T".into())),
+            visibility: Visibility::Private
+        }];
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn generic_struct_definition() {
+        let tokens = tokenize(
+            "
+struct pair<T, U>:
+    first: T
+    second: array<U>
+",
+        )
+        .unwrap();
+        let result = parse_ast(tokens).unwrap();
+        let expected = vec![AST::StructDeclaration {
+            struct_name: "pair".into(),
+            type_parameters: vec!["T".into(), "U".into()],
+            body: vec![
+                TypeBoundName::simple("first", "T"),
+                TypeBoundName {
+                    name: "second".into(),
+                    name_type: ASTType::Generic("array".into(), vec![ASTType::Simple("U".into())])
+                }
+            ],
+            visibility: Visibility::Private
+        }];
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn function_type_parameter() {
+        let tokens = tokenize(
+            "
+def apply(f: fn(i32) -> i32, x: i32) -> i32:
+    return f(x)
+",
+        )
+        .unwrap();
+        let result = parse_ast(tokens).unwrap();
+        let expected = vec![AST::DeclareFunction {
+            function_name: "apply".into(),
+            type_parameters: vec![],
+            parameters: vec![
+                TypeBoundName {
+                    name: "f".into(),
+                    name_type: ASTType::Function(
+                        vec![ASTType::Simple("i32".into())],
+                        Box::new(ASTType::Simple("i32".into()))
+                    )
+                },
+                TypeBoundName::simple("x", "i32"),
+            ],
+            body: vec![AST::Return(Some(Expr::FunctionCall(
+                Box::new(Expr::Variable("f".into())),
+                vec![Expr::Variable("x".into())],
+            )))],
+            return_type: Some(ASTType::Simple("i32".into())),
+            visibility: Visibility::Private
+        }];
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn function_type_with_no_args_and_generic_return() {
+        let tokens = tokenize("x: fn() -> array<fn(str, u8) -> bool> = make()").unwrap();
+        let result = parse_ast(tokens).unwrap();
+        let fn_str_u8_bool = ASTType::Function(
+            vec![ASTType::Simple("str".into()), ASTType::Simple("u8".into())],
+            Box::new(ASTType::Simple("bool".into()))
+        );
+        let expected = vec![AST::Declare {
+            var: TypeBoundName {
+                name: "x".into(),
+                name_type: ASTType::Function(
+                    vec![],
+                    Box::new(ASTType::Generic("array".into(), vec![fn_str_u8_bool]))
+                )
+            },
+            expression: Expr::FunctionCall(Box::new(Expr::Variable("make".into())), vec![]),
+        }];
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn pub_function_decl() {
+        let tokens = tokenize(
+            "
+pub def function(x: i32):
+    print(x)
+",
+        )
+        .unwrap();
+        let result = parse_ast(tokens).unwrap();
+        let expected = vec![AST::DeclareFunction {
+            function_name: "function".into(),
+            type_parameters: vec![],
+            parameters: vec![TypeBoundName::simple("x", "i32")],
+            body: vec![AST::StandaloneExpr(Expr::FunctionCall(
+                Box::new(Expr::Variable("print".into())),
+                vec![Expr::Variable("x".into())],
+            ))],
+            return_type: None,
+            visibility: Visibility::Public
+        }];
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn pub_struct_definition() {
+        let tokens = tokenize(
+            "
+pub struct SomeStruct:
+    field: i32
+",
+        )
+        .unwrap();
+        let result = parse_ast(tokens).unwrap();
+        let expected = vec![AST::StructDeclaration {
+            struct_name: "SomeStruct".into(),
+            type_parameters: vec![],
+            body: vec![
+                TypeBoundName::simple("field", "i32")
+            ],
+            visibility: Visibility::Public
+        }];
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn import_then_function_decl() {
+        let tokens = tokenize(
+            "
+import other
+
+def function():
+    return
+",
+        )
+        .unwrap();
+        let result = parse_ast(tokens).unwrap();
+        let expected = vec![
+            AST::Import("other".into()),
+            AST::DeclareFunction {
+                function_name: "function".into(),
+                type_parameters: vec![],
+                parameters: vec![],
+                body: vec![AST::Return(None)],
+                return_type: None,
+                visibility: Visibility::Private
+            }
+        ];
+        assert_eq!(expected, result);
+    }
+
     #[test]
     fn access_at_index() {
         let tokens = tokenize("list[1]").unwrap();
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
         let expected = vec![AST::StandaloneExpr(Expr::IndexAccess(
             Box::new(Expr::Variable("list".into())),
             Box::new(Expr::IntegerValue(1)),
@@ -3209,7 +4478,7 @@ struct SomeStruct:
     #[test]
     fn access_at_string() {
         let tokens = tokenize("a_map[\"value\"]").unwrap();
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
         let expected = vec![AST::StandaloneExpr(Expr::IndexAccess(
             Box::new(Expr::Variable("a_map".into())),
             Box::new(Expr::StringValue("value".into())),
@@ -3217,11 +4486,27 @@ struct SomeStruct:
         assert_eq!(expected, result);
     }
 
+    #[test]
+    fn slice_access() {
+        let tokens = tokenize("arr[1:x + 1]").unwrap();
+        let result = parse_ast(tokens).unwrap();
+        let expected = vec![AST::StandaloneExpr(Expr::SliceAccess(
+            Box::new(Expr::Variable("arr".into())),
+            Box::new(Expr::IntegerValue(1)),
+            Box::new(Expr::BinaryOperation(
+                Box::new(Expr::Variable("x".into())),
+                Operator::Plus,
+                Box::new(Expr::IntegerValue(1))
+            )),
+        ))];
+        assert_eq!(expected, result);
+    }
+
     #[test]
     fn access_at_list() {
         //this is crazy
         let tokens = tokenize("a_map[[]]").unwrap();
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
         let expected = vec![AST::StandaloneExpr(Expr::IndexAccess(
             Box::new(Expr::Variable("a_map".into())),
             Box::new(Expr::Array(vec![])),
@@ -3232,7 +4517,7 @@ struct SomeStruct:
     #[test]
     fn function_return_indexed() {
         let tokens = tokenize("some_call()[1]").unwrap();
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
         let expected = vec![AST::StandaloneExpr(Expr::IndexAccess(
             Box::new(Expr::FunctionCall(
                 Box::new(Expr::Variable("some_call".into())),
@@ -3246,7 +4531,7 @@ struct SomeStruct:
     #[test]
     fn function_argument_is_indexed() {
         let tokens = tokenize("some_call(var[1])").unwrap();
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
         let expected = vec![AST::StandaloneExpr(
             Expr::FunctionCall(
                 Box::new(Expr::Variable("some_call".into())),
@@ -3262,7 +4547,7 @@ struct SomeStruct:
     #[test]
     fn method_call_empty() {
         let tokens = tokenize("method.call()").unwrap();
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
         let expected = vec![AST::StandaloneExpr(Expr::FunctionCall(
             Box::new(Expr::MemberAccess(
                 Box::new(Expr::Variable("method".into())),
@@ -3275,7 +4560,7 @@ struct SomeStruct:
     #[test]
     fn method_call_oneparam() {
         let tokens = tokenize("method.call(1)").unwrap();
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
         let expected = vec![AST::StandaloneExpr(Expr::FunctionCall(
             Box::new(Expr::MemberAccess(
                 Box::new(Expr::Variable("method".into())),
@@ -3289,7 +4574,7 @@ struct SomeStruct:
     #[test]
     fn method_call_manyparam() {
         let tokens = tokenize("method.call(1, 2)").unwrap();
-        let result = parse_ast(tokens);
+        let result = parse_ast(tokens).unwrap();
         let expected = vec![AST::StandaloneExpr(Expr::FunctionCall(
             Box::new(Expr::MemberAccess(
                 Box::new(Expr::Variable("method".into())),
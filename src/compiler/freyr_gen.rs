@@ -1,10 +1,53 @@
 use core::panic;
 use std::collections::{HashMap, HashSet};
 use crate::ast::lexer::Operator;
-use crate::freyr::asm::asm::{AssemblyInstruction, AsmArithmeticBinaryOp, AsmSignFlag, AsmLoadStoreMode, AsmIntegerBitwiseBinaryOp, AsmIntegerCompareBinaryOp};
+use crate::freyr::vm::instructions::NumberOfBytes;
+use crate::freyr::asm::asm::{AssemblyInstruction, AsmArithmeticBinaryOp, AsmSignFlag, AsmLoadStoreMode, AsmIntegerBitwiseBinaryOp, AsmIntegerCompareBinaryOp, AsmControlRegister, AsmHostFunction, AsmNumberKind, AsmShiftDirection};
+use crate::semantic::analysis::{EntryPoint, EntryPointError};
 use crate::semantic::hir::{HIRExpr, TrivialHIRExpr, TypedTrivialHIRExpr, HIRExprMetadata};
 use crate::semantic::mir::{MIRBlock, MIRBlockNode, MIRScope, MIRTopLevelNode, MIRTypedBoundName, MIRBlockFinal, BlockId};
-use crate::types::type_db::{TypeInstance, TypeDatabase, TypeSign};
+use crate::types::type_db::{TypeInstance, TypeDatabase, TypeRecord, TypeSign};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CodegenError {
+    //extern functions have no body in the program, so they only compile when the VM has a host function for them
+    ExternFunctionCalled { on_function: String, function_name: String },
+    //calling a function stored in a variable or parameter needs its address at runtime, which is not supported yet
+    IndirectCall { on_function: String, variable: String },
+    //the program starts with a jump to the entry point, so there has to be one
+    NoEntryPoint(EntryPointError),
+    //nothing fills args before the VM starts running
+    EntryPointTakesArgs { function_name: String },
+    //generic functions are not monomorphized yet, so there is no code to call
+    GenericFunctionCalled { on_function: String, function_name: String },
+    //an integer literal that does not fit its type, type inference should have rejected it already
+    LiteralOutOfRange(String),
+}
+
+impl std::fmt::Display for CodegenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CodegenError::ExternFunctionCalled { on_function, function_name } => write!(
+                f,
+                "In function {on_function}, call to extern function {function_name} cannot be compiled, the VM has no host function for it with these arguments"
+            ),
+            CodegenError::IndirectCall { on_function, variable } => write!(
+                f,
+                "In function {on_function}, call through variable {variable} cannot be compiled, only functions can be called by name"
+            ),
+            CodegenError::NoEntryPoint(error) => write!(f, "Program has no valid entry point: {error:?}"),
+            CodegenError::EntryPointTakesArgs { function_name } => write!(
+                f,
+                "Entry point {function_name} takes args, which the VM cannot pass yet"
+            ),
+            CodegenError::GenericFunctionCalled { on_function, function_name } => write!(
+                f,
+                "In function {on_function}, call to generic function {function_name} cannot be compiled yet"
+            ),
+            CodegenError::LiteralOutOfRange(message) => write!(f, "Cannot push immediate: {message}"),
+        }
+    }
+}
 
 pub struct FreyrEmitter {
     pub assembly: Vec<AssemblyInstruction>,
@@ -23,81 +66,78 @@ fn build_write_scope_byte_layout(
     all_scopes: &[MIRScope],
     type_db: &TypeDatabase
 ) -> HashMap<String, ByteRange> {
-    let original_scope = scope.index;
+    //walk up to the function scope (scope 0, which holds the parameters), then lay out
+    //from the outermost scope inwards, keeping the declaration order inside each scope
+    let mut scope_chain = vec![];
     let mut current_index = scope.index;
-    let mut found_var = vec![];
     loop {
-        let scope = &all_scopes[current_index];
-
-        for var in scope.boundnames.iter() {
-            let type_record = type_db.find(var.typename.expect_simple());
-            found_var.push((var.name.clone(), type_record.size));
-        }
-
-        current_index = scope.inherit.0;
+        scope_chain.push(&all_scopes[current_index]);
         if current_index == 0 {
             break;
         }
+        current_index = all_scopes[current_index].inherit.0;
     }
 
     let mut map: HashMap<String, ByteRange> = HashMap::new();
     let mut used_bytes = 0usize;
-    for (name, size) in found_var.into_iter().rev() {
-        map.insert(name, ByteRange { begin: used_bytes as u32, end: used_bytes as u32 + size as u32});
-        used_bytes += size;
+    for scope in scope_chain.into_iter().rev() {
+        for var in scope.boundnames.iter() {
+            let size = type_db.find(var.typename.expect_simple()).size;
+            map.insert(var.name.clone(), ByteRange { begin: used_bytes as u32, end: used_bytes as u32 + size as u32});
+            used_bytes += size;
+        }
     }
 
     map
 }
 
-//returns (upper 16 bits, lower 16 bits)
-pub fn encode_i32(raw: i128) -> ([u8; 2], [u8; 2]) {
-    let as_i32 = raw as i32;
-    let all_bytes = as_i32.to_le_bytes();
-    return (all_bytes[0 .. 2].try_into().unwrap(), all_bytes[2 .. 4].try_into().unwrap());
-}
-
-//returns (upper 16 bits, lower 16 bits)
-pub fn encode_u32(raw: i128) -> ([u8; 2], [u8; 2]) {
-    let as_u32 = raw as u32;
-    let all_bytes = as_u32.to_le_bytes();
-    return (all_bytes[0 .. 2].try_into().unwrap(), all_bytes[2 .. 4].try_into().unwrap());
-}
-
-//returns (upper 16 bits, lower 16 bits)
-pub fn encode_i64(raw: i128) -> [[u8; 2]; 4] {
-    let as_i64 = raw as i64;
-    let all_bytes = as_i64.to_le_bytes();
-    return [all_bytes[0 .. 2].try_into().unwrap(), 
-            all_bytes[2 .. 4].try_into().unwrap(),
-            all_bytes[4 .. 6].try_into().unwrap(),
-            all_bytes[6 .. 8].try_into().unwrap()];
+fn number_of_bytes(size: u8) -> NumberOfBytes {
+    match size {
+        1 => NumberOfBytes::Bytes1,
+        2 => NumberOfBytes::Bytes2,
+        4 => NumberOfBytes::Bytes4,
+        8 => NumberOfBytes::Bytes8,
+        _ => panic!("Integers of size {size} not implemented in asm generator yet"),
+    }
 }
 
-//returns (upper 16 bits, lower 16 bits)
-pub fn encode_u64(raw: i128) -> [[u8; 2]; 4] {
-    let as_u64 = raw as u64;
-    let all_bytes = as_u64.to_le_bytes();
-    return [all_bytes[0 .. 2].try_into().unwrap(), 
-            all_bytes[2 .. 4].try_into().unwrap(),
-            all_bytes[4 .. 6].try_into().unwrap(),
-            all_bytes[6 .. 8].try_into().unwrap()];
+//Narrows a literal to the little-endian bytes of the target width, negatives in two's complement.
+//Type inference already rejects literals out of range, this catches anything that slips through it.
+pub fn narrow_integer_literal(value: i128, bytes: NumberOfBytes, sign: &TypeSign) -> Result<Vec<u8>, String> {
+    let bits = bytes.get_bytes() as u32 * 8;
+    let (min, max) = match sign {
+        TypeSign::Signed => (-(1i128 << (bits - 1)), (1i128 << (bits - 1)) - 1),
+        TypeSign::Unsigned => (0, (1i128 << bits) - 1),
+    };
+    if value < min || value > max {
+        return Err(format!("{value} does not fit in {bits} bits, expected a value between {min} and {max}"));
+    }
+    Ok(value.to_le_bytes()[.. bytes.get_bytes() as usize].to_vec())
 }
 
 fn generate_trivial_expr(type_db: &TypeDatabase, expression: &TypedTrivialHIRExpr, bytecode: &mut Vec<AssemblyInstruction>,
-    scope: &HashMap<String, ByteRange>) -> u32 {
+    scope: &HashMap<String, ByteRange>) -> Result<u32, CodegenError> {
     let trivial_type = expression.1.expect_resolved();
     let size = type_db.find(trivial_type.expect_simple()).size as u8;
     match &expression.0 {
         TrivialHIRExpr::IntegerValue(v) => {
-            if size == 4 {
-                let (lower, upper) = if trivial_type == &type_db.special_types.i32{
-                    encode_i32(*v)
-                } else if trivial_type == &type_db.special_types.u32 {
-                    encode_u32(*v)
-                } else {
-                    panic!("Tried to compile immediate push of a 4-byte type, but somehow types don't match size")
-                };
+            let record = type_db.find(trivial_type.expect_simple());
+            let narrowed = narrow_integer_literal(*v, number_of_bytes(size), &record.sign)
+                .map_err(CodegenError::LiteralOutOfRange)?;
+            //goes from lower .. upper, 16 bits each, a single byte is zero-padded
+            let parts = narrowed.chunks(2)
+                .map(|chunk| [chunk[0], *chunk.get(1).unwrap_or(&0)])
+                .collect::<Vec<_>>();
+
+            if size <= 2 {
+                bytecode.push(AssemblyInstruction::PushImmediate { 
+                    bytes: size, 
+                    shift_size: 0, 
+                    immediate: parts[0]
+                })
+            }
+            else if size == 4 {
+                let (lower, upper) = (parts[0], parts[1]);
                 if upper == [0u8, 0u8] {
                     bytecode.push(AssemblyInstruction::PushImmediate { 
                         bytes: size, 
@@ -106,7 +146,7 @@ fn generate_trivial_expr(type_db: &TypeDatabase, expression: &TypedTrivialHIRExp
                     })
                 } else {
                     bytecode.push(AssemblyInstruction::PushImmediate { 
-                        bytes: type_db.find(trivial_type.expect_simple()).size as u8, 
+                        bytes: size, 
                         shift_size: 16, 
                         immediate: upper
                     });
@@ -120,35 +160,29 @@ fn generate_trivial_expr(type_db: &TypeDatabase, expression: &TypedTrivialHIRExp
                 
             }
             else if size == 8 {
-                let parts = if trivial_type == &type_db.special_types.i64{
-                    encode_i64(*v)
-                } else if trivial_type == &type_db.special_types.u64 {
-                    encode_u64(*v)
-                } else {
-                    panic!("Tried to compile immediate push of a 4-byte type, but somehow types don't match size")
-                };
-                //goes from lower .. upper
-                //set the upper first shifting as needed
+                //only the non-zero 16-bit parts need a push
+                let nonzero_parts = parts.iter().enumerate()
+                    .filter(|(_, bytes)| **bytes != [0u8, 0])
+                    .collect::<Vec<_>>();
 
-                //common case: value is positive and < 65536
-                if parts[1] == [0u8, 0] && parts[2] == [0u8, 0] && parts[3] == [0u8, 0] {
+                if nonzero_parts.len() == 0 {
                     bytecode.push(AssemblyInstruction::PushImmediate { 
                         bytes: size, 
                         shift_size: 0, 
-                        immediate: *&parts[0]
+                        immediate: [0u8, 0]
                     });
                 } else {
-                    let indexed = parts.iter().enumerate().rev();
-                    for (index, bytes) in indexed {
+                    //set the upper first shifting as needed
+                    for (index, bytes) in nonzero_parts.iter().rev() {
                         let shift = index * 16; //0, 16, 32, 48
                         bytecode.push(AssemblyInstruction::PushImmediate { 
                             bytes: size, 
                             shift_size: shift as u8, 
-                            immediate: *bytes
+                            immediate: **bytes
                         });
                     }
                     //sum everything on stack
-                    for _ in 0 .. parts.len() - 1 {
+                    for _ in 0 .. nonzero_parts.len() - 1 {
                         bytecode.push(AssemblyInstruction::IntegerArithmeticBinaryOperation {
                             bytes: size,
                             immediate: None,
@@ -157,10 +191,8 @@ fn generate_trivial_expr(type_db: &TypeDatabase, expression: &TypedTrivialHIRExp
                         })
                     }
                 }
-            } else {
-                todo!("Integers of size {size} not implemented in asm generator yet")
             }
-            return size as u32
+            return Ok(size as u32)
         },
         TrivialHIRExpr::FloatValue(_) => todo!("Floats not implemented in asm generator yet"),
         TrivialHIRExpr::StringValue(_) => todo!("Strings not implemented in asm generator yet"),
@@ -170,7 +202,7 @@ fn generate_trivial_expr(type_db: &TypeDatabase, expression: &TypedTrivialHIRExp
                 shift_size: 0, 
                 immediate: if *v { 1u16.to_le_bytes() } else { 0u16.to_le_bytes() }
             });
-            return 1;
+            return Ok(1);
         },
         TrivialHIRExpr::Variable(var) => {
             //emit a loadaddr_relY bp+X where Y = size in bits, X = start of the value 
@@ -181,7 +213,7 @@ fn generate_trivial_expr(type_db: &TypeDatabase, expression: &TypedTrivialHIRExp
                     offset: var_range.begin as i32
                 }
             });
-            return var_range.size()
+            return Ok(var_range.size())
         },
         TrivialHIRExpr::None => todo!("None not implemented yet, probably should be a 0 as u32 behaving as a nullptr"),
     }
@@ -215,15 +247,40 @@ const fn is_compare(op: &Operator) -> bool {
 }
 
 fn generate_expr(type_db: &TypeDatabase, expression: &HIRExpr, bytecode: &mut Vec<AssemblyInstruction>,
-    scope: &HashMap<String, ByteRange>)-> u32 {
+    scope: &HashMap<String, ByteRange>, return_sizes: &HashMap<String, u32>) -> Result<u32, CodegenError> {
     match expression {
         HIRExpr::Trivial(trivial_expr, ..) => {
-            generate_trivial_expr(type_db, trivial_expr,  bytecode, scope)
+            generate_trivial_expr(type_db, trivial_expr, bytecode, scope)
         },
-        HIRExpr::Cast(_, _, _) => todo!("Cast not supported yet"),
+        //type inference only allows casts between numbers, the VM converts the value on top of the stack
+        HIRExpr::Cast(value, target, _) => {
+            generate_trivial_expr(type_db, value, bytecode, scope)?;
+            let from = type_db.find(value.1.expect_resolved().expect_simple());
+            let to = type_db.find(target.expect_resolved().expect_simple());
+            let number_kind = |record: &TypeRecord| if record.is_float(type_db) {
+                AsmNumberKind::Float
+            } else {
+                match record.sign {
+                    TypeSign::Signed => AsmNumberKind::Signed,
+                    TypeSign::Unsigned => AsmNumberKind::Unsigned,
+                }
+            };
+            bytecode.push(AssemblyInstruction::ConvertNumber {
+                from_bytes: from.size as u8,
+                from_kind: number_kind(from),
+                to_bytes: to.size as u8,
+                to_kind: number_kind(to),
+            });
+            return Ok(to.size as u32);
+        },
+        //the bytes stay the same, only the type changes
+        HIRExpr::Reinterpret(value, ..) => generate_trivial_expr(type_db, value, bytecode, scope),
+        HIRExpr::SizeOf(..) => panic!("sizeof should have been folded into a literal during type inference"),
+        HIRExpr::Tuple(..) => panic!("tuples should have been rejected during type inference"),
         HIRExpr::BinaryOperation(lhs, op, rhs, _, _) if is_arith(op) => {
-            generate_trivial_expr(type_db, rhs, bytecode, scope);
-            generate_trivial_expr(type_db, lhs, bytecode, scope);
+            //the VM pops rhs first, so lhs has to be pushed first
+            generate_trivial_expr(type_db, lhs, bytecode, scope)?;
+            generate_trivial_expr(type_db, rhs, bytecode, scope)?;
             //since both expr are the same type, we take the lhs type size and sign
             let lhs_type = lhs.1.expect_resolved();
             let type_db_record = type_db.find(lhs_type.expect_simple());
@@ -233,7 +290,7 @@ fn generate_expr(type_db: &TypeDatabase, expression: &HIRExpr, bytecode: &mut Ve
                 Operator::Minus => AsmArithmeticBinaryOp::Subtract,
                 Operator::Multiply => AsmArithmeticBinaryOp::Multiply,
                 Operator::Divide => AsmArithmeticBinaryOp::Divide,
-                Operator::Mod => todo!("mod operator not included in VM yet"),
+                Operator::Mod => AsmArithmeticBinaryOp::Remainder,
                 _ => panic!("Not arithmetic: {op:?}")
             };
 
@@ -256,12 +313,12 @@ fn generate_expr(type_db: &TypeDatabase, expression: &HIRExpr, bytecode: &mut Ve
                 panic!("Could not generate binary arithmetic operation, type is not integer or float")
             }
 
-            return type_db_record.size as u32;
+            return Ok(type_db_record.size as u32);
             
         },
         HIRExpr::BinaryOperation(lhs, op, rhs, _, _) if is_bitwise(op) => {
-            generate_trivial_expr(type_db, rhs, bytecode, scope);
-            generate_trivial_expr(type_db, lhs, bytecode, scope);
+            generate_trivial_expr(type_db, lhs, bytecode, scope)?;
+            generate_trivial_expr(type_db, rhs, bytecode, scope)?;
             //since both expr are the same type, we take the lhs type size and sign
             let lhs_type = lhs.1.expect_resolved();
             let type_db_record = type_db.find(lhs_type.expect_simple());
@@ -288,11 +345,41 @@ fn generate_expr(type_db: &TypeDatabase, expression: &HIRExpr, bytecode: &mut Ve
                 panic!("Could not generate binary arithmetic operation, type is not integer or float")
             }
 
-            return type_db_record.size as u32;
+            return Ok(type_db_record.size as u32);
+        },
+        HIRExpr::BinaryOperation(lhs, op, rhs, _, _) if is_shift(op) => {
+            generate_trivial_expr(type_db, lhs, bytecode, scope)?;
+            generate_trivial_expr(type_db, rhs, bytecode, scope)?;
+            //the shift amount has the same type as the value, so the lhs type gives size and sign
+            let lhs_type = lhs.1.expect_resolved();
+            let type_db_record = type_db.find(lhs_type.expect_simple());
+            let direction = match op {
+                Operator::BitShiftLeft => AsmShiftDirection::Left,
+                Operator::BitShiftRight => AsmShiftDirection::Right,
+                _ => panic!("Not a shift: {op:?}")
+            };
+
+            let sign_flag = match type_db_record.sign {
+                TypeSign::Signed => AsmSignFlag::Signed,
+                TypeSign::Unsigned => AsmSignFlag::Unsigned,
+            };
+
+            if type_db_record.is_integer(type_db) {
+                bytecode.push(AssemblyInstruction::BitShift {
+                    bytes: type_db_record.size as u8,
+                    direction,
+                    sign: sign_flag,
+                    immediate: None
+                });
+            } else {
+                panic!("Could not generate bit shift operation, type is not integer")
+            }
+
+            return Ok(type_db_record.size as u32);
         },
         HIRExpr::BinaryOperation(lhs, op, rhs, _, _) if is_compare(op) => {
-            generate_trivial_expr(type_db, rhs, bytecode, scope);
-            generate_trivial_expr(type_db, lhs, bytecode, scope);
+            generate_trivial_expr(type_db, lhs, bytecode, scope)?;
+            generate_trivial_expr(type_db, rhs, bytecode, scope)?;
             //since both expr are the same type, we take the lhs type size and sign
             let lhs_type = lhs.1.expect_resolved();
             let type_db_record = type_db.find(lhs_type.expect_simple());
@@ -325,10 +412,21 @@ fn generate_expr(type_db: &TypeDatabase, expression: &HIRExpr, bytecode: &mut Ve
                 panic!("Could not generate binary arithmetic operation, type is not integer or float")
             }
             let boolean_type = type_db.find(type_db.special_types.bool.expect_simple());
-            return boolean_type.size as u32;
+            return Ok(boolean_type.size as u32);
         },
         HIRExpr::BinaryOperation(_,_,_,_,_) => panic!("Tried to compile this: {expression:#?} but is not arithmetic, bitwise or compare op"),
-        HIRExpr::FunctionCall(_, _, _, _) => todo!("Function calls not implemented"),
+        HIRExpr::FunctionCall(function, args, return_type, ..) => {
+            let TrivialHIRExpr::Variable(function_name) = &function.0 else {
+                panic!("Functions should be bound to a name")
+            };
+            let return_size = type_size(type_db, return_type.expect_resolved());
+            generate_call(type_db, function_name, return_size, args, bytecode, scope, return_sizes)?;
+            return Ok(return_size);
+        },
+        //+x is the identity, type inference only allows it on numbers
+        HIRExpr::UnaryExpression(Operator::Plus, operand, _, _) => {
+            generate_trivial_expr(type_db, operand, bytecode, scope)
+        }
         HIRExpr::UnaryExpression(_, _, _, _) => todo!("unary expression not implemented"),
         HIRExpr::MemberAccess(_, _, _, _) => todo!("member access not implemented"),
         HIRExpr::Array(_, _, _) => todo!("arrays not implemented"),
@@ -336,6 +434,106 @@ fn generate_expr(type_db: &TypeDatabase, expression: &HIRExpr, bytecode: &mut Ve
     
 }
 
+//builtins and externs the VM implements itself, picked by name and argument type.
+//strings are not supported by codegen yet, so the only str print takes is a literal, pushed right before the call
+fn host_call_for(type_db: &TypeDatabase, function_name: &str, args: &[TypedTrivialHIRExpr]) -> Option<AssemblyInstruction> {
+    let [arg] = args else {
+        return None;
+    };
+    if function_name != "print" {
+        return None;
+    }
+    if let TrivialHIRExpr::StringValue(_) = arg.0 {
+        return Some(AssemblyInstruction::HostCall { function: AsmHostFunction::PrintStr, bytes: 4, kind: AsmNumberKind::Unsigned });
+    }
+    let arg_type = arg.1.expect_resolved();
+    if *arg_type == type_db.special_types.bool {
+        return Some(AssemblyInstruction::HostCall { function: AsmHostFunction::PrintBool, bytes: 1, kind: AsmNumberKind::Unsigned });
+    }
+    let TypeInstance::Simple(arg_type_id) = arg_type else {
+        return None;
+    };
+    let record = type_db.find(*arg_type_id);
+    let kind = if record.is_float(type_db) {
+        AsmNumberKind::Float
+    } else if record.is_integer(type_db) {
+        match record.sign {
+            TypeSign::Signed => AsmNumberKind::Signed,
+            TypeSign::Unsigned => AsmNumberKind::Unsigned,
+        }
+    } else {
+        return None;
+    };
+    Some(AssemblyInstruction::HostCall { function: AsmHostFunction::Print, bytes: record.size as u8, kind })
+}
+
+fn type_size(type_db: &TypeDatabase, typ: &TypeInstance) -> u32 {
+    type_db.find(typ.expect_simple()).size as u32
+}
+
+fn block_label(function_name: &str, block: usize) -> String {
+    format!("{function_name}_LBL_{block}")
+}
+
+/*
+Calling convention, the caller pushes:
+    [return space] [arg 0] ... [arg n] [saved bp]
+then call pushes the return address and sets bp = sp.
+After the call returns, the caller restores bp and pops the args, leaving the return value on the stack.
+
+Not supported yet: methods or functions stored in variables, generate_freyr rejects them.
+The callee has to be a function declared in the same program, since we jump to its label,
+or a builtin/extern the VM implements as a host function. Host functions pop their args and push nothing.
+*/
+fn generate_call(type_db: &TypeDatabase, function_name: &str, return_size: u32, args: &[TypedTrivialHIRExpr],
+    bytecode: &mut Vec<AssemblyInstruction>, scope: &HashMap<String, ByteRange>, return_sizes: &HashMap<String, u32>) -> Result<(), CodegenError> {
+    //check_callees_are_declared already made sure every callee without a body maps to a host function
+    if !return_sizes.contains_key(function_name) {
+        let host_call = host_call_for(type_db, function_name, args)
+            .unwrap_or_else(|| panic!("Function {function_name} not found"));
+        for arg in args {
+            match &arg.0 {
+                //the text goes on the stack byte by byte, followed by its length
+                TrivialHIRExpr::StringValue(text) => {
+                    for byte in text.bytes() {
+                        bytecode.push(AssemblyInstruction::PushImmediate { bytes: 1, shift_size: 0, immediate: [byte, 0] });
+                    }
+                    bytecode.push(AssemblyInstruction::PushImmediate {
+                        bytes: 4,
+                        shift_size: 0,
+                        immediate: (text.len() as u16).to_le_bytes()
+                    });
+                }
+                _ => {
+                    generate_trivial_expr(type_db, arg, bytecode, scope)?;
+                }
+            }
+        }
+        bytecode.push(host_call);
+        return Ok(());
+    }
+    if return_size > 0 {
+        bytecode.push(AssemblyInstruction::PushImmediate { 
+            bytes: return_size as u8, 
+            shift_size: 0, 
+            immediate: [0, 0]
+        });
+    }
+
+    let arg_sizes = args
+        .iter()
+        .map(|arg| generate_trivial_expr(type_db, arg, bytecode, scope))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    bytecode.push(AssemblyInstruction::PushRegister { register: AsmControlRegister::BasePointer });
+    bytecode.push(AssemblyInstruction::UnresolvedCall { label: Some(function_name.to_string()) });
+    bytecode.push(AssemblyInstruction::PopRegister { register: AsmControlRegister::BasePointer });
+    for arg_size in arg_sizes.into_iter().rev() {
+        bytecode.push(AssemblyInstruction::PopBytes { bytes: arg_size as u8 });
+    }
+    Ok(())
+}
+
 fn generate_decl_function(
     name: &str,
     parameters: &[MIRTypedBoundName],
@@ -343,8 +541,9 @@ fn generate_decl_function(
     scopes: &[MIRScope],
     return_type: &TypeInstance,
     bytecode: &mut Vec<AssemblyInstruction>,
+    return_sizes: &HashMap<String, u32>,
     type_db: &TypeDatabase
-) {
+) -> Result<(), CodegenError> {
     let scope_byte_layout = scopes
         .iter()
         .map(| scope| build_write_scope_byte_layout(scope, scopes, type_db))
@@ -357,6 +556,18 @@ fn generate_decl_function(
             largest_scope = sum;
         }
     }
+    bytecode.push(AssemblyInstruction::Label { label: name.to_string() });
+
+    //args are below the saved bp and the return address, copy them into the function frame
+    let args_size: u32 = parameters.iter().map(|param| type_size(type_db, &param.typename)).sum();
+    for param in parameters {
+        let range = scope_byte_layout[0].get(&param.name).unwrap();
+        bytecode.push(AssemblyInstruction::LoadAddress { 
+            bytes: range.size() as u8, 
+            mode: AsmLoadStoreMode::Relative { offset: range.begin as i32 - 8 - args_size as i32 }
+        });
+    }
+
     bytecode.push(AssemblyInstruction::StackOffset { bytes: largest_scope });
 
     //find the blocks that genuinely participate in some interesting control flow stuff
@@ -383,9 +594,7 @@ fn generate_decl_function(
         let scope = &scope_byte_layout[block.scope.0];
         
         if target_blocks.contains(&BlockId(block.index)) {
-            let label = format!("LBL_{}", block.index);
-            bytecode.push(AssemblyInstruction::Label { label: label });
-    
+            bytecode.push(AssemblyInstruction::Label { label: block_label(name, block.index) });
         }
       
         for elems in block.block.iter() {
@@ -398,7 +607,7 @@ fn generate_decl_function(
                     let var_name = path.first().unwrap();
                     println!("storing var {}", var_name);
                     let range = scope.get(var_name).unwrap();
-                    let size = generate_expr(type_db, expression, bytecode, scope);
+                    let size = generate_expr(type_db, expression, bytecode, scope, return_sizes)?;
                     bytecode.push(AssemblyInstruction::StoreAddress { 
                         bytes: size as u8, 
                         mode: AsmLoadStoreMode::Relative { offset: range.begin as i32 } 
@@ -411,32 +620,40 @@ fn generate_decl_function(
                 MIRBlockNode::FunctionCall {
                     function,
                     args,
-                    meta_ast,
-                } => todo!("Function calls not implemented"),
+                    ..
+                } => {
+                    //the return value is not used, host functions don't push one
+                    let return_size = return_sizes.get(function).copied().unwrap_or(0);
+                    generate_call(type_db, function, return_size, args, bytecode, scope, return_sizes)?;
+                    if return_size > 0 {
+                        bytecode.push(AssemblyInstruction::PopBytes { bytes: return_size as u8 });
+                    }
+                },
             }
         }
 
         match &block.finish {
             MIRBlockFinal::If(true_expr, true_branch, false_branch, ..) => {
                 let hirexpr = HIRExpr::Trivial(true_expr.clone(), None);
-                generate_expr(type_db, &hirexpr, bytecode, scope);
+                generate_expr(type_db, &hirexpr, bytecode, scope, return_sizes)?;
                 //generate a jz to the false branch
                 //assert that the true branch is just the next one
                 assert_eq!(true_branch.0, block.index + 1);
-                bytecode.push(AssemblyInstruction::UnresolvedJumpIfZero { label: Some(format!("LBL_{}", false_branch.0)) });
+                bytecode.push(AssemblyInstruction::UnresolvedJumpIfZero { label: Some(block_label(name, false_branch.0)) });
             },
             MIRBlockFinal::GotoBlock(block_id) => {
                 //if it just goes to the next, do not generate a goto!
                 if block_id.0 != block.index + 1 {
-                    bytecode.push(AssemblyInstruction::UnresolvedJumpIfZero { label: Some(format!("LBL_{}", block_id.0)) });
+                    bytecode.push(AssemblyInstruction::UnresolvedJump { label: Some(block_label(name, block_id.0)) });
                 }
             },
             MIRBlockFinal::Return(expr, _) => {
-                let size = generate_expr(type_db, expr, bytecode, scope);
+                let size = generate_expr(type_db, expr, bytecode, scope, return_sizes)?;
                 //destroy stack
                 bytecode.push(AssemblyInstruction::StoreAddress { 
                     bytes: size as u8,  
-                    mode: AsmLoadStoreMode::Relative { offset: -8 - size as i32 } //-8 - 4 for i32 would result in -12 
+                    //return space is right below the args, saved bp and return address
+                    mode: AsmLoadStoreMode::Relative { offset: -8 - args_size as i32 - size as i32 }
                 });
                 bytecode.push(AssemblyInstruction::StackOffset { bytes: 0 });
                 bytecode.push(AssemblyInstruction::Return);
@@ -448,10 +665,12 @@ fn generate_decl_function(
         }
         
     }
+    Ok(())
 }
 
 
-fn generate_for_top_lvl(type_db: &TypeDatabase, node: &MIRTopLevelNode, emitter: &mut FreyrEmitter) {
+fn generate_for_top_lvl(type_db: &TypeDatabase, node: &MIRTopLevelNode, emitter: &mut FreyrEmitter,
+    return_sizes: &HashMap<String, u32>) -> Result<(), CodegenError> {
     match node {
         MIRTopLevelNode::DeclareFunction {
             function_name,
@@ -459,6 +678,7 @@ fn generate_for_top_lvl(type_db: &TypeDatabase, node: &MIRTopLevelNode, emitter:
             body,
             scopes,
             return_type,
+            ..
         } => generate_decl_function(
             function_name,
             parameters,
@@ -466,31 +686,114 @@ fn generate_for_top_lvl(type_db: &TypeDatabase, node: &MIRTopLevelNode, emitter:
             scopes,
             return_type,
             &mut emitter.assembly,
+            return_sizes,
             type_db
         ),
         MIRTopLevelNode::StructDeclaration { struct_name, body } => todo!(),
     }
 }
 
-pub fn generate_freyr(type_db: &TypeDatabase, mir_top_level_nodes: &[MIRTopLevelNode]) -> Vec<AssemblyInstruction> {
-    let mut emitter = FreyrEmitter { assembly: vec![] };
+//The VM starts at the first instruction, which jumps to the entry point found by analysis.
+//It is a jump and not a call, so the entry point runs in the frame the VM was prepared with.
+pub fn generate_freyr(
+    type_db: &TypeDatabase,
+    mir_top_level_nodes: &[MIRTopLevelNode],
+    entry_point: &Result<EntryPoint, EntryPointError>,
+) -> Result<Vec<AssemblyInstruction>, CodegenError> {
+    let entry_point = match entry_point {
+        Ok(EntryPoint { function_name, takes_args: true }) => {
+            return Err(CodegenError::EntryPointTakesArgs { function_name: function_name.clone() })
+        }
+        Ok(entry_point) => entry_point,
+        Err(error) => return Err(CodegenError::NoEntryPoint(error.clone())),
+    };
+    let mut emitter = FreyrEmitter {
+        assembly: vec![AssemblyInstruction::UnresolvedJump { label: Some(entry_point.function_name.clone()) }],
+    };
+    //standalone calls need to know how many bytes to discard after the call
+    let (generic, mir_top_level_nodes): (Vec<_>, Vec<_>) = mir_top_level_nodes.iter().partition(|mir_node| matches!(
+        mir_node, MIRTopLevelNode::DeclareFunction { type_parameters, .. } if !type_parameters.is_empty()
+    ));
+    let generic_functions = generic.iter().filter_map(|mir_node| match mir_node {
+        MIRTopLevelNode::DeclareFunction { function_name, .. } => Some(function_name.clone()),
+        _ => None
+    }).collect::<Vec<_>>();
+    let mut return_sizes = HashMap::new();
+    for mir_node in mir_top_level_nodes.iter() {
+        if let MIRTopLevelNode::DeclareFunction { function_name, return_type, .. } = mir_node {
+            return_sizes.insert(function_name.clone(), type_size(type_db, return_type));
+        }
+    }
+    check_callees_are_declared(type_db, &mir_top_level_nodes, &return_sizes, &generic_functions)?;
     for mir_node in mir_top_level_nodes {
-        generate_for_top_lvl(type_db, mir_node, &mut emitter);
+        generate_for_top_lvl(type_db, mir_node, &mut emitter, &return_sizes)?;
+    }
+    return Ok(emitter.assembly)
+}
+
+//every function with a body is in the MIR, so any other callee is a variable, a generic function,
+//or a builtin/extern that has to map to a host function
+fn check_callees_are_declared(type_db: &TypeDatabase, mir_top_level_nodes: &[&MIRTopLevelNode], declared: &HashMap<String, u32>,
+    generic_functions: &[String]) -> Result<(), CodegenError> {
+    for mir_node in mir_top_level_nodes {
+        let MIRTopLevelNode::DeclareFunction { function_name: on_function, body, scopes, .. } = mir_node else {
+            continue;
+        };
+        let is_variable = |callee: &str| scopes.iter()
+            .any(|scope| scope.boundnames.iter().any(|bound_name| bound_name.name == callee));
+        let check = |callee: &str, args: &[TypedTrivialHIRExpr]| match declared.contains_key(callee) {
+            _ if is_variable(callee) => Err(CodegenError::IndirectCall {
+                on_function: on_function.clone(),
+                variable: callee.to_string()
+            }),
+            true => Ok(()),
+            false if host_call_for(type_db, callee, args).is_some() => Ok(()),
+            false if generic_functions.iter().any(|name| name == callee) => Err(CodegenError::GenericFunctionCalled {
+                on_function: on_function.clone(),
+                function_name: callee.to_string()
+            }),
+            false => Err(CodegenError::ExternFunctionCalled {
+                on_function: on_function.clone(),
+                function_name: callee.to_string()
+            }),
+        };
+        for block in body {
+            for node in block.block.iter() {
+                match node {
+                    MIRBlockNode::FunctionCall { function, args, .. } => check(function, args)?,
+                    MIRBlockNode::Assign { expression: HIRExpr::FunctionCall(function, args, ..), .. } => {
+                        if let TrivialHIRExpr::Variable(callee) = &function.0 {
+                            check(callee, args)?;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            if let MIRBlockFinal::Return(HIRExpr::FunctionCall(function, args, ..), _) = &block.finish {
+                if let TrivialHIRExpr::Variable(callee) = &function.0 {
+                    check(callee, args)?;
+                }
+            }
+        }
     }
-    return emitter.assembly
+    Ok(())
 }
 
 #[cfg(test)]
 mod test {
+    use std::collections::HashMap;
+
     use crate::{
         ast::parser::{Parser, AST},
         semantic::{
+            analysis::{EntryPoint, EntryPointError},
+            dead_branches::prune_constant_branches,
             mir::{hir_to_mir, MIRTopLevelNode},
             mir_printer,
             name_registry::NameRegistry,
             type_checker::check_type,
         },
-        types::{type_db::TypeDatabase, type_errors::TypeErrors}, compiler::freyr_gen::generate_freyr, freyr::{asm::{assembler::{as_freyr_instructions, resolve}, self}, vm::{memory::Memory, runner::{ControlRegisterValues, self}}},
+        types::{type_db::{TypeDatabase, TypeSign}, type_errors::TypeErrors}, compiler::freyr_gen::{generate_freyr, generate_trivial_expr, narrow_integer_literal, CodegenError}, semantic::hir::{HIRTypeDef, TrivialHIRExpr, TypedTrivialHIRExpr}, freyr::{asm::{assembler::{as_freyr_instructions, resolve}, self}, vm::{instructions::NumberOfBytes, memory::Memory, runner::{ControlRegisterValues, self}}},
     };
 
     pub struct TestContext {
@@ -498,6 +801,7 @@ mod test {
         database: TypeDatabase,
         globals: NameRegistry,
         type_errors: TypeErrors,
+        entry_point: Result<EntryPoint, EntryPointError>,
     }
 
     fn prepare(source: &str) -> TestContext {
@@ -510,16 +814,52 @@ mod test {
         let analysis_result = crate::semantic::analysis::do_analysis(&ast);
         let mir = hir_to_mir(&analysis_result.final_mir, &analysis_result.type_db);
         let errors = check_type(&mir, &analysis_result.type_db, &analysis_result.globals);
+        //dead branches are only pruned once they have been type checked
+        let pruned = prune_constant_branches(analysis_result.final_mir);
+        let mir = hir_to_mir(&pruned, &analysis_result.type_db);
         return TestContext {
             mir: mir,
             database: analysis_result.type_db,
             globals: analysis_result.globals,
             type_errors: errors,
+            entry_point: analysis_result.entry_point,
         };
     }
 
    
 
+    #[test]
+    fn narrow_negative_one_to_i8() {
+        let narrowed = narrow_integer_literal(-1, NumberOfBytes::Bytes1, &TypeSign::Signed);
+        assert_eq!(narrowed, Ok(vec![0xFF]));
+    }
+
+    #[test]
+    fn narrow_negative_i32_is_twos_complement() {
+        let narrowed = narrow_integer_literal(-2, NumberOfBytes::Bytes4, &TypeSign::Signed);
+        assert_eq!(narrowed, Ok(vec![0xFE, 0xFF, 0xFF, 0xFF]));
+    }
+
+    #[test]
+    fn narrow_literal_out_of_range_is_error() {
+        assert!(narrow_integer_literal(128, NumberOfBytes::Bytes1, &TypeSign::Signed).is_err());
+        assert!(narrow_integer_literal(-1, NumberOfBytes::Bytes4, &TypeSign::Unsigned).is_err());
+        assert!(narrow_integer_literal(u64::MAX as i128 + 1, NumberOfBytes::Bytes8, &TypeSign::Unsigned).is_err());
+        assert_eq!(narrow_integer_literal(u64::MAX as i128, NumberOfBytes::Bytes8, &TypeSign::Unsigned), Ok(vec![0xFF; 8]));
+    }
+
+    #[test]
+    fn literal_out_of_range_is_codegen_error() {
+        let type_db = TypeDatabase::new();
+        let literal = TypedTrivialHIRExpr(TrivialHIRExpr::IntegerValue(1 << 40), HIRTypeDef::Resolved(type_db.special_types.i32.clone()));
+        let mut bytecode = vec![];
+
+        let error = generate_trivial_expr(&type_db, &literal, &mut bytecode, &HashMap::new()).unwrap_err();
+
+        assert_eq!(error.to_string(), "Cannot push immediate: 1099511627776 does not fit in 32 bits, expected a value between -2147483648 and 2147483647");
+        assert!(bytecode.is_empty());
+    }
+
     #[test]
     fn branchless_mutable_var_test() {
         let src = "
@@ -532,13 +872,14 @@ def main():
 ";
 
         let prepared = prepare(src);
-        let generated_asm = generate_freyr(&prepared.database, &prepared.mir);
+        let generated_asm = generate_freyr(&prepared.database, &prepared.mir, &prepared.entry_point).unwrap();
         println!("Assembly:");
         asm::asm_printer::print(&generated_asm);
         assert_eq!(prepared.type_errors.count(), 0);
-        let as_instructions = as_freyr_instructions(&generated_asm);
+        let resolved_asm = resolve(&generated_asm);
+        let as_instructions = as_freyr_instructions(&resolved_asm);
         let (mut memory, mut registers) = runner::prepare_vm();
-        runner::run(&as_instructions, &mut memory, &mut registers);
+        runner::run(&as_instructions, &mut memory, &mut registers).unwrap();
 
         let result_value = memory.native_read::<i32>(registers.bp + 12);
         assert_eq!(result_value, 26);
@@ -557,7 +898,7 @@ def main():
 ";
 
         let prepared = prepare(src);
-        let generated_asm = generate_freyr(&prepared.database, &prepared.mir);
+        let generated_asm = generate_freyr(&prepared.database, &prepared.mir, &prepared.entry_point).unwrap();
         println!("Assembly:");
         asm::asm_printer::print(&generated_asm);
         let resolved_asm = resolve(&generated_asm);
@@ -566,9 +907,346 @@ def main():
         assert_eq!(prepared.type_errors.count(), 0);
         let as_instructions = as_freyr_instructions(&resolved_asm);
         let (mut memory, mut registers) = runner::prepare_vm();
-        runner::run(&as_instructions, &mut memory, &mut registers);
+        runner::run(&as_instructions, &mut memory, &mut registers).unwrap();
 
         let result_value = memory.native_read::<i32>(registers.bp + 4);
         assert_eq!(result_value, 15);
     }
+
+    #[test]
+    fn i64_literal_above_16_bits() {
+        let src = "
+def main():
+    x : i64 = 70000
+    y : i64 = 5000000000
+";
+
+        let prepared = prepare(src);
+        let generated_asm = generate_freyr(&prepared.database, &prepared.mir, &prepared.entry_point).unwrap();
+        println!("Assembly:");
+        asm::asm_printer::print(&generated_asm);
+        assert_eq!(prepared.type_errors.count(), 0);
+        let resolved_asm = resolve(&generated_asm);
+        let as_instructions = as_freyr_instructions(&resolved_asm);
+        let (mut memory, mut registers) = runner::prepare_vm();
+        runner::run(&as_instructions, &mut memory, &mut registers).unwrap();
+
+        let x = memory.native_read::<i64>(registers.bp);
+        assert_eq!(x, 70000);
+        let y = memory.native_read::<i64>(registers.bp + 8);
+        assert_eq!(y, 5000000000);
+    }
+
+    #[test]
+    fn casts_between_integer_sizes_convert_the_value() {
+        let src = "
+def main():
+    x: i32 = 0 - 5
+    y: i64 = cast<i64>(x)
+    z: i32 = cast<i32>(y + cast<i64>(x))
+";
+        let prepared = prepare(src);
+        let generated_asm = generate_freyr(&prepared.database, &prepared.mir, &prepared.entry_point).unwrap();
+        println!("Assembly:");
+        asm::asm_printer::print(&generated_asm);
+        assert_eq!(prepared.type_errors.count(), 0);
+        let resolved_asm = resolve(&generated_asm);
+        let as_instructions = as_freyr_instructions(&resolved_asm);
+        let (mut memory, mut registers) = runner::prepare_vm();
+        runner::run(&as_instructions, &mut memory, &mut registers).unwrap();
+
+        assert_eq!(memory.native_read::<i32>(registers.bp), -5);
+        assert_eq!(memory.native_read::<i64>(registers.bp + 4), -5);
+        //$0 and $1 hold the i64 operands of the sum before z
+        assert_eq!(memory.native_read::<i64>(registers.bp + 20), -10);
+        assert_eq!(memory.native_read::<i32>(registers.bp + 28), -10);
+    }
+
+    #[test]
+    fn bool_literals_and_comparisons_are_one_byte() {
+        let src = "
+def main():
+    t : bool = True
+    f : bool = False
+    c : bool = 1 < 2
+    result: i32 = 0
+    if t:
+        result = result + 1
+    if f:
+        result = result + 10
+    if c:
+        result = result + 100
+";
+
+        let prepared = prepare(src);
+        let generated_asm = generate_freyr(&prepared.database, &prepared.mir, &prepared.entry_point).unwrap();
+        println!("Assembly:");
+        asm::asm_printer::print(&generated_asm);
+        assert_eq!(prepared.type_errors.count(), 0);
+        let resolved_asm = resolve(&generated_asm);
+        let as_instructions = as_freyr_instructions(&resolved_asm);
+        let (mut memory, mut registers) = runner::prepare_vm();
+        runner::run(&as_instructions, &mut memory, &mut registers).unwrap();
+
+        //each bool takes a single byte, so result starts right after them
+        assert_eq!(memory.read_single(registers.bp), 1);
+        assert_eq!(memory.read_single(registers.bp + 1), 0);
+        assert_eq!(memory.read_single(registers.bp + 2), 1);
+        let result_value = memory.native_read::<i32>(registers.bp + 3);
+        assert_eq!(result_value, 101);
+    }
+
+    #[test]
+    fn unary_plus_is_a_no_op() {
+        let src = "
+def main():
+    x : i32 = 15
+    y : i32 = +x + 1
+";
+
+        let prepared = prepare(src);
+        let generated_asm = generate_freyr(&prepared.database, &prepared.mir, &prepared.entry_point).unwrap();
+        assert_eq!(prepared.type_errors.count(), 0);
+        let resolved_asm = resolve(&generated_asm);
+        let as_instructions = as_freyr_instructions(&resolved_asm);
+        let (mut memory, mut registers) = runner::prepare_vm();
+        runner::run(&as_instructions, &mut memory, &mut registers).unwrap();
+
+        //+x gets its own intermediary between x and y
+        assert_eq!(memory.native_read::<i32>(registers.bp + 4), 15);
+        let result_value = memory.native_read::<i32>(registers.bp + 8);
+        assert_eq!(result_value, 16);
+    }
+
+    #[test]
+    fn while_loop_sums_until_condition_is_false() {
+        let src = "
+def main():
+    result: i32 = 0
+    i: i32 = 0
+    while i < 5:
+        i = i + 1
+        result = result + i
+";
+
+        let prepared = prepare(src);
+        let generated_asm = generate_freyr(&prepared.database, &prepared.mir, &prepared.entry_point).unwrap();
+        println!("Assembly:");
+        asm::asm_printer::print(&generated_asm);
+        assert_eq!(prepared.type_errors.count(), 0);
+        let resolved_asm = resolve(&generated_asm);
+        let as_instructions = as_freyr_instructions(&resolved_asm);
+        let (mut memory, mut registers) = runner::prepare_vm();
+        runner::run(&as_instructions, &mut memory, &mut registers).unwrap();
+
+        assert_eq!(memory.native_read::<i32>(registers.bp), 15);
+        assert_eq!(memory.native_read::<i32>(registers.bp + 4), 5);
+    }
+
+    #[test]
+    fn remainder_and_shifts_run_on_the_vm() {
+        let src = "
+def main():
+    rem: i32 = 17 % 5
+    left: i32 = 3 << 4
+    negative: i32 = 0 - 64
+    right: i32 = negative >> 2
+    mixed: i32 = 6 ^ 3
+";
+
+        let prepared = prepare(src);
+        assert_eq!(prepared.type_errors.count(), 0);
+        let generated_asm = generate_freyr(&prepared.database, &prepared.mir, &prepared.entry_point).unwrap();
+        let as_instructions = as_freyr_instructions(&resolve(&generated_asm));
+        let (mut memory, mut registers) = runner::prepare_vm();
+        runner::run(&as_instructions, &mut memory, &mut registers).unwrap();
+
+        assert_eq!(memory.native_read::<i32>(registers.bp), 2);
+        assert_eq!(memory.native_read::<i32>(registers.bp + 4), 48);
+        assert_eq!(memory.native_read::<i32>(registers.bp + 12), -16);
+        assert_eq!(memory.native_read::<i32>(registers.bp + 16), 5);
+    }
+
+    #[test]
+    fn binary_operands_keep_their_order_on_the_vm() {
+        let src = "
+def main():
+    difference: i32 = 10 - 3
+    dividend: i32 = 20
+    divisor: i32 = 4
+    quotient: i32 = dividend / divisor
+    shifted: i32 = 1 << 3
+";
+
+        let prepared = prepare(src);
+        assert_eq!(prepared.type_errors.count(), 0);
+        let generated_asm = generate_freyr(&prepared.database, &prepared.mir, &prepared.entry_point).unwrap();
+        let as_instructions = as_freyr_instructions(&resolve(&generated_asm));
+        let (mut memory, mut registers) = runner::prepare_vm();
+        runner::run(&as_instructions, &mut memory, &mut registers).unwrap();
+
+        assert_eq!(memory.native_read::<i32>(registers.bp), 7);
+        assert_eq!(memory.native_read::<i32>(registers.bp + 12), 5);
+        assert_eq!(memory.native_read::<i32>(registers.bp + 16), 8);
+    }
+
+    #[test]
+    fn program_without_main_is_rejected() {
+        let src = "
+def not_main():
+    result: i32 = 5
+";
+
+        let prepared = prepare(src);
+        let generated = generate_freyr(&prepared.database, &prepared.mir, &prepared.entry_point);
+        assert_eq!(generated, Err(CodegenError::NoEntryPoint(EntryPointError::MainNotFound)));
+    }
+
+    #[test]
+    fn call_to_extern_function_is_rejected() {
+        let src = "
+extern def log(x: i32) -> i32
+
+def main():
+    result: i32 = log(5)
+";
+
+        let prepared = prepare(src);
+        assert_eq!(prepared.type_errors.count(), 0);
+        let generated = generate_freyr(&prepared.database, &prepared.mir, &prepared.entry_point);
+        assert_eq!(generated, Err(CodegenError::ExternFunctionCalled {
+            on_function: "main".into(),
+            function_name: "log".into()
+        }));
+    }
+
+    fn run_program(src: &str) -> Memory {
+        let prepared = prepare(src);
+        assert_eq!(prepared.type_errors.count(), 0);
+        let generated_asm = generate_freyr(&prepared.database, &prepared.mir, &prepared.entry_point).unwrap();
+        let as_instructions = as_freyr_instructions(&resolve(&generated_asm));
+        let (mut memory, mut registers) = runner::prepare_vm();
+        runner::run(&as_instructions, &mut memory, &mut registers).unwrap();
+        memory
+    }
+
+    #[test]
+    fn builtin_print_is_a_host_call() {
+        let src = "
+def main():
+    print(\"hello\")
+";
+
+        let memory = run_program(src);
+        assert_eq!(memory.output, "hello\n");
+    }
+
+    #[test]
+    fn extern_print_of_numbers_and_bools_is_a_host_call() {
+        let src = "
+extern def print(x: i32)
+
+def main():
+    x: i32 = 40 + 2
+    print(x)
+";
+
+        let memory = run_program(src);
+        assert_eq!(memory.output, "42\n");
+
+        let src = "
+extern def print(x: bool)
+
+def main():
+    print(3 > 50)
+";
+
+        let memory = run_program(src);
+        assert_eq!(memory.output, "False\n");
+    }
+
+    #[test]
+    fn print_of_a_string_variable_is_rejected() {
+        let src = "
+def main():
+    text = \"a\"
+    print(text)
+";
+
+        let prepared = prepare(src);
+        assert_eq!(prepared.type_errors.count(), 0);
+        let generated = generate_freyr(&prepared.database, &prepared.mir, &prepared.entry_point);
+        assert_eq!(generated, Err(CodegenError::ExternFunctionCalled {
+            on_function: "main".into(),
+            function_name: "print".into()
+        }));
+    }
+
+    #[test]
+    fn call_through_function_parameter_is_rejected() {
+        let src = "
+def double(x: i32) -> i32:
+    return x * 2
+
+def apply(f: fn(i32) -> i32, x: i32) -> i32:
+    return f(x)
+
+def main():
+    result: i32 = apply(double, 5)
+";
+
+        let prepared = prepare(src);
+        assert_eq!(prepared.type_errors.count(), 0);
+        let generated = generate_freyr(&prepared.database, &prepared.mir, &prepared.entry_point);
+        assert_eq!(generated, Err(CodegenError::IndirectCall {
+            on_function: "apply".into(),
+            variable: "f".into()
+        }));
+    }
+
+    #[test]
+    fn call_to_generic_function_is_rejected() {
+        let src = "
+def identity<T>(x: T) -> T:
+    return x
+
+def main():
+    result: i32 = identity(5)
+";
+
+        let prepared = prepare(src);
+        assert_eq!(prepared.type_errors.count(), 0);
+        let generated = generate_freyr(&prepared.database, &prepared.mir, &prepared.entry_point);
+        assert_eq!(generated, Err(CodegenError::GenericFunctionCalled {
+            on_function: "main".into(),
+            function_name: "identity".into()
+        }));
+    }
+
+    #[test]
+    fn recursive_factorial_end_to_end() {
+        //the first instruction jumps to main, so it doesn't have to be declared first
+        let src = "
+def factorial(n: i32) -> i32:
+    if n <= 1:
+        return 1
+    return n * factorial(n - 1)
+
+def main():
+    result: i32 = factorial(5)
+";
+
+        let prepared = prepare(src);
+        let generated_asm = generate_freyr(&prepared.database, &prepared.mir, &prepared.entry_point).unwrap();
+        println!("Assembly:");
+        asm::asm_printer::print(&generated_asm);
+        assert_eq!(prepared.type_errors.count(), 0);
+        let resolved_asm = resolve(&generated_asm);
+        let as_instructions = as_freyr_instructions(&resolved_asm);
+        let (mut memory, mut registers) = runner::prepare_vm();
+        runner::run(&as_instructions, &mut memory, &mut registers).unwrap();
+
+        let result_value = memory.native_read::<i32>(registers.bp);
+        assert_eq!(result_value, 120);
+    }
 }
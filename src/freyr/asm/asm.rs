@@ -14,6 +14,7 @@ pub enum AsmArithmeticBinaryOp {
     Multiply,
     Divide,
     Power,
+    Remainder,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -23,6 +24,12 @@ pub enum AsmIntegerBitwiseBinaryOp {
     Xor,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AsmShiftDirection {
+    Left,
+    Right,
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum AsmIntegerCompareBinaryOp {
     Equals,
@@ -39,6 +46,20 @@ pub enum AsmSignFlag {
     Unsigned,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AsmNumberKind {
+    Signed,
+    Unsigned,
+    Float,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AsmHostFunction {
+    Print,
+    PrintBool,
+    PrintStr,
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum AsmControlRegister {
     BasePointer,
@@ -70,6 +91,13 @@ pub enum AssemblyInstruction {
         sign: AsmSignFlag,
         immediate: Option<[u8; 2]>,
     },
+    //right shifts on signed values keep the sign bit
+    BitShift {
+        bytes: u8,
+        direction: AsmShiftDirection,
+        sign: AsmSignFlag,
+        immediate: Option<u8>,
+    },
     IntegerArithmeticBinaryOperation {
         bytes: u8,
         operation: AsmArithmeticBinaryOp,
@@ -82,6 +110,14 @@ pub enum AssemblyInstruction {
         sign: AsmSignFlag,
         immediate: Option<[u8; 2]>,
     },
+    FloatArithmeticBinaryOperation {
+        bytes: u8,
+        operation: AsmArithmeticBinaryOp,
+    },
+    FloatCompareBinaryOperation {
+        bytes: u8,
+        operation: AsmIntegerCompareBinaryOp,
+    },
     PopRegister {
         register: AsmControlRegister,
     },
@@ -91,6 +127,17 @@ pub enum AssemblyInstruction {
     PopBytes {
         bytes: u8,
     },
+    ConvertNumber {
+        from_bytes: u8,
+        from_kind: AsmNumberKind,
+        to_bytes: u8,
+        to_kind: AsmNumberKind,
+    },
+    HostCall {
+        function: AsmHostFunction,
+        bytes: u8,
+        kind: AsmNumberKind,
+    },
     Label {
         label: String,
     },
@@ -122,6 +169,20 @@ pub enum AssemblyInstruction {
         offset: u32
     },
     JumpFromStack,
+    //relative to the jump instruction itself, written as jnz -3 or jz +2
+    CallRelative {
+        offset: i32
+    },
+    JumpIfZeroRelative {
+        offset: i32
+    },
+    JumpIfNotZeroRelative {
+        offset: i32
+    },
+    JumpRelative {
+        offset: i32
+    },
+    Noop,
     Exit,
     Return,
 }
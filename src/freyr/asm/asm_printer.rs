@@ -1,202 +1,169 @@
-use crate::freyr::asm::{asm::{AsmLoadStoreMode, AsmIntegerBitwiseBinaryOp, AsmSignFlag, AsmArithmeticBinaryOp, AsmControlRegister}, self};
+use crate::freyr::asm::{asm::{AsmLoadStoreMode, AsmIntegerBitwiseBinaryOp, AsmSignFlag, AsmArithmeticBinaryOp, AsmControlRegister, AsmHostFunction, AsmIntegerCompareBinaryOp, AsmNumberKind, AsmShiftDirection}};
 
 use super::asm::AssemblyInstruction;
 
+const OPS_INDENT: &str = "\t\t\t";
+
+fn arith_op(operation: &AsmArithmeticBinaryOp) -> &'static str {
+    match operation {
+        AsmArithmeticBinaryOp::Sum => "sum",
+        AsmArithmeticBinaryOp::Subtract => "sub",
+        AsmArithmeticBinaryOp::Multiply => "mul",
+        AsmArithmeticBinaryOp::Divide => "div",
+        AsmArithmeticBinaryOp::Power => "pow",
+        AsmArithmeticBinaryOp::Remainder => "rem",
+    }
+}
+
+fn compare_op(operation: &AsmIntegerCompareBinaryOp) -> &'static str {
+    match operation {
+        AsmIntegerCompareBinaryOp::Equals => "eq",
+        AsmIntegerCompareBinaryOp::NotEquals => "ne",
+        AsmIntegerCompareBinaryOp::LessThan => "lt",
+        AsmIntegerCompareBinaryOp::LessThanOrEquals => "le",
+        AsmIntegerCompareBinaryOp::GreaterThan => "gt",
+        AsmIntegerCompareBinaryOp::GreaterThanOrEquals => "ge",
+    }
+}
+
+fn sign(sign: &AsmSignFlag) -> &'static str {
+    match sign {
+        AsmSignFlag::Signed => "s",
+        AsmSignFlag::Unsigned => "u",
+    }
+}
+
+fn kind(kind: &AsmNumberKind) -> &'static str {
+    match kind {
+        AsmNumberKind::Signed => "s",
+        AsmNumberKind::Unsigned => "u",
+        AsmNumberKind::Float => "f",
+    }
+}
+
+fn register(register: &AsmControlRegister) -> &'static str {
+    match register {
+        AsmControlRegister::BasePointer => "bp",
+        AsmControlRegister::StackPointer => "sp",
+        AsmControlRegister::InstructionPointer => "ip",
+    }
+}
+
+fn load_store(instruction: &str, bytes: u8, mode: &AsmLoadStoreMode) -> String {
+    let bits = bytes as u32 * 8;
+    match mode {
+        AsmLoadStoreMode::StackPop => format!("{instruction}{bits}"),
+        AsmLoadStoreMode::Relative { offset } => format!("{instruction}_rel{bits}{OPS_INDENT}bp{offset:+}"),
+        AsmLoadStoreMode::Immediate { absolute_address } => format!("{instruction}_imm{bits}{OPS_INDENT}{absolute_address}"),
+    }
+}
+
+//binary operations are written as op_imm32 N when one operand is in the instruction, or just op32 otherwise
+fn binary_op(op: &str, bytes: u8, immediate: Option<u16>) -> String {
+    let bits = bytes as u32 * 8;
+    match immediate {
+        Some(imm) => format!("{op}_imm{bits}{OPS_INDENT}{imm}"),
+        None => format!("{op}{bits}"),
+    }
+}
+
+fn jump(op: &str, label: &Option<String>) -> String {
+    match label {
+        Some(label) => format!("{op}{OPS_INDENT}\t{label}"),
+        None => format!("{op}_stack"),
+    }
+}
+
+//formats a single instruction the way the assembler reads it back
+pub fn print_instruction(inst: &AssemblyInstruction) -> String {
+    match inst {
+        AssemblyInstruction::StackOffset { bytes } => format!("stackoffset{OPS_INDENT}{bytes}"),
+        AssemblyInstruction::LoadAddress { bytes, mode } => load_store("loadaddr", *bytes, mode),
+        AssemblyInstruction::StoreAddress { bytes, mode } => load_store("storeaddr", *bytes, mode),
+        AssemblyInstruction::PushImmediate { bytes, shift_size, immediate } => {
+            let bits = *bytes as u32 * 8;
+            let immediate = u16::from_le_bytes(*immediate);
+            if *shift_size > 0 {
+                format!("push_imm{bits}{OPS_INDENT}{immediate} <<{shift_size}")
+            } else {
+                format!("push_imm{bits}{OPS_INDENT}{immediate}")
+            }
+        },
+        AssemblyInstruction::IntegerBitwiseBinaryOperation { bytes, operation, sign, immediate } => {
+            let op = match operation {
+                AsmIntegerBitwiseBinaryOp::And => "and",
+                AsmIntegerBitwiseBinaryOp::Or => "or",
+                AsmIntegerBitwiseBinaryOp::Xor => "xor",
+            };
+            //the k suffix means the signed variant, s would be read as part of the mnemonic
+            let k = match sign {
+                AsmSignFlag::Signed => "k",
+                AsmSignFlag::Unsigned => "",
+            };
+            binary_op(&format!("{op}{k}"), *bytes, immediate.map(u16::from_le_bytes))
+        },
+        AssemblyInstruction::BitShift { bytes, direction, sign: sign_flag, immediate } => {
+            let op = match direction {
+                AsmShiftDirection::Left => "shl",
+                AsmShiftDirection::Right => "shr",
+            };
+            binary_op(&format!("{op}{}", sign(sign_flag)), *bytes, immediate.map(|imm| imm as u16))
+        },
+        AssemblyInstruction::IntegerArithmeticBinaryOperation { bytes, operation, sign: sign_flag, immediate } => {
+            binary_op(&format!("{}{}", arith_op(operation), sign(sign_flag)), *bytes, immediate.map(u16::from_le_bytes))
+        },
+        AssemblyInstruction::IntegerCompareBinaryOperation { bytes, operation, sign: sign_flag, immediate } => {
+            binary_op(&format!("{}{}", compare_op(operation), sign(sign_flag)), *bytes, immediate.map(u16::from_le_bytes))
+        },
+        AssemblyInstruction::FloatArithmeticBinaryOperation { bytes, operation } => {
+            binary_op(&format!("{}f", arith_op(operation)), *bytes, None)
+        },
+        AssemblyInstruction::FloatCompareBinaryOperation { bytes, operation } => {
+            binary_op(&format!("{}f", compare_op(operation)), *bytes, None)
+        },
+        AssemblyInstruction::PopRegister { register: reg } => format!("pop_reg{OPS_INDENT}{}", register(reg)),
+        AssemblyInstruction::PushRegister { register: reg } => format!("push_reg{OPS_INDENT}{}", register(reg)),
+        AssemblyInstruction::PopBytes { bytes } => format!("pop{bits}", bits = *bytes as u32 * 8),
+        AssemblyInstruction::ConvertNumber { from_bytes, from_kind, to_bytes, to_kind } => {
+            format!("convert{OPS_INDENT}{from}{from_bits} {to}{to_bits}",
+                from = kind(from_kind), from_bits = *from_bytes as u32 * 8, to = kind(to_kind), to_bits = *to_bytes as u32 * 8)
+        },
+        AssemblyInstruction::HostCall { function, bytes, kind: number_kind } => {
+            let function = match function {
+                AsmHostFunction::Print => "print",
+                AsmHostFunction::PrintBool => "print_bool",
+                AsmHostFunction::PrintStr => "print_str",
+            };
+            format!("hostcall{OPS_INDENT}{function} {kind}{bits}", kind = kind(number_kind), bits = *bytes as u32 * 8)
+        },
+        AssemblyInstruction::Label { label } => format!("{label}:"),
+        AssemblyInstruction::UnresolvedCall { label } => jump("call", label),
+        AssemblyInstruction::UnresolvedJumpIfZero { label } => jump("jz", label),
+        AssemblyInstruction::UnresolvedJumpIfNotZero { label } => jump("jnz", label),
+        AssemblyInstruction::UnresolvedJump { label } => jump("jmp", label),
+        AssemblyInstruction::Call { offset } => format!("call{OPS_INDENT}\t{offset}"),
+        AssemblyInstruction::CallFromStack => "call_stack".to_string(),
+        AssemblyInstruction::JumpIfZero { offset } => format!("jz{OPS_INDENT}\t{offset}"),
+        AssemblyInstruction::JumpIfZeroFromStack => "jz_stack".to_string(),
+        AssemblyInstruction::JumpIfNotZero { offset } => format!("jnz{OPS_INDENT}\t{offset}"),
+        AssemblyInstruction::JumpIfNotZeroFromStack => "jnz_stack".to_string(),
+        AssemblyInstruction::Jump { offset } => format!("jmp{OPS_INDENT}\t{offset}"),
+        AssemblyInstruction::JumpFromStack => "jmp_stack".to_string(),
+        AssemblyInstruction::CallRelative { offset } => format!("call{OPS_INDENT}\t{offset:+}"),
+        AssemblyInstruction::JumpIfZeroRelative { offset } => format!("jz{OPS_INDENT}\t{offset:+}"),
+        AssemblyInstruction::JumpIfNotZeroRelative { offset } => format!("jnz{OPS_INDENT}\t{offset:+}"),
+        AssemblyInstruction::JumpRelative { offset } => format!("jmp{OPS_INDENT}\t{offset:+}"),
+        AssemblyInstruction::Noop => "noop".to_string(),
+        AssemblyInstruction::Exit => "exit".to_string(),
+        AssemblyInstruction::Return => "return".to_string(),
+    }
+}
 
 pub fn print(instructions: &[AssemblyInstruction]) {
-    let ops_indent = "\t\t\t";
     for inst in instructions {
-        print!("\t");
         match inst {
-            AssemblyInstruction::StackOffset { bytes } => println!("stackoffset{ops_indent}{bytes}", bytes=bytes),
-            AssemblyInstruction::LoadAddress { bytes, mode } => {
-                print!("loadaddr");
-                let bytes_str = if *bytes == 4 { "".to_string() } else { format!("{bytes}", bytes = bytes *8) };
-                       
-                match mode {
-                    AsmLoadStoreMode::StackPop => print!("{bytes}"),
-                    AsmLoadStoreMode::Relative { offset } =>{
-                        print!("_rel{bytes_str}{ops_indent}");
-                        if *offset > 0 {
-                            println!("bp+{offset}")
-                        } else {
-                            println!("bp-{offset}")
-                        }
-                    },
-                    AsmLoadStoreMode::Immediate { absolute_address } => {
-                        println!("_imm{bytes}{ops_indent}{absolute_address}", bytes=bytes*8);
-                    },
-                }
-            },
-            AssemblyInstruction::StoreAddress { bytes, mode } => {
-                print!("storeaddr");
-                let bytes_str = if *bytes == 4 { "".to_string() } else { format!("{bytes}", bytes = bytes *8) };
-               
-                match mode {
-                    AsmLoadStoreMode::StackPop => print!("{bytes}"),
-                    AsmLoadStoreMode::Relative { offset } =>{
-                        print!("_rel{bytes_str}{ops_indent}");
-                        if *offset > 0 {
-                            println!("bp+{offset}")
-                        } else {
-                            println!("bp-{offset}")
-                        }
-                    },
-                    AsmLoadStoreMode::Immediate { absolute_address } => {
-                        println!("_imm{bytes_str}{ops_indent}{absolute_address}");
-                    },
-                }
-            },
-            AssemblyInstruction::PushImmediate { bytes, shift_size, immediate } => {
-                let bytes_str = if *bytes == 4 { "".to_string() } else { format!("{bytes}", bytes = bytes *8) };
-               
-                if *shift_size > 0 {
-                    println!("push_imm{bytes_str}{ops_indent}{immediate} <<{shift_size}", immediate=u16::from_le_bytes(*immediate));
-                } else {
-                    println!("push_imm{bytes_str}{ops_indent}{immediate}", immediate=u16::from_le_bytes(*immediate));
-                }
-            },
-            AssemblyInstruction::IntegerBitwiseBinaryOperation { bytes, operation, sign, immediate } => {
-                let bytes_str = if *bytes == 4 { "".to_string() } else { format!("{bytes}", bytes = bytes *8) };
-               
-                let op = match operation {
-                    AsmIntegerBitwiseBinaryOp::And => "and",
-                    AsmIntegerBitwiseBinaryOp::Or => "or",
-                    AsmIntegerBitwiseBinaryOp::Xor => "xor",
-                };
-                let s = match sign {
-                    AsmSignFlag::Signed => "s",
-                    AsmSignFlag::Unsigned => "u",
-                };
-               
-                match immediate {
-                    Some(imm) => {
-                        println!("{op}{s}_imm{bytes_str}{ops_indent}{immediate}", immediate=u16::from_le_bytes(*imm));
-                    },
-                    None => {
-                        println!("{op}{s}{bytes_str}");
-                    }
-                }
-            },
-            AssemblyInstruction::IntegerArithmeticBinaryOperation { bytes, operation, sign, immediate } => {
-                let bytes_str = if *bytes == 4 { "".to_string() } else { format!("{bytes}", bytes = bytes *8) };
-               
-                let op = match operation {
-                    AsmArithmeticBinaryOp::Sum => "sum",
-                    AsmArithmeticBinaryOp::Subtract => "sub",
-                    AsmArithmeticBinaryOp::Multiply => "mul",
-                    AsmArithmeticBinaryOp::Divide => "div",
-                    AsmArithmeticBinaryOp::Power => "pow",
-                };
-                let s = match sign {
-                    AsmSignFlag::Signed => "s",
-                    AsmSignFlag::Unsigned => "u",
-                };
-               
-                match immediate {
-                    Some(imm) => {
-                        println!("{op}{s}_imm{bytes_str}{ops_indent}{immediate}", immediate=u16::from_le_bytes(*imm));
-                    },
-                    None => {
-                        println!("{op}{s}{bytes_str}");
-                    }
-                }
-            },
-            AssemblyInstruction::IntegerCompareBinaryOperation { bytes, operation, sign, immediate } => {
-                let bytes_str = if *bytes == 4 { "".to_string() } else { format!("{bytes}", bytes = bytes *8) };
-               
-                let op = match operation {
-                    asm::asm::AsmIntegerCompareBinaryOp::Equals => "eq",
-                    asm::asm::AsmIntegerCompareBinaryOp::NotEquals => "ne",
-                    asm::asm::AsmIntegerCompareBinaryOp::LessThan => "lt",
-                    asm::asm::AsmIntegerCompareBinaryOp::LessThanOrEquals => "le",
-                    asm::asm::AsmIntegerCompareBinaryOp::GreaterThan => "gt",
-                    asm::asm::AsmIntegerCompareBinaryOp::GreaterThanOrEquals => "ge",
-                };
-                let s = match sign {
-                    AsmSignFlag::Signed => "s",
-                    AsmSignFlag::Unsigned => "u",
-                };
-               
-                match immediate {
-                    Some(imm) => {
-                        println!("{op}{s}_imm{bytes_str}{ops_indent}{immediate}",  immediate=u16::from_le_bytes(*imm));
-                    },
-                    None => {
-                        println!("{op}{s}{bytes_str}");
-                    }
-                }
-            },
-            AssemblyInstruction::PopRegister { register } => {
-                match register {
-                    AsmControlRegister::BasePointer => println!("pop_reg{ops_indent}bp"),
-                    AsmControlRegister::StackPointer => println!("pop_reg{ops_indent}sp"),
-                    AsmControlRegister::InstructionPointer => println!("pop_reg{ops_indent}ip"),
-                }
-            }
-            AssemblyInstruction::PushRegister { register } => {
-                match register {
-                    AsmControlRegister::BasePointer => println!("push_reg{ops_indent}bp"),
-                    AsmControlRegister::StackPointer => println!("push_reg{ops_indent}sp"),
-                    AsmControlRegister::InstructionPointer => println!("push_reg{ops_indent}ip"),
-                }
-            },
-            AssemblyInstruction::PopBytes { bytes } => {
-                println!("pop{ops_indent}{bytes}");
-            },
-            AssemblyInstruction::Label { label } => {
-                println!("\n{label}:");
-            }
-            AssemblyInstruction::UnresolvedCall { label } => {
-                match label {
-                    Some(label) => println!("call{ops_indent}\t{label}"),
-                    None => println!("call_stack"),
-                }
-            },
-            AssemblyInstruction::UnresolvedJumpIfZero { label } => {
-                match label {
-                    Some(label) => println!("jz{ops_indent}\t{label}"),
-                    None => println!("jz_stack"),
-                }
-            },
-            AssemblyInstruction::UnresolvedJumpIfNotZero { label } => {
-                match label {
-                    Some(label) => println!("jnz{ops_indent}\t{label}"),
-                    None => println!("jnz_stack"),
-                }
-            }
-            AssemblyInstruction::UnresolvedJump { label } => {
-                match label {
-                    Some(label) => println!("jmp{ops_indent}\t{label}"),
-                    None => println!("jmp_stack"),
-                }
-            }
-            AssemblyInstruction::Call { offset } => {
-                println!("call{ops_indent}\t{offset}");
-            },
-            AssemblyInstruction::CallFromStack => {
-                println!("call_stack");
-            },
-            AssemblyInstruction::JumpIfZero { offset } => {
-                println!("jz{ops_indent}\t{offset}");
-            }
-            AssemblyInstruction::JumpIfZeroFromStack => {
-                println!("jz_stack");
-            }
-            AssemblyInstruction::JumpIfNotZero { offset }  => {
-                println!("jnz{ops_indent}\t{offset}");
-            }
-            AssemblyInstruction::JumpIfNotZeroFromStack => {
-                println!("jnz_stack");
-            }
-            AssemblyInstruction::Jump { offset } => {
-                println!("jmp{ops_indent}\t{offset}");
-            }
-            AssemblyInstruction::JumpFromStack => {
-                println!("jmp_stack");
-            },
-            AssemblyInstruction::Exit => println!("exit"),
-            AssemblyInstruction::Return => println!("return"),
+            AssemblyInstruction::Label { .. } => println!("\n{}", print_instruction(inst)),
+            _ => println!("\t{}", print_instruction(inst)),
         }
     }
-}
\ No newline at end of file
+}
@@ -1,16 +1,17 @@
-use core::num;
+use std::fmt::Display;
+use std::str::FromStr;
 
 use crate::freyr::{
-    asm::asm::{AsmIntegerBitwiseBinaryOp, AsmIntegerCompareBinaryOp},
+    asm::asm::{AsmIntegerBitwiseBinaryOp, AsmIntegerCompareBinaryOp, AsmShiftDirection},
     vm::instructions::{
         ArithmeticOperation, BitwiseOperation, AddressJumpAddressSource, CompareOperation,
-        ControlRegister, Instruction, LeftShift, LoadStoreAddressingMode, NumberOfBytes,
-        OperationMode, SignFlag,
+        ControlRegister, HostFunction, Instruction, LeftShift, LoadStoreAddressingMode, NumberKind, NumberOfBytes,
+        OperationMode, ShiftDirection, SignFlag,
     },
 };
 
 use super::asm::{
-    AsmArithmeticBinaryOp, AsmControlRegister, AsmLoadStoreMode, AsmSignFlag, AssemblyInstruction,
+    AsmArithmeticBinaryOp, AsmControlRegister, AsmHostFunction, AsmLoadStoreMode, AsmNumberKind, AsmSignFlag, AssemblyInstruction,
 };
 
 fn split_in_whitespace_tab_etc_ignore_comment(asm_line: &str) -> Vec<String> {
@@ -63,16 +64,112 @@ fn split_instruction_mnemonic(mnemonic: &str) -> Vec<String> {
     return all_parts;
 }
 
-fn parse_asm_line(line: u32, asm_line: &str) -> Option<AssemblyInstruction> {
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: u32,
+    pub message: String,
+}
+
+impl Display for AsmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{message} at line {line}", message = self.message, line = self.line)
+    }
+}
+
+fn asm_error<T>(line: u32, message: String) -> Result<T, AsmError> {
+    Err(AsmError { line, message })
+}
+
+fn operand(line: u32, splitted: &[String], index: usize) -> Result<&str, AsmError> {
+    match splitted.get(index) {
+        Some(operand) => Ok(operand.as_str()),
+        None => asm_error(line, format!("Missing operand {index} in {instruction}", instruction = splitted[0])),
+    }
+}
+
+fn parse_number<T: FromStr>(line: u32, text: &str) -> Result<T, AsmError> {
+    match text.parse::<T>() {
+        Ok(number) => Ok(number),
+        Err(_) => asm_error(line, format!("Could not parse number {text}")),
+    }
+}
+
+fn parse_size(line: u32, size: &str) -> Result<u8, AsmError> {
+    Ok(parse_number::<u8>(line, size)? / 8)
+}
+
+//operands of convert look like s32, u8 or f64: the kind followed by the size in bits
+fn parse_number_type(line: u32, operand: &str) -> Result<(AsmNumberKind, u8), AsmError> {
+    let kind = match operand.chars().next() {
+        Some('s') => AsmNumberKind::Signed,
+        Some('u') => AsmNumberKind::Unsigned,
+        Some('f') => AsmNumberKind::Float,
+        _ => return asm_error(line, format!("Number type {operand} must start with s, u or f")),
+    };
+    Ok((kind, parse_size(line, &operand[1..])?))
+}
+
+fn parse_relative_offset(line: u32, instruction: &str, offset: &str) -> Result<i32, AsmError> {
+    if !offset.contains("bp") {
+        return asm_error(line, format!("Please say BP in the offset for {instruction} to make it clear where the data is"));
+    }
+    if !offset.contains('+') && !offset.contains('-') {
+        return asm_error(line, format!("Please say whether the offset in {instruction} is positive or negative"));
+    }
+    parse_number(line, &offset.replace("bp", ""))
+}
+
+fn parse_load_store_mode(line: u32, splitted: &[String], instruction: &str, rest: &[&str]) -> Result<(u8, AsmLoadStoreMode), AsmError> {
+    match rest {
+        ["rel", size] => {
+            let offset = parse_relative_offset(line, instruction, operand(line, splitted, 1)?)?;
+            Ok((parse_size(line, size)?, AsmLoadStoreMode::Relative { offset }))
+        }
+        ["imm", size] => {
+            let address = parse_number::<u32>(line, operand(line, splitted, 1)?)?;
+            Ok((
+                parse_size(line, size)?,
+                AsmLoadStoreMode::Immediate {
+                    absolute_address: address,
+                },
+            ))
+        }
+        [size] => Ok((parse_size(line, size)?, AsmLoadStoreMode::StackPop)),
+        _ => asm_error(line, format!("Could not parse instruction {instruction} {rest:?}")),
+    }
+}
+
+//immediates are written as i32 but only the 2 lower bytes go in the instruction
+fn parse_binary_op_operands(line: u32, splitted: &[String], rest: &[&str]) -> Result<(Option<[u8; 2]>, u8), AsmError> {
+    match rest {
+        ["imm", size] => {
+            let immediate = parse_number::<i32>(line, operand(line, splitted, 1)?)?.to_le_bytes();
+            Ok((Some([immediate[0], immediate[1]]), parse_size(line, size)?))
+        }
+        [size] => Ok((None, parse_size(line, size)?)),
+        _ => asm_error(line, format!("Failed to parse instruction: {rest:?}")),
+    }
+}
+
+fn parse_register(line: u32, splitted: &[String]) -> Result<AsmControlRegister, AsmError> {
+    match operand(line, splitted, 1)? {
+        "bp" => Ok(AsmControlRegister::BasePointer),
+        "ip" => Ok(AsmControlRegister::InstructionPointer),
+        "sp" => Ok(AsmControlRegister::StackPointer),
+        _ => asm_error(line, format!("control register not found, invalid instruction: {splitted:?}")),
+    }
+}
+
+fn parse_asm_line(line: u32, asm_line: &str) -> Result<Option<AssemblyInstruction>, AsmError> {
     let splitted = split_in_whitespace_tab_etc_ignore_comment(asm_line);
     if splitted.len() == 1 && splitted[0] == "" {
-        return None;
+        return Ok(None);
     }
 
     if splitted[0].ends_with(":") {
-        return Some(AssemblyInstruction::Label {
+        return Ok(Some(AssemblyInstruction::Label {
             label: splitted[0].replace(":", ""),
-        });
+        }));
     }
 
     let mnemonics = split_instruction_mnemonic(splitted[0].to_lowercase().as_str());
@@ -80,18 +177,18 @@ fn parse_asm_line(line: u32, asm_line: &str) -> Option<AssemblyInstruction> {
 
     let mnems_str = mnems_str_vec.as_slice();
 
-    Some(match mnems_str {
+    Ok(Some(match mnems_str {
         ["stackoffset"] => {
-            let arg = splitted[1].parse().unwrap();
+            let arg = parse_number(line, operand(line, &splitted, 1)?)?;
             AssemblyInstruction::StackOffset { bytes: arg }
         }
         ["push", "imm", size] => {
-            let bytes = size.parse::<u8>().unwrap() / 8;
-            let immediate: u16 = splitted[1].parse().unwrap();
+            let bytes = parse_size(line, size)?;
+            let immediate: u16 = parse_number(line, operand(line, &splitted, 1)?)?;
             let left_shift = splitted.len() > 2 && splitted[2].contains("<<");
 
             let shift_size = if left_shift {
-                splitted[2].replace("<<", "").parse().unwrap()
+                parse_number(line, &splitted[2].replace("<<", ""))?
             } else {
                 0
             };
@@ -103,94 +200,22 @@ fn parse_asm_line(line: u32, asm_line: &str) -> Option<AssemblyInstruction> {
             }
         }
         ["storeaddr", rest @ ..] => {
-            let (bytes, lsm) = match rest {
-                ["rel", size] => {
-                    let bytes = size.parse::<u8>().unwrap() / 8;
-                    if !splitted[1].contains("bp") {
-                        panic!("Please say BP in the offset for storeaddr to make it clear where you're storing data at line {line}");
-                    }
-                    if !splitted[1].contains("+") && !splitted[1].contains("-") {
-                        panic!("Please say whether the offset in storeaddr is positive or negative at line {line}");
-                    }
-                    let remove_bp = splitted[1].replace("bp", "");
-                    let offset = remove_bp.parse().unwrap();
-                    (bytes, AsmLoadStoreMode::Relative { offset: offset })
-                }
-                ["imm", size] => {
-                    let bytes = size.parse::<u8>().unwrap() / 8;
-                    let address = splitted[1].parse::<u32>().unwrap();
-
-                    (
-                        bytes,
-                        AsmLoadStoreMode::Immediate {
-                            absolute_address: address,
-                        },
-                    )
-                }
-                [size] => {
-                    let bytes = size.parse::<u8>().unwrap() / 8;
-
-                    (bytes, AsmLoadStoreMode::StackPop)
-                }
-                _ => panic!("Could not parse instruction storeaddr {rest:?} at line {line}"),
-            };
+            let (bytes, lsm) = parse_load_store_mode(line, &splitted, "storeaddr", rest)?;
             AssemblyInstruction::StoreAddress {
                 bytes: bytes,
                 mode: lsm,
             }
         }
-
         ["loadaddr", rest @ ..] => {
-            let (bytes, lsm) = match &rest {
-                ["rel", size] => {
-                    let bytes = size.parse::<u8>().unwrap() / 8;
-                    if !splitted[1].contains("bp") {
-                        panic!("Please say BP in the offset for storeaddr to make it clear where you're storing data at line {line}");
-                    }
-                    if !splitted[1].contains("+") && !splitted[1].contains("-") {
-                        panic!("Please say whether the offset in storeaddr is positive or negative at line {line}");
-                    }
-                    let remove_bp = splitted[1].replace("bp", "");
-                    let offset = remove_bp.parse().unwrap();
-                    (bytes, AsmLoadStoreMode::Relative { offset: offset })
-                }
-                ["imm", size] => {
-                    let bytes = size.parse::<u8>().unwrap() / 8;
-                    let address = splitted[1].parse::<u32>().unwrap();
-
-                    (
-                        bytes,
-                        AsmLoadStoreMode::Immediate {
-                            absolute_address: address,
-                        },
-                    )
-                }
-                [size] => {
-                    let bytes = size.parse::<u8>().unwrap() / 8;
-                    (bytes, AsmLoadStoreMode::StackPop)
-                }
-                _ => panic!("Could not parse instruction {asm_line} at line {line}"),
-            };
+            let (bytes, lsm) = parse_load_store_mode(line, &splitted, "loadaddr", rest)?;
             AssemblyInstruction::LoadAddress {
                 bytes: bytes,
                 mode: lsm,
             }
         }
-        [operation @ ("sums"|"subs"| "divs"| "muls"| "eqs"|"les"|"lts"|"ges"|"gts"|"nes"|
-                             "sumu"|"subu"| "divu"| "mulu"| "equ"|"leu"|"ltu"|"geu"|"gtu"|"neu"), rest @ ..] => {
-            let (immediate, num_bytes) = match rest {
-                ["imm", size] => {
-                    let immediate = &splitted[1].parse::<i32>().unwrap()
-                        .to_le_bytes()[0..2];
-                    let imm_2bytes: [u8; 2] = immediate.try_into().unwrap();
-                    let bytes = size.parse::<u8>().unwrap() / 8;
-                    (Some(imm_2bytes), bytes)
-                },
-                [size] => {
-                    (None, size.parse::<u8>().unwrap() / 8)
-                },
-                _ => panic!("Failed to parse instruction: {mnems_str:?}")
-            };
+        [operation @ ("sums"|"subs"| "divs"| "muls"| "rems"| "pows"| "eqs"|"les"|"lts"|"ges"|"gts"|"nes"|
+                             "sumu"|"subu"| "divu"| "mulu"| "remu"| "powu"| "equ"|"leu"|"ltu"|"geu"|"gtu"|"neu"), rest @ ..] => {
+            let (immediate, num_bytes) = parse_binary_op_operands(line, &splitted, rest)?;
 
             if operation.len() == 4 {
                 //in this case it's an arithmetic op
@@ -202,7 +227,7 @@ fn parse_asm_line(line: u32, asm_line: &str) -> Option<AssemblyInstruction> {
                     sign: sign_flag,
                     immediate: immediate,
                 }
-            } else if operation.len() == 3 {
+            } else {
                 let arith_op = get_compare_op(&operation[0..2]);
                 let sign_flag = get_sign(operation.chars().nth(2).unwrap());
                 AssemblyInstruction::IntegerCompareBinaryOperation {
@@ -211,39 +236,34 @@ fn parse_asm_line(line: u32, asm_line: &str) -> Option<AssemblyInstruction> {
                     sign: sign_flag,
                     immediate: immediate,
                 }
-            } else {
-                panic!("unknown op: {operation:?}")
             }
-            
         },
+        [operation @ ("sumf"|"subf"|"mulf"|"divf"|"remf"|"powf"), size] => {
+            AssemblyInstruction::FloatArithmeticBinaryOperation {
+                bytes: parse_size(line, size)?,
+                operation: get_arith_op(&operation[0..3]),
+            }
+        }
+        [operation @ ("eqf"|"nef"|"ltf"|"lef"|"gtf"|"gef"), size] => {
+            AssemblyInstruction::FloatCompareBinaryOperation {
+                bytes: parse_size(line, size)?,
+                operation: get_compare_op(&operation[0..2]),
+            }
+        }
         [operation @ ("and"|"or"|"xor"|"andk"|"ork"|"xork"), rest @ ..] => {
-            let (immediate, num_bytes) = match rest {
-                ["imm", size] => {
-                    let immediate = &splitted[1].parse::<i32>().unwrap()
-                        .to_le_bytes()[0..2];
-                    let imm_2bytes: [u8; 2] = immediate.try_into().unwrap();
-                    let bytes = size.parse::<u8>().unwrap() / 8;
-                    (Some(imm_2bytes), bytes)
-                },
-                [size] => {
-                    (None, size.parse::<u8>().unwrap() / 8)
-                },
-                _ => panic!("Failed to parse instruction: {mnems_str:?}")
-            };
+            let (immediate, num_bytes) = parse_binary_op_operands(line, &splitted, rest)?;
 
-            let arith_op_str = &operation[0.. operation.len() - 1];
-            let arith_op = match arith_op_str {
+            //the k suffix means the signed variant
+            let arith_op = match operation.strip_suffix('k').unwrap_or(operation) {
                 "and" => AsmIntegerBitwiseBinaryOp::And,
                 "or" => AsmIntegerBitwiseBinaryOp::Or,
-                "xor" => AsmIntegerBitwiseBinaryOp::Xor,
-                _ => panic!("Unknown op: {arith_op_str:?}")
+                _ => AsmIntegerBitwiseBinaryOp::Xor,
             };
             let sign_flag = match operation.chars().last().unwrap() {
                 'k' => AsmSignFlag::Signed,
                 _ => AsmSignFlag::Unsigned,
             };
-                
-           
+
             AssemblyInstruction::IntegerBitwiseBinaryOperation {
                 bytes: num_bytes,
                 operation: arith_op,
@@ -251,62 +271,73 @@ fn parse_asm_line(line: u32, asm_line: &str) -> Option<AssemblyInstruction> {
                 immediate: immediate,
             }
         },
-        ["pop", "reg"] => {
-            let register = match splitted[1].as_str() {
-                "bp" => AsmControlRegister::BasePointer,
-                "ip" => AsmControlRegister::InstructionPointer,
-                "sp" => AsmControlRegister::StackPointer,
-                _ => panic!(
-                    "control register not found, invalid instruction: {splitted:?} at line {line}"
-                ),
+        [operation @ ("shls"|"shlu"|"shrs"|"shru"), rest @ ..] => {
+            let (immediate, num_bytes) = parse_binary_op_operands(line, &splitted, rest)?;
+            let direction = match &operation[0..3] {
+                "shl" => AsmShiftDirection::Left,
+                _ => AsmShiftDirection::Right,
             };
-
-            AssemblyInstruction::PopRegister { register: register }
-        }
-        ["push", "reg"] => {
-            let register = match splitted[1].as_str() {
-                "bp" => AsmControlRegister::BasePointer,
-                "ip" => AsmControlRegister::InstructionPointer,
-                "sp" => AsmControlRegister::StackPointer,
-                _ => panic!(
-                    "control register not found, invalid instruction: {splitted:?} at line {line}"
-                ),
-            };
-
-            AssemblyInstruction::PushRegister { register: register }
-        }
+            AssemblyInstruction::BitShift {
+                bytes: num_bytes,
+                direction,
+                sign: get_sign(operation.chars().nth(3).unwrap()),
+                immediate: immediate.map(|imm| imm[0]),
+            }
+        },
+        ["pop", "reg"] => AssemblyInstruction::PopRegister {
+            register: parse_register(line, &splitted)?,
+        },
+        ["push", "reg"] => AssemblyInstruction::PushRegister {
+            register: parse_register(line, &splitted)?,
+        },
         ["pop", size] => {
-            let bytes = size.parse::<u8>().unwrap() / 8;
+            let bytes = parse_size(line, size)?;
             AssemblyInstruction::PopBytes { bytes }
         }
         ["call"] => AssemblyInstruction::UnresolvedCall {
-            label: Some(splitted[1].to_string()),
+            label: Some(operand(line, &splitted, 1)?.to_string()),
         },
         ["call", "stack"] => AssemblyInstruction::UnresolvedCall { label: None },
         ["jz"] => AssemblyInstruction::UnresolvedJumpIfZero {
-            label: Some(splitted[1].to_string())
+            label: Some(operand(line, &splitted, 1)?.to_string())
         },
         ["jz", "stack"]  => AssemblyInstruction::UnresolvedJumpIfZero {
             label: None
         },
         ["jnz"] => AssemblyInstruction::UnresolvedJumpIfNotZero {
-            label: Some(splitted[1].to_string())
+            label: Some(operand(line, &splitted, 1)?.to_string())
         },
         ["jnz", "stack"] => AssemblyInstruction::UnresolvedJumpIfNotZero {
             label: None
         },
         ["jmp"] => AssemblyInstruction::UnresolvedJump {
-            label: Some(splitted[1].to_string())
+            label: Some(operand(line, &splitted, 1)?.to_string())
         },
         ["jmp", "stack"] => AssemblyInstruction::UnresolvedJump {
             label: None
         },
+        ["convert"] => {
+            let (from_kind, from_bytes) = parse_number_type(line, operand(line, &splitted, 1)?)?;
+            let (to_kind, to_bytes) = parse_number_type(line, operand(line, &splitted, 2)?)?;
+            AssemblyInstruction::ConvertNumber { from_bytes, from_kind, to_bytes, to_kind }
+        }
+        ["hostcall"] => {
+            let function = match operand(line, &splitted, 1)? {
+                "print" => AsmHostFunction::Print,
+                "print_bool" => AsmHostFunction::PrintBool,
+                "print_str" => AsmHostFunction::PrintStr,
+                other => return asm_error(line, format!("Unknown host function {other}")),
+            };
+            let (kind, bytes) = parse_number_type(line, operand(line, &splitted, 2)?)?;
+            AssemblyInstruction::HostCall { function, bytes, kind }
+        }
+        ["noop"] => AssemblyInstruction::Noop,
         ["exit"] => AssemblyInstruction::Exit,
         ["return"] => AssemblyInstruction::Return,
         _ => {
-            panic!("Freyr assembly instruction not recognized at line {line}: {mnems_str:?}")
+            return asm_error(line, format!("Freyr assembly instruction not recognized: {mnems_str:?}"));
         }
-    })
+    }))
 }
 
 fn get_sign(flag: char) -> AsmSignFlag {
@@ -324,6 +355,7 @@ fn get_arith_op(operation: &str) -> AsmArithmeticBinaryOp {
         "mul" => AsmArithmeticBinaryOp::Multiply,
         "div" => AsmArithmeticBinaryOp::Divide,
         "pow" => AsmArithmeticBinaryOp::Power,
+        "rem" => AsmArithmeticBinaryOp::Remainder,
         _ => panic!("Parse asm arith failed: {operation}")
     }
 }
@@ -340,14 +372,39 @@ fn get_compare_op(operation: &str) -> AsmIntegerCompareBinaryOp {
     }
 }
 
+//blank lines and ; comments are skipped, the first error stops the assembler
+pub fn assemble(asm: &str) -> Result<Vec<AssemblyInstruction>, AsmError> {
+    let mut instructions = vec![];
+    for (i, asm_line) in asm.lines().enumerate() {
+        if let Some(instruction) = parse_asm_line(i as u32 + 1, asm_line)? {
+            instructions.push(instruction);
+        }
+    }
+    Ok(instructions)
+}
+
 pub fn parse_asm(asm: &str) -> Vec<AssemblyInstruction> {
-    let lines = asm.lines();
-    let parsed = lines
-        .into_iter()
-        .enumerate()
-        .map(|(i, x)| parse_asm_line(i as u32 + 1, x));
+    match assemble(asm) {
+        Ok(instructions) => instructions,
+        Err(e) => panic!("{e}"),
+    }
+}
 
-    return parsed.filter(|x| x.is_some()).map(|x| x.unwrap()).collect();
+//jump operands like +2 or -3 are offsets relative to the jump itself, not labels
+fn relative_offset(label: &str) -> Option<i32> {
+    if label.starts_with('+') || label.starts_with('-') {
+        label.parse::<i32>().ok()
+    } else {
+        None
+    }
+}
+
+//a plain number is an absolute instruction index, as printed by the disassembler
+fn absolute_offset(label_offsets: &std::collections::HashMap<String, u32>, label: &str) -> u32 {
+    match label_offsets.get(label) {
+        Some(offset) => *offset,
+        None => label.parse::<u32>().unwrap_or_else(|_| panic!("Could not find label {label}")),
+    }
 }
 
 pub fn resolve(instructions: &[AssemblyInstruction]) -> Vec<AssemblyInstruction> {
@@ -371,28 +428,43 @@ pub fn resolve(instructions: &[AssemblyInstruction]) -> Vec<AssemblyInstruction>
             AssemblyInstruction::Label { .. } => {
                 continue; //ignore labels
             }
+            AssemblyInstruction::UnresolvedCall { label: Some(label), .. } if relative_offset(label).is_some() => {
+                current_instruction_index = current_instruction_index + 1;
+                resolved_instructions.push(AssemblyInstruction::CallRelative {
+                    offset: relative_offset(label).unwrap(),
+                })
+            }
             AssemblyInstruction::UnresolvedCall { label: Some(label), .. } => {
-                let offset = label_offsets.get(label);
                 current_instruction_index = current_instruction_index + 1;
                 resolved_instructions.push(AssemblyInstruction::Call {
-                    offset: *offset.expect(&format!("Could not find label {label}")),
+                    offset: absolute_offset(&label_offsets, label),
                 })
             }
             AssemblyInstruction::UnresolvedCall { label: None, .. } => {
                 resolved_instructions.push(AssemblyInstruction::CallFromStack)
             }
+            AssemblyInstruction::UnresolvedJumpIfZero { label: Some(label), .. } if relative_offset(label).is_some() => {
+                current_instruction_index = current_instruction_index + 1;
+                resolved_instructions.push(AssemblyInstruction::JumpIfZeroRelative {
+                    offset: relative_offset(label).unwrap(),
+                })
+            }
             AssemblyInstruction::UnresolvedJumpIfZero { label: Some(label), .. } => {
-                let offset = label_offsets.get(label);
                 current_instruction_index = current_instruction_index + 1;
                 resolved_instructions.push(AssemblyInstruction::JumpIfZero {
-                    offset: *offset.expect(&format!("Could not find label {label}")),
+                    offset: absolute_offset(&label_offsets, label),
+                })
+            }
+            AssemblyInstruction::UnresolvedJumpIfNotZero { label: Some(label), .. } if relative_offset(label).is_some() => {
+                current_instruction_index = current_instruction_index + 1;
+                resolved_instructions.push(AssemblyInstruction::JumpIfNotZeroRelative {
+                    offset: relative_offset(label).unwrap(),
                 })
             }
             AssemblyInstruction::UnresolvedJumpIfNotZero { label: Some(label), .. } => {
-                let offset = label_offsets.get(label);
                 current_instruction_index = current_instruction_index + 1;
                 resolved_instructions.push(AssemblyInstruction::JumpIfNotZero {
-                    offset: *offset.expect(&format!("Could not find label {label}")),
+                    offset: absolute_offset(&label_offsets, label),
                 })
             }
             AssemblyInstruction::UnresolvedJumpIfZero { label: None, .. } => {
@@ -401,11 +473,16 @@ pub fn resolve(instructions: &[AssemblyInstruction]) -> Vec<AssemblyInstruction>
             AssemblyInstruction::UnresolvedJumpIfNotZero { label: None, .. } => {
                 resolved_instructions.push(AssemblyInstruction::JumpIfNotZeroFromStack)
             }
+            AssemblyInstruction::UnresolvedJump { label: Some(label) } if relative_offset(label).is_some() => {
+                current_instruction_index = current_instruction_index + 1;
+                resolved_instructions.push(AssemblyInstruction::JumpRelative {
+                    offset: relative_offset(label).unwrap(),
+                })
+            }
             AssemblyInstruction::UnresolvedJump { label: Some(label) } => {
-                let offset = label_offsets.get(label);
                 current_instruction_index = current_instruction_index + 1;
                 resolved_instructions.push(AssemblyInstruction::Jump {
-                    offset: *offset.expect(&format!("Could not find label {label}")),
+                    offset: absolute_offset(&label_offsets, label),
                 })
             }
             AssemblyInstruction::UnresolvedJump { label: None } => {
@@ -418,6 +495,24 @@ pub fn resolve(instructions: &[AssemblyInstruction]) -> Vec<AssemblyInstruction>
     return resolved_instructions;
 }
 
+//Offset to encode in a RelativeForward jump at instruction from_index so that it lands on to_index.
+//The VM adds the offset to the address of the jump itself, so jumping to the next instruction is an offset of 1.
+pub fn resolve_forward_jump(from_index: u32, to_index: u32) -> u32 {
+    assert!(to_index > from_index, "Forward jump from {from_index} to {to_index} does not go forward");
+    let offset = to_index - from_index;
+    //jump offsets are 25 bits wide in the encoded instruction
+    assert!(offset < 1 << 25, "Forward jump offset {offset} does not fit in the instruction");
+    offset
+}
+
+fn relative_source(offset: i32) -> AddressJumpAddressSource {
+    if offset < 0 {
+        AddressJumpAddressSource::RelativeBackward
+    } else {
+        AddressJumpAddressSource::RelativeForward
+    }
+}
+
 pub fn as_freyr_instructions(instructions: &[AssemblyInstruction]) -> Vec<Instruction> {
     fn load_store(ls: AsmLoadStoreMode) -> (LoadStoreAddressingMode, u32) {
         match ls {
@@ -449,13 +544,7 @@ pub fn as_freyr_instructions(instructions: &[AssemblyInstruction]) -> Vec<Instru
     }
 
     fn lshift_size(shift: &u8) -> LeftShift {
-        match shift {
-            0 => LeftShift::None,
-            16 => LeftShift::Shift16,
-            32 => LeftShift::Shift32,
-            48 => LeftShift::Shift48,
-            _ => panic!("Unsupported shift size: {shift}"),
-        }
+        LeftShift::from_shift_size(*shift).unwrap_or_else(|| panic!("Unsupported shift size: {shift}"))
     }
 
     fn arith_op(op: &AsmArithmeticBinaryOp) -> ArithmeticOperation {
@@ -465,6 +554,7 @@ pub fn as_freyr_instructions(instructions: &[AssemblyInstruction]) -> Vec<Instru
             AsmArithmeticBinaryOp::Subtract => ArithmeticOperation::Subtract,
             AsmArithmeticBinaryOp::Divide => ArithmeticOperation::Divide,
             AsmArithmeticBinaryOp::Power => ArithmeticOperation::Power,
+            AsmArithmeticBinaryOp::Remainder => ArithmeticOperation::Remainder,
         }
     }
 
@@ -494,6 +584,14 @@ pub fn as_freyr_instructions(instructions: &[AssemblyInstruction]) -> Vec<Instru
         }
     }
 
+    fn number_kind(kind: &AsmNumberKind) -> NumberKind {
+        match kind {
+            AsmNumberKind::Signed => NumberKind::SignedInteger,
+            AsmNumberKind::Unsigned => NumberKind::UnsignedInteger,
+            AsmNumberKind::Float => NumberKind::Float,
+        }
+    }
+
     fn control_register(sign: &AsmControlRegister) -> ControlRegister {
         match sign {
             AsmControlRegister::BasePointer => ControlRegister::BasePointer,
@@ -569,6 +667,22 @@ pub fn as_freyr_instructions(instructions: &[AssemblyInstruction]) -> Vec<Instru
                     operand,
                 }
             }
+            AssemblyInstruction::BitShift { bytes, direction, sign, immediate } => {
+                let (mode, operand) = match immediate {
+                    Some(operand) => (OperationMode::StackAndImmediate, *operand),
+                    None => (OperationMode::PureStack, 0),
+                };
+                Instruction::BitShift {
+                    bytes: num_bytes(bytes),
+                    direction: match direction {
+                        AsmShiftDirection::Left => ShiftDirection::Left,
+                        AsmShiftDirection::Right => ShiftDirection::Right,
+                    },
+                    mode,
+                    sign: sign_flag(sign),
+                    operand,
+                }
+            }
             AssemblyInstruction::IntegerCompareBinaryOperation { bytes, operation, sign, immediate } => {
                 let (mode, operand) = match immediate {
                     Some(operand) => (OperationMode::StackAndImmediate, *operand),
@@ -582,7 +696,14 @@ pub fn as_freyr_instructions(instructions: &[AssemblyInstruction]) -> Vec<Instru
                     operand,
                 }
             },
-
+            AssemblyInstruction::FloatArithmeticBinaryOperation { bytes, operation } => Instruction::FloatArithmetic {
+                bytes: num_bytes(bytes),
+                operation: arith_op(operation),
+            },
+            AssemblyInstruction::FloatCompareBinaryOperation { bytes, operation } => Instruction::FloatCompare {
+                bytes: num_bytes(bytes),
+                operation: compare_op(operation),
+            },
             AssemblyInstruction::PopRegister { register } => Instruction::PopIntoRegister {
                 control_register: control_register(register),
             },
@@ -592,6 +713,21 @@ pub fn as_freyr_instructions(instructions: &[AssemblyInstruction]) -> Vec<Instru
             AssemblyInstruction::PopBytes { bytes } => Instruction::Pop {
                 bytes: num_bytes(bytes),
             },
+            AssemblyInstruction::ConvertNumber { from_bytes, from_kind, to_bytes, to_kind } => Instruction::ConvertNumber {
+                from_bytes: num_bytes(from_bytes),
+                from_kind: number_kind(from_kind),
+                to_bytes: num_bytes(to_bytes),
+                to_kind: number_kind(to_kind),
+            },
+            AssemblyInstruction::HostCall { function, bytes, kind } => Instruction::HostCall {
+                function: match function {
+                    AsmHostFunction::Print => HostFunction::Print,
+                    AsmHostFunction::PrintBool => HostFunction::PrintBool,
+                    AsmHostFunction::PrintStr => HostFunction::PrintStr,
+                },
+                bytes: num_bytes(bytes),
+                kind: number_kind(kind),
+            },
             AssemblyInstruction::UnresolvedCall { label } => {
                 panic!("Unresolved call reached ASM compiler!")
             }
@@ -599,6 +735,10 @@ pub fn as_freyr_instructions(instructions: &[AssemblyInstruction]) -> Vec<Instru
                 source: AddressJumpAddressSource::FromOperand,
                 offset: *offset,
             },
+            AssemblyInstruction::CallRelative { offset } => Instruction::Call {
+                source: relative_source(*offset),
+                offset: offset.unsigned_abs(),
+            },
             AssemblyInstruction::CallFromStack => Instruction::Call {
                 source: AddressJumpAddressSource::PopFromStack,
                 offset: 0,
@@ -611,6 +751,10 @@ pub fn as_freyr_instructions(instructions: &[AssemblyInstruction]) -> Vec<Instru
             AssemblyInstruction::JumpIfNotZeroFromStack { .. } => Instruction::JumpIfNotZero { source: AddressJumpAddressSource::PopFromStack, offset: 0 },
             AssemblyInstruction::Jump { offset } => Instruction::JumpUnconditional { source: AddressJumpAddressSource::FromOperand, offset: *offset },
             AssemblyInstruction::JumpFromStack => Instruction::JumpUnconditional { source: AddressJumpAddressSource::PopFromStack, offset: 0 },
+            AssemblyInstruction::JumpIfZeroRelative { offset } => Instruction::JumpIfZero { source: relative_source(*offset), offset: offset.unsigned_abs() },
+            AssemblyInstruction::JumpIfNotZeroRelative { offset } => Instruction::JumpIfNotZero { source: relative_source(*offset), offset: offset.unsigned_abs() },
+            AssemblyInstruction::JumpRelative { offset } => Instruction::JumpUnconditional { source: relative_source(*offset), offset: offset.unsigned_abs() },
+            AssemblyInstruction::Noop => Instruction::Noop,
             AssemblyInstruction::Exit => Instruction::Exit,
             AssemblyInstruction::UnresolvedJumpIfZero { label } => panic!("Unresolved jz reached ASM compiler!"),
             AssemblyInstruction::UnresolvedJumpIfNotZero { label } => panic!("Unresolved jnz reached ASM compiler!"),
@@ -627,8 +771,8 @@ mod tests {
 
     use crate::freyr::{asm::{
         asm::*,
-        assembler::{parse_asm, resolve},
-    }};
+        assembler::{as_freyr_instructions, assemble, parse_asm, resolve, AsmError},
+    }, vm::instructions::{ArithmeticOperation, HostFunction, Instruction, NumberKind, NumberOfBytes, OperationMode, ShiftDirection, SignFlag}, disassembler::disassemble, encoder::LayoutHelper};
 
 
     #[test]
@@ -809,4 +953,164 @@ another:
 
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn assemble_skips_comments_and_blank_lines() {
+        let asm = "
+;; computes 5 & 3
+
+    push_imm32 5
+    and_imm32 3 ;unsigned and
+    return
+";
+
+        let result = assemble(asm).unwrap();
+
+        let expected = vec![
+            AssemblyInstruction::PushImmediate {
+                bytes: 4,
+                shift_size: 0,
+                immediate: 5u16.to_le_bytes(),
+            },
+            AssemblyInstruction::IntegerBitwiseBinaryOperation {
+                bytes: 4,
+                operation: AsmIntegerBitwiseBinaryOp::And,
+                sign: AsmSignFlag::Unsigned,
+                immediate: Some(3u16.to_le_bytes()),
+            },
+            AssemblyInstruction::Return,
+        ];
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn assemble_reports_line_of_unknown_instruction() {
+        let asm = "
+    stackoffset 8
+    frobnicate32
+";
+
+        let result = assemble(asm);
+
+        assert_eq!(
+            result,
+            Err(AsmError {
+                line: 3,
+                message: "Freyr assembly instruction not recognized: [\"frobnicate\", \"32\"]".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn assemble_reports_line_of_bad_operand() {
+        let asm = "main:
+    stackoffset 8
+    push_imm32 lots
+    return
+";
+
+        let error = assemble(asm).unwrap_err();
+
+        assert_eq!(error.to_string(), "Could not parse number lots at line 3");
+    }
+
+    #[test]
+    fn assemble_reports_missing_operand() {
+        let error = assemble("stackoffset").unwrap_err();
+
+        assert_eq!(error.to_string(), "Missing operand 1 in stackoffset at line 1");
+    }
+
+    #[test]
+    fn assemble_convert() {
+        let instructions = assemble("convert s32 f64").unwrap();
+
+        assert_eq!(instructions, vec![AssemblyInstruction::ConvertNumber {
+            from_bytes: 4,
+            from_kind: AsmNumberKind::Signed,
+            to_bytes: 8,
+            to_kind: AsmNumberKind::Float,
+        }]);
+        assert_eq!(as_freyr_instructions(&instructions), vec![Instruction::ConvertNumber {
+            from_bytes: NumberOfBytes::Bytes4,
+            from_kind: NumberKind::SignedInteger,
+            to_bytes: NumberOfBytes::Bytes8,
+            to_kind: NumberKind::Float,
+        }]);
+    }
+
+    #[test]
+    fn assemble_hostcall() {
+        let instructions = assemble("hostcall print s64\nhostcall print_bool u8").unwrap();
+
+        assert_eq!(as_freyr_instructions(&instructions), vec![
+            Instruction::HostCall { function: HostFunction::Print, bytes: NumberOfBytes::Bytes8, kind: NumberKind::SignedInteger },
+            Instruction::HostCall { function: HostFunction::PrintBool, bytes: NumberOfBytes::Bytes1, kind: NumberKind::UnsignedInteger },
+        ]);
+    }
+
+    #[test]
+    fn assemble_shifts_and_remainder() {
+        let instructions = assemble("shrs32\nshlu_imm8 3\nremu64").unwrap();
+
+        assert_eq!(as_freyr_instructions(&instructions), vec![
+            Instruction::BitShift { bytes: NumberOfBytes::Bytes4, direction: ShiftDirection::Right, mode: OperationMode::PureStack, sign: SignFlag::Signed, operand: 0 },
+            Instruction::BitShift { bytes: NumberOfBytes::Bytes1, direction: ShiftDirection::Left, mode: OperationMode::StackAndImmediate, sign: SignFlag::Unsigned, operand: 3 },
+            Instruction::IntegerArithmetic { bytes: NumberOfBytes::Bytes8, operation: ArithmeticOperation::Remainder, sign: SignFlag::Unsigned, mode: OperationMode::PureStack, operand: [0, 0] },
+        ]);
+    }
+
+    #[test]
+    fn assemble_reports_unknown_host_function() {
+        let error = assemble("hostcall format s32").unwrap_err();
+
+        assert_eq!(error.to_string(), "Unknown host function format at line 1");
+    }
+
+    fn encode(asm: &str) -> Vec<u32> {
+        let instructions = as_freyr_instructions(&resolve(&assemble(asm).unwrap()));
+        LayoutHelper::new().encode_program(&instructions).unwrap()
+    }
+
+    #[test]
+    fn disassembled_program_assembles_to_the_same_words() {
+        let program = "
+    stackoffset 16
+main:
+    push_imm32 25 <<16
+    push_imm64 7
+    storeaddr_rel32 bp+8
+    loadaddr_rel32 bp-4
+    loadaddr_imm16 128
+    storeaddr8
+    sums_imm32 3
+    powu64
+    andk_imm64 99
+    xor16
+    shrs_imm8 2
+    ltu64
+    gef32
+    mulf64
+    convert s32 f64
+    hostcall print_bool u8
+    jz +2
+    jnz main
+    jmp -3
+    call main
+    call_stack
+    push_reg bp
+    pop_reg sp
+    pop32
+    noop
+    return
+    exit";
+        let words = encode(program);
+        let decoded = LayoutHelper::new().decode_program(&words).unwrap();
+
+        let disassembled = disassemble(&decoded);
+
+        assert_eq!(encode(&disassembled), words);
+        assert_eq!(as_freyr_instructions(&resolve(&assemble(&disassembled).unwrap())), decoded);
+    }
 }
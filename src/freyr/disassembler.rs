@@ -0,0 +1,293 @@
+use super::asm::asm::{
+    AsmArithmeticBinaryOp, AsmControlRegister, AsmHostFunction, AsmIntegerBitwiseBinaryOp,
+    AsmIntegerCompareBinaryOp, AsmLoadStoreMode, AsmNumberKind, AsmShiftDirection, AsmSignFlag,
+    AssemblyInstruction,
+};
+use super::asm::asm_printer::print_instruction;
+use super::vm::instructions::{
+    AddressJumpAddressSource, ArithmeticOperation, BitwiseOperation, CompareOperation,
+    ControlRegister, HostFunction, Instruction, LoadStoreAddressingMode, NumberKind, OperationMode,
+    ShiftDirection, SignFlag,
+};
+
+fn load_store_mode(mode: &LoadStoreAddressingMode, operand: u32) -> AsmLoadStoreMode {
+    match mode {
+        LoadStoreAddressingMode::Stack => AsmLoadStoreMode::StackPop,
+        LoadStoreAddressingMode::RelativeForward => AsmLoadStoreMode::Relative { offset: operand as i32 },
+        LoadStoreAddressingMode::RelativeBackward => AsmLoadStoreMode::Relative { offset: -(operand as i32) },
+        LoadStoreAddressingMode::Absolute => AsmLoadStoreMode::Immediate { absolute_address: operand },
+    }
+}
+
+fn arithmetic_op(op: &ArithmeticOperation) -> AsmArithmeticBinaryOp {
+    match op {
+        ArithmeticOperation::Sum => AsmArithmeticBinaryOp::Sum,
+        ArithmeticOperation::Subtract => AsmArithmeticBinaryOp::Subtract,
+        ArithmeticOperation::Multiply => AsmArithmeticBinaryOp::Multiply,
+        ArithmeticOperation::Divide => AsmArithmeticBinaryOp::Divide,
+        ArithmeticOperation::Power => AsmArithmeticBinaryOp::Power,
+        ArithmeticOperation::Remainder => AsmArithmeticBinaryOp::Remainder,
+    }
+}
+
+fn bitwise_op(op: &BitwiseOperation) -> AsmIntegerBitwiseBinaryOp {
+    match op {
+        BitwiseOperation::And => AsmIntegerBitwiseBinaryOp::And,
+        BitwiseOperation::Or => AsmIntegerBitwiseBinaryOp::Or,
+        BitwiseOperation::Xor => AsmIntegerBitwiseBinaryOp::Xor,
+    }
+}
+
+fn compare_op(op: &CompareOperation) -> AsmIntegerCompareBinaryOp {
+    match op {
+        CompareOperation::Equals => AsmIntegerCompareBinaryOp::Equals,
+        CompareOperation::NotEquals => AsmIntegerCompareBinaryOp::NotEquals,
+        CompareOperation::LessThan => AsmIntegerCompareBinaryOp::LessThan,
+        CompareOperation::LessThanOrEquals => AsmIntegerCompareBinaryOp::LessThanOrEquals,
+        CompareOperation::GreaterThan => AsmIntegerCompareBinaryOp::GreaterThan,
+        CompareOperation::GreaterThanOrEquals => AsmIntegerCompareBinaryOp::GreaterThanOrEquals,
+    }
+}
+
+fn sign(sign: &SignFlag) -> AsmSignFlag {
+    match sign {
+        SignFlag::Signed => AsmSignFlag::Signed,
+        SignFlag::Unsigned => AsmSignFlag::Unsigned,
+    }
+}
+
+fn number_kind(kind: &NumberKind) -> AsmNumberKind {
+    match kind {
+        NumberKind::UnsignedInteger => AsmNumberKind::Unsigned,
+        NumberKind::SignedInteger => AsmNumberKind::Signed,
+        NumberKind::Float => AsmNumberKind::Float,
+    }
+}
+
+fn register(register: &ControlRegister) -> AsmControlRegister {
+    match register {
+        ControlRegister::BasePointer => AsmControlRegister::BasePointer,
+        ControlRegister::StackPointer => AsmControlRegister::StackPointer,
+        ControlRegister::InstructionPointer => AsmControlRegister::InstructionPointer,
+    }
+}
+
+//the immediate is only meaningful when the operation takes one of its operands from the instruction
+fn immediate_operand(mode: &OperationMode, operand: &[u8; 2]) -> Option<[u8; 2]> {
+    match mode {
+        OperationMode::PureStack => None,
+        OperationMode::StackAndImmediate => Some(*operand),
+    }
+}
+
+//Jumps come out of the encoder already resolved, so there are no labels to give back:
+//absolute targets are printed as instruction indexes and relative ones as +N/-N.
+fn jump(
+    source: &AddressJumpAddressSource,
+    offset: u32,
+    absolute: fn(u32) -> AssemblyInstruction,
+    relative: fn(i32) -> AssemblyInstruction,
+    from_stack: AssemblyInstruction,
+) -> AssemblyInstruction {
+    match source {
+        AddressJumpAddressSource::FromOperand => absolute(offset),
+        AddressJumpAddressSource::RelativeForward => relative(offset as i32),
+        AddressJumpAddressSource::RelativeBackward => relative(-(offset as i32)),
+        AddressJumpAddressSource::PopFromStack => from_stack,
+    }
+}
+
+//the inverse of as_freyr_instructions, minus the labels
+pub fn as_assembly(instruction: &Instruction) -> AssemblyInstruction {
+    match instruction {
+        Instruction::Noop => AssemblyInstruction::Noop,
+        Instruction::StackOffset { bytes } => AssemblyInstruction::StackOffset { bytes: *bytes },
+        Instruction::PushImmediate { bytes, lshift, immediate } => AssemblyInstruction::PushImmediate {
+            bytes: bytes.get_bytes(),
+            shift_size: lshift.get_shift_size(),
+            immediate: *immediate,
+        },
+        Instruction::LoadAddress { bytes, mode, operand } => AssemblyInstruction::LoadAddress {
+            bytes: bytes.get_bytes(),
+            mode: load_store_mode(mode, *operand),
+        },
+        Instruction::StoreAddress { bytes, mode, operand } => AssemblyInstruction::StoreAddress {
+            bytes: bytes.get_bytes(),
+            mode: load_store_mode(mode, *operand),
+        },
+        Instruction::BitShift { bytes, direction, mode, sign: sign_flag, operand } => AssemblyInstruction::BitShift {
+            bytes: bytes.get_bytes(),
+            direction: match direction {
+                ShiftDirection::Left => AsmShiftDirection::Left,
+                ShiftDirection::Right => AsmShiftDirection::Right,
+            },
+            sign: sign(sign_flag),
+            immediate: match mode {
+                OperationMode::PureStack => None,
+                OperationMode::StackAndImmediate => Some(*operand),
+            },
+        },
+        Instruction::Bitwise { bytes, operation, sign: sign_flag, mode, operand } => {
+            AssemblyInstruction::IntegerBitwiseBinaryOperation {
+                bytes: bytes.get_bytes(),
+                operation: bitwise_op(operation),
+                sign: sign(sign_flag),
+                immediate: immediate_operand(mode, operand),
+            }
+        }
+        Instruction::IntegerArithmetic { bytes, operation, sign: sign_flag, mode, operand } => {
+            AssemblyInstruction::IntegerArithmeticBinaryOperation {
+                bytes: bytes.get_bytes(),
+                operation: arithmetic_op(operation),
+                sign: sign(sign_flag),
+                immediate: immediate_operand(mode, operand),
+            }
+        }
+        Instruction::IntegerCompare { bytes, operation, sign: sign_flag, mode, operand } => {
+            AssemblyInstruction::IntegerCompareBinaryOperation {
+                bytes: bytes.get_bytes(),
+                operation: compare_op(operation),
+                sign: sign(sign_flag),
+                immediate: immediate_operand(mode, operand),
+            }
+        }
+        Instruction::FloatArithmetic { bytes, operation } => AssemblyInstruction::FloatArithmeticBinaryOperation {
+            bytes: bytes.get_bytes(),
+            operation: arithmetic_op(operation),
+        },
+        Instruction::FloatCompare { bytes, operation } => AssemblyInstruction::FloatCompareBinaryOperation {
+            bytes: bytes.get_bytes(),
+            operation: compare_op(operation),
+        },
+        Instruction::PushFromRegister { control_register } => AssemblyInstruction::PushRegister {
+            register: register(control_register),
+        },
+        Instruction::PopIntoRegister { control_register } => AssemblyInstruction::PopRegister {
+            register: register(control_register),
+        },
+        Instruction::Pop { bytes } => AssemblyInstruction::PopBytes { bytes: bytes.get_bytes() },
+        Instruction::Call { source, offset } => jump(
+            source,
+            *offset,
+            |offset| AssemblyInstruction::Call { offset },
+            |offset| AssemblyInstruction::CallRelative { offset },
+            AssemblyInstruction::CallFromStack,
+        ),
+        Instruction::JumpIfZero { source, offset } => jump(
+            source,
+            *offset,
+            |offset| AssemblyInstruction::JumpIfZero { offset },
+            |offset| AssemblyInstruction::JumpIfZeroRelative { offset },
+            AssemblyInstruction::JumpIfZeroFromStack,
+        ),
+        Instruction::JumpIfNotZero { source, offset } => jump(
+            source,
+            *offset,
+            |offset| AssemblyInstruction::JumpIfNotZero { offset },
+            |offset| AssemblyInstruction::JumpIfNotZeroRelative { offset },
+            AssemblyInstruction::JumpIfNotZeroFromStack,
+        ),
+        Instruction::JumpUnconditional { source, offset } => jump(
+            source,
+            *offset,
+            |offset| AssemblyInstruction::Jump { offset },
+            |offset| AssemblyInstruction::JumpRelative { offset },
+            AssemblyInstruction::JumpFromStack,
+        ),
+        Instruction::ConvertNumber { from_bytes, from_kind, to_bytes, to_kind } => AssemblyInstruction::ConvertNumber {
+            from_bytes: from_bytes.get_bytes(),
+            from_kind: number_kind(from_kind),
+            to_bytes: to_bytes.get_bytes(),
+            to_kind: number_kind(to_kind),
+        },
+        Instruction::HostCall { function, bytes, kind } => AssemblyInstruction::HostCall {
+            function: match function {
+                HostFunction::Print => AsmHostFunction::Print,
+                HostFunction::PrintBool => AsmHostFunction::PrintBool,
+                HostFunction::PrintStr => AsmHostFunction::PrintStr,
+            },
+            bytes: bytes.get_bytes(),
+            kind: number_kind(kind),
+        },
+        Instruction::Exit => AssemblyInstruction::Exit,
+        Instruction::Return => AssemblyInstruction::Return,
+    }
+}
+
+pub fn disassemble_instruction(instruction: &Instruction) -> String {
+    print_instruction(&as_assembly(instruction))
+}
+
+//one line per instruction in the same syntax the assembler reads, meant for inspecting programs read back with decode_program
+pub fn disassemble(instructions: &[Instruction]) -> String {
+    instructions
+        .iter()
+        .map(|instruction| disassemble_instruction(instruction) + "\n")
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    #[cfg(test)]
+    use pretty_assertions::assert_eq;
+
+    use crate::freyr::{disassembler::disassemble, vm::instructions::*};
+
+    #[test]
+    fn disassemble_program() {
+        let program = vec![
+            Instruction::StackOffset { bytes: 8 },
+            Instruction::PushImmediate {
+                bytes: NumberOfBytes::Bytes4,
+                lshift: LeftShift::Shift16,
+                immediate: 25u16.to_le_bytes(),
+            },
+            Instruction::LoadAddress {
+                bytes: NumberOfBytes::Bytes2,
+                mode: LoadStoreAddressingMode::RelativeForward,
+                operand: 45,
+            },
+            Instruction::IntegerArithmetic {
+                bytes: NumberOfBytes::Bytes4,
+                operation: ArithmeticOperation::Sum,
+                sign: SignFlag::Signed,
+                mode: OperationMode::StackAndImmediate,
+                operand: 3u16.to_le_bytes(),
+            },
+            Instruction::IntegerCompare {
+                bytes: NumberOfBytes::Bytes8,
+                operation: CompareOperation::LessThan,
+                sign: SignFlag::Unsigned,
+                mode: OperationMode::PureStack,
+                operand: [0, 0],
+            },
+            Instruction::JumpIfZero {
+                source: AddressJumpAddressSource::RelativeForward,
+                offset: 2,
+            },
+            Instruction::ConvertNumber {
+                from_bytes: NumberOfBytes::Bytes4,
+                from_kind: NumberKind::SignedInteger,
+                to_bytes: NumberOfBytes::Bytes8,
+                to_kind: NumberKind::Float,
+            },
+            Instruction::PopIntoRegister {
+                control_register: ControlRegister::BasePointer,
+            },
+            Instruction::Return,
+        ];
+
+        let expected = "stackoffset\t\t\t8
+push_imm32\t\t\t25 <<16
+loadaddr_rel16\t\t\tbp+45
+sums_imm32\t\t\t3
+ltu64
+jz\t\t\t\t+2
+convert\t\t\ts32 f64
+pop_reg\t\t\tbp
+return
+";
+        assert_eq!(disassemble(&program), expected);
+    }
+}
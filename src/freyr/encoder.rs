@@ -1,45 +1,110 @@
 use core::panic;
 use std::collections::HashMap;
+use std::fmt::Display;
 
 use super::{
-    asm::asm::AssemblyInstruction,
+    asm::{
+        asm::AssemblyInstruction,
+        assembler::{as_freyr_instructions, resolve},
+    },
     vm::instructions::{
-        get_all_instruction_layouts, BitLayout, Instruction, InstructionTable,
-        LoadStoreAddressingMode, PartType,
+        get_all_instruction_layouts, AddressJumpAddressSource, BitLayout, Instruction,
+        InstructionTable, LoadStoreAddressingMode, PartType,
     },
 };
 
+//shifting a u32 by 32 overflows, so the edges are handled before shifting
 pub fn truncate_to_bits(num: u32, bits: u32) -> u32 {
-    (num << (32 - bits)) >> (32 - bits)
+    match bits {
+        0 => 0,
+        32.. => num,
+        _ => (num << (32 - bits)) >> (32 - bits),
+    }
 }
 
 pub fn delete_msb_bits(num: u32, bits: u32) -> u32 {
+    if bits >= 32 {
+        return num;
+    }
     (num << bits) >> bits
 }
 
-pub fn encode_stackoffset(offset: u32) -> u32 {
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncodeError {
+    //the value doesn't fit in the bits the instruction layout has for it
+    OperandOverflow { part: String, bits: u32, value: u32 },
+    UnknownInstruction(String),
+    UnknownPart { instruction: String, part: String },
+    //the part only accepts the values in its bit patterns
+    InvalidValue { instruction: String, part: String, value: u32, allowed: Vec<u32> },
+    //labels and unresolved jumps/calls only exist before resolve, they have no encoding
+    Label(String),
+    UnresolvedLabel { instruction: String, label: Option<String> },
+}
+
+impl Display for EncodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EncodeError::OperandOverflow { part, bits, value } => write!(f, "{value} does not fit in the {bits} bits of {part}"),
+            EncodeError::UnknownInstruction(name) => write!(f, "Could not find instruction {name}"),
+            EncodeError::UnknownPart { instruction, part } => write!(f, "Could not find instruction part {part} in {instruction}"),
+            EncodeError::InvalidValue { instruction, part, value, allowed } => {
+                write!(f, "{value} is not a valid {part} for {instruction}, expected one of {allowed:?}")
+            }
+            EncodeError::Label(label) => write!(f, "Label {label} has no encoding, resolve the program first"),
+            EncodeError::UnresolvedLabel { instruction, label: Some(label) } => {
+                write!(f, "{instruction} to label {label} has no encoding, resolve the program first")
+            }
+            EncodeError::UnresolvedLabel { instruction, label: None } => {
+                write!(f, "{instruction} has no encoding, resolve the program first")
+            }
+        }
+    }
+}
+
+pub fn checked_encode_stackoffset(offset: u32) -> Result<u32, EncodeError> {
+    if offset >= 1 << 27 {
+        return Err(EncodeError::OperandOverflow { part: "num bytes".to_string(), bits: 27, value: offset });
+    }
     let bit_pattern: u32 = 0b01101 << 27;
-    let bytes: u32 = truncate_to_bits(offset, 27);
+    return Ok(bit_pattern + offset);
+}
 
-    return bit_pattern + bytes;
+//panics instead of truncating, a truncated offset would silently corrupt the stack frame
+pub fn encode_stackoffset(offset: u32) -> u32 {
+    match checked_encode_stackoffset(offset) {
+        Ok(encoded) => encoded,
+        Err(e) => panic!("Could not encode stackoffset: {e:?}")
+    }
 }
 
-pub fn encode_instruction(ins: &AssemblyInstruction) -> u32 {
+//labels and unresolved jumps have no encoding, use encode_asm for whole programs
+pub fn encode_instruction(layout: &LayoutHelper, ins: &AssemblyInstruction) -> Result<u32, EncodeError> {
+    let unresolved = |instruction: &str, label: &Option<String>| {
+        Err(EncodeError::UnresolvedLabel { instruction: instruction.to_string(), label: label.clone() })
+    };
     match ins {
-        AssemblyInstruction::StackOffset { bytes } => {
-            return encode_stackoffset(*bytes);
-        }
-        _ => 0,
+        AssemblyInstruction::Label { label } => return Err(EncodeError::Label(label.clone())),
+        AssemblyInstruction::UnresolvedCall { label } => return unresolved("call", label),
+        AssemblyInstruction::UnresolvedJumpIfZero { label } => return unresolved("jz", label),
+        AssemblyInstruction::UnresolvedJumpIfNotZero { label } => return unresolved("jnz", label),
+        AssemblyInstruction::UnresolvedJump { label } => return unresolved("jmp", label),
+        _ => {}
     }
+    let instructions = as_freyr_instructions(std::slice::from_ref(ins));
+    layout.encode_instruction(&instructions[0])
 }
 
-pub fn encode_asm(code: &[AssemblyInstruction]) -> Vec<u32> {
-    code.iter().map(|x| encode_instruction(x)).collect()
+pub fn encode_asm(layout: &LayoutHelper, code: &[AssemblyInstruction]) -> Result<Vec<u32>, EncodeError> {
+    let resolved = resolve(code);
+    layout.encode_program(&as_freyr_instructions(&resolved))
 }
 
+//encode and encode_bytes keep the first error and make returns it, so calls can still be chained
 pub struct InstructionEncoder<'a> {
-    pub layout: &'a BitLayout,
+    pub layout: Option<&'a BitLayout>,
     pub current: u32,
+    error: Option<EncodeError>,
 }
 
 impl<'a> InstructionEncoder<'a> {
@@ -63,39 +128,59 @@ impl<'a> InstructionEncoder<'a> {
     }
 
     pub fn encode(&mut self, part: &str, value: u32) -> &mut Self {
+        if self.error.is_some() {
+            return self;
+        }
+        if let Err(e) = self.try_encode(part, value) {
+            self.error = Some(e);
+        }
+        self
+    }
+
+    fn try_encode(&mut self, part: &str, value: u32) -> Result<(), EncodeError> {
+        let Some(layout) = self.layout else {
+            return Ok(());
+        };
         let mut bit_offset = 5;
-        let mut found = false;
-        for layout_part in self.layout.layout.iter() {
+        for layout_part in layout.layout.iter() {
             if layout_part.name == part {
-                found = true;
-                match &layout_part.layout_type {
+                let encoded = match &layout_part.layout_type {
                     PartType::BitPattern(patterns) => {
-                        let pattern = patterns.iter().find(|x| x.value == value).unwrap();
-                        let offseted = delete_msb_bits(pattern.pattern, bit_offset);
-                        let position_offset = (32 - bit_offset) - layout_part.length as u32;
-                        let positioned = offseted << position_offset;
-                        self.current += positioned;
-                        break;
+                        let Some(pattern) = patterns.iter().find(|x| x.value == value) else {
+                            return Err(EncodeError::InvalidValue {
+                                instruction: layout.name.clone(),
+                                part: part.to_string(),
+                                value,
+                                allowed: patterns.iter().map(|x| x.value).collect(),
+                            });
+                        };
+                        pattern.pattern
                     }
                     PartType::Immediate => {
-                        let offseted = delete_msb_bits(value, bit_offset);
-                        let position_offset = (32 - bit_offset) - layout_part.length as u32;
-                        let positioned = offseted << position_offset;
-                        self.current += positioned;
-                        break;
+                        //the bits past the part length would spill into the next part
+                        let bits = layout_part.length as u32;
+                        if bits < 32 && value >> bits != 0 {
+                            return Err(EncodeError::OperandOverflow { part: part.to_string(), bits, value });
+                        }
+                        value
                     }
-                }
+                };
+                let offseted = delete_msb_bits(encoded, bit_offset);
+                let position_offset = (32 - bit_offset) - layout_part.length as u32;
+                let positioned = offseted << position_offset;
+                self.current += positioned;
+                return Ok(());
             }
             bit_offset += layout_part.length as u32;
         }
-        if !found {
-            panic!("Could not find instruction part {part}");
-        }
-        self
+        Err(EncodeError::UnknownPart { instruction: layout.name.clone(), part: part.to_string() })
     }
 
-    pub fn make(&self) -> u32 {
-        self.current
+    pub fn make(&self) -> Result<u32, EncodeError> {
+        match &self.error {
+            Some(e) => Err(e.clone()),
+            None => Ok(self.current),
+        }
     }
 }
 
@@ -105,152 +190,198 @@ pub struct InstructionDecoder<'a> {
 }
 
 impl<'a> InstructionDecoder<'a> {
-    pub fn decode(&self) -> Instruction {
+    fn part(&self, name: &str) -> Result<(u32, u32), DecodeError> {
+        self.layout.get_part(name, self.instruction).ok_or_else(|| DecodeError::InvalidPart {
+            instruction: self.layout.name.clone(),
+            part: name.to_string(),
+            word: self.instruction,
+        })
+    }
+
+    //call, jz, jnz and jmp share the same source and offset parts
+    fn jump_operands(&self) -> Result<(AddressJumpAddressSource, u32), DecodeError> {
+        let (source_pattern, _) = self.part("source")?;
+        let (_, offset) = self.part("offset")?;
+        Ok(((source_pattern as u8).into(), offset))
+    }
+
+    pub fn decode(&self) -> Result<Instruction, DecodeError> {
         let pseudoop = self.layout.instruction_pseudoop;
 
         match pseudoop {
-            0 => Instruction::Noop,
+            0 => return Ok(Instruction::Noop),
             0b00001 => {
-                let (num_bytes_pattern, _) = self.layout.get_part("num bytes", self.instruction);
-                let (shift_pattern, shift_value) = self.layout.get_part("lshift", self.instruction);
-                let immediate_lsb = self.layout.get_part("immediate lsb", self.instruction);
-                return Instruction::PushImmediate {
+                let (num_bytes_pattern, _) = self.part("num bytes")?;
+                let (shift_pattern, shift_value) = self.part("lshift")?;
+                let immediate_lsb = self.part("immediate lsb")?;
+                return Ok(Instruction::PushImmediate {
                     bytes: (num_bytes_pattern as u8).into(),
                     immediate: (immediate_lsb.0 as u16).to_le_bytes(),
                     lshift: (shift_pattern as u8).into(),
-                };
+                });
             }
             0b01101 => {
-                let (_, value) = self.layout.get_part("num bytes", self.instruction);
-                return Instruction::StackOffset { bytes: value };
+                let (_, value) = self.part("num bytes")?;
+                return Ok(Instruction::StackOffset { bytes: value });
             }
             0b00010 => {
-                let (bytes_pattern, _) = self.layout.get_part("num bytes", self.instruction);
-                let (mode_pattern, _) = self.layout.get_part("mode", self.instruction);
-                let (_, operand_value) = self.layout.get_part("operand", self.instruction);
-                return Instruction::LoadAddress {
+                let (bytes_pattern, _) = self.part("num bytes")?;
+                let (mode_pattern, _) = self.part("mode")?;
+                let (_, operand_value) = self.part("operand")?;
+                return Ok(Instruction::LoadAddress {
                     bytes: (bytes_pattern as u8).into(),
                     mode: (mode_pattern as u8).into(),
                     operand: operand_value,
-                };
+                });
             }
             0b00011 => {
-                let (bytes_pattern, _) = self.layout.get_part("num bytes", self.instruction);
-                let (mode_pattern, _) = self.layout.get_part("mode", self.instruction);
-                let (_, operand_value) = self.layout.get_part("operand", self.instruction);
-                return Instruction::StoreAddress {
+                let (bytes_pattern, _) = self.part("num bytes")?;
+                let (mode_pattern, _) = self.part("mode")?;
+                let (_, operand_value) = self.part("operand")?;
+                return Ok(Instruction::StoreAddress {
                     bytes: (bytes_pattern as u8).into(),
                     mode: (mode_pattern as u8).into(),
                     operand: operand_value,
-                };
+                });
             }
             0b00100 => {
-                let (bytes_pattern, _) = self.layout.get_part("num bytes", self.instruction);
-                let (direction_pattern, _) = self.layout.get_part("direction", self.instruction);
-                let (mode_pattern, _) = self.layout.get_part("mode", self.instruction);
-                let (sign_pattern, _) = self.layout.get_part("keep sign", self.instruction);
-                let (_, operand_value) = self.layout.get_part("operand", self.instruction);
-                return Instruction::BitShift {
+                let (bytes_pattern, _) = self.part("num bytes")?;
+                let (direction_pattern, _) = self.part("direction")?;
+                let (mode_pattern, _) = self.part("mode")?;
+                let (sign_pattern, _) = self.part("keep sign")?;
+                let (_, operand_value) = self.part("operand")?;
+                return Ok(Instruction::BitShift {
                     bytes: (bytes_pattern as u8).into(),
                     mode: (mode_pattern as u8).into(),
                     direction: (direction_pattern as u8).into(),
                     sign: (sign_pattern as u8).into(),
                     operand: operand_value as u8,
-                };
+                });
             }
             0b00101 => {
-                let (bytes_pattern, _) = self.layout.get_part("num bytes", self.instruction);
-                let (operation_pattern, _) = self.layout.get_part("operation", self.instruction);
-                let (sign_pattern, _) = self.layout.get_part("sign", self.instruction);
-                let (mode_pattern, _) = self.layout.get_part("mode", self.instruction);
-                let (_, operand_value) = self.layout.get_part("operand", self.instruction);
-                return Instruction::Bitwise {
+                let (bytes_pattern, _) = self.part("num bytes")?;
+                let (operation_pattern, _) = self.part("operation")?;
+                let (sign_pattern, _) = self.part("sign")?;
+                let (mode_pattern, _) = self.part("mode")?;
+                let (_, operand_value) = self.part("operand")?;
+                return Ok(Instruction::Bitwise {
                     bytes: (bytes_pattern as u8).into(),
                     mode: (mode_pattern as u8).into(),
                     operation: (operation_pattern as u8).into(),
                     sign: (sign_pattern as u8).into(),
                     operand: (operand_value as u16).to_le_bytes(),
-                };
+                });
             }
             0b00110 => {
-                let (bytes_pattern, _) = self.layout.get_part("num bytes", self.instruction);
-                let (operation_pattern, _) = self.layout.get_part("operation", self.instruction);
-                let (sign_pattern, _) = self.layout.get_part("sign", self.instruction);
-                let (mode_pattern, _) = self.layout.get_part("mode", self.instruction);
-                let (_, operand_value) = self.layout.get_part("operand", self.instruction);
-                return Instruction::IntegerArithmetic {
+                let (bytes_pattern, _) = self.part("num bytes")?;
+                let (operation_pattern, _) = self.part("operation")?;
+                let (sign_pattern, _) = self.part("sign")?;
+                let (mode_pattern, _) = self.part("mode")?;
+                let (_, operand_value) = self.part("operand")?;
+                return Ok(Instruction::IntegerArithmetic {
                     bytes: (bytes_pattern as u8).into(),
                     sign: (sign_pattern as u8).into(),
                     mode: (mode_pattern as u8).into(),
                     operation: (operation_pattern as u8).into(),
                     operand: (operand_value as u16).to_le_bytes(),
-                };
+                });
             }
             0b00111 => {
-                let (bytes_pattern, _) = self.layout.get_part("num bytes", self.instruction);
-                let (operation_pattern, _) = self.layout.get_part("operation", self.instruction);
-                let (sign_pattern, _) = self.layout.get_part("sign", self.instruction);
-                let (mode_pattern, _) = self.layout.get_part("mode", self.instruction);
-                let (_, operand_value) = self.layout.get_part("operand", self.instruction);
-                return Instruction::IntegerCompare {
+                let (bytes_pattern, _) = self.part("num bytes")?;
+                let (operation_pattern, _) = self.part("operation")?;
+                let (sign_pattern, _) = self.part("sign")?;
+                let (mode_pattern, _) = self.part("mode")?;
+                let (_, operand_value) = self.part("operand")?;
+                return Ok(Instruction::IntegerCompare {
                     bytes: (bytes_pattern as u8).into(),
                     sign: (sign_pattern as u8).into(),
                     mode: (mode_pattern as u8).into(),
                     operation: (operation_pattern as u8).into(),
                     operand: (operand_value as u16).to_le_bytes(),
-                };
+                });
             }
             0b01000 => {
-                let (bytes_pattern, _) = self.layout.get_part("num bytes", self.instruction);
-                let (operation_pattern, _) = self.layout.get_part("operation", self.instruction);
-                return Instruction::FloatArithmetic {
+                let (bytes_pattern, _) = self.part("num bytes")?;
+                let (operation_pattern, _) = self.part("operation")?;
+                return Ok(Instruction::FloatArithmetic {
                     bytes: (bytes_pattern as u8).into(),
                     operation: (operation_pattern as u8).into(),
-                };
+                });
             }
             0b01001 => {
-                let (bytes_pattern, _) = self.layout.get_part("num bytes", self.instruction);
-                let (operation_pattern, _) = self.layout.get_part("operation", self.instruction);
-                return Instruction::FloatCompare {
+                let (bytes_pattern, _) = self.part("num bytes")?;
+                let (operation_pattern, _) = self.part("operation")?;
+                return Ok(Instruction::FloatCompare {
                     bytes: (bytes_pattern as u8).into(),
                     operation: (operation_pattern as u8).into(),
-                };
+                });
             }
             0b01010 => {
-                let (register_pattern, _) = self.layout.get_part("register", self.instruction);
-                return Instruction::PushFromRegister {
+                let (register_pattern, _) = self.part("register")?;
+                return Ok(Instruction::PushFromRegister {
                     control_register: (register_pattern as u8).into(),
-                };
+                });
             }
             0b01011 => {
-                let (register_pattern, _) = self.layout.get_part("register", self.instruction);
-                return Instruction::PopIntoRegister {
+                let (register_pattern, _) = self.part("register")?;
+                return Ok(Instruction::PopIntoRegister {
                     control_register: (register_pattern as u8).into(),
-                };
+                });
             }
             0b01100 => {
-                let (bytes_pattern, _) = self.layout.get_part("num bytes", self.instruction);
-                return Instruction::Pop {
+                let (bytes_pattern, _) = self.part("num bytes")?;
+                return Ok(Instruction::Pop {
                     bytes: (bytes_pattern as u8).into(),
-                };
+                });
             }
             0b01110 => {
-                let (source_pattern, _) = self.layout.get_part("source", self.instruction);
-                let (_, offset) = self.layout.get_part("offset", self.instruction);
-                return Instruction::Call {
-                    source: (source_pattern as u8).into(),
-                    offset,
-                };
+                let (source, offset) = self.jump_operands()?;
+                return Ok(Instruction::Call { source, offset });
             }
             0b01111 => {
-                return Instruction::Return;
+                return Ok(Instruction::Return);
+            }
+            0b10000 => {
+                let (source, offset) = self.jump_operands()?;
+                return Ok(Instruction::JumpIfZero { source, offset });
+            }
+            0b10001 => {
+                let (source, offset) = self.jump_operands()?;
+                return Ok(Instruction::JumpIfNotZero { source, offset });
+            }
+            0b10010 => {
+                return Ok(Instruction::Exit);
+            }
+            0b10100 => {
+                let (source, offset) = self.jump_operands()?;
+                return Ok(Instruction::JumpUnconditional { source, offset });
+            }
+            0b10011 => {
+                let (from_bytes_pattern, _) = self.part("from bytes")?;
+                let (from_kind_pattern, _) = self.part("from kind")?;
+                let (to_bytes_pattern, _) = self.part("to bytes")?;
+                let (to_kind_pattern, _) = self.part("to kind")?;
+                return Ok(Instruction::ConvertNumber {
+                    from_bytes: (from_bytes_pattern as u8).into(),
+                    from_kind: (from_kind_pattern as u8).into(),
+                    to_bytes: (to_bytes_pattern as u8).into(),
+                    to_kind: (to_kind_pattern as u8).into(),
+                });
+            }
+            0b10101 => {
+                let (function_pattern, _) = self.part("function")?;
+                let (bytes_pattern, _) = self.part("bytes")?;
+                let (kind_pattern, _) = self.part("kind")?;
+                return Ok(Instruction::HostCall {
+                    function: (function_pattern as u8).into(),
+                    bytes: (bytes_pattern as u8).into(),
+                    kind: (kind_pattern as u8).into(),
+                });
             }
             _ => {
-                panic!("Not recognized: {inst:#05b}", inst = pseudoop as u8)
+                return Err(DecodeError::UnknownPseudoOp(pseudoop));
             }
-        };
-
-        Instruction::Noop
+        }
     }
 }
 
@@ -266,17 +397,20 @@ impl LayoutHelper {
     }
 
     pub fn begin_encode(&self, name: &str) -> InstructionEncoder {
-        let instruction = self.table.table.get(name).unwrap();
-
+        let layout = self.table.table.get(name);
         InstructionEncoder {
-            layout: instruction,
-            current: (instruction.instruction_pseudoop as u32) << 27,
+            layout,
+            current: layout.map_or(0, |x| (x.instruction_pseudoop as u32) << 27),
+            error: match layout {
+                Some(_) => None,
+                None => Some(EncodeError::UnknownInstruction(name.to_string())),
+            },
         }
     }
 
-    pub fn encode_instruction(&self, instruction: &Instruction) -> u32 {
+    pub fn encode_instruction(&self, instruction: &Instruction) -> Result<u32, EncodeError> {
         match instruction {
-            Instruction::Noop => 0,
+            Instruction::Noop => Ok(0),
             Instruction::StackOffset { bytes } => self
                 .begin_encode("stackoffset")
                 .encode("num bytes", *bytes)
@@ -409,29 +543,107 @@ impl LayoutHelper {
                 .encode("source", source.get_bit_pattern() as u32)
                 .encode("offset", *offset)
                 .make(),
+            Instruction::ConvertNumber {
+                from_bytes,
+                from_kind,
+                to_bytes,
+                to_kind,
+            } => self
+                .begin_encode("convert")
+                .encode("from bytes", from_bytes.get_bytes() as u32)
+                .encode("from kind", from_kind.get_bit_pattern() as u32)
+                .encode("to bytes", to_bytes.get_bytes() as u32)
+                .encode("to kind", to_kind.get_bit_pattern() as u32)
+                .make(),
+            Instruction::HostCall { function, bytes, kind } => self
+                .begin_encode("hostcall")
+                .encode("function", function.get_bit_pattern() as u32)
+                .encode("bytes", bytes.get_bytes() as u32)
+                .encode("kind", kind.get_bit_pattern() as u32)
+                .make(),
             Instruction::Exit => self.begin_encode("exit").make(),
             Instruction::Return => self.begin_encode("return").make(),
         }
     }
 
-    pub fn begin_decode(&self, instruction: u32) -> InstructionDecoder {
+    pub fn begin_decode(&self, instruction: u32) -> Result<InstructionDecoder, DecodeError> {
         let pseudo_op = (instruction >> 27) as u8;
-        let instruction_name = self.table.pseudoops.get(&pseudo_op);
-        match instruction_name {
-            Some(name) => {
-                let layout = self.table.table.get(name).unwrap();
-                InstructionDecoder {
-                    layout: layout,
-                    instruction,
-                }
-            }
-            None => {
-                panic!("No instruction found for pseudo op {pseudo_op:#05b}")
+        match self.table.layout_for_pseudoop(pseudo_op) {
+            Some(layout) => Ok(InstructionDecoder {
+                layout: layout,
+                instruction,
+            }),
+            None => Err(DecodeError::UnknownPseudoOp(pseudo_op)),
+        }
+    }
+
+    pub fn encode_program(&self, program: &[Instruction]) -> Result<Vec<u32>, EncodeError> {
+        program.iter().map(|instruction| self.encode_instruction(instruction)).collect()
+    }
+
+    pub fn decode_program(&self, words: &[u32]) -> Result<Vec<Instruction>, ProgramDecodeError> {
+        words
+            .iter()
+            .enumerate()
+            .map(|(index, word)| {
+                Instruction::from_word(self, *word).map_err(|error| ProgramDecodeError {
+                    index,
+                    word: *word,
+                    error,
+                })
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    //the 5 most significant bits don't match any instruction
+    UnknownPseudoOp(u8),
+    //a part of the word doesn't match any of the patterns in the layout
+    InvalidPart { instruction: String, part: String, word: u32 },
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::UnknownPseudoOp(pseudo_op) => write!(f, "No instruction found for pseudo op {pseudo_op:#07b}"),
+            DecodeError::InvalidPart { instruction, part, word } => {
+                write!(f, "Invalid {part} in {instruction} instruction {word:#034b}")
             }
         }
     }
 }
 
+//a decode error in a program, with the position of the word that failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProgramDecodeError {
+    pub index: usize,
+    pub word: u32,
+    pub error: DecodeError,
+}
+
+impl Display for ProgramDecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Could not decode word {} ({:#034b}): {}", self.index, self.word, self.error)
+    }
+}
+
+impl Instruction {
+    pub fn to_word(&self, layout: &LayoutHelper) -> Result<u32, EncodeError> {
+        layout.encode_instruction(self)
+    }
+
+    pub fn from_word(layout: &LayoutHelper, word: u32) -> Result<Instruction, DecodeError> {
+        let pseudo_op = (word >> 27) as u8;
+        //noop has no layout in the table, it's just a zeroed word
+        if pseudo_op == 0 {
+            return Ok(Instruction::Noop);
+        }
+        layout.begin_decode(word)?.decode()
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -439,7 +651,97 @@ mod tests {
     #[cfg(test)]
     use pretty_assertions::assert_eq;
 
-    use crate::freyr::{encoder::*, vm::instructions::*};
+    use crate::freyr::{asm::{asm::AssemblyInstruction, assembler::parse_asm}, encoder::*, vm::instructions::*};
+
+    #[test]
+    fn truncate_to_bits_edges() {
+        assert_eq!(truncate_to_bits(0xDEADBEEF, 0), 0);
+        assert_eq!(truncate_to_bits(0xDEADBEEF, 16), 0xBEEF);
+        assert_eq!(truncate_to_bits(0xDEADBEEF, 32), 0xDEADBEEF);
+    }
+
+    #[test]
+    fn delete_msb_bits_edges() {
+        assert_eq!(delete_msb_bits(0xDEADBEEF, 0), 0xDEADBEEF);
+        assert_eq!(delete_msb_bits(0xDEADBEEF, 16), 0xBEEF);
+        assert_eq!(delete_msb_bits(0xDEADBEEF, 32), 0xDEADBEEF);
+    }
+
+    fn over_wide_layout() -> BitLayout {
+        let part = |name: &str, length| BitLayoutPart {
+            length,
+            name: name.into(),
+            description: name.into(),
+            layout_type: PartType::Immediate,
+        };
+        BitLayout {
+            instruction_pseudoop: 0b11111,
+            name: "wide".into(),
+            layout: vec![part("lower", 20), part("upper", 10)],
+        }
+    }
+
+    #[test]
+    fn all_instruction_layouts_fit_in_an_instruction() {
+        let table = get_all_instruction_layouts();
+        for layout in table.table.values() {
+            assert_eq!(layout.validate(), Ok(()), "{}", layout.name);
+        }
+    }
+
+    #[test]
+    fn over_wide_layout_is_rejected() {
+        assert_eq!(
+            over_wide_layout().validate(),
+            Err(LayoutError::TooWide { instruction: "wide".into(), bits: 30 })
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Malformed layout: TooWide { instruction: \"wide\", bits: 30 }")]
+    fn get_part_past_instruction_width_panics() {
+        over_wide_layout().get_part("upper", 0);
+    }
+
+    //the expected words are written part by part from the layouts in instructions.rs,
+    //so a change to the layout fails here even if encode and decode still agree with each other
+    #[test]
+    fn push_immediate_encoded_word() {
+        let encoder = LayoutHelper::new();
+        let encoded = encoder.encode_instruction(&Instruction::PushImmediate {
+            bytes: NumberOfBytes::Bytes4,
+            lshift: LeftShift::Shift16,
+            immediate: 0xBEEFu16.to_le_bytes(),
+        }).unwrap();
+        //opcode, num bytes, lshift, immediate, unused
+        assert_eq!(encoded, 0b00001_10_01_1011111011101111_0000000);
+        assert_eq!(encoded, 0x0CDF7780);
+    }
+
+    #[test]
+    fn stackoffset_encoded_word() {
+        let encoder = LayoutHelper::new();
+        let encoded = encoder.encode_instruction(&Instruction::StackOffset { bytes: 12 }).unwrap();
+        //opcode, num bytes
+        assert_eq!(encoded, 0b01101_000000000000000000000001100);
+        assert_eq!(encoded, 0x6800000C);
+        assert_eq!(encode_stackoffset(12), encoded);
+    }
+
+    #[test]
+    fn add_i32_encoded_word() {
+        let encoder = LayoutHelper::new();
+        let encoded = encoder.encode_instruction(&Instruction::IntegerArithmetic {
+            bytes: NumberOfBytes::Bytes4,
+            operation: ArithmeticOperation::Sum,
+            sign: SignFlag::Signed,
+            mode: OperationMode::PureStack,
+            operand: 0u16.to_le_bytes(),
+        }).unwrap();
+        //opcode, num bytes, operation, sign, mode, operand, unused
+        assert_eq!(encoded, 0b00110_10_000_1_0_0000000000000000_0000);
+        assert_eq!(encoded, 0x34200000);
+    }
 
     #[test]
     fn encode_decode_push_immediate32_lshift16() {
@@ -449,9 +751,9 @@ mod tests {
             .encode("num bytes", 4)
             .encode("lshift", 16)
             .encode_bytes("immediate lsb", &25u16.to_le_bytes())
-            .make();
+            .make().unwrap();
 
-        let decoded = encoder.begin_decode(encoded).decode();
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
         assert_eq!(
             decoded,
             Instruction::PushImmediate {
@@ -461,13 +763,96 @@ mod tests {
             }
         );
 
-        let reencoded = encoder.encode_instruction(&decoded);
+        let reencoded = encoder.encode_instruction(&decoded).unwrap();
         assert_eq!(reencoded, encoded);
 
-        let redecoded = encoder.begin_decode(reencoded).decode();
+        let redecoded = encoder.begin_decode(reencoded).unwrap().decode().unwrap();
         assert_eq!(redecoded, decoded);
     }
 
+    #[test]
+    fn encode_decode_push_immediate64_every_lshift() {
+        let encoder = LayoutHelper::new();
+        for shift in [0, 16, 32, 48] {
+            let encoded = encoder
+                .begin_encode("push_imm")
+                .encode("num bytes", 8)
+                .encode("lshift", shift)
+                .encode_bytes("immediate lsb", &0xBEEFu16.to_le_bytes())
+                .make().unwrap();
+
+            let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
+            assert_eq!(
+                decoded,
+                Instruction::PushImmediate {
+                    bytes: NumberOfBytes::Bytes8,
+                    lshift: LeftShift::from_shift_size(shift as u8).unwrap(),
+                    immediate: 0xBEEFu16.to_le_bytes()
+                }
+            );
+            assert_eq!(encoder.encode_instruction(&decoded).unwrap(), encoded);
+        }
+    }
+
+    #[test]
+    fn encode_push_immediate_unsupported_lshift() {
+        let encoder = LayoutHelper::new();
+        let error = encoder
+            .begin_encode("push_imm")
+            .encode("num bytes", 8)
+            .encode("lshift", 24)
+            .make()
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "24 is not a valid lshift for push_imm, expected one of [0, 16, 32, 48]"
+        );
+    }
+
+    #[test]
+    fn encode_unknown_part_is_error() {
+        let encoder = LayoutHelper::new();
+        let result = encoder
+            .begin_encode("stackoffset")
+            .encode("lshift", 16)
+            .make();
+        assert_eq!(
+            result,
+            Err(EncodeError::UnknownPart { instruction: "stackoffset".into(), part: "lshift".into() })
+        );
+    }
+
+    #[test]
+    fn encode_operand_wider_than_part_is_error() {
+        let encoder = LayoutHelper::new();
+        let result = encoder
+            .begin_encode("loadaddr")
+            .encode("num bytes", 4)
+            .encode("mode", LoadStoreAddressingMode::Absolute.get_bit_pattern() as u32)
+            .encode("operand", 1 << 23)
+            .make();
+        assert_eq!(
+            result,
+            Err(EncodeError::OperandOverflow { part: "operand".into(), bits: 23, value: 1 << 23 })
+        );
+    }
+
+    #[test]
+    fn encode_unknown_instruction_is_error() {
+        let encoder = LayoutHelper::new();
+        let result = encoder.begin_encode("frobnicate").encode("num bytes", 4).make();
+        assert_eq!(result, Err(EncodeError::UnknownInstruction("frobnicate".into())));
+    }
+
+    #[test]
+    fn decode_unknown_pseudo_op_is_error() {
+        let encoder = LayoutHelper::new();
+        assert_eq!(
+            encoder.begin_decode(0b11111 << 27).err(),
+            Some(DecodeError::UnknownPseudoOp(0b11111))
+        );
+    }
+
     #[test]
     fn encode_decode_push_immediate16_nolshift() {
         let encoder = LayoutHelper::new();
@@ -476,9 +861,9 @@ mod tests {
             .encode("num bytes", 2)
             .encode("lshift", 0)
             .encode_bytes("immediate lsb", &250u16.to_le_bytes())
-            .make();
+            .make().unwrap();
 
-        let decoded = encoder.begin_decode(encoded).decode();
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
 
         assert_eq!(
             decoded,
@@ -489,10 +874,10 @@ mod tests {
             }
         );
 
-        let reencoded = encoder.encode_instruction(&decoded);
+        let reencoded = encoder.encode_instruction(&decoded).unwrap();
         assert_eq!(reencoded, encoded);
 
-        let redecoded = encoder.begin_decode(reencoded).decode();
+        let redecoded = encoder.begin_decode(reencoded).unwrap().decode().unwrap();
         assert_eq!(redecoded, decoded);
     }
 
@@ -503,9 +888,9 @@ mod tests {
             .begin_encode("loadaddr")
             .encode("num bytes", 2)
             .encode("mode", 0)
-            .make();
+            .make().unwrap();
 
-        let decoded = encoder.begin_decode(encoded).decode();
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
 
         assert_eq!(
             decoded,
@@ -516,10 +901,10 @@ mod tests {
             }
         );
 
-        let reencoded = encoder.encode_instruction(&decoded);
+        let reencoded = encoder.encode_instruction(&decoded).unwrap();
         assert_eq!(reencoded, encoded);
 
-        let redecoded = encoder.begin_decode(reencoded).decode();
+        let redecoded = encoder.begin_decode(reencoded).unwrap().decode().unwrap();
         assert_eq!(redecoded, decoded);
     }
 
@@ -531,9 +916,9 @@ mod tests {
             .encode("num bytes", 2)
             .encode("mode", 1)
             .encode("operand", 45)
-            .make();
+            .make().unwrap();
 
-        let decoded = encoder.begin_decode(encoded).decode();
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
 
         assert_eq!(
             decoded,
@@ -544,10 +929,10 @@ mod tests {
             }
         );
 
-        let reencoded = encoder.encode_instruction(&decoded);
+        let reencoded = encoder.encode_instruction(&decoded).unwrap();
         assert_eq!(reencoded, encoded);
 
-        let redecoded = encoder.begin_decode(reencoded).decode();
+        let redecoded = encoder.begin_decode(reencoded).unwrap().decode().unwrap();
         assert_eq!(redecoded, decoded);
     }
 
@@ -559,9 +944,9 @@ mod tests {
             .encode("num bytes", 8)
             .encode("mode", 2)
             .encode("operand", 453)
-            .make();
+            .make().unwrap();
 
-        let decoded = encoder.begin_decode(encoded).decode();
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
 
         assert_eq!(
             decoded,
@@ -572,10 +957,10 @@ mod tests {
             }
         );
 
-        let reencoded = encoder.encode_instruction(&decoded);
+        let reencoded = encoder.encode_instruction(&decoded).unwrap();
         assert_eq!(reencoded, encoded);
 
-        let redecoded = encoder.begin_decode(reencoded).decode();
+        let redecoded = encoder.begin_decode(reencoded).unwrap().decode().unwrap();
         assert_eq!(redecoded, decoded);
     }
 
@@ -587,9 +972,9 @@ mod tests {
             .encode("num bytes", 1)
             .encode("mode", 3)
             .encode("operand", 123)
-            .make();
+            .make().unwrap();
 
-        let decoded = encoder.begin_decode(encoded).decode();
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
 
         assert_eq!(
             decoded,
@@ -600,10 +985,10 @@ mod tests {
             }
         );
 
-        let reencoded = encoder.encode_instruction(&decoded);
+        let reencoded = encoder.encode_instruction(&decoded).unwrap();
         assert_eq!(reencoded, encoded);
 
-        let redecoded = encoder.begin_decode(reencoded).decode();
+        let redecoded = encoder.begin_decode(reencoded).unwrap().decode().unwrap();
         assert_eq!(redecoded, decoded);
     }
 
@@ -614,9 +999,9 @@ mod tests {
             .begin_encode("storeaddr")
             .encode("num bytes", 2)
             .encode("mode", 0)
-            .make();
+            .make().unwrap();
 
-        let decoded = encoder.begin_decode(encoded).decode();
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
 
         assert_eq!(
             decoded,
@@ -627,10 +1012,10 @@ mod tests {
             }
         );
 
-        let reencoded = encoder.encode_instruction(&decoded);
+        let reencoded = encoder.encode_instruction(&decoded).unwrap();
         assert_eq!(reencoded, encoded);
 
-        let redecoded = encoder.begin_decode(reencoded).decode();
+        let redecoded = encoder.begin_decode(reencoded).unwrap().decode().unwrap();
         assert_eq!(redecoded, decoded);
     }
 
@@ -642,9 +1027,9 @@ mod tests {
             .encode("num bytes", 2)
             .encode("mode", 1)
             .encode("operand", 45)
-            .make();
+            .make().unwrap();
 
-        let decoded = encoder.begin_decode(encoded).decode();
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
 
         assert_eq!(
             decoded,
@@ -655,10 +1040,10 @@ mod tests {
             }
         );
 
-        let reencoded = encoder.encode_instruction(&decoded);
+        let reencoded = encoder.encode_instruction(&decoded).unwrap();
         assert_eq!(reencoded, encoded);
 
-        let redecoded = encoder.begin_decode(reencoded).decode();
+        let redecoded = encoder.begin_decode(reencoded).unwrap().decode().unwrap();
         assert_eq!(redecoded, decoded);
     }
 
@@ -670,9 +1055,9 @@ mod tests {
             .encode("num bytes", 8)
             .encode("mode", 2)
             .encode("operand", 453)
-            .make();
+            .make().unwrap();
 
-        let decoded = encoder.begin_decode(encoded).decode();
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
 
         assert_eq!(
             decoded,
@@ -683,10 +1068,10 @@ mod tests {
             }
         );
 
-        let reencoded = encoder.encode_instruction(&decoded);
+        let reencoded = encoder.encode_instruction(&decoded).unwrap();
         assert_eq!(reencoded, encoded);
 
-        let redecoded = encoder.begin_decode(reencoded).decode();
+        let redecoded = encoder.begin_decode(reencoded).unwrap().decode().unwrap();
         assert_eq!(redecoded, decoded);
     }
 
@@ -698,9 +1083,9 @@ mod tests {
             .encode("num bytes", 1)
             .encode("mode", 3)
             .encode("operand", 123)
-            .make();
+            .make().unwrap();
 
-        let decoded = encoder.begin_decode(encoded).decode();
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
 
         assert_eq!(
             decoded,
@@ -711,10 +1096,10 @@ mod tests {
             }
         );
 
-        let reencoded = encoder.encode_instruction(&decoded);
+        let reencoded = encoder.encode_instruction(&decoded).unwrap();
         assert_eq!(reencoded, encoded);
 
-        let redecoded = encoder.begin_decode(reencoded).decode();
+        let redecoded = encoder.begin_decode(reencoded).unwrap().decode().unwrap();
         assert_eq!(redecoded, decoded);
     }
 
@@ -727,9 +1112,9 @@ mod tests {
             .encode("direction", 0)
             .encode("mode", 0)
             .encode("keep sign", 0)
-            .make();
+            .make().unwrap();
 
-        let decoded = encoder.begin_decode(encoded).decode();
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
 
         assert_eq!(
             decoded,
@@ -742,10 +1127,10 @@ mod tests {
             }
         );
 
-        let reencoded = encoder.encode_instruction(&decoded);
+        let reencoded = encoder.encode_instruction(&decoded).unwrap();
         assert_eq!(reencoded, encoded);
 
-        let redecoded = encoder.begin_decode(reencoded).decode();
+        let redecoded = encoder.begin_decode(reencoded).unwrap().decode().unwrap();
         assert_eq!(redecoded, decoded);
     }
 
@@ -759,9 +1144,9 @@ mod tests {
             .encode("mode", 1)
             .encode("operand", 12)
             .encode("keep sign", 1)
-            .make();
+            .make().unwrap();
 
-        let decoded = encoder.begin_decode(encoded).decode();
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
 
         assert_eq!(
             decoded,
@@ -774,10 +1159,10 @@ mod tests {
             }
         );
 
-        let reencoded = encoder.encode_instruction(&decoded);
+        let reencoded = encoder.encode_instruction(&decoded).unwrap();
         assert_eq!(reencoded, encoded);
 
-        let redecoded = encoder.begin_decode(reencoded).decode();
+        let redecoded = encoder.begin_decode(reencoded).unwrap().decode().unwrap();
         assert_eq!(redecoded, decoded);
     }
 
@@ -791,9 +1176,9 @@ mod tests {
             .encode("mode", 0)
             .encode("sign", 0)
             .encode_bytes("operand", &[0, 0])
-            .make();
+            .make().unwrap();
 
-        let decoded = encoder.begin_decode(encoded).decode();
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
 
         assert_eq!(
             decoded,
@@ -806,10 +1191,10 @@ mod tests {
             }
         );
 
-        let reencoded = encoder.encode_instruction(&decoded);
+        let reencoded = encoder.encode_instruction(&decoded).unwrap();
         assert_eq!(reencoded, encoded);
 
-        let redecoded = encoder.begin_decode(reencoded).decode();
+        let redecoded = encoder.begin_decode(reencoded).unwrap().decode().unwrap();
         assert_eq!(redecoded, decoded);
     }
 
@@ -823,9 +1208,9 @@ mod tests {
             .encode("mode", 0)
             .encode("sign", 1)
             .encode_bytes("operand", &[0, 0])
-            .make();
+            .make().unwrap();
 
-        let decoded = encoder.begin_decode(encoded).decode();
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
 
         assert_eq!(
             decoded,
@@ -838,10 +1223,10 @@ mod tests {
             }
         );
 
-        let reencoded = encoder.encode_instruction(&decoded);
+        let reencoded = encoder.encode_instruction(&decoded).unwrap();
         assert_eq!(reencoded, encoded);
 
-        let redecoded = encoder.begin_decode(reencoded).decode();
+        let redecoded = encoder.begin_decode(reencoded).unwrap().decode().unwrap();
         assert_eq!(redecoded, decoded);
     }
 
@@ -855,9 +1240,9 @@ mod tests {
             .encode("sign", 0)
             .encode("mode", 1)
             .encode_bytes("operand", &123u16.to_le_bytes())
-            .make();
+            .make().unwrap();
 
-        let decoded = encoder.begin_decode(encoded).decode();
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
 
         assert_eq!(
             decoded,
@@ -870,10 +1255,10 @@ mod tests {
             }
         );
 
-        let reencoded = encoder.encode_instruction(&decoded);
+        let reencoded = encoder.encode_instruction(&decoded).unwrap();
         assert_eq!(reencoded, encoded);
 
-        let redecoded = encoder.begin_decode(reencoded).decode();
+        let redecoded = encoder.begin_decode(reencoded).unwrap().decode().unwrap();
         assert_eq!(redecoded, decoded);
     }
 
@@ -887,9 +1272,9 @@ mod tests {
             .encode("sign", 0)
             .encode("mode", 1)
             .encode_bytes("operand", &65535u16.to_le_bytes())
-            .make();
+            .make().unwrap();
 
-        let decoded = encoder.begin_decode(encoded).decode();
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
 
         assert_eq!(
             decoded,
@@ -902,10 +1287,10 @@ mod tests {
             }
         );
 
-        let reencoded = encoder.encode_instruction(&decoded);
+        let reencoded = encoder.encode_instruction(&decoded).unwrap();
         assert_eq!(reencoded, encoded);
 
-        let redecoded = encoder.begin_decode(reencoded).decode();
+        let redecoded = encoder.begin_decode(reencoded).unwrap().decode().unwrap();
         assert_eq!(redecoded, decoded);
     }
 
@@ -918,9 +1303,9 @@ mod tests {
             .encode("operation", 0b000)
             .encode("sign", 1)
             .encode("mode", 0)
-            .make();
+            .make().unwrap();
 
-        let decoded = encoder.begin_decode(encoded).decode();
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
 
         assert_eq!(
             decoded,
@@ -933,10 +1318,10 @@ mod tests {
             }
         );
 
-        let reencoded = encoder.encode_instruction(&decoded);
+        let reencoded = encoder.encode_instruction(&decoded).unwrap();
         assert_eq!(reencoded, encoded);
 
-        let redecoded = encoder.begin_decode(reencoded).decode();
+        let redecoded = encoder.begin_decode(reencoded).unwrap().decode().unwrap();
         assert_eq!(redecoded, decoded);
     }
 
@@ -950,9 +1335,9 @@ mod tests {
             .encode("sign", 0)
             .encode("mode", 1)
             .encode("operand", 65535)
-            .make();
+            .make().unwrap();
 
-        let decoded = encoder.begin_decode(encoded).decode();
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
 
         assert_eq!(
             decoded,
@@ -965,10 +1350,10 @@ mod tests {
             }
         );
 
-        let reencoded = encoder.encode_instruction(&decoded);
+        let reencoded = encoder.encode_instruction(&decoded).unwrap();
         assert_eq!(reencoded, encoded);
 
-        let redecoded = encoder.begin_decode(reencoded).decode();
+        let redecoded = encoder.begin_decode(reencoded).unwrap().decode().unwrap();
         assert_eq!(redecoded, decoded);
     }
 
@@ -982,9 +1367,9 @@ mod tests {
             .encode("sign", 0)
             .encode("mode", 1)
             .encode("operand", 15)
-            .make();
+            .make().unwrap();
 
-        let decoded = encoder.begin_decode(encoded).decode();
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
 
         assert_eq!(
             decoded,
@@ -993,17 +1378,64 @@ mod tests {
                 sign: SignFlag::Unsigned,
                 operation: ArithmeticOperation::Power,
                 mode: OperationMode::StackAndImmediate,
-                operand: 15i16.to_le_bytes()
+                operand: 15u16.to_le_bytes()
             }
         );
 
-        let reencoded = encoder.encode_instruction(&decoded);
+        let reencoded = encoder.encode_instruction(&decoded).unwrap();
         assert_eq!(reencoded, encoded);
 
-        let redecoded = encoder.begin_decode(reencoded).decode();
+        let redecoded = encoder.begin_decode(reencoded).unwrap().decode().unwrap();
         assert_eq!(redecoded, decoded);
     }
 
+    #[test]
+    fn encode_decode_arithmetic_operation_sum_32bits_signed_negative_imm() {
+        let encoder = LayoutHelper::new();
+        let encoded = encoder
+            .begin_encode("integer_binary_op")
+            .encode("num bytes", 4)
+            .encode("operation", 0b000)
+            .encode("sign", 1)
+            .encode("mode", 1)
+            .encode_bytes("operand", &(-3i16).to_le_bytes())
+            .make().unwrap();
+
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
+
+        assert_eq!(
+            decoded,
+            Instruction::IntegerArithmetic {
+                bytes: NumberOfBytes::Bytes4,
+                sign: SignFlag::Signed,
+                operation: ArithmeticOperation::Sum,
+                mode: OperationMode::StackAndImmediate,
+                operand: (-3i16).to_le_bytes()
+            }
+        );
+
+        let reencoded = encoder.encode_instruction(&decoded).unwrap();
+        assert_eq!(reencoded, encoded);
+
+        let redecoded = encoder.begin_decode(reencoded).unwrap().decode().unwrap();
+        assert_eq!(redecoded, decoded);
+    }
+
+    #[test]
+    fn encode_decode_compare_operation_lt_8bits_signed_negative_imm() {
+        let instruction = Instruction::IntegerCompare {
+            bytes: NumberOfBytes::Bytes1,
+            sign: SignFlag::Signed,
+            operation: CompareOperation::LessThan,
+            mode: OperationMode::StackAndImmediate,
+            operand: i16::MIN.to_le_bytes()
+        };
+        let encoder = LayoutHelper::new();
+        let encoded = encoder.encode_instruction(&instruction).unwrap();
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
+        assert_eq!(decoded, instruction);
+    }
+
     #[test]
     fn encode_decode_compare_operation_eq_32bits_unsigned_imm() {
         let encoder = LayoutHelper::new();
@@ -1014,9 +1446,9 @@ mod tests {
             .encode("sign", 0)
             .encode("mode", 1)
             .encode_bytes("operand", &15u16.to_le_bytes())
-            .make();
+            .make().unwrap();
 
-        let decoded = encoder.begin_decode(encoded).decode();
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
 
         assert_eq!(
             decoded,
@@ -1029,10 +1461,10 @@ mod tests {
             }
         );
 
-        let reencoded = encoder.encode_instruction(&decoded);
+        let reencoded = encoder.encode_instruction(&decoded).unwrap();
         assert_eq!(reencoded, encoded);
 
-        let redecoded = encoder.begin_decode(reencoded).decode();
+        let redecoded = encoder.begin_decode(reencoded).unwrap().decode().unwrap();
         assert_eq!(redecoded, decoded);
     }
 
@@ -1045,9 +1477,9 @@ mod tests {
             .encode("operation", 0b101)
             .encode("sign", 1)
             .encode("mode", 0)
-            .make();
+            .make().unwrap();
 
-        let decoded = encoder.begin_decode(encoded).decode();
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
 
         assert_eq!(
             decoded,
@@ -1060,10 +1492,10 @@ mod tests {
             }
         );
 
-        let reencoded = encoder.encode_instruction(&decoded);
+        let reencoded = encoder.encode_instruction(&decoded).unwrap();
         assert_eq!(reencoded, encoded);
 
-        let redecoded = encoder.begin_decode(reencoded).decode();
+        let redecoded = encoder.begin_decode(reencoded).unwrap().decode().unwrap();
         assert_eq!(redecoded, decoded);
     }
 
@@ -1074,9 +1506,9 @@ mod tests {
             .begin_encode("float_binary_op")
             .encode("num bytes", 4)
             .encode("operation", 0b000)
-            .make();
+            .make().unwrap();
 
-        let decoded = encoder.begin_decode(encoded).decode();
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
 
         assert_eq!(
             decoded,
@@ -1086,10 +1518,10 @@ mod tests {
             }
         );
 
-        let reencoded = encoder.encode_instruction(&decoded);
+        let reencoded = encoder.encode_instruction(&decoded).unwrap();
         assert_eq!(reencoded, encoded);
 
-        let redecoded = encoder.begin_decode(reencoded).decode();
+        let redecoded = encoder.begin_decode(reencoded).unwrap().decode().unwrap();
         assert_eq!(redecoded, decoded);
     }
 
@@ -1100,9 +1532,9 @@ mod tests {
             .begin_encode("float_binary_op")
             .encode("num bytes", 8)
             .encode("operation", 0b011)
-            .make();
+            .make().unwrap();
 
-        let decoded = encoder.begin_decode(encoded).decode();
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
 
         assert_eq!(
             decoded,
@@ -1112,10 +1544,10 @@ mod tests {
             }
         );
 
-        let reencoded = encoder.encode_instruction(&decoded);
+        let reencoded = encoder.encode_instruction(&decoded).unwrap();
         assert_eq!(reencoded, encoded);
 
-        let redecoded = encoder.begin_decode(reencoded).decode();
+        let redecoded = encoder.begin_decode(reencoded).unwrap().decode().unwrap();
         assert_eq!(redecoded, decoded);
     }
 
@@ -1126,9 +1558,9 @@ mod tests {
             .begin_encode("float_compare_op")
             .encode("num bytes", 4)
             .encode("operation", 0b000)
-            .make();
+            .make().unwrap();
 
-        let decoded = encoder.begin_decode(encoded).decode();
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
 
         assert_eq!(
             decoded,
@@ -1138,10 +1570,10 @@ mod tests {
             }
         );
 
-        let reencoded = encoder.encode_instruction(&decoded);
+        let reencoded = encoder.encode_instruction(&decoded).unwrap();
         assert_eq!(reencoded, encoded);
 
-        let redecoded = encoder.begin_decode(reencoded).decode();
+        let redecoded = encoder.begin_decode(reencoded).unwrap().decode().unwrap();
         assert_eq!(redecoded, decoded);
     }
 
@@ -1152,9 +1584,9 @@ mod tests {
             .begin_encode("float_compare_op")
             .encode("num bytes", 8)
             .encode("operation", 0b101)
-            .make();
+            .make().unwrap();
 
-        let decoded = encoder.begin_decode(encoded).decode();
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
 
         assert_eq!(
             decoded,
@@ -1164,10 +1596,10 @@ mod tests {
             }
         );
 
-        let reencoded = encoder.encode_instruction(&decoded);
+        let reencoded = encoder.encode_instruction(&decoded).unwrap();
         assert_eq!(reencoded, encoded);
 
-        let redecoded = encoder.begin_decode(reencoded).decode();
+        let redecoded = encoder.begin_decode(reencoded).unwrap().decode().unwrap();
         assert_eq!(redecoded, decoded);
     }
 
@@ -1177,9 +1609,9 @@ mod tests {
         let encoded = encoder
             .begin_encode("push_reg")
             .encode("register", 0b00)
-            .make();
+            .make().unwrap();
 
-        let decoded = encoder.begin_decode(encoded).decode();
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
 
         assert_eq!(
             decoded,
@@ -1188,10 +1620,10 @@ mod tests {
             }
         );
 
-        let reencoded = encoder.encode_instruction(&decoded);
+        let reencoded = encoder.encode_instruction(&decoded).unwrap();
         assert_eq!(reencoded, encoded);
 
-        let redecoded = encoder.begin_decode(reencoded).decode();
+        let redecoded = encoder.begin_decode(reencoded).unwrap().decode().unwrap();
         assert_eq!(redecoded, decoded);
     }
 
@@ -1201,9 +1633,9 @@ mod tests {
         let encoded = encoder
             .begin_encode("pop_reg")
             .encode("register", 0b10)
-            .make();
+            .make().unwrap();
 
-        let decoded = encoder.begin_decode(encoded).decode();
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
 
         assert_eq!(
             decoded,
@@ -1212,19 +1644,19 @@ mod tests {
             }
         );
 
-        let reencoded = encoder.encode_instruction(&decoded);
+        let reencoded = encoder.encode_instruction(&decoded).unwrap();
         assert_eq!(reencoded, encoded);
 
-        let redecoded = encoder.begin_decode(reencoded).decode();
+        let redecoded = encoder.begin_decode(reencoded).unwrap().decode().unwrap();
         assert_eq!(redecoded, decoded);
     }
 
     #[test]
     fn encode_decode_pop_stack() {
         let encoder = LayoutHelper::new();
-        let encoded = encoder.begin_encode("pop").encode("num bytes", 8).make();
+        let encoded = encoder.begin_encode("pop").encode("num bytes", 8).make().unwrap();
 
-        let decoded = encoder.begin_decode(encoded).decode();
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
 
         assert_eq!(
             decoded,
@@ -1233,10 +1665,10 @@ mod tests {
             }
         );
 
-        let reencoded = encoder.encode_instruction(&decoded);
+        let reencoded = encoder.encode_instruction(&decoded).unwrap();
         assert_eq!(reencoded, encoded);
 
-        let redecoded = encoder.begin_decode(reencoded).decode();
+        let redecoded = encoder.begin_decode(reencoded).unwrap().decode().unwrap();
         assert_eq!(redecoded, decoded);
     }
 
@@ -1246,19 +1678,32 @@ mod tests {
         let encoded = encoder
             .begin_encode("stackoffset")
             .encode("num bytes", 12347)
-            .make();
+            .make().unwrap();
 
-        let decoded = encoder.begin_decode(encoded).decode();
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
 
         assert_eq!(decoded, Instruction::StackOffset { bytes: 12347 });
 
-        let reencoded = encoder.encode_instruction(&decoded);
+        let reencoded = encoder.encode_instruction(&decoded).unwrap();
         assert_eq!(reencoded, encoded);
 
-        let redecoded = encoder.begin_decode(reencoded).decode();
+        let redecoded = encoder.begin_decode(reencoded).unwrap().decode().unwrap();
         assert_eq!(redecoded, decoded);
     }
 
+    #[test]
+    fn stackoffset_larger_than_27_bits_is_rejected() {
+        let largest = (1 << 27) - 1;
+        let encoded = checked_encode_stackoffset(largest).unwrap();
+        let decoded = LayoutHelper::new().begin_decode(encoded).unwrap().decode().unwrap();
+        assert_eq!(decoded, Instruction::StackOffset { bytes: largest });
+
+        assert_eq!(
+            checked_encode_stackoffset(1 << 27),
+            Err(EncodeError::OperandOverflow { part: "num bytes".to_string(), bits: 27, value: 1 << 27 })
+        );
+    }
+
     #[test]
     fn encode_decode_call_from_operand() {
         let encoder = LayoutHelper::new();
@@ -1266,9 +1711,9 @@ mod tests {
             .begin_encode("call")
             .encode("source", 0)
             .encode("offset", 151)
-            .make();
+            .make().unwrap();
 
-        let decoded = encoder.begin_decode(encoded).decode();
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
 
         assert_eq!(
             decoded,
@@ -1278,19 +1723,19 @@ mod tests {
             }
         );
 
-        let reencoded = encoder.encode_instruction(&decoded);
+        let reencoded = encoder.encode_instruction(&decoded).unwrap();
         assert_eq!(reencoded, encoded);
 
-        let redecoded = encoder.begin_decode(reencoded).decode();
+        let redecoded = encoder.begin_decode(reencoded).unwrap().decode().unwrap();
         assert_eq!(redecoded, decoded);
     }
 
     #[test]
     fn encode_decode_call_from_stack() {
         let encoder = LayoutHelper::new();
-        let encoded = encoder.begin_encode("call").encode("source", 1).make();
+        let encoded = encoder.begin_encode("call").encode("source", 1).make().unwrap();
 
-        let decoded = encoder.begin_decode(encoded).decode();
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
 
         assert_eq!(
             decoded,
@@ -1300,25 +1745,303 @@ mod tests {
             }
         );
 
-        let reencoded = encoder.encode_instruction(&decoded);
+        let reencoded = encoder.encode_instruction(&decoded).unwrap();
+        assert_eq!(reencoded, encoded);
+
+        let redecoded = encoder.begin_decode(reencoded).unwrap().decode().unwrap();
+        assert_eq!(redecoded, decoded);
+    }
+
+    #[test]
+    fn encode_decode_jnz_relative_backward() {
+        let encoder = LayoutHelper::new();
+        let encoded = encoder
+            .begin_encode("jnz")
+            .encode("source", 0b11)
+            .encode("offset", 3)
+            .make().unwrap();
+
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
+
+        assert_eq!(
+            decoded,
+            Instruction::JumpIfNotZero {
+                source: AddressJumpAddressSource::RelativeBackward,
+                offset: 3
+            }
+        );
+
+        let reencoded = encoder.encode_instruction(&decoded).unwrap();
+        assert_eq!(reencoded, encoded);
+
+        let redecoded = encoder.begin_decode(reencoded).unwrap().decode().unwrap();
+        assert_eq!(redecoded, decoded);
+    }
+
+    #[test]
+    fn encode_decode_jump_family_from_every_source() {
+        let encoder = LayoutHelper::new();
+        let sources = [
+            AddressJumpAddressSource::FromOperand,
+            AddressJumpAddressSource::PopFromStack,
+            AddressJumpAddressSource::RelativeForward,
+            AddressJumpAddressSource::RelativeBackward,
+        ];
+        for source in sources {
+            for instruction in [
+                Instruction::JumpIfZero { source, offset: 151 },
+                Instruction::JumpIfNotZero { source, offset: 151 },
+                Instruction::JumpUnconditional { source, offset: 151 },
+            ] {
+                let encoded = encoder.encode_instruction(&instruction).unwrap();
+                let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
+                assert_eq!(decoded, instruction);
+
+                let reencoded = encoder.encode_instruction(&decoded).unwrap();
+                assert_eq!(reencoded, encoded);
+            }
+        }
+    }
+
+    #[test]
+    fn encode_decode_jump_unconditional_from_operand() {
+        let encoder = LayoutHelper::new();
+        let encoded = encoder
+            .begin_encode("jmp")
+            .encode("source", 0)
+            .encode("offset", 151)
+            .make().unwrap();
+
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
+
+        assert_eq!(
+            decoded,
+            Instruction::JumpUnconditional {
+                source: AddressJumpAddressSource::FromOperand,
+                offset: 151
+            }
+        );
+
+        let reencoded = encoder.encode_instruction(&decoded).unwrap();
         assert_eq!(reencoded, encoded);
 
-        let redecoded = encoder.begin_decode(reencoded).decode();
+        let redecoded = encoder.begin_decode(reencoded).unwrap().decode().unwrap();
         assert_eq!(redecoded, decoded);
     }
 
     #[test]
     fn encode_decode_return() {
         let encoder = LayoutHelper::new();
-        let encoded = encoder.begin_encode("return").make();
-        let decoded = encoder.begin_decode(encoded).decode();
+        let encoded = encoder.begin_encode("return").make().unwrap();
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
 
         assert_eq!(decoded, Instruction::Return);
 
-        let reencoded = encoder.encode_instruction(&decoded);
+        let reencoded = encoder.encode_instruction(&decoded).unwrap();
         assert_eq!(reencoded, encoded);
 
-        let redecoded = encoder.begin_decode(reencoded).decode();
+        let redecoded = encoder.begin_decode(reencoded).unwrap().decode().unwrap();
         assert_eq!(redecoded, decoded);
     }
+
+    #[test]
+    fn encode_decode_exit() {
+        let encoder = LayoutHelper::new();
+        let encoded = encoder.begin_encode("exit").make().unwrap();
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
+
+        assert_eq!(decoded, Instruction::Exit);
+
+        let reencoded = encoder.encode_instruction(&decoded).unwrap();
+        assert_eq!(reencoded, encoded);
+
+        let redecoded = encoder.begin_decode(reencoded).unwrap().decode().unwrap();
+        assert_eq!(redecoded, decoded);
+    }
+
+    #[test]
+    fn encode_decode_convert_float_to_signed() {
+        let encoder = LayoutHelper::new();
+        let instruction = Instruction::ConvertNumber {
+            from_bytes: NumberOfBytes::Bytes8,
+            from_kind: NumberKind::Float,
+            to_bytes: NumberOfBytes::Bytes4,
+            to_kind: NumberKind::SignedInteger,
+        };
+        let encoded = encoder.encode_instruction(&instruction).unwrap();
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
+
+        assert_eq!(decoded, instruction);
+    }
+
+    #[test]
+    fn encode_decode_hostcall_print() {
+        let encoder = LayoutHelper::new();
+        let instruction = Instruction::HostCall {
+            function: HostFunction::Print,
+            bytes: NumberOfBytes::Bytes8,
+            kind: NumberKind::SignedInteger,
+        };
+        let encoded = encoder.encode_instruction(&instruction).unwrap();
+        let decoded = encoder.begin_decode(encoded).unwrap().decode().unwrap();
+
+        assert_eq!(decoded, instruction);
+    }
+
+    #[test]
+    fn every_pseudoop_resolves_to_its_layout() {
+        let layout = LayoutHelper::new();
+        for (op, name) in layout.table.pseudoops.iter() {
+            let found = layout.table.layout_for_pseudoop(*op).unwrap();
+            assert_eq!(found.instruction_pseudoop, *op);
+            assert_eq!(&found.name, name);
+        }
+        //noop is the zeroed word and has no layout
+        assert!(layout.table.layout_for_pseudoop(0).is_none());
+    }
+
+    #[test]
+    fn word_round_trip_with_inherent_methods() {
+        let layout = LayoutHelper::new();
+        let instructions = [
+            Instruction::Noop,
+            Instruction::StackOffset { bytes: 12 },
+            Instruction::PushImmediate {
+                bytes: NumberOfBytes::Bytes4,
+                lshift: LeftShift::Shift16,
+                immediate: 7u16.to_le_bytes()
+            },
+            Instruction::Return,
+        ];
+
+        for instruction in instructions {
+            let word = instruction.to_word(&layout).unwrap();
+            assert_eq!(word, layout.encode_instruction(&instruction).unwrap());
+            assert_eq!(Instruction::from_word(&layout, word), Ok(instruction));
+        }
+    }
+
+    #[test]
+    fn from_word_unknown_pseudo_op() {
+        let layout = LayoutHelper::new();
+        assert_eq!(
+            Instruction::from_word(&layout, 0b11111 << 27),
+            Err(DecodeError::UnknownPseudoOp(0b11111))
+        );
+    }
+
+    #[test]
+    fn encode_decode_program_roundtrip() {
+        let layout = LayoutHelper::new();
+        let program = vec![
+            Instruction::StackOffset { bytes: 8 },
+            Instruction::PushImmediate {
+                bytes: NumberOfBytes::Bytes4,
+                lshift: LeftShift::None,
+                immediate: 42u16.to_le_bytes(),
+            },
+            Instruction::StoreAddress {
+                bytes: NumberOfBytes::Bytes4,
+                mode: LoadStoreAddressingMode::RelativeForward,
+                operand: 4,
+            },
+            Instruction::Noop,
+            Instruction::LoadAddress {
+                bytes: NumberOfBytes::Bytes4,
+                mode: LoadStoreAddressingMode::RelativeForward,
+                operand: 4,
+            },
+            Instruction::Return,
+        ];
+
+        let words = layout.encode_program(&program).unwrap();
+        assert_eq!(words.len(), program.len());
+        assert_eq!(layout.decode_program(&words), Ok(program));
+    }
+
+    #[test]
+    fn decode_program_reports_failing_index() {
+        let layout = LayoutHelper::new();
+        let words = [
+            layout.encode_instruction(&Instruction::Return).unwrap(),
+            0b11111 << 27,
+        ];
+        assert_eq!(
+            layout.decode_program(&words),
+            Err(ProgramDecodeError { index: 1, word: 0b11111 << 27, error: DecodeError::UnknownPseudoOp(0b11111) })
+        );
+    }
+
+    #[test]
+    fn encode_asm_program() {
+        let asm = "
+main:
+    push_imm32 5
+    push_imm32 7
+    sums32
+    jmp main
+    return
+";
+        let layout = LayoutHelper::new();
+        let words = encode_asm(&layout, &parse_asm(asm)).unwrap();
+
+        let expected = layout
+            .encode_program(&[
+                Instruction::PushImmediate {
+                    bytes: NumberOfBytes::Bytes4,
+                    lshift: LeftShift::None,
+                    immediate: 5u16.to_le_bytes(),
+                },
+                Instruction::PushImmediate {
+                    bytes: NumberOfBytes::Bytes4,
+                    lshift: LeftShift::None,
+                    immediate: 7u16.to_le_bytes(),
+                },
+                Instruction::IntegerArithmetic {
+                    bytes: NumberOfBytes::Bytes4,
+                    operation: ArithmeticOperation::Sum,
+                    sign: SignFlag::Signed,
+                    mode: OperationMode::PureStack,
+                    operand: [0, 0],
+                },
+                Instruction::JumpUnconditional {
+                    source: AddressJumpAddressSource::FromOperand,
+                    offset: 0,
+                },
+                Instruction::Return,
+            ])
+            .unwrap();
+        assert_eq!(words, expected);
+        assert!(words.iter().all(|word| *word != 0));
+    }
+
+    #[test]
+    fn encode_single_asm_instruction() {
+        let layout = LayoutHelper::new();
+        assert_eq!(
+            encode_instruction(&layout, &AssemblyInstruction::Return),
+            layout.encode_instruction(&Instruction::Return)
+        );
+        assert_eq!(
+            encode_instruction(&layout, &AssemblyInstruction::StackOffset { bytes: 12 }),
+            Ok(encode_stackoffset(12))
+        );
+    }
+
+    #[test]
+    fn encode_label_or_unresolved_jump_is_error() {
+        let layout = LayoutHelper::new();
+        assert_eq!(
+            encode_instruction(&layout, &AssemblyInstruction::Label { label: "main".to_string() }),
+            Err(EncodeError::Label("main".to_string()))
+        );
+        assert_eq!(
+            encode_instruction(&layout, &AssemblyInstruction::UnresolvedJump { label: Some("main".to_string()) }),
+            Err(EncodeError::UnresolvedLabel { instruction: "jmp".to_string(), label: Some("main".to_string()) })
+        );
+        assert_eq!(
+            encode_instruction(&layout, &AssemblyInstruction::UnresolvedCall { label: None }),
+            Err(EncodeError::UnresolvedLabel { instruction: "call".to_string(), label: None })
+        );
+    }
 }
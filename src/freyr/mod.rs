@@ -1,3 +1,4 @@
 pub mod asm;
+pub mod disassembler;
 pub mod encoder;
 pub mod vm;
\ No newline at end of file
@@ -109,6 +109,17 @@ impl From<u8> for LeftShift {
 }
 
 impl LeftShift {
+    //only shifts by whole 16 bit chunks can be encoded, so a 64 bit immediate is staged in 4 pushes
+    pub fn from_shift_size(size: u8) -> Option<LeftShift> {
+        match size {
+            0 => Some(LeftShift::None),
+            16 => Some(LeftShift::Shift16),
+            32 => Some(LeftShift::Shift32),
+            48 => Some(LeftShift::Shift48),
+            _ => None,
+        }
+    }
+
     pub fn get_shift_size(&self) -> u8 {
         match self {
             LeftShift::None => 0,
@@ -179,6 +190,7 @@ pub enum ArithmeticOperation {
     Multiply = 0b010,
     Divide = 0b011,
     Power = 0b100,
+    Remainder = 0b101,
 }
 
 impl From<u8> for ArithmeticOperation {
@@ -189,6 +201,7 @@ impl From<u8> for ArithmeticOperation {
             0b010 => Self::Multiply,
             0b011 => Self::Divide,
             0b100 => Self::Power,
+            0b101 => Self::Remainder,
             _ => panic!("Cannot convert {u} to ArithmeticOperation"),
         }
     }
@@ -202,6 +215,7 @@ impl ArithmeticOperation {
             ArithmeticOperation::Multiply => 0b010,
             ArithmeticOperation::Divide => 0b011,
             ArithmeticOperation::Power => 0b100,
+            ArithmeticOperation::Remainder => 0b101,
         }
     }
 }
@@ -268,6 +282,64 @@ impl SignFlag {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberKind {
+    UnsignedInteger = 0b00,
+    SignedInteger = 0b01,
+    Float = 0b10,
+}
+
+impl From<u8> for NumberKind {
+    fn from(u: u8) -> Self {
+        match u {
+            0b00 => Self::UnsignedInteger,
+            0b01 => Self::SignedInteger,
+            0b10 => Self::Float,
+            _ => panic!("Cannot convert {u} to NumberKind"),
+        }
+    }
+}
+
+impl NumberKind {
+    pub fn get_bit_pattern(&self) -> u8 {
+        match self {
+            NumberKind::UnsignedInteger => 0b00,
+            NumberKind::SignedInteger => 0b01,
+            NumberKind::Float => 0b10,
+        }
+    }
+}
+
+//functions the VM implements itself, so programs can call builtins and externs like print
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HostFunction {
+    Print = 0b00,
+    PrintBool = 0b01,
+    //pops a u32 length, then that many bytes of utf-8 text
+    PrintStr = 0b10,
+}
+
+impl From<u8> for HostFunction {
+    fn from(u: u8) -> Self {
+        match u {
+            0b00 => Self::Print,
+            0b01 => Self::PrintBool,
+            0b10 => Self::PrintStr,
+            _ => panic!("Cannot convert {u} to HostFunction"),
+        }
+    }
+}
+
+impl HostFunction {
+    pub fn get_bit_pattern(&self) -> u8 {
+        match self {
+            HostFunction::Print => 0b00,
+            HostFunction::PrintBool => 0b01,
+            HostFunction::PrintStr => 0b10,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ControlRegister {
     BasePointer = 0b00,
@@ -298,15 +370,20 @@ impl From<u8> for ControlRegister {
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AddressJumpAddressSource {
-    FromOperand = 0b0,
-    PopFromStack = 0b1,
+    FromOperand = 0b00,
+    PopFromStack = 0b01,
+    //offset is relative to the address of the jump instruction itself
+    RelativeForward = 0b10,
+    RelativeBackward = 0b11,
 }
 
 impl From<u8> for AddressJumpAddressSource {
     fn from(u: u8) -> Self {
         match u {
-            0b0 => Self::FromOperand,
-            0b1 => Self::PopFromStack,
+            0b00 => Self::FromOperand,
+            0b01 => Self::PopFromStack,
+            0b10 => Self::RelativeForward,
+            0b11 => Self::RelativeBackward,
             _ => panic!("Cannot convert {u} to CallAddressSource"),
         }
     }
@@ -317,6 +394,8 @@ impl AddressJumpAddressSource {
         match self {
             AddressJumpAddressSource::FromOperand => 0b00,
             AddressJumpAddressSource::PopFromStack => 0b01,
+            AddressJumpAddressSource::RelativeForward => 0b10,
+            AddressJumpAddressSource::RelativeBackward => 0b11,
         }
     }
 }
@@ -403,6 +482,19 @@ pub enum Instruction {
         source: AddressJumpAddressSource,
         offset: u32
     },
+    //pops a number of the source type and pushes it converted to the target type
+    ConvertNumber {
+        from_bytes: NumberOfBytes,
+        from_kind: NumberKind,
+        to_bytes: NumberOfBytes,
+        to_kind: NumberKind,
+    },
+    //pops a value of the given size and kind and hands it to the host function
+    HostCall {
+        function: HostFunction,
+        bytes: NumberOfBytes,
+        kind: NumberKind,
+    },
     Exit,
     Return,
 }
@@ -413,11 +505,31 @@ pub struct BitLayout {
     pub name: String,
 }
 
+//bits left for the parts after the 5-bit pseudoop
+pub const LAYOUT_BITS: u32 = 27;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutError {
+    //the parts take more bits than an instruction has after the pseudoop
+    TooWide { instruction: String, bits: u32 },
+}
+
 impl BitLayout {
-    //returns (pattern, value) or (value, value)
-    pub fn get_part(&self, name: &str, value: u32) -> (u32, u32) {
-        let mut skipped_bits = 5;
+    pub fn validate(&self) -> Result<(), LayoutError> {
+        let bits = self.layout.iter().map(|x| x.length as u32).sum::<u32>();
+        if bits > LAYOUT_BITS {
+            return Err(LayoutError::TooWide { instruction: self.name.clone(), bits });
+        }
+        Ok(())
+    }
+
+    //returns (pattern, value) or (value, value), None if there's no such part or the bits match no pattern
+    pub fn get_part(&self, name: &str, value: u32) -> Option<(u32, u32)> {
+        let mut skipped_bits: u32 = 5;
         for layout_item in &self.layout {
+            if skipped_bits + layout_item.length as u32 > 32 {
+                panic!("Malformed layout: {:?}", self.validate().unwrap_err());
+            }
             if name == layout_item.name {
                 let left_shift = value << skipped_bits;
                 let right_shift = left_shift >> (32 - layout_item.length);
@@ -425,18 +537,17 @@ impl BitLayout {
                 match &layout_item.layout_type {
                     PartType::BitPattern(patterns) => {
                         //find in patterns, return
-                        let found_pattern =
-                            patterns.iter().find(|x| x.pattern == extracted).unwrap();
-                        return (found_pattern.pattern, found_pattern.value);
+                        let found_pattern = patterns.iter().find(|x| x.pattern == extracted)?;
+                        return Some((found_pattern.pattern, found_pattern.value));
                     }
                     PartType::Immediate => {
-                        return (extracted, extracted);
+                        return Some((extracted, extracted));
                     }
                 }
             }
-            skipped_bits += layout_item.length
+            skipped_bits += layout_item.length as u32
         }
-        panic!("Failed to get pattern {name} from bits {value:#034b}");
+        None
     }
 }
 
@@ -463,7 +574,10 @@ pub struct BitLayoutPart {
 
 fn validate_instruction_sizes(table: &InstructionTable) {
     for layout in table.table.values() {
-        let sum: u8 = (5 as u8) + layout.layout.iter().map(|x| x.length as u8).sum::<u8>() as u8;
+        if let Err(e) = layout.validate() {
+            panic!("Malformed layout: {e:?}");
+        }
+        let sum = 5 + layout.layout.iter().map(|x| x.length as u32).sum::<u32>();
         if sum != 32 {
             panic!(
                 "Instruction {ins} has {defined} bits defined instead of required 32!",
@@ -516,6 +630,10 @@ impl InstructionTable {
             .insert(layout.instruction_pseudoop, layout.name.clone());
         self.table.insert(layout.name.clone(), layout);
     }
+
+    pub fn layout_for_pseudoop(&self, op: u8) -> Option<&BitLayout> {
+        self.pseudoops.get(&op).and_then(|name| self.table.get(name))
+    }
 }
 
 macro_rules! bit_pattern_values {
@@ -694,13 +812,14 @@ pub fn get_all_instruction_layouts() -> InstructionTable {
         part!(21 bits, "operand", "operand 22 bits")
     ));
 
-    let arith_ops = part!(3 bits, "operation", "sum, subtract, multiply, divide or power",
+    let arith_ops = part!(3 bits, "operation", "sum, subtract, multiply, divide, power or remainder",
         bit_pattern![
             0b000 => "sum",
             0b001 => "subtract",
             0b010 => "multiply",
             0b011 => "divide",
-            0b100 => "power"
+            0b100 => "power",
+            0b101 => "remainder"
         ]
     );
 
@@ -777,15 +896,19 @@ pub fn get_all_instruction_layouts() -> InstructionTable {
         part!(27 bits, "num bytes", "offset from bp")
     ));
 
+    let jump_source = part!(2 bits, "source", "where the jump address comes from",
+        bit_pattern![
+            0b00 => "from operand",
+            0b01 => "pop from stack",
+            0b10 => "relative forward",
+            0b11 => "relative backward"
+        ]
+    );
+
     table.add(layout!(
         0b01110 "call",
-        part!(1 bit, "source", "pop from stack or use operand",
-            bit_pattern![
-                0 => "from operand",
-                1 => "pop from stack"
-            ]
-        ),
-        part!(26 bits, "offset", "instruction offset")
+        jump_source,
+        part!(25 bits, "offset", "instruction offset")
     ));
 
     table.add(layout!(
@@ -795,24 +918,14 @@ pub fn get_all_instruction_layouts() -> InstructionTable {
 
     table.add(layout!(
         0b10000 "jz",
-        part!(1 bit, "source", "pop from stack or use operand",
-            bit_pattern![
-                0 => "from operand",
-                1 => "pop from stack"
-            ]
-        ),
-        part!(26 bits, "offset", "instruction offset")
+        jump_source,
+        part!(25 bits, "offset", "instruction offset")
     ));
 
     table.add(layout!(
         0b10001 "jnz",
-        part!(1 bit, "source", "pop from stack or use operand",
-            bit_pattern![
-                0 => "from operand",
-                1 => "pop from stack"
-            ]
-        ),
-        part!(26 bits, "offset", "instruction offset")
+        jump_source,
+        part!(25 bits, "offset", "instruction offset")
     ));
 
     table.add(layout!(
@@ -820,6 +933,45 @@ pub fn get_all_instruction_layouts() -> InstructionTable {
         unused!(27 bits)
     ));
 
+    let number_kind = bit_pattern![
+        0b00 => "unsigned integer",
+        0b01 => "signed integer",
+        0b10 => "float"
+    ];
+    let number_size = bit_pattern_values![
+        (0b00, 1) => "8 bits",
+        (0b01, 2) => "16 bits",
+        (0b10, 4) => "32 bits",
+        (0b11, 8) => "64 bits"
+    ];
+
+    table.add(layout!(
+        0b10011 "convert",
+        part!(2 bits, "from bytes", "size of the popped number", number_size.clone()),
+        part!(2 bits, "from kind", "representation of the popped number", number_kind.clone()),
+        part!(2 bits, "to bytes", "size of the pushed number", number_size.clone()),
+        part!(2 bits, "to kind", "representation of the pushed number", number_kind.clone()),
+        unused!(19 bits)
+    ));
+
+    table.add(layout!(
+        0b10100 "jmp",
+        jump_source,
+        part!(25 bits, "offset", "instruction offset")
+    ));
+
+    table.add(layout!(
+        0b10101 "hostcall",
+        part!(2 bits, "function", "host function to call", bit_pattern![
+            0b00 => "print",
+            0b01 => "print bool",
+            0b10 => "print str"
+        ]),
+        part!(2 bits, "bytes", "size of the popped value", number_size),
+        part!(2 bits, "kind", "representation of the popped value", number_kind),
+        unused!(21 bits)
+    ));
+
     validate_instruction_sizes(&table);
 
     return table;
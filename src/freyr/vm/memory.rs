@@ -2,6 +2,8 @@ const PAGE_SIZE: usize = 65536;
 const NUM_PAGES: usize = 65536;
 const PAGE_LAST_INDEX: usize = PAGE_SIZE - 1;
 type Page = [u8; PAGE_SIZE];
+//128 pages
+pub const DEFAULT_STACK_SIZE: u32 = 128 << 16;
 
 pub struct Memory {
     pub mem: Vec<Option<Box<[u8; PAGE_SIZE]>>>, //1 page
@@ -10,8 +12,11 @@ pub struct Memory {
     pub data_start: u32,
     pub code_start: u32,
     pub stack_start: u32,
+    pub stack_size: u32,
     pub heap_start: u32,
     pub ready: bool,
+    //text written by print host calls, whoever runs the VM decides where it goes
+    pub output: String,
 }
 
 #[derive(PartialEq, Eq, Debug, Clone)]
@@ -74,8 +79,10 @@ impl Memory {
             data_start: 0,
             code_start: 0,
             stack_start: 0,
+            stack_size: DEFAULT_STACK_SIZE,
             heap_start: 0,
             ready: false,
+            output: String::new(),
         };
     }
 
@@ -154,8 +161,9 @@ impl Memory {
         page_start += self.code_pages;
         self.stack_start = page_start << 16;
 
-        //pages used by stack (128)
-        let stack_pages = 128u32;
+        //plus one guard page: an instruction writes at most a few bytes past the stack end
+        //before the runner notices the overflow, so those bytes never reach the heap
+        let stack_pages = ((self.stack_size as usize + PAGE_LAST_INDEX) / PAGE_SIZE) as u32 + 1;
         page_start += stack_pages;
         self.heap_start = page_start << 16;
 
@@ -163,6 +171,11 @@ impl Memory {
         self.ready = true;
     }
 
+    //first address past the usable stack
+    pub fn stack_end(&self) -> u32 {
+        self.stack_start + self.stack_size
+    }
+
     //returns the data read and a flag indicating whether a page fault occured,
     //and the VM must read the remaining bytes again passing another address
     //and handle that accordingly
@@ -1,3 +1,5 @@
 pub mod instructions;
 pub mod memory;
-pub mod runner;
\ No newline at end of file
+pub mod runner;
+
+pub use runner::Machine;
\ No newline at end of file
@@ -5,18 +5,31 @@ use crate::freyr::vm::instructions::AddressJumpAddressSource;
 
 use super::{
     instructions::{
-        ArithmeticOperation, CompareOperation, Instruction, LoadStoreAddressingMode, NumberOfBytes,
-        OperationMode, ShiftDirection, SignFlag,
+        ArithmeticOperation, BitwiseOperation, CompareOperation, HostFunction, Instruction, LoadStoreAddressingMode, NumberKind,
+        NumberOfBytes, OperationMode, ShiftDirection, SignFlag,
     },
-    memory::{Memory, NativeNumericType},
+    memory::{Memory, NativeNumericType, DEFAULT_STACK_SIZE},
 };
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuntimeError {
+    //ip is the instruction that pushed sp past the end of the stack
+    StackOverflow { ip: usize, sp: u32, stack_end: u32 },
+    //ip points past the end of the program, i.e. stepping a machine with nothing loaded or after it finished.
+    //A relative jump that would land before the start reports its own ip, the target has no ip to report.
+    IpOutOfBounds { ip: usize, program_len: usize },
+    //integer division and remainder have no result for a zero divisor, the VM stops before doing them
+    DivisionByZero { ip: usize },
+    RemainderByZero { ip: usize },
+}
+
 pub struct ControlRegisterValues {
     pub ip: usize,
     pub sp: u32,
     pub bp: u32,
 }
 
+//>> is arithmetic on signed types and logical on unsigned ones, so T decides which right shift is done
 pub fn stacked_bitshift<T>(
     memory: &mut Memory,
     reg: &mut ControlRegisterValues,
@@ -60,16 +73,86 @@ pub fn immediate_bitshift<T>(
     reg.sp += std::mem::size_of::<T>() as u32;
 }
 
+//integers wrap around at their width like the hardware would, instead of panicking on overflow.
+//the type picks the signedness, so division and power are signed only for signed types
+pub trait VmArithmetic: Copy {
+    fn arith(self, rhs: Self, operation: ArithmeticOperation) -> Self;
+}
+
+macro_rules! impl_unsigned_arith {
+    ($type:ty) => {
+        impl VmArithmetic for $type {
+            fn arith(self, rhs: $type, operation: ArithmeticOperation) -> $type {
+                match operation {
+                    ArithmeticOperation::Sum => self.wrapping_add(rhs),
+                    ArithmeticOperation::Subtract => self.wrapping_sub(rhs),
+                    ArithmeticOperation::Multiply => self.wrapping_mul(rhs),
+                    ArithmeticOperation::Divide => self.wrapping_div(rhs),
+                    ArithmeticOperation::Power => self.wrapping_pow(u32::try_from(rhs).unwrap_or(u32::MAX)),
+                    ArithmeticOperation::Remainder => self.wrapping_rem(rhs),
+                }
+            }
+        }
+    };
+}
+
+//a negative exponent truncates towards zero like division does: only 1 and -1 survive it
+macro_rules! impl_signed_arith {
+    ($type:ty) => {
+        impl VmArithmetic for $type {
+            fn arith(self, rhs: $type, operation: ArithmeticOperation) -> $type {
+                match operation {
+                    ArithmeticOperation::Sum => self.wrapping_add(rhs),
+                    ArithmeticOperation::Subtract => self.wrapping_sub(rhs),
+                    ArithmeticOperation::Multiply => self.wrapping_mul(rhs),
+                    ArithmeticOperation::Divide => self.wrapping_div(rhs),
+                    ArithmeticOperation::Power if rhs < 0 => match self {
+                        1 => 1,
+                        -1 => if rhs % 2 == 0 { 1 } else { -1 },
+                        _ => 0,
+                    },
+                    ArithmeticOperation::Power => self.wrapping_pow(u32::try_from(rhs).unwrap_or(u32::MAX)),
+                    ArithmeticOperation::Remainder => self.wrapping_rem(rhs),
+                }
+            }
+        }
+    };
+}
+
+macro_rules! impl_float_arith {
+    ($type:ty) => {
+        impl VmArithmetic for $type {
+            fn arith(self, rhs: $type, operation: ArithmeticOperation) -> $type {
+                match operation {
+                    ArithmeticOperation::Sum => self + rhs,
+                    ArithmeticOperation::Subtract => self - rhs,
+                    ArithmeticOperation::Multiply => self * rhs,
+                    ArithmeticOperation::Divide => self / rhs,
+                    ArithmeticOperation::Power => self.powf(rhs),
+                    ArithmeticOperation::Remainder => self % rhs,
+                }
+            }
+        }
+    };
+}
+
+impl_unsigned_arith!(u8);
+impl_unsigned_arith!(u16);
+impl_unsigned_arith!(u32);
+impl_unsigned_arith!(u64);
+impl_signed_arith!(i8);
+impl_signed_arith!(i16);
+impl_signed_arith!(i32);
+impl_signed_arith!(i64);
+impl_float_arith!(f32);
+impl_float_arith!(f64);
+
 pub fn stacked_binop_arith<T>(
     memory: &mut Memory,
     reg: &mut ControlRegisterValues,
     operation: ArithmeticOperation,
 ) where
-    T: NativeNumericType<T>
-        + std::ops::Add<T, Output = T>
-        + std::ops::Div<T, Output = T>
-        + std::ops::Mul<T, Output = T>
-        + std::ops::Sub<T, Output = T>,
+    T: NativeNumericType<T> + VmArithmetic,
     [(); std::mem::size_of::<T>()]:,
 {
     reg.sp -= std::mem::size_of::<T>() as u32;
@@ -77,41 +160,70 @@ pub fn stacked_binop_arith<T>(
     reg.sp -= std::mem::size_of::<T>() as u32;
     let lhs = memory.native_read::<T>(reg.sp);
 
-    let bytes = match operation {
-        ArithmeticOperation::Sum => (lhs + rhs).to_bytes(),
-        ArithmeticOperation::Subtract => (lhs - rhs).to_bytes(),
-        ArithmeticOperation::Multiply => (lhs * rhs).to_bytes(),
-        ArithmeticOperation::Divide => (lhs / rhs).to_bytes(),
-        ArithmeticOperation::Power => todo!(),
-    };
+    let bytes = lhs.arith(rhs, operation).to_bytes();
 
     memory.write(reg.sp, &bytes);
     reg.sp += std::mem::size_of::<T>() as u32;
 }
 
+//The immediate is always 16 bits: it is sign-extended for signed operations and zero-extended
+//for unsigned ones, then truncated to the operation size.
+pub fn immediate_operand<T>(operand: &[u8; 2], sign: SignFlag) -> T
+where
+    T: NativeNumericType<T>,
+{
+    let extended = match sign {
+        SignFlag::Signed => (i16::from_le_bytes(*operand) as i64).to_le_bytes(),
+        SignFlag::Unsigned => (u16::from_le_bytes(*operand) as u64).to_le_bytes(),
+    };
+    T::from_bytes(&extended[0..std::mem::size_of::<T>()])
+}
+
 pub fn immediate_integer_arith<T>(
     memory: &mut Memory,
     reg: &mut ControlRegisterValues,
     operation: ArithmeticOperation,
+    sign: SignFlag,
     rhs: &[u8; 2],
 ) where
-    T: NativeNumericType<T>
-        + std::ops::Add<T, Output = T>
-        + std::ops::Div<T, Output = T>
-        + std::ops::Mul<T, Output = T>
-        + std::ops::Sub<T, Output = T>
-        + std::fmt::Debug,
+    T: NativeNumericType<T> + VmArithmetic,
+    [(); std::mem::size_of::<T>()]:,
+{
+    reg.sp -= std::mem::size_of::<T>() as u32;
+    let lhs = memory.native_read::<T>(reg.sp);
+    let rhs = immediate_operand::<T>(rhs, sign);
+    let bytes = lhs.arith(rhs, operation).to_bytes();
+
+    memory.write(reg.sp, &bytes);
+    reg.sp += std::mem::size_of::<T>() as u32;
+}
+
+//and, or and xor give the same bits for signed and unsigned values, the sign only decides how the immediate is extended
+pub fn integer_bitwise<T>(
+    memory: &mut Memory,
+    reg: &mut ControlRegisterValues,
+    operation: BitwiseOperation,
+    sign: SignFlag,
+    mode: OperationMode,
+    operand: &[u8; 2],
+) where
+    T: NativeNumericType<T> + std::ops::BitAnd<Output = T> + std::ops::BitOr<Output = T> + std::ops::BitXor<Output = T>,
     [(); std::mem::size_of::<T>()]:,
 {
+    let rhs = match mode {
+        OperationMode::PureStack => {
+            reg.sp -= std::mem::size_of::<T>() as u32;
+            memory.native_read::<T>(reg.sp)
+        }
+        OperationMode::StackAndImmediate => immediate_operand::<T>(operand, sign),
+    };
     reg.sp -= std::mem::size_of::<T>() as u32;
     let lhs = memory.native_read::<T>(reg.sp);
-    let rhs = T::from_bytes(rhs);
+
     let bytes = match operation {
-        ArithmeticOperation::Sum => (lhs + rhs).to_bytes(),
-        ArithmeticOperation::Subtract => (lhs - rhs).to_bytes(),
-        ArithmeticOperation::Multiply => (lhs * rhs).to_bytes(),
-        ArithmeticOperation::Divide => (lhs / rhs).to_bytes(),
-        ArithmeticOperation::Power => todo!(),
+        BitwiseOperation::And => (lhs & rhs).to_bytes(),
+        BitwiseOperation::Or => (lhs | rhs).to_bytes(),
+        BitwiseOperation::Xor => (lhs ^ rhs).to_bytes(),
     };
 
     memory.write(reg.sp, &bytes);
@@ -148,6 +260,7 @@ pub fn immediate_integer_compare<T>(
     memory: &mut Memory,
     reg: &mut ControlRegisterValues,
     operation: CompareOperation,
+    sign: SignFlag,
     operand: &[u8; 2],
 ) where
     T: NativeNumericType<T> + std::cmp::PartialEq<T> + std::cmp::PartialOrd<T> + Display,
@@ -155,7 +268,7 @@ pub fn immediate_integer_compare<T>(
 {
     reg.sp -= std::mem::size_of::<T>() as u32;
     let lhs = memory.native_read::<T>(reg.sp);
-    let rhs = T::from_bytes(operand);
+    let rhs = immediate_operand::<T>(operand, sign);
     println!("Freyr: Comparing {lhs} and {rhs}");
     let result = match operation {
         CompareOperation::Equals => lhs == rhs,
@@ -169,6 +282,86 @@ pub fn immediate_integer_compare<T>(
     reg.sp += std::mem::size_of::<u8>() as u32;
 }
 
+//integers are widened to i128 so any 64 bit value, signed or not, fits before converting
+enum PoppedNumber {
+    Integer(i128),
+    Float(f64),
+}
+
+fn pop_number(memory: &Memory, reg: &mut ControlRegisterValues, bytes: NumberOfBytes, kind: NumberKind) -> PoppedNumber {
+    reg.sp -= bytes.get_bytes() as u32;
+    match (kind, bytes) {
+        (NumberKind::Float, NumberOfBytes::Bytes4) => PoppedNumber::Float(memory.native_read::<f32>(reg.sp) as f64),
+        (NumberKind::Float, NumberOfBytes::Bytes8) => PoppedNumber::Float(memory.native_read::<f64>(reg.sp)),
+        (NumberKind::Float, _) => panic!("Float size operation not allowed"),
+        (NumberKind::SignedInteger, NumberOfBytes::Bytes1) => PoppedNumber::Integer(memory.native_read::<i8>(reg.sp) as i128),
+        (NumberKind::SignedInteger, NumberOfBytes::Bytes2) => PoppedNumber::Integer(memory.native_read::<i16>(reg.sp) as i128),
+        (NumberKind::SignedInteger, NumberOfBytes::Bytes4) => PoppedNumber::Integer(memory.native_read::<i32>(reg.sp) as i128),
+        (NumberKind::SignedInteger, NumberOfBytes::Bytes8) => PoppedNumber::Integer(memory.native_read::<i64>(reg.sp) as i128),
+        (NumberKind::UnsignedInteger, NumberOfBytes::Bytes1) => PoppedNumber::Integer(memory.native_read::<u8>(reg.sp) as i128),
+        (NumberKind::UnsignedInteger, NumberOfBytes::Bytes2) => PoppedNumber::Integer(memory.native_read::<u16>(reg.sp) as i128),
+        (NumberKind::UnsignedInteger, NumberOfBytes::Bytes4) => PoppedNumber::Integer(memory.native_read::<u32>(reg.sp) as i128),
+        (NumberKind::UnsignedInteger, NumberOfBytes::Bytes8) => PoppedNumber::Integer(memory.native_read::<u64>(reg.sp) as i128),
+    }
+}
+
+//float to integer truncates toward zero and saturates at the bounds of the target type, like rust's `as`
+fn push_converted_number(memory: &mut Memory, reg: &mut ControlRegisterValues, bytes: NumberOfBytes, kind: NumberKind, number: PoppedNumber) {
+    let written = match (kind, number) {
+        (NumberKind::Float, PoppedNumber::Integer(i)) => match bytes {
+            NumberOfBytes::Bytes4 => (i as f32).to_bytes().to_vec(),
+            NumberOfBytes::Bytes8 => (i as f64).to_bytes().to_vec(),
+            _ => panic!("Float size operation not allowed"),
+        },
+        (NumberKind::Float, PoppedNumber::Float(f)) => match bytes {
+            NumberOfBytes::Bytes4 => (f as f32).to_bytes().to_vec(),
+            NumberOfBytes::Bytes8 => f.to_bytes().to_vec(),
+            _ => panic!("Float size operation not allowed"),
+        },
+        (NumberKind::SignedInteger, number) => {
+            let as_bytes = match number {
+                PoppedNumber::Integer(i) => (i as i64).to_le_bytes(),
+                PoppedNumber::Float(f) => match bytes {
+                    NumberOfBytes::Bytes1 => (f as i8 as i64).to_le_bytes(),
+                    NumberOfBytes::Bytes2 => (f as i16 as i64).to_le_bytes(),
+                    NumberOfBytes::Bytes4 => (f as i32 as i64).to_le_bytes(),
+                    NumberOfBytes::Bytes8 => (f as i64).to_le_bytes(),
+                },
+            };
+            as_bytes[0..bytes.get_bytes() as usize].to_vec()
+        }
+        (NumberKind::UnsignedInteger, number) => {
+            let as_bytes = match number {
+                PoppedNumber::Integer(i) => (i as u64).to_le_bytes(),
+                PoppedNumber::Float(f) => match bytes {
+                    NumberOfBytes::Bytes1 => (f as u8 as u64).to_le_bytes(),
+                    NumberOfBytes::Bytes2 => (f as u16 as u64).to_le_bytes(),
+                    NumberOfBytes::Bytes4 => (f as u32 as u64).to_le_bytes(),
+                    NumberOfBytes::Bytes8 => (f as u64).to_le_bytes(),
+                },
+            };
+            as_bytes[0..bytes.get_bytes() as usize].to_vec()
+        }
+    };
+    memory.write(reg.sp, &written);
+    reg.sp += written.len() as u32;
+}
+
+//relative offsets are counted from the address of the jump instruction itself,
+//None if a backward jump would land before the start of the program
+pub fn jump_target(ip: usize, source: &AddressJumpAddressSource, offset: u32) -> Option<usize> {
+    match source {
+        AddressJumpAddressSource::FromOperand => Some(offset as usize),
+        AddressJumpAddressSource::RelativeForward => ip.checked_add(offset as usize),
+        AddressJumpAddressSource::RelativeBackward => ip.checked_sub(offset as usize),
+        AddressJumpAddressSource::PopFromStack => panic!("Jump target from stack must be popped"),
+    }
+}
+
+fn checked_jump_target(ip: usize, source: &AddressJumpAddressSource, offset: u32) -> usize {
+    jump_target(ip, source, offset).expect("Jump targets before the start of the program are rejected by execute_checked")
+}
+
 pub fn execute(inst: &Instruction, memory: &mut Memory, reg: &mut ControlRegisterValues) -> bool {
     const IP_OFFSET: usize = 1 as usize;
     match inst {
@@ -354,7 +547,13 @@ pub fn execute(inst: &Instruction, memory: &mut Memory, reg: &mut ControlRegiste
             mode,
             operand,
         } => {
-            todo!("Bitwise ops not implemented in the VM")
+            match bytes {
+                NumberOfBytes::Bytes1 => integer_bitwise::<u8>(memory, reg, *operation, *sign, *mode, operand),
+                NumberOfBytes::Bytes2 => integer_bitwise::<u16>(memory, reg, *operation, *sign, *mode, operand),
+                NumberOfBytes::Bytes4 => integer_bitwise::<u32>(memory, reg, *operation, *sign, *mode, operand),
+                NumberOfBytes::Bytes8 => integer_bitwise::<u64>(memory, reg, *operation, *sign, *mode, operand),
+            }
+            reg.ip += IP_OFFSET;
         }
         Instruction::IntegerArithmetic {
             bytes,
@@ -400,28 +599,28 @@ pub fn execute(inst: &Instruction, memory: &mut Memory, reg: &mut ControlRegiste
         } => {
             match (bytes, sign) {
                 (NumberOfBytes::Bytes1, SignFlag::Unsigned) => {
-                    immediate_integer_arith::<u8>(memory, reg, *operation, operand)
+                    immediate_integer_arith::<u8>(memory, reg, *operation, *sign, operand)
                 }
                 (NumberOfBytes::Bytes2, SignFlag::Unsigned) => {
-                    immediate_integer_arith::<u16>(memory, reg, *operation, operand)
+                    immediate_integer_arith::<u16>(memory, reg, *operation, *sign, operand)
                 }
                 (NumberOfBytes::Bytes4, SignFlag::Unsigned) => {
-                    immediate_integer_arith::<u32>(memory, reg, *operation, operand)
+                    immediate_integer_arith::<u32>(memory, reg, *operation, *sign, operand)
                 }
                 (NumberOfBytes::Bytes8, SignFlag::Unsigned) => {
-                    immediate_integer_arith::<u64>(memory, reg, *operation, operand)
+                    immediate_integer_arith::<u64>(memory, reg, *operation, *sign, operand)
                 }
                 (NumberOfBytes::Bytes1, SignFlag::Signed) => {
-                    immediate_integer_arith::<i8>(memory, reg, *operation, operand)
+                    immediate_integer_arith::<i8>(memory, reg, *operation, *sign, operand)
                 }
                 (NumberOfBytes::Bytes2, SignFlag::Signed) => {
-                    immediate_integer_arith::<i16>(memory, reg, *operation, operand)
+                    immediate_integer_arith::<i16>(memory, reg, *operation, *sign, operand)
                 }
                 (NumberOfBytes::Bytes4, SignFlag::Signed) => {
-                    immediate_integer_arith::<i32>(memory, reg, *operation, operand)
+                    immediate_integer_arith::<i32>(memory, reg, *operation, *sign, operand)
                 }
                 (NumberOfBytes::Bytes8, SignFlag::Signed) => {
-                    immediate_integer_arith::<i64>(memory, reg, *operation, operand)
+                    immediate_integer_arith::<i64>(memory, reg, *operation, *sign, operand)
                 }
             }
             reg.ip += IP_OFFSET;
@@ -470,28 +669,28 @@ pub fn execute(inst: &Instruction, memory: &mut Memory, reg: &mut ControlRegiste
         } => {
             match (bytes, sign) {
                 (NumberOfBytes::Bytes1, SignFlag::Unsigned) => {
-                    immediate_integer_compare::<u8>(memory, reg, *operation, operand)
+                    immediate_integer_compare::<u8>(memory, reg, *operation, *sign, operand)
                 }
                 (NumberOfBytes::Bytes2, SignFlag::Unsigned) => {
-                    immediate_integer_compare::<u16>(memory, reg, *operation, operand)
+                    immediate_integer_compare::<u16>(memory, reg, *operation, *sign, operand)
                 }
                 (NumberOfBytes::Bytes4, SignFlag::Unsigned) => {
-                    immediate_integer_compare::<u32>(memory, reg, *operation, operand)
+                    immediate_integer_compare::<u32>(memory, reg, *operation, *sign, operand)
                 }
                 (NumberOfBytes::Bytes8, SignFlag::Unsigned) => {
-                    immediate_integer_compare::<u64>(memory, reg, *operation, operand)
+                    immediate_integer_compare::<u64>(memory, reg, *operation, *sign, operand)
                 }
                 (NumberOfBytes::Bytes1, SignFlag::Signed) => {
-                    immediate_integer_compare::<i8>(memory, reg, *operation, operand)
+                    immediate_integer_compare::<i8>(memory, reg, *operation, *sign, operand)
                 }
                 (NumberOfBytes::Bytes2, SignFlag::Signed) => {
-                    immediate_integer_compare::<i16>(memory, reg, *operation, operand)
+                    immediate_integer_compare::<i16>(memory, reg, *operation, *sign, operand)
                 }
                 (NumberOfBytes::Bytes4, SignFlag::Signed) => {
-                    immediate_integer_compare::<i32>(memory, reg, *operation, operand)
+                    immediate_integer_compare::<i32>(memory, reg, *operation, *sign, operand)
                 }
                 (NumberOfBytes::Bytes8, SignFlag::Signed) => {
-                    immediate_integer_compare::<i64>(memory, reg, *operation, operand)
+                    immediate_integer_compare::<i64>(memory, reg, *operation, *sign, operand)
                 }
             }
             reg.ip += IP_OFFSET;
@@ -552,14 +751,16 @@ pub fn execute(inst: &Instruction, memory: &mut Memory, reg: &mut ControlRegiste
         }
         Instruction::Call { source, offset } => {
             match source {
-                super::instructions::AddressJumpAddressSource::FromOperand => {
+                source @ (AddressJumpAddressSource::FromOperand
+                | AddressJumpAddressSource::RelativeForward
+                | AddressJumpAddressSource::RelativeBackward) => {
                     let return_ip = (reg.ip + IP_OFFSET) as u32;
-                    reg.ip = *offset as usize;
+                    reg.ip = checked_jump_target(reg.ip, source, *offset);
                     memory.write(reg.sp, &return_ip.to_le_bytes());
                     reg.sp += std::mem::size_of::<u32>() as u32;
                     reg.bp = reg.sp;
                 }
-                super::instructions::AddressJumpAddressSource::PopFromStack => {
+                AddressJumpAddressSource::PopFromStack => {
                     reg.sp -= std::mem::size_of::<u32>() as u32;
                     let popped = memory.native_read::<u32>(reg.sp);
                     let return_ip = (reg.ip + IP_OFFSET) as u32;
@@ -576,13 +777,16 @@ pub fn execute(inst: &Instruction, memory: &mut Memory, reg: &mut ControlRegiste
             reg.ip = popped as usize;
         }
         Instruction::JumpIfZero {
-            source: AddressJumpAddressSource::FromOperand,
+            source:
+                source @ (AddressJumpAddressSource::FromOperand
+                | AddressJumpAddressSource::RelativeForward
+                | AddressJumpAddressSource::RelativeBackward),
             offset,
         } => {
             reg.sp -= std::mem::size_of::<u8>() as u32;
             let popped = memory.read_single(reg.sp);
             if popped == 0 {
-                reg.ip = *offset as usize;
+                reg.ip = checked_jump_target(reg.ip, source, *offset);
             } else {
                 reg.ip += 1;
             }
@@ -602,13 +806,16 @@ pub fn execute(inst: &Instruction, memory: &mut Memory, reg: &mut ControlRegiste
             }
         }
         Instruction::JumpIfNotZero {
-            source: AddressJumpAddressSource::FromOperand,
+            source:
+                source @ (AddressJumpAddressSource::FromOperand
+                | AddressJumpAddressSource::RelativeForward
+                | AddressJumpAddressSource::RelativeBackward),
             offset,
         } => {
             reg.sp -= std::mem::size_of::<u8>() as u32;
             let popped = memory.read_single(reg.sp);
             if popped != 0 {
-                reg.ip = *offset as usize;
+                reg.ip = checked_jump_target(reg.ip, source, *offset);
             } else {
                 reg.ip += 1;
             }
@@ -628,10 +835,13 @@ pub fn execute(inst: &Instruction, memory: &mut Memory, reg: &mut ControlRegiste
             }
         }
         Instruction::JumpUnconditional {
-            source: AddressJumpAddressSource::FromOperand,
+            source:
+                source @ (AddressJumpAddressSource::FromOperand
+                | AddressJumpAddressSource::RelativeForward
+                | AddressJumpAddressSource::RelativeBackward),
             offset,
         } => {
-            reg.ip = *offset as usize;
+            reg.ip = checked_jump_target(reg.ip, source, *offset);
         }
         Instruction::JumpUnconditional {
             source: AddressJumpAddressSource::PopFromStack,
@@ -641,6 +851,38 @@ pub fn execute(inst: &Instruction, memory: &mut Memory, reg: &mut ControlRegiste
             let offset = memory.native_read::<u32>(reg.sp);
             reg.ip = offset as usize;
         }
+        Instruction::ConvertNumber { from_bytes, from_kind, to_bytes, to_kind } => {
+            let number = pop_number(memory, reg, *from_bytes, *from_kind);
+            push_converted_number(memory, reg, *to_bytes, *to_kind, number);
+            reg.ip += IP_OFFSET;
+        }
+        Instruction::HostCall { function: HostFunction::Print, bytes, kind } => {
+            let printed = match pop_number(memory, reg, *bytes, *kind) {
+                PoppedNumber::Integer(i) => i.to_string(),
+                PoppedNumber::Float(f) => f.to_string(),
+            };
+            memory.output.push_str(&printed);
+            memory.output.push('\n');
+            reg.ip += IP_OFFSET;
+        }
+        Instruction::HostCall { function: HostFunction::PrintBool, .. } => {
+            reg.sp -= std::mem::size_of::<u8>() as u32;
+            let printed = if memory.read_single(reg.sp) != 0 { "True" } else { "False" };
+            memory.output.push_str(printed);
+            memory.output.push('\n');
+            reg.ip += IP_OFFSET;
+        }
+        Instruction::HostCall { function: HostFunction::PrintStr, .. } => {
+            reg.sp -= std::mem::size_of::<u32>() as u32;
+            let len = memory.native_read::<u32>(reg.sp);
+            reg.sp -= len;
+            let text = (reg.sp..reg.sp + len)
+                .map(|address| memory.read_single(address))
+                .collect::<Vec<_>>();
+            memory.output.push_str(&String::from_utf8_lossy(&text));
+            memory.output.push('\n');
+            reg.ip += IP_OFFSET;
+        }
         Instruction::Exit => return true,
         /*_ => {
             panic!("Tried to execute unknown instruction {:?}", inst);
@@ -662,7 +904,12 @@ pub fn print_stack(memory: &mut Memory) {
 }
 
 pub fn prepare_vm() -> (Memory, ControlRegisterValues) {
+    prepare_vm_with_stack_size(DEFAULT_STACK_SIZE)
+}
+
+pub fn prepare_vm_with_stack_size(stack_size: u32) -> (Memory, ControlRegisterValues) {
     let mut mem = Memory::new();
+    mem.stack_size = stack_size;
     mem.make_ready();
     mem.write(mem.stack_start, &0u32.to_le_bytes());
     mem.write(mem.stack_start + 4, &u32::MAX.to_le_bytes());
@@ -676,9 +923,171 @@ pub fn prepare_vm() -> (Memory, ControlRegisterValues) {
 }
 
 
-pub fn run(code: &[Instruction], memory: &mut Memory, registers: &mut ControlRegisterValues) {
+//Snapshot taken right before an instruction executes, used to debug generated bytecode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEvent {
+    pub instruction: Instruction,
+    pub ip: usize,
+    pub sp: u32,
+    pub bp: u32,
+    //up to TRACE_STACK_TOP_BYTES bytes right below sp, lowest address first
+    pub stack_top: Vec<u8>,
+}
+
+pub const TRACE_STACK_TOP_BYTES: u32 = 8;
+
+fn make_trace_event(inst: &Instruction, memory: &Memory, registers: &ControlRegisterValues) -> TraceEvent {
+    let num_bytes = TRACE_STACK_TOP_BYTES.min(registers.sp - memory.stack_start);
+    let stack_top = (registers.sp - num_bytes..registers.sp)
+        .map(|address| memory.read_single(address))
+        .collect();
+    TraceEvent {
+        instruction: inst.clone(),
+        ip: registers.ip,
+        sp: registers.sp,
+        bp: registers.bp,
+        stack_top,
+    }
+}
+
+pub fn run(code: &[Instruction], memory: &mut Memory, registers: &mut ControlRegisterValues) -> Result<(), RuntimeError> {
+    run_loop(code, memory, registers, None)
+}
+
+//Same as run, but calls tracer before every instruction
+pub fn run_with_tracer(
+    code: &[Instruction],
+    memory: &mut Memory,
+    registers: &mut ControlRegisterValues,
+    mut tracer: impl FnMut(&TraceEvent),
+) -> Result<(), RuntimeError> {
+    run_loop(code, memory, registers, Some(&mut tracer))
+}
+
+//memory and registers prepared by prepare_vm, for running programs without threading both around
+pub struct Machine {
+    pub memory: Memory,
+    pub registers: ControlRegisterValues,
+    //where the stack of the program starts, right after the frame prepare_vm writes
+    stack_base: u32,
+    //the program step executes, set by load
+    program: Vec<Instruction>,
+}
+
+//what a single step executed, finished is set when the program exited or ran past its end
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepResult {
+    pub ip: usize,
+    pub instruction: Instruction,
+    pub finished: bool,
+}
+
+impl Machine {
+    pub fn new() -> Machine {
+        let (memory, registers) = prepare_vm();
+        let stack_base = registers.sp;
+        Machine { memory, registers, stack_base, program: vec![] }
+    }
+
+    pub fn load(&mut self, program: &[Instruction]) {
+        self.program = program.to_vec();
+    }
+
+    pub fn run(&mut self, program: &[Instruction]) -> Result<(), RuntimeError> {
+        run(program, &mut self.memory, &mut self.registers)
+    }
+
+    pub fn run_with_trace(&mut self, program: &[Instruction], hook: impl FnMut(&TraceEvent)) -> Result<(), RuntimeError> {
+        run_with_tracer(program, &mut self.memory, &mut self.registers, hook)
+    }
+
+    pub fn step(&mut self) -> Result<StepResult, RuntimeError> {
+        let ip = self.registers.ip;
+        let Some(instruction) = self.program.get(ip).cloned() else {
+            return Err(RuntimeError::IpOutOfBounds { ip, program_len: self.program.len() });
+        };
+        let exited = execute_checked(&instruction, &mut self.memory, &mut self.registers, self.program.len())?;
+        Ok(StepResult {
+            ip,
+            instruction,
+            finished: exited || self.registers.ip >= self.program.len(),
+        })
+    }
+
+    //bytes the program left on the stack, lowest address first
+    pub fn stack(&self) -> Vec<u8> {
+        (self.stack_base..self.registers.sp)
+            .map(|address| self.memory.read_single(address))
+            .collect()
+    }
+}
+
+//the divisor is the value on top of the stack, or the immediate sign or zero extended to the operation size
+fn divisor_is_zero(bytes: NumberOfBytes, mode: OperationMode, operand: &[u8; 2], memory: &Memory, registers: &ControlRegisterValues) -> bool {
+    let size = bytes.get_bytes() as u32;
+    match mode {
+        OperationMode::PureStack => (registers.sp - size..registers.sp).all(|address| memory.read_single(address) == 0),
+        OperationMode::StackAndImmediate => operand.iter().take(size as usize).all(|byte| *byte == 0),
+    }
+}
+
+//source and offset of a jump or call with its target in the instruction, if executing it would take the jump
+fn taken_jump<'a>(inst: &'a Instruction, memory: &Memory, registers: &ControlRegisterValues) -> Option<(&'a AddressJumpAddressSource, u32)> {
+    let (source, offset, taken) = match inst {
+        Instruction::Call { source, offset } | Instruction::JumpUnconditional { source, offset } => (source, *offset, true),
+        //the condition is the byte on top of the stack
+        Instruction::JumpIfZero { source, offset } => (source, *offset, memory.read_single(registers.sp - 1) == 0),
+        Instruction::JumpIfNotZero { source, offset } => (source, *offset, memory.read_single(registers.sp - 1) != 0),
+        _ => return None,
+    };
+    match source {
+        AddressJumpAddressSource::PopFromStack => None,
+        _ if taken => Some((source, offset)),
+        _ => None,
+    }
+}
+
+fn execute_checked(inst: &Instruction, memory: &mut Memory, registers: &mut ControlRegisterValues, program_len: usize) -> Result<bool, RuntimeError> {
+    let ip = registers.ip;
+    if let Some((source, offset)) = taken_jump(inst, memory, registers) {
+        if jump_target(ip, source, offset).is_none() {
+            return Err(RuntimeError::IpOutOfBounds { ip, program_len });
+        }
+    }
+    if let Instruction::IntegerArithmetic { bytes, operation, mode, operand, .. } = inst {
+        match operation {
+            ArithmeticOperation::Divide if divisor_is_zero(*bytes, *mode, operand, memory, registers) => {
+                return Err(RuntimeError::DivisionByZero { ip });
+            }
+            ArithmeticOperation::Remainder if divisor_is_zero(*bytes, *mode, operand, memory, registers) => {
+                return Err(RuntimeError::RemainderByZero { ip });
+            }
+            _ => {}
+        }
+    }
+    let finished = execute(inst, memory, registers);
+
+    if registers.sp > memory.stack_end() {
+        return Err(RuntimeError::StackOverflow { ip, sp: registers.sp, stack_end: memory.stack_end() });
+    }
+    Ok(finished)
+}
+
+//without a tracer the only cost is checking the option
+fn run_loop(
+    code: &[Instruction],
+    memory: &mut Memory,
+    registers: &mut ControlRegisterValues,
+    mut tracer: Option<&mut dyn FnMut(&TraceEvent)>,
+) -> Result<(), RuntimeError> {
     loop {
-        let inst = &code[registers.ip];
+        let Some(inst) = code.get(registers.ip) else {
+            return Err(RuntimeError::IpOutOfBounds { ip: registers.ip, program_len: code.len() });
+        };
+
+        if let Some(tracer) = tracer.as_mut() {
+            tracer(&make_trace_event(inst, memory, registers));
+        }
 
         /*println!(
             "Executing instruction {inst:?} ip = {ip} sp = {sp} bp = {bp}",
@@ -688,12 +1097,10 @@ pub fn run(code: &[Instruction], memory: &mut Memory, registers: &mut ControlReg
         );
         print_stack(memory);
         */
-        if execute(inst, memory, registers) {
-            break;
-        }
+        let finished = execute_checked(inst, memory, registers, code.len())?;
 
-        if registers.ip >= code.len() {
-            break;
+        if finished || registers.ip >= code.len() {
+            return Ok(());
         }
     }
 }
@@ -707,12 +1114,16 @@ mod tests {
     use crate::freyr::{
         asm::{
             asm::*,
-            assembler::{as_freyr_instructions, parse_asm, resolve},
+            assembler::{as_freyr_instructions, parse_asm, resolve, resolve_forward_jump},
+        },
+        vm::{
+            instructions::{AddressJumpAddressSource, ArithmeticOperation, Instruction, NumberKind, NumberOfBytes, OperationMode, ShiftDirection, SignFlag},
+            memory::{Memory, NativeNumericType},
+            runner::execute,
         },
-        vm::{instructions::Instruction, memory::Memory, runner::execute},
     };
 
-    use super::{run, ControlRegisterValues};
+    use super::{prepare_vm_with_stack_size, run, run_with_tracer, ControlRegisterValues, Machine, RuntimeError, StepResult, TraceEvent, TRACE_STACK_TOP_BYTES};
 
     fn assemble(code: &str) -> Vec<Instruction> {
         let parsed = parse_asm(code);
@@ -734,7 +1145,7 @@ mod tests {
     fn run_code(code: &str) -> (Memory, ControlRegisterValues) {
         let assembled = assemble(code);
         let (mut mem, mut registers) = prepare_vm();
-        run(&assembled, &mut mem, &mut registers);
+        run(&assembled, &mut mem, &mut registers).unwrap();
         (mem, registers)
     }
 
@@ -927,4 +1338,625 @@ mod tests {
         assert_eq!(x, 10); //even pushes and pops result in not moving the stack ptr
         assert_eq!(reg.ip, 8);
     }
+
+    #[test]
+    fn countdown_loop_with_backward_jump() {
+        let code = "
+    main:
+        push_imm32      5           ; x = 5
+        loadaddr_rel32  bp+0        ; load x
+        subu_imm32      1           ; temp = x - 1
+        storeaddr_rel32 bp+0        ; x = temp
+        loadaddr_rel32  bp+0        ; load x
+        neu_imm32       0           ; x != 0
+        jnz             -5          ; if x != 0 go back to the first load
+        exit
+";
+        let assembled = assemble(code);
+        assert_eq!(
+            assembled[6],
+            Instruction::JumpIfNotZero {
+                source: AddressJumpAddressSource::RelativeBackward,
+                offset: 5
+            }
+        );
+        let (mem, reg) = run_code(code);
+        let x: u32 = mem.native_read(reg.bp + 0);
+        assert_eq!(x, 0);
+        assert_eq!(reg.ip, 7);
+    }
+
+    #[test]
+    fn countdown_loop_with_jnz_to_label() {
+        let code = "
+    main:
+        push_imm32      3           ; x = 3
+        push_imm32      0           ; iterations = 0
+    loop_start:
+        loadaddr_rel32  bp+4        ; load iterations
+        sumu_imm32      1           ; temp = iterations + 1
+        storeaddr_rel32 bp+4        ; iterations = temp
+        loadaddr_rel32  bp+0        ; load x
+        subu_imm32      1           ; temp = x - 1
+        storeaddr_rel32 bp+0        ; x = temp
+        loadaddr_rel32  bp+0        ; load x
+        neu_imm32       0           ; x != 0
+        jnz             loop_start  ; if x != 0 loop again
+        exit
+";
+        let (mem, reg) = run_code(code);
+        let x: u32 = mem.native_read(reg.bp + 0);
+        let iterations: u32 = mem.native_read(reg.bp + 4);
+        assert_eq!(x, 0);
+        assert_eq!(iterations, 3);
+        assert_eq!(reg.sp, reg.bp + 8);
+        assert_eq!(reg.ip, 11);
+    }
+
+    #[test]
+    fn countdown_loop_with_jump_targets_from_stack() {
+        //the target is pushed first, jnz_stack pops the condition and then the target
+        let code = "
+    main:
+        push_imm32      4           ; x = 4
+        loadaddr_rel32  bp+0        ; load x
+        subu_imm32      1           ; temp = x - 1
+        storeaddr_rel32 bp+0        ; x = temp
+        push_imm32      1           ; loop start
+        loadaddr_rel32  bp+0        ; load x
+        neu_imm32       0           ; x != 0
+        jnz_stack                   ; if x != 0 go to loop start
+        push_imm32      11          ; after the exit below
+        jmp_stack
+        exit
+        push_imm32      42
+        exit
+";
+        let (mem, reg) = run_code(code);
+        let x: u32 = mem.native_read(reg.bp + 0);
+        let pushed: u32 = mem.native_read(reg.bp + 4);
+        assert_eq!(x, 0);
+        assert_eq!(pushed, 42);
+        assert_eq!(reg.ip, 12);
+    }
+
+    #[test]
+    fn forward_conditional_jump_skips_instruction() {
+        //jz is at index 3, and jumps over the exit at index 4
+        let offset = resolve_forward_jump(3, 5);
+        assert_eq!(offset, 2);
+        let code = format!("
+    main:
+        push_imm32      7           ; x = 7
+        loadaddr_rel32  bp+0        ; load x
+        neu_imm32       7           ; x != 7
+        jz              +{offset}   ; x == 7, skip the exit
+        exit
+        push_imm32      42
+        exit
+");
+        let assembled = assemble(&code);
+        assert_eq!(
+            assembled[3],
+            Instruction::JumpIfZero {
+                source: AddressJumpAddressSource::RelativeForward,
+                offset: 2
+            }
+        );
+        let (mem, reg) = run_code(&code);
+        let pushed: u32 = mem.native_read(reg.bp + 4);
+        assert_eq!(pushed, 42);
+        assert_eq!(reg.ip, 6);
+    }
+
+    #[test]
+    fn unbounded_recursion_overflows_small_stack() {
+        let code = "
+    main:
+        push_reg        bp
+        call recurse
+        exit
+    recurse:
+        push_reg        bp
+        call recurse
+        return
+";
+        let assembled = assemble(code);
+        let (mut mem, mut reg) = prepare_vm_with_stack_size(64);
+        let result = run(&assembled, &mut mem, &mut reg);
+
+        let Err(RuntimeError::StackOverflow { sp, stack_end, .. }) = result else {
+            panic!("Expected stack overflow, got {result:?}");
+        };
+        assert_eq!(stack_end, mem.stack_start + 64);
+        //each push_reg and call pushes 4 bytes, the first one past the end is reported
+        assert_eq!(sp, stack_end + 4);
+    }
+
+    #[test]
+    fn tracer_sees_every_instruction_in_order() {
+        let code = "
+    main:
+        push_imm32      20
+        push_imm32      22
+        sums32
+";
+        let assembled = assemble(code);
+        let (mut mem, mut reg) = prepare_vm();
+        let mut events: Vec<TraceEvent> = vec![];
+        run_with_tracer(&assembled, &mut mem, &mut reg, |event| events.push(event.clone())).unwrap();
+
+        assert_eq!(events.len(), 3);
+        assert_eq!(events.iter().map(|e| e.ip).collect::<Vec<_>>(), vec![0, 1, 2]);
+        assert_eq!(
+            events.iter().map(|e| e.instruction.clone()).collect::<Vec<_>>(),
+            assembled
+        );
+        assert_eq!(events[0].sp, mem.stack_start);
+        assert_eq!(events[0].stack_top, Vec::<u8>::new());
+        assert_eq!(events[2].sp, mem.stack_start + 8);
+        assert_eq!(events[2].stack_top, [20u32.to_le_bytes(), 22u32.to_le_bytes()].concat());
+        assert_eq!(mem.native_read::<i32>(reg.sp - 4), 42);
+    }
+
+    fn push_native<T: NativeNumericType<T>>(
+        mem: &mut Memory,
+        reg: &mut ControlRegisterValues,
+        value: T,
+    ) where
+        [(); std::mem::size_of::<T>()]:,
+    {
+        mem.write(reg.sp, &value.to_bytes());
+        reg.sp += std::mem::size_of::<T>() as u32;
+    }
+
+    fn float_binop<T: NativeNumericType<T>>(
+        bytes: NumberOfBytes,
+        operation: ArithmeticOperation,
+        lhs: T,
+        rhs: T,
+    ) -> (T, ControlRegisterValues)
+    where
+        [(); std::mem::size_of::<T>()]:,
+    {
+        let (mut mem, mut reg) = prepare_vm();
+        push_native(&mut mem, &mut reg, lhs);
+        push_native(&mut mem, &mut reg, rhs);
+        execute(
+            &Instruction::FloatArithmetic { bytes, operation },
+            &mut mem,
+            &mut reg,
+        );
+        let result = mem.native_read::<T>(reg.sp - std::mem::size_of::<T>() as u32);
+        (result, reg)
+    }
+
+    #[test]
+    fn convert_f64_to_i32_truncates() {
+        let (mut mem, mut reg) = prepare_vm();
+        push_native(&mut mem, &mut reg, 3.9f64);
+        execute(
+            &Instruction::ConvertNumber {
+                from_bytes: NumberOfBytes::Bytes8,
+                from_kind: NumberKind::Float,
+                to_bytes: NumberOfBytes::Bytes4,
+                to_kind: NumberKind::SignedInteger,
+            },
+            &mut mem,
+            &mut reg,
+        );
+        assert_eq!(reg.sp, reg.bp + 4);
+        assert_eq!(mem.native_read::<i32>(reg.sp - 4), 3);
+        assert_eq!(reg.ip, 1);
+    }
+
+    #[test]
+    fn convert_i32_to_f64() {
+        let (mut mem, mut reg) = prepare_vm();
+        push_native(&mut mem, &mut reg, 5i32);
+        execute(
+            &Instruction::ConvertNumber {
+                from_bytes: NumberOfBytes::Bytes4,
+                from_kind: NumberKind::SignedInteger,
+                to_bytes: NumberOfBytes::Bytes8,
+                to_kind: NumberKind::Float,
+            },
+            &mut mem,
+            &mut reg,
+        );
+        assert_eq!(reg.sp, reg.bp + 8);
+        assert_eq!(mem.native_read::<f64>(reg.sp - 8), 5.0);
+    }
+
+    #[test]
+    fn print_host_call_pops_value_into_output() {
+        let (mem, reg) = run_code("
+    push_imm32 42
+    hostcall print s32
+    push_imm8 1
+    hostcall print_bool u8
+    push_imm8 104
+    push_imm8 105
+    push_imm32 2
+    hostcall print_str u32
+");
+        assert_eq!(mem.output, "42\nTrue\nhi\n");
+        assert_eq!(reg.sp, mem.stack_start);
+    }
+
+    fn shift_right_4_bytes(sign: SignFlag, mode: OperationMode, value: i32, amount: u8) -> u32 {
+        let (mut mem, mut reg) = prepare_vm();
+        push_native(&mut mem, &mut reg, value);
+        if mode == OperationMode::PureStack {
+            push_native(&mut mem, &mut reg, amount as u32);
+        }
+        execute(
+            &Instruction::BitShift {
+                bytes: NumberOfBytes::Bytes4,
+                direction: ShiftDirection::Right,
+                mode,
+                sign,
+                operand: amount,
+            },
+            &mut mem,
+            &mut reg,
+        );
+        assert_eq!(reg.sp, reg.bp + 4);
+        mem.native_read::<u32>(reg.sp - 4)
+    }
+
+    #[test]
+    fn signed_shift_right_keeps_sign_bit() {
+        for mode in [OperationMode::PureStack, OperationMode::StackAndImmediate] {
+            let result = shift_right_4_bytes(SignFlag::Signed, mode, -16, 2);
+            assert_eq!(result as i32, -4);
+            assert_eq!(result, 0xFFFF_FFFC);
+        }
+    }
+
+    #[test]
+    fn unsigned_shift_right_fills_with_zeros() {
+        for mode in [OperationMode::PureStack, OperationMode::StackAndImmediate] {
+            let result = shift_right_4_bytes(SignFlag::Unsigned, mode, -16, 2);
+            assert_eq!(result, 0xFFFF_FFF0 >> 2);
+            assert_eq!(result, 0x3FFF_FFFC);
+        }
+    }
+
+    #[test]
+    fn float_sum_f32() {
+        let (result, reg) =
+            float_binop::<f32>(NumberOfBytes::Bytes4, ArithmeticOperation::Sum, 1.5, 2.5);
+        assert_eq!(result, 4.0);
+        assert_eq!(reg.sp, reg.bp + 4);
+        assert_eq!(reg.ip, 1);
+    }
+
+    #[test]
+    fn float_sum_f64() {
+        let (result, reg) =
+            float_binop::<f64>(NumberOfBytes::Bytes8, ArithmeticOperation::Sum, 1.5, 2.5);
+        assert_eq!(result, 4.0);
+        assert_eq!(reg.sp, reg.bp + 8);
+        assert_eq!(reg.ip, 1);
+    }
+
+    #[test]
+    fn float_subtract_multiply_f64() {
+        let (result, _) =
+            float_binop::<f64>(NumberOfBytes::Bytes8, ArithmeticOperation::Subtract, 1.5, 2.5);
+        assert_eq!(result, -1.0);
+        let (result, _) =
+            float_binop::<f64>(NumberOfBytes::Bytes8, ArithmeticOperation::Multiply, 1.5, 2.5);
+        assert_eq!(result, 3.75);
+    }
+
+    #[test]
+    fn float_divide_by_zero_f32() {
+        let (result, _) =
+            float_binop::<f32>(NumberOfBytes::Bytes4, ArithmeticOperation::Divide, 1.0, 0.0);
+        assert_eq!(result, f32::INFINITY);
+    }
+
+    #[test]
+    fn float_divide_by_zero_f64() {
+        let (result, _) =
+            float_binop::<f64>(NumberOfBytes::Bytes8, ArithmeticOperation::Divide, 1.0, 0.0);
+        assert_eq!(result, f64::INFINITY);
+        let (result, _) =
+            float_binop::<f64>(NumberOfBytes::Bytes8, ArithmeticOperation::Divide, 0.0, 0.0);
+        assert!(result.is_nan());
+    }
+
+    fn immediate_arith<T: NativeNumericType<T>>(
+        bytes: NumberOfBytes,
+        sign: SignFlag,
+        operation: ArithmeticOperation,
+        lhs: T,
+        operand: [u8; 2],
+    ) -> T
+    where
+        [(); std::mem::size_of::<T>()]:,
+    {
+        let (mut mem, mut reg) = prepare_vm();
+        push_native(&mut mem, &mut reg, lhs);
+        execute(
+            &Instruction::IntegerArithmetic { bytes, sign, operation, mode: OperationMode::StackAndImmediate, operand },
+            &mut mem,
+            &mut reg,
+        );
+        mem.native_read::<T>(reg.sp - std::mem::size_of::<T>() as u32)
+    }
+
+    #[test]
+    fn immediate_sum_negative_i32() {
+        let result = immediate_arith::<i32>(
+            NumberOfBytes::Bytes4, SignFlag::Signed, ArithmeticOperation::Sum, 10, (-3i16).to_le_bytes());
+        assert_eq!(result, 7);
+    }
+
+    #[test]
+    fn immediate_multiply_negative_i64() {
+        let result = immediate_arith::<i64>(
+            NumberOfBytes::Bytes8, SignFlag::Signed, ArithmeticOperation::Multiply, 1000, (-2i16).to_le_bytes());
+        assert_eq!(result, -2000);
+    }
+
+    #[test]
+    fn immediate_sum_negative_i8() {
+        let result = immediate_arith::<i8>(
+            NumberOfBytes::Bytes1, SignFlag::Signed, ArithmeticOperation::Sum, 10, (-3i16).to_le_bytes());
+        assert_eq!(result, 7);
+    }
+
+    #[test]
+    fn immediate_sum_unsigned_is_zero_extended() {
+        let result = immediate_arith::<u32>(
+            NumberOfBytes::Bytes4, SignFlag::Unsigned, ArithmeticOperation::Sum, 1, 0xFFFFu16.to_le_bytes());
+        assert_eq!(result, 0x10000);
+    }
+
+    fn stacked_arith<T: NativeNumericType<T>>(
+        bytes: NumberOfBytes,
+        sign: SignFlag,
+        operation: ArithmeticOperation,
+        lhs: T,
+        rhs: T,
+    ) -> T
+    where
+        [(); std::mem::size_of::<T>()]:,
+    {
+        let (mut mem, mut reg) = prepare_vm();
+        push_native(&mut mem, &mut reg, lhs);
+        push_native(&mut mem, &mut reg, rhs);
+        execute(
+            &Instruction::IntegerArithmetic { bytes, sign, operation, mode: OperationMode::PureStack, operand: [0, 0] },
+            &mut mem,
+            &mut reg,
+        );
+        assert_eq!(reg.sp, reg.bp + std::mem::size_of::<T>() as u32);
+        mem.native_read::<T>(reg.sp - std::mem::size_of::<T>() as u32)
+    }
+
+    #[test]
+    fn signed_division_truncates_towards_zero() {
+        let divide = |lhs, rhs| stacked_arith::<i32>(NumberOfBytes::Bytes4, SignFlag::Signed, ArithmeticOperation::Divide, lhs, rhs);
+        assert_eq!(divide(-7, 2), -3);
+        assert_eq!(divide(7, -2), -3);
+        assert_eq!(divide(-7, -2), 3);
+        assert_eq!(divide(i32::MIN, -1), i32::MIN);
+    }
+
+    #[test]
+    fn unsigned_division_reads_bits_as_unsigned() {
+        let result = stacked_arith::<u32>(
+            NumberOfBytes::Bytes4, SignFlag::Unsigned, ArithmeticOperation::Divide, -7i32 as u32, 2);
+        assert_eq!(result, 0x7FFF_FFFC);
+    }
+
+    #[test]
+    fn multiply_overflow_wraps_at_every_width() {
+        let (signed, unsigned, multiply) = (SignFlag::Signed, SignFlag::Unsigned, ArithmeticOperation::Multiply);
+        assert_eq!(stacked_arith::<u8>(NumberOfBytes::Bytes1, unsigned, multiply, 200, 2), 144);
+        assert_eq!(stacked_arith::<i8>(NumberOfBytes::Bytes1, signed, multiply, 100, 2), -56);
+        assert_eq!(stacked_arith::<u16>(NumberOfBytes::Bytes2, unsigned, multiply, 60000, 2), 54464);
+        assert_eq!(stacked_arith::<i16>(NumberOfBytes::Bytes2, signed, multiply, i16::MAX, 2), -2);
+        assert_eq!(stacked_arith::<u32>(NumberOfBytes::Bytes4, unsigned, multiply, u32::MAX, 2), u32::MAX - 1);
+        assert_eq!(stacked_arith::<i32>(NumberOfBytes::Bytes4, signed, multiply, i32::MAX, 2), -2);
+        assert_eq!(stacked_arith::<u64>(NumberOfBytes::Bytes8, unsigned, multiply, u64::MAX, 2), u64::MAX - 1);
+        assert_eq!(stacked_arith::<i64>(NumberOfBytes::Bytes8, signed, multiply, i64::MIN, -1), i64::MIN);
+    }
+
+    #[test]
+    fn integer_power() {
+        let (signed, power) = (SignFlag::Signed, ArithmeticOperation::Power);
+        assert_eq!(stacked_arith::<i32>(NumberOfBytes::Bytes4, signed, power, 3, 4), 81);
+        assert_eq!(stacked_arith::<i32>(NumberOfBytes::Bytes4, signed, power, -2, 3), -8);
+        assert_eq!(stacked_arith::<i32>(NumberOfBytes::Bytes4, signed, power, 2, -1), 0);
+        assert_eq!(stacked_arith::<i32>(NumberOfBytes::Bytes4, signed, power, -1, -3), -1);
+        assert_eq!(stacked_arith::<u8>(NumberOfBytes::Bytes1, SignFlag::Unsigned, power, 2, 9), 0);
+        assert_eq!(immediate_arith::<i64>(NumberOfBytes::Bytes8, signed, power, 10, 3i16.to_le_bytes()), 1000);
+    }
+
+    #[test]
+    fn immediate_and_stack_operands_agree() {
+        for operation in [
+            ArithmeticOperation::Sum,
+            ArithmeticOperation::Subtract,
+            ArithmeticOperation::Multiply,
+            ArithmeticOperation::Divide,
+        ] {
+            let stacked = stacked_arith::<i16>(NumberOfBytes::Bytes2, SignFlag::Signed, operation, -100, -7);
+            let immediate = immediate_arith::<i16>(NumberOfBytes::Bytes2, SignFlag::Signed, operation, -100, (-7i16).to_le_bytes());
+            assert_eq!(stacked, immediate, "{operation:?}");
+        }
+    }
+
+    #[test]
+    fn machine_runs_program_and_exposes_stack() {
+        let code = "
+    main:
+        push_imm32 20
+        push_imm32 25
+        sums32
+        jmp end
+        push_imm32 99
+    end:
+        push_imm16 7
+        exit
+";
+        let mut machine = Machine::new();
+        machine.run(&assemble(code)).unwrap();
+
+        let mut expected = 45i32.to_le_bytes().to_vec();
+        expected.extend(7u16.to_le_bytes());
+        assert_eq!(machine.stack(), expected);
+    }
+
+    #[test]
+    fn machine_trace_records_every_instruction() {
+        let code = "
+    main:
+        push_imm32 6
+        push_imm32 7
+        mulu32
+        push_imm16 1
+        exit
+";
+        let assembled = assemble(code);
+        let mut machine = Machine::new();
+        let start = machine.registers.sp;
+        let mut events: Vec<TraceEvent> = vec![];
+        machine.run_with_trace(&assembled, |event| events.push(event.clone())).unwrap();
+
+        let summary = events
+            .iter()
+            .map(|e| (e.ip, e.instruction.clone(), e.sp - start, e.stack_top.clone()))
+            .collect::<Vec<_>>();
+        //the snapshot also sees the return ip and bp that prepare_vm writes below the program stack
+        let stack_top = |pushed: &[u8]| {
+            let all = [0u32.to_le_bytes().as_slice(), u32::MAX.to_le_bytes().as_slice(), pushed].concat();
+            all[all.len() - TRACE_STACK_TOP_BYTES as usize..].to_vec()
+        };
+        let six = 6u32.to_le_bytes();
+        let six_seven = [6u32.to_le_bytes(), 7u32.to_le_bytes()].concat();
+        let fourty_two = 42u32.to_le_bytes();
+        let fourty_two_one = [42u32.to_le_bytes().as_slice(), 1u16.to_le_bytes().as_slice()].concat();
+        assert_eq!(
+            summary,
+            vec![
+                (0, assembled[0].clone(), 0, stack_top(&[])),
+                (1, assembled[1].clone(), 4, stack_top(&six)),
+                (2, assembled[2].clone(), 8, stack_top(&six_seven)),
+                (3, assembled[3].clone(), 4, stack_top(&fourty_two)),
+                (4, assembled[4].clone(), 6, stack_top(&fourty_two_one)),
+            ]
+        );
+        assert_eq!(machine.stack(), fourty_two_one);
+    }
+
+    #[test]
+    fn machine_steps_one_instruction_at_a_time() {
+        let code = "
+    main:
+        push_imm32 2
+        push_imm32 3
+        sumu32
+        exit
+";
+        let assembled = assemble(code);
+        let mut machine = Machine::new();
+        machine.load(&assembled);
+
+        let mut steps = vec![];
+        loop {
+            let step = machine.step().unwrap();
+            let finished = step.finished;
+            steps.push(step);
+            if finished {
+                break;
+            }
+        }
+
+        assert_eq!(
+            steps,
+            assembled
+                .iter()
+                .enumerate()
+                .map(|(ip, instruction)| StepResult {
+                    ip,
+                    instruction: instruction.clone(),
+                    finished: ip == 3,
+                })
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(machine.stack(), 5u32.to_le_bytes().to_vec());
+    }
+
+    #[test]
+    fn integer_division_by_zero_is_error() {
+        let code = "
+    main:
+        push_imm32      10
+        push_imm32      0
+        divs32
+        exit
+";
+        let assembled = assemble(code);
+        let (mut mem, mut reg) = prepare_vm();
+        assert_eq!(run(&assembled, &mut mem, &mut reg), Err(RuntimeError::DivisionByZero { ip: 2 }));
+    }
+
+    #[test]
+    fn jump_before_start_of_program_is_error() {
+        let code = "
+    main:
+        push_imm32      1
+        jmp             -2
+        exit
+";
+        let assembled = assemble(code);
+        let (mut mem, mut reg) = prepare_vm();
+        assert_eq!(run(&assembled, &mut mem, &mut reg), Err(RuntimeError::IpOutOfBounds { ip: 1, program_len: 3 }));
+    }
+
+    #[test]
+    fn conditional_jump_before_start_not_taken_is_fine() {
+        let code = "
+    main:
+        push_imm8       1
+        jz              -5
+        exit
+";
+        let assembled = assemble(code);
+        let (mut mem, mut reg) = prepare_vm();
+        assert_eq!(run(&assembled, &mut mem, &mut reg), Ok(()));
+    }
+
+    #[test]
+    fn integer_remainder_by_zero_immediate_is_error() {
+        let code = "
+    main:
+        push_imm8       10
+        remu_imm8       256
+        exit
+";
+        let assembled = assemble(code);
+        let (mut mem, mut reg) = prepare_vm();
+        assert_eq!(run(&assembled, &mut mem, &mut reg), Err(RuntimeError::RemainderByZero { ip: 1 }));
+    }
+
+    #[test]
+    fn machine_step_past_the_end_is_error() {
+        let mut machine = Machine::new();
+        assert_eq!(machine.step(), Err(RuntimeError::IpOutOfBounds { ip: 0, program_len: 0 }));
+
+        //no exit, so ip ends up right after the last instruction
+        machine.load(&assemble("
+    main:
+        push_imm32 2
+"));
+        assert!(machine.step().unwrap().finished);
+        assert_eq!(machine.step(), Err(RuntimeError::IpOutOfBounds { ip: 1, program_len: 1 }));
+    }
 }
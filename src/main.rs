@@ -18,9 +18,11 @@ use std::fs;
 use std::fs::File;
 use std::io::Read;
 use std::io::Write;
+use std::path::Path;
 use std::time::Instant;
 use crate::ast::lexer;
 use crate::ast::parser;
+use crate::freyr::disassembler::disassemble;
 use crate::freyr::encoder::InstructionEncoder;
 use crate::freyr::encoder::LayoutHelper;
 use crate::freyr::vm::memory::Memory;
@@ -86,7 +88,13 @@ fn main() {
 
     if args[1] == "asm" {
         let input = fs::read_to_string(args[2].clone()).expect(&format!("Could not read file {}", args[2]));
-        let parsed = crate::freyr::asm::assembler::parse_asm(input.as_str());
+        let parsed = match crate::freyr::asm::assembler::assemble(input.as_str()) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                println!("{e}");
+                return;
+            }
+        };
         let resolved = crate::freyr::asm::assembler::resolve(&parsed);
         let vm_instructions = crate::freyr::asm::assembler::as_freyr_instructions(&resolved);
 
@@ -101,8 +109,7 @@ fn main() {
 
             let instruction_layout = LayoutHelper::new();
 
-            for ins in vm_instructions {
-                let encoded = instruction_layout.encode_instruction(&ins);
+            for encoded in instruction_layout.encode_program(&vm_instructions).unwrap() {
                 let bytes = encoded.to_le_bytes();
                 file.write_all(&bytes).unwrap();
             }
@@ -115,24 +122,45 @@ fn main() {
             let mut all_bytes = vec![];
             file.read_to_end(&mut all_bytes).unwrap();
 
-            for i in (0 .. all_bytes.len()).step_by(4) {
-                let instruction_bytes = &all_bytes[i..=(i + 3)];
-                let instruction_as_u32 = u32::from_le_bytes(instruction_bytes.try_into().expect("could not get 4 bytes"));
-                let decoded = instruction_layout.begin_decode(instruction_as_u32).decode();
-                println!("{:?}", decoded);
-            }
+            let words: Vec<u32> = all_bytes
+                .chunks(4)
+                .map(|instruction_bytes| u32::from_le_bytes(instruction_bytes.try_into().expect("could not get 4 bytes")))
+                .collect();
+            print!("{}", disassemble(&instruction_layout.decode_program(&words).unwrap()));
         }
 
     } else {
-        let input = fs::read_to_string(args[1].clone()).expect(&format!("Could not read file {}", args[1]));
-        let tokens = lexer::tokenize(input.as_str());
-        let ast = parser::parse_ast(tokens.unwrap());
-    
-        let root = parser::AST::Root(ast);
-        let result = crate::semantic::analysis::do_analysis(&root);
+        //every file passed is a module, named after the file name without extension
+        let mut modules = vec![];
+        for file_name in &args[1..] {
+            let input = fs::read_to_string(file_name).expect(&format!("Could not read file {}", file_name));
+            let tokens = match lexer::tokenize(input.as_str()) {
+                Ok(tokens) => tokens,
+                Err(e) => {
+                    println!("{file_name}: {e}");
+                    return;
+                }
+            };
+            let ast = match parser::parse_ast(tokens) {
+                Ok(ast) => ast,
+                Err(errors) => {
+                    for error in errors {
+                        println!("{file_name}: {error}");
+                    }
+                    return;
+                }
+            };
+            let module_name = Path::new(file_name).file_stem().unwrap().to_string_lossy().to_string();
+            modules.push(crate::semantic::analysis::SourceModule {
+                name: module_name,
+                ast: parser::AST::Root(ast)
+            });
+        }
+
+        let result = crate::semantic::analysis::do_analysis_modules(&modules);
     
         //crate::semantic::mir_printer::print_mir(&result.initial_mir, &result.type_db);
-        //crate::semantic::mir_printer::print_mir(&result.after_make_declarations_mir, &result.type_db);
+        //println!("{}", result.print_after_make_declarations());
         println!("{}", crate::semantic::hir_printer::print_hir(&result.final_mir, &result.type_db));
     
     }
@@ -1,10 +1,12 @@
 use crate::semantic::hir_printer::print_hir;
-use crate::{ast::parser::*, types::type_errors::TypeErrors};
+use crate::{ast::parser::*, types::type_errors::{TypeErrors, ReturnOutsideFunction, ModuleNotFound, NameDeclaredInManyModules, NameNotImported, PrivateNameUsed}};
 use crate::semantic::hir::*;
 use crate::semantic::*;
-use crate::types::type_db::TypeDatabase;
+use crate::types::type_db::{TypeDatabase, TypeInstance};
 
-use super::{name_registry::NameRegistry};
+use super::{name_registry::NameRegistry, undeclared_vars::ShadowsGlobal};
+
+use std::collections::{HashMap, HashSet};
 
 
 pub struct AnalysisResult {
@@ -13,27 +15,400 @@ pub struct AnalysisResult {
     pub final_mir: Vec<HIR>,
     pub type_db: TypeDatabase,
     pub globals: NameRegistry,
-    pub type_errors: TypeErrors
+    pub type_errors: TypeErrors,
+    pub shadowed_globals: Vec<ShadowsGlobal>,
+    //only executables need an entry point, so it's up to the caller to report the error
+    pub entry_point: Result<EntryPoint, EntryPointError>
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryPoint {
+    pub function_name: String,
+    //main(args: array<str>) as opposed to main()
+    pub takes_args: bool
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntryPointError {
+    MainNotFound,
+    //main takes either no parameters or a single array<str>
+    InvalidMainParameters,
+    //main returns either Void or i32
+    InvalidMainReturnType(HIRTypeDef),
+}
+
+impl AnalysisResult {
+    //shows which assignments became declarations, before any type is inferred
+    pub fn print_after_make_declarations(&self) -> String {
+        print_hir(&self.after_make_declarations_mir, &self.type_db)
+    }
+
+    pub fn into_result(self) -> Result<AnalysisResult, TypeErrors> {
+        if self.type_errors.is_empty() {
+            Ok(self)
+        } else {
+            Err(self.type_errors)
+        }
+    }
+}
+
+pub struct SourceModule {
+    pub name: String,
+    pub ast: AST
+}
+
+//The types and names a program starts with. Embedders targeting a different environment
+//can provide their own instead of the builtin ones.
+pub struct Prelude {
+    pub type_db: TypeDatabase,
+    pub globals: NameRegistry,
+}
+
+impl Prelude {
+    pub fn builtin() -> Prelude {
+        let type_db = TypeDatabase::new();
+        let globals = name_registry::builtin_names(&type_db);
+        Prelude { type_db, globals }
+    }
 }
 
 pub fn do_analysis(ast: &AST) -> AnalysisResult {
+    do_analysis_with_prelude(ast, Prelude::builtin())
+}
+
+pub fn do_analysis_with_prelude(ast: &AST, prelude: Prelude) -> AnalysisResult {
     let mut hir = vec![];
     ast_to_hir(ast, 0, &mut hir);
+    analyze_hir(hir, prelude)
+}
+
+fn get_imports(ast: &AST) -> Vec<&String> {
+    match ast {
+        AST::Root(nodes) => nodes.iter().filter_map(|node| match node {
+            AST::Import(module_name) => Some(module_name),
+            _ => None
+        }).collect(),
+        AST::Import(module_name) => vec![module_name],
+        _ => vec![]
+    }
+}
+
+fn top_level_name(node: &HIR) -> Option<&String> {
+    match node {
+        HIR::DeclareFunction { function_name, .. } => Some(function_name),
+        HIR::DeclareExternFunction { function_name, .. } => Some(function_name),
+        HIR::StructDeclaration { struct_name, .. } => Some(struct_name),
+        HIR::TypeAlias { alias_name, .. } => Some(alias_name),
+        HIR::DeclareConst { var, .. } => Some(var),
+        HIR::Declare { var, .. } => Some(var),
+        HIR::Assign { path, .. } if path.len() == 1 => Some(&path[0]),
+        _ => None
+    }
+}
+
+fn names_in_type(typ: &HIRType, used: &mut Vec<String>) {
+    match typ {
+        HIRType::Simple(name) => used.push(name.clone()),
+        HIRType::Generic(name, args) => {
+            used.push(name.clone());
+            args.iter().for_each(|arg| names_in_type(arg, used));
+        }
+        HIRType::Function(args, return_type) => {
+            args.iter().for_each(|arg| names_in_type(arg, used));
+            names_in_type(return_type, used);
+        }
+        HIRType::Const(_) => {}
+    }
+}
+
+fn names_in_typedef(typedef: &HIRTypeDef, used: &mut Vec<String>) {
+    if let HIRTypeDef::Unresolved(typ) = typedef {
+        names_in_type(typ, used);
+    }
+}
+
+fn names_in_trivial(trivial: &TypedTrivialHIRExpr, used: &mut Vec<String>) {
+    if let TrivialHIRExpr::Variable(name) = &trivial.0 {
+        used.push(name.clone());
+    }
+}
+
+fn names_in_expr(expr: &HIRExpr, used: &mut Vec<String>) {
+    match expr {
+        HIRExpr::Trivial(trivial, _) => names_in_trivial(trivial, used),
+        HIRExpr::Cast(trivial, typedef, _) | HIRExpr::Reinterpret(trivial, typedef, _) => {
+            names_in_trivial(trivial, used);
+            names_in_typedef(typedef, used);
+        }
+        HIRExpr::SizeOf(typedef, _, _) => names_in_typedef(typedef, used),
+        HIRExpr::BinaryOperation(lhs, _, rhs, _, _) => {
+            names_in_trivial(lhs, used);
+            names_in_trivial(rhs, used);
+        }
+        HIRExpr::FunctionCall(function, args, _, _) => {
+            names_in_trivial(function, used);
+            args.iter().for_each(|arg| names_in_trivial(arg, used));
+        }
+        HIRExpr::Tuple(items, _, _) | HIRExpr::Array(items, _, _) => {
+            items.iter().for_each(|item| names_in_trivial(item, used));
+        }
+        HIRExpr::UnaryExpression(_, operand, _, _) => names_in_trivial(operand, used),
+        HIRExpr::MemberAccess(obj, _, _, _) => names_in_trivial(obj, used),
+    }
+}
+
+//untyped declarations are still assignments at this point, so any assigned name counts as a local
+fn declared_locals(body: &[HIR], locals: &mut HashSet<String>) {
+    for node in body {
+        match node {
+            HIR::Declare { var, .. } => {
+                locals.insert(var.clone());
+            }
+            HIR::Assign { path, .. } if path.len() == 1 => {
+                locals.insert(path[0].clone());
+            }
+            HIR::If(_, true_branch, false_branch, _) => {
+                declared_locals(true_branch, locals);
+                declared_locals(false_branch, locals);
+            }
+            HIR::While(_, body, _) => declared_locals(body, locals),
+            _ => {}
+        }
+    }
+}
+
+//Names the nodes read from the top level: variables and functions used in expressions, and types in annotations.
+//Parameters, locals and type parameters of a function shadow top-level names, so they are left out
+fn used_names(nodes: &[HIR], used: &mut Vec<String>) {
+    for node in nodes {
+        match node {
+            HIR::Assign { expression, .. } => names_in_expr(expression, used),
+            HIR::Declare { typedef, expression, .. } => {
+                names_in_typedef(typedef, used);
+                names_in_expr(expression, used);
+            }
+            HIR::DeclareFunction { type_parameters, parameters, body, return_type, .. } => {
+                let mut in_function = vec![];
+                for parameter in parameters {
+                    names_in_typedef(&parameter.typename, &mut in_function);
+                }
+                names_in_typedef(return_type, &mut in_function);
+                used_names(body, &mut in_function);
+
+                let mut locals = parameters.iter().map(|parameter| parameter.name.clone()).collect::<HashSet<_>>();
+                locals.extend(type_parameters.iter().cloned());
+                declared_locals(body, &mut locals);
+                used.extend(in_function.into_iter().filter(|name| !locals.contains(name)));
+            }
+            HIR::DeclareExternFunction { parameters, return_type, .. } => {
+                for parameter in parameters {
+                    names_in_typedef(&parameter.typename, used);
+                }
+                names_in_typedef(return_type, used);
+            }
+            HIR::StructDeclaration { type_parameters, body, .. } => {
+                let mut in_struct = vec![];
+                for field in body {
+                    names_in_typedef(&field.typename, &mut in_struct);
+                }
+                used.extend(in_struct.into_iter().filter(|name| !type_parameters.contains(name)));
+            }
+            HIR::FunctionCall { function, args, .. } => {
+                names_in_trivial(function, used);
+                args.iter().for_each(|arg| names_in_trivial(arg, used));
+            }
+            HIR::If(condition, true_branch, false_branch, _) => {
+                names_in_trivial(condition, used);
+                used_names(true_branch, used);
+                used_names(false_branch, used);
+            }
+            HIR::While(condition, body, _) => {
+                names_in_trivial(condition, used);
+                used_names(body, used);
+            }
+            HIR::Return(expr, _, _) => names_in_expr(expr, used),
+            HIR::TypeAlias { target, .. } => names_in_type(target, used),
+            HIR::DeclareConst { typedef, .. } => names_in_typedef(typedef, used),
+            HIR::ImplBlock { type_parameters, target, methods, .. } => {
+                let mut in_impl = vec![];
+                names_in_type(target, &mut in_impl);
+                used_names(methods, &mut in_impl);
+                used.extend(in_impl.into_iter().filter(|name| !type_parameters.contains(name)));
+            }
+            HIR::EmptyReturn => {}
+        }
+    }
+}
+
+fn top_level_visibility(node: &HIR) -> Option<Visibility> {
+    match node {
+        HIR::DeclareFunction { visibility, .. } => Some(*visibility),
+        HIR::StructDeclaration { visibility, .. } => Some(*visibility),
+        _ => None
+    }
+}
+
+//Analyzes many source files together. Top-level declarations of all modules
+//end up in the same name registry, so a name can only be declared by one module.
+//A clashing declaration is reported and left out, the first module to declare the name keeps it.
+//A module must import the modules whose names it uses, and functions and structs declared without pub
+//can only be used by their own module.
+pub fn do_analysis_modules(modules: &[SourceModule]) -> AnalysisResult {
+    let mut lowered = vec![];
+    let mut declared_in: HashMap<String, &str> = HashMap::new();
+    let mut visibilities: HashMap<String, Visibility> = HashMap::new();
+    let mut errors = TypeErrors::new();
+
+    for module in modules {
+        for import in get_imports(&module.ast) {
+            if !modules.iter().any(|m| &m.name == import) {
+                errors.modules_not_found.push(ModuleNotFound {
+                    module_name: import.clone(),
+                    imported_by: module.name.clone()
+                });
+            }
+        }
+
+        let mut module_hir = vec![];
+        ast_to_hir(&module.ast, 0, &mut module_hir);
+
+        let mut kept = vec![];
+        for node in module_hir {
+            if let Some(declared_name) = top_level_name(&node) {
+                match declared_in.get(declared_name) {
+                    Some(other_module) if *other_module != module.name => {
+                        errors.names_declared_in_many_modules.push(NameDeclaredInManyModules {
+                            name: declared_name.clone(),
+                            module_name: module.name.clone(),
+                            declared_in: other_module.to_string()
+                        });
+                        continue;
+                    }
+                    Some(_) => {}
+                    None => {
+                        declared_in.insert(declared_name.clone(), &module.name);
+                    }
+                }
+                if let Some(visibility) = top_level_visibility(&node) {
+                    visibilities.insert(declared_name.clone(), visibility);
+                }
+            }
+            kept.push(node);
+        }
+        lowered.push((module, kept));
+    }
+
+    let mut hir = vec![];
+    for (module, module_hir) in lowered {
+        let imports = get_imports(&module.ast);
+        let mut used = vec![];
+        used_names(&module_hir, &mut used);
+        let mut reported = HashSet::new();
+        for name in used {
+            let Some(declared_module) = declared_in.get(&name) else {
+                continue;
+            };
+            if *declared_module == module.name || !reported.insert(name.clone()) {
+                continue;
+            }
+            if !imports.iter().any(|import| import == declared_module) {
+                errors.names_not_imported.push(NameNotImported {
+                    name,
+                    used_in: module.name.clone(),
+                    declared_in: declared_module.to_string()
+                });
+            } else if let Some(Visibility::Private) = visibilities.get(&name) {
+                errors.private_names_used.push(PrivateNameUsed {
+                    name,
+                    used_in: module.name.clone(),
+                    declared_in: declared_module.to_string()
+                });
+            }
+        }
+        hir.extend(module_hir);
+    }
+
+    let mut result = analyze_hir(hir, Prelude::builtin());
+    errors.merge(std::mem::replace(&mut result.type_errors, TypeErrors::new()));
+    result.type_errors = errors;
+    result
+}
+
+//a top-level return has no function to return from, it's reported and dropped before the later passes see it.
+//top-level if and while bodies are still outside any function, so they are searched too
+fn remove_top_level_returns(hir: Vec<HIR>, errors: &mut TypeErrors) -> Vec<HIR> {
+    hir.into_iter().filter_map(|node| match node {
+        HIR::Return(..) | HIR::EmptyReturn => {
+            errors.returns_outside_function.push(ReturnOutsideFunction {});
+            None
+        }
+        HIR::If(condition, true_body, false_body, meta) => Some(HIR::If(
+            condition,
+            remove_top_level_returns(true_body, errors),
+            remove_top_level_returns(false_body, errors),
+            meta
+        )),
+        HIR::While(condition, body, meta) => Some(HIR::While(
+            condition,
+            remove_top_level_returns(body, errors),
+            meta
+        )),
+        other => Some(other)
+    }).collect()
+}
+
+fn find_entry_point(hir: &[HIR], type_db: &TypeDatabase) -> Result<EntryPoint, EntryPointError> {
+    let Some((parameters, return_type)) = hir.iter().find_map(|node| match node {
+        HIR::DeclareFunction { function_name, parameters, return_type, .. } if function_name == "main" => {
+            Some((parameters, return_type))
+        }
+        _ => None
+    }) else {
+        return Err(EntryPointError::MainNotFound);
+    };
+
+    let array_of_str = TypeInstance::Generic(
+        type_db.expect_find_by_name("array").id,
+        vec![type_db.expect_find_by_name("str").to_instance()]
+    );
+    let takes_args = match parameters.as_slice() {
+        [] => false,
+        [HIRTypedBoundName { typename: HIRTypeDef::Resolved(args_type), .. }] if *args_type == array_of_str => true,
+        _ => return Err(EntryPointError::InvalidMainParameters)
+    };
 
+    match return_type {
+        HIRTypeDef::Resolved(t) if *t == type_db.special_types.void || *t == type_db.special_types.i32 => {
+            Ok(EntryPoint { function_name: "main".to_string(), takes_args })
+        }
+        other => Err(EntryPointError::InvalidMainReturnType(other.clone()))
+    }
+}
+
+fn analyze_hir(mut hir: Vec<HIR>, prelude: Prelude) -> AnalysisResult {
     let initial_mir = hir.clone();
-    let type_db = TypeDatabase::new();
+    let Prelude { mut type_db, globals: prelude_names } = prelude;
+    let mut errors = TypeErrors::new();
 
-    let mut globals = name_registry::build_name_registry(&type_db, &hir);
+    hir = remove_top_level_returns(hir, &mut errors);
+    //struct names first, so that aliases can refer to them, then fields, which can use aliases
+    type_inference::declare_struct_types(&mut type_db, &hir);
+    type_inference::register_type_aliases(&mut type_db, &hir, &mut errors);
+    type_inference::register_struct_types(&mut type_db, &hir, &mut errors);
+    type_inference::register_type_parameters(&mut type_db, &hir);
+    type_inference::register_impl_methods(&mut type_db, &hir, &mut errors);
+    hir = const_fold::fold_constants(&mut type_db, hir, &mut errors);
+    let mut globals = name_registry::build_name_registry(&prelude_names, &hir);
 
     hir = first_assignments::transform_first_assignment_into_declaration(hir);
     let after_make_declarations_mir = hir.clone();
-    undeclared_vars::detect_undeclared_vars_and_redeclarations(&globals, &hir);
+    let shadowed_globals = undeclared_vars::detect_undeclared_vars_and_redeclarations(&globals, &hir);
 
     //println!("Before type inference:\n{}", print_hir(&hir, &type_db));
 
-    let mut errors = TypeErrors::new();
-
     hir = type_inference::infer_types(&mut globals, &type_db, hir, &mut errors);
+    hir = const_fold::propagate_constants(&type_db, hir);
+    let entry_point = find_entry_point(&hir, &type_db);
 
     return AnalysisResult {
         initial_mir,
@@ -41,7 +416,9 @@ pub fn do_analysis(ast: &AST) -> AnalysisResult {
         final_mir: hir,
         type_db,
         globals,
-        type_errors: errors
+        type_errors: errors,
+        shadowed_globals,
+        entry_point
     };
 }
 
@@ -53,7 +430,7 @@ mod tests {
     #[cfg(test)]
     use pretty_assertions::assert_eq;
 
-    use crate::{types::type_db::TypeInstance, ast::lexer::Operator};
+    use crate::{types::{type_db::TypeInstance, type_errors::TypeErrorPrinter}, ast::lexer::Operator};
 
     use super::*;
 
@@ -290,6 +667,28 @@ def main() -> Void:
         assert_eq!(expected.trim(), final_result.trim());
     }
 
+    #[test]
+    fn function_returning_generic_is_printed_like_inferred_type() {
+        let analyzed = hir("
+def wrap(x: str) -> array<str>:
+    return [x]
+
+def main():
+    my_func = wrap
+    wrapped = my_func(\"a\")");
+
+        let final_result = hir_printer::print_hir(&analyzed.final_mir, &analyzed.type_db);
+        let expected = "
+def wrap(x: str) -> array<str>:
+    return [x]
+def main() -> Void:
+    my_func : fn (str) -> array<str> = wrap
+    wrapped : array<str> = my_func(\"a\")";
+
+        assert_eq!(expected.trim(), final_result.trim());
+        assert_eq!(declared_type(&analyzed, "main", "my_func"), "fn (str) -> array<str>");
+    }
+
     #[test]
     fn access_property_of_struct_and_infer_type() {
         let analyzed = hir("
@@ -329,15 +728,13 @@ def main(x: i32) -> i32:
 
     #[test]
     fn self_decl_read() {
-        let result = std::panic::catch_unwind(|| {
-            hir("
+        let analyzed = hir("
 def main(x: i32) -> i32:
     y = y + 1
 ");
-        });
-        let err = result.unwrap_err();
-        let as_str = err.downcast_ref::<String>().unwrap();
-        assert_eq!(as_str, "Could not find a name for y");
+        assert_eq!(analyzed.type_errors.names_not_in_scope.len(), 1);
+        let message = TypeErrorPrinter::new(&analyzed.type_errors, &analyzed.type_db).to_string();
+        assert!(message.contains("In function main, y is not a variable or function in scope\n"));
     }
 
     #[test]
@@ -468,6 +865,42 @@ def main() -> i32:
         assert_eq!(expected.trim(), final_result.trim());
     }
 
+    #[test]
+    fn while_condition_is_recomputed_at_end_of_body() {
+        let analyzed = hir("
+def main():
+    x = 0
+    while x < 10:
+        x = x + 1
+");
+
+        assert_eq!(analyzed.type_errors.count(), 0);
+        let final_result = hir_printer::print_hir_typed(&analyzed.final_mir, &analyzed.type_db);
+        let expected = "
+def main() -> Void:
+    x : i32 = (0 : i32)
+    $0 : bool = (x : i32) < (10 : i32) : bool
+    while ($0 : bool):
+        x = (x : i32) + (1 : i32) : i32
+        $0 = (x : i32) < (10 : i32) : bool";
+
+        assert_eq!(expected.trim(), final_result.trim());
+    }
+
+    #[test]
+    fn while_condition_must_be_bool() {
+        let analyzed = hir("
+def main():
+    x = 10
+    while x:
+        x = x - 1
+");
+
+        assert_eq!(analyzed.type_errors.non_bool_conditions.len(), 1);
+        let message = TypeErrorPrinter::new(&analyzed.type_errors, &analyzed.type_db).to_string();
+        assert_eq!(message, "In function main, while condition must be bool, but has type i32\n");
+    }
+
     #[test]
     fn if_nested_branch_but_some_do_not_return() {
         let analyzed = hir("
@@ -506,8 +939,8 @@ def main() -> i32:
         print(\"nice\")
     else:
         y : i32 = 3
-        $0 : bool = 2 == 2
-        if $0:
+        $1 : bool = 2 == 2
+        if $1:
             y = y + 1
             print(y)
         else:
@@ -626,51 +1059,1662 @@ def my_function():
     }
 
     #[test]
-    fn unary_operator_not_found() {
+    fn function_name_in_binary_expression_is_error() {
         let analyzed = hir("
+def myFunc(x: i32) -> i32:
+    return x
 def my_function():
-    x = \"1\"
-    y = +x");
+    y = myFunc + 1");
 
-        let result = hir_printer::print_hir(&analyzed.final_mir, &analyzed.type_db);
-        println!("{}", result);
         assert_eq!(analyzed.type_errors.count(), 1);
-        assert_eq!(analyzed.type_errors.unary_op_not_found.len(), 1);
+        assert_eq!(analyzed.type_errors.unexpected_types.len(), 1);
 
-        assert_eq!(analyzed.type_errors.unary_op_not_found[0].rhs.as_string(&analyzed.type_db), "str");
-        assert_eq!(analyzed.type_errors.unary_op_not_found[0].operator, Operator::Plus);
-        assert_eq!(analyzed.type_errors.unary_op_not_found[0].on_function, "my_function");
-            
+        let message = TypeErrorPrinter::new(&analyzed.type_errors, &analyzed.type_db).to_string();
+        assert_eq!(message, "In function my_function, unexpected type found in expression: fn (i32) -> i32\n");
     }
 
     #[test]
-    fn insufficient_array_type_info() {
+    fn str_index_returns_u8() {
         let analyzed = hir("
 def my_function():
-    x = []");
+    c = \"abc\"[0]");
 
         let result = hir_printer::print_hir(&analyzed.final_mir, &analyzed.type_db);
-        println!("{}", result);
+        let expected = "
+def my_function() -> Void:
+    $0 : fn (u32) -> u8 = \"abc\".__index__
+    c : u8 = $0(0)";
+        assert_eq!(expected.trim(), result.trim());
+        assert_eq!(analyzed.type_errors.count(), 0);
+    }
+
+    #[test]
+    fn none_takes_declared_option_type() {
+        let analyzed = hir("
+def my_function():
+    x: Option<i32> = None");
+
+        let result = hir_printer::print_hir(&analyzed.final_mir, &analyzed.type_db);
+        let expected = "
+def my_function() -> Void:
+    x : Option<i32> = None";
+        assert_eq!(expected.trim(), result.trim());
+        assert_eq!(analyzed.type_errors.count(), 0);
+    }
+
+    #[test]
+    fn none_as_argument_takes_parameter_type() {
+        let analyzed = hir("
+def takes_option(x: Option<i32>) -> i32:
+    return 1
+
+def my_function():
+    y = takes_option(None)");
+
+        let result = hir_printer::print_hir_typed(&analyzed.final_mir, &analyzed.type_db);
+        assert!(result.contains("(takes_option : fn (Option<i32>) -> i32)((None : Option<i32>)) : i32"), "{result}");
+        assert_eq!(analyzed.type_errors.count(), 0);
+    }
+
+    #[test]
+    fn fixed_size_array_length_is_constant() {
+        let analyzed = hir("
+def my_function():
+    arr: array<i32, 4> = [1, 2, 3, 4]
+    n = arr.length");
+
+        let result = hir_printer::print_hir(&analyzed.final_mir, &analyzed.type_db);
+        let expected = "
+def my_function() -> Void:
+    arr : array<i32, 4> = [1, 2, 3, 4]
+    n : u32 = 4";
+        assert_eq!(expected.trim(), result.trim());
+        assert_eq!(analyzed.type_errors.count(), 0);
+    }
+
+    #[test]
+    fn fixed_size_array_literal_index_out_of_bounds() {
+        let analyzed = hir("
+def my_function():
+    arr: array<i32, 4> = [1, 2, 3, 4]
+    x = arr[3]
+    y = arr[4]");
+
+        assert_eq!(analyzed.type_errors.index_out_of_bounds.len(), 1);
+        assert_eq!(analyzed.type_errors.index_out_of_bounds[0].index, 4);
         assert_eq!(analyzed.type_errors.count(), 1);
-        assert_eq!(analyzed.type_errors.insufficient_array_type_info.len(), 1);
-        assert_eq!(analyzed.type_errors.insufficient_array_type_info[0].on_function, "my_function");
     }
 
+    #[test]
+    fn array_items_take_element_type_from_hint() {
+        let analyzed = hir("
+def my_function():
+    x: array<i64> = [1, 2, 3]");
+
+        let result = hir_printer::print_hir_typed(&analyzed.final_mir, &analyzed.type_db);
+        assert!(result.contains("[(1 : i64), (2 : i64), (3 : i64)]"), "{result}");
+        assert_eq!(analyzed.type_errors.count(), 0);
+    }
 
     #[test]
-    fn call_to_non_callable() {
+    fn void_call_as_operand_is_rejected() {
         let analyzed = hir("
 def my_function():
-    x: array<i32> = []
-    y = x()");
+    x = print(\"a\") + 2");
+
+        assert_eq!(analyzed.type_errors.void_used_as_value.len(), 1);
+        assert_eq!(analyzed.type_errors.binary_op_not_found.len(), 0);
+    }
+
+    #[test]
+    fn void_call_as_array_item_is_rejected() {
+        let analyzed = hir("
+def my_function():
+    x = [print(\"a\")]");
+
+        assert_eq!(analyzed.type_errors.void_used_as_value.len(), 1);
+    }
+
+    #[test]
+    fn void_call_as_statement_is_allowed() {
+        let analyzed = hir("
+def my_function():
+    print(\"a\")");
+
+        assert_eq!(analyzed.type_errors.count(), 0);
+    }
+
+    #[test]
+    fn generic_field_binds_receiver_type_args() {
+        let analyzed = hir("
+def my_function():
+    arr: array<i32> = [1, 2, 3]
+    p = arr.items");
 
         let result = hir_printer::print_hir(&analyzed.final_mir, &analyzed.type_db);
-        println!("{}", result);
+        let expected = "
+def my_function() -> Void:
+    arr : array<i32> = [1, 2, 3]
+    p : ptr<i32> = arr.items";
+        assert_eq!(expected.trim(), result.trim());
+        assert_eq!(analyzed.type_errors.count(), 0);
+    }
+
+    #[test]
+    fn array_slice_returns_array() {
+        let analyzed = hir("
+def my_function():
+    arr: array<i32> = [1, 2, 3, 4]
+    s = arr[1:3]");
+
+        let result = hir_printer::print_hir(&analyzed.final_mir, &analyzed.type_db);
+        let expected = "
+def my_function() -> Void:
+    arr : array<i32> = [1, 2, 3, 4]
+    $0 : fn (u32, u32) -> array<i32> = arr.__slice__
+    s : array<i32> = $0(1, 3)";
+        assert_eq!(expected.trim(), result.trim());
+        assert_eq!(analyzed.type_errors.count(), 0);
+    }
+
+    #[test]
+    fn generic_argument_count_mismatch() {
+        let analyzed = hir("
+def my_function():
+    x: array<i32, str> = [1, 2, 3]");
+
         assert_eq!(analyzed.type_errors.count(), 1);
-        assert_eq!(analyzed.type_errors.call_non_callable.len(), 1);
-        println!("{:?}", analyzed.type_errors.call_non_callable[0].actual_type);
+        assert_eq!(analyzed.type_errors.generic_argument_count.len(), 1);
 
-        assert_eq!(analyzed.type_errors.call_non_callable[0].actual_type.as_string(&analyzed.type_db), "array<i32>");
+        let message = TypeErrorPrinter::new(&analyzed.type_errors, &analyzed.type_db).to_string();
+        assert_eq!(message, "In function my_function, type array expects 1 generic arguments, but 2 were passed\n");
+    }
+
+    #[test]
+    fn generic_argument_count_matches() {
+        let analyzed = hir("
+def my_function():
+    x: array<i32> = [1, 2, 3]");
+
+        let result = hir_printer::print_hir(&analyzed.final_mir, &analyzed.type_db);
+        let expected = "
+def my_function() -> Void:
+    x : array<i32> = [1, 2, 3]";
+        assert_eq!(expected.trim(), result.trim());
+        assert_eq!(analyzed.type_errors.count(), 0);
+    }
+
+    #[test]
+    fn struct_name_used_as_value_is_not_in_scope() {
+        let analyzed = hir("
+struct Point:
+    x: i32
+
+def my_function():
+    p = Point");
+
+        assert_eq!(analyzed.type_errors.count(), 1);
+        assert_eq!(analyzed.type_errors.names_not_in_scope.len(), 1);
+
+        let message = TypeErrorPrinter::new(&analyzed.type_errors, &analyzed.type_db).to_string();
+        assert_eq!(message, "In function my_function, Point is not a variable or function in scope\n");
+    }
+
+    #[test]
+    fn variable_with_failed_inference_is_reported_when_used() {
+        let analyzed = hir("
+def my_function():
+    x = \"a\" - 1
+    y = x");
+
+        assert_eq!(analyzed.type_errors.count(), 2);
+        assert_eq!(analyzed.type_errors.binary_op_not_found.len(), 1);
+        assert_eq!(analyzed.type_errors.variable_types_unknown.len(), 1);
+        assert_eq!(analyzed.type_errors.names_not_in_scope.len(), 0);
+
+        let message = TypeErrorPrinter::new(&analyzed.type_errors, &analyzed.type_db).to_string();
+        assert!(message.contains("In function my_function, variable x is used but its type could not be inferred\n"));
+    }
+
+    #[test]
+    fn call_to_later_function_with_unknown_parameter_type_is_reported() {
+        let analyzed = hir("
+def main():
+    x = later(1)
+
+def later(a: Unknown) -> i32:
+    return 1
+");
+
+        assert_eq!(analyzed.type_errors.unresolved_functions_called.len(), 1);
+
+        let message = TypeErrorPrinter::new(&analyzed.type_errors, &analyzed.type_db).to_string();
+        assert!(message.contains("In function later, type not found: Unknown\n"));
+        assert!(message.contains("In function main, function later is called but its signature could not be resolved, check the errors reported on later\n"));
+    }
+
+    #[test]
+    fn constant_true_condition_keeps_only_true_branch() {
+        let analyzed = hir("
+def main() -> i32:
+    x = 1
+    if True:
+        y = x + 1
+        return y
+    else:
+        return 0
+");
+
+        let pruned = crate::semantic::dead_branches::prune_constant_branches(analyzed.final_mir);
+        let result = hir_printer::print_hir(&pruned, &analyzed.type_db);
+        let expected = "
+def main() -> i32:
+    x : i32 = 1
+    y : i32 = x + 1
+    return y";
+        assert_eq!(expected.trim(), result.trim());
+    }
+
+    #[test]
+    fn constant_false_condition_keeps_only_false_branch() {
+        let analyzed = hir("
+def main(a: i32) -> i32:
+    if 1 == 2:
+        x = 10
+        return x
+    else:
+        x = 20
+        if a == 1:
+            return 1
+        return x
+");
+
+        let pruned = crate::semantic::dead_branches::prune_constant_branches(analyzed.final_mir);
+        let result = hir_printer::print_hir(&pruned, &analyzed.type_db);
+        //a == 1 is not known at compile time, so that if stays
+        let expected = "
+def main(a: i32) -> i32:
+    x : i32 = 20
+    $1 : bool = a == 1
+    if $1:
+        return 1
+    else:
+        pass
+    return x";
+        assert_eq!(expected.trim(), result.trim());
+    }
+
+    #[test]
+    fn constant_false_loop_is_removed() {
+        let analyzed = hir("
+def main() -> i32:
+    x = 1
+    while 1 == 2:
+        x = x + 1
+    while False:
+        x = x + 2
+    return x
+");
+
+        let pruned = crate::semantic::dead_branches::prune_constant_branches(analyzed.final_mir);
+        let result = hir_printer::print_hir(&pruned, &analyzed.type_db);
+        let expected = "
+def main() -> i32:
+    x : i32 = 1
+    return x";
+        assert_eq!(expected.trim(), result.trim());
+    }
+
+    #[test]
+    fn main_is_the_entry_point() {
+        let analyzed = hir("
+def main(args: array<str>) -> i32:
+    return 0
+");
+        assert_eq!(analyzed.entry_point, Ok(EntryPoint { function_name: "main".to_string(), takes_args: true }));
+    }
+
+    #[test]
+    fn missing_main_has_no_entry_point() {
+        let analyzed = hir("
+def not_main():
+    return
+");
+        assert_eq!(analyzed.entry_point, Err(EntryPointError::MainNotFound));
+    }
+
+    #[test]
+    fn main_with_invalid_signature_is_not_an_entry_point() {
+        let analyzed = hir("
+def main(x: i32):
+    return
+");
+        assert_eq!(analyzed.entry_point, Err(EntryPointError::InvalidMainParameters));
+
+        let analyzed = hir("
+def main() -> str:
+    return \"\"
+");
+        assert_eq!(
+            analyzed.entry_point,
+            Err(EntryPointError::InvalidMainReturnType(HIRTypeDef::Resolved(analyzed.type_db.expect_find_by_name("str").to_instance())))
+        );
+    }
+
+    #[test]
+    fn unary_operator_not_found() {
+        let analyzed = hir("
+def my_function():
+    x = \"1\"
+    y = +x");
+
+        let result = hir_printer::print_hir(&analyzed.final_mir, &analyzed.type_db);
+        println!("{}", result);
+        assert_eq!(analyzed.type_errors.count(), 1);
+        assert_eq!(analyzed.type_errors.unary_op_not_found.len(), 1);
+
+        assert_eq!(analyzed.type_errors.unary_op_not_found[0].rhs.as_string(&analyzed.type_db), "str");
+        assert_eq!(analyzed.type_errors.unary_op_not_found[0].operator, Operator::Plus);
+        assert_eq!(analyzed.type_errors.unary_op_not_found[0].on_function, "my_function");
+            
+    }
+
+    #[test]
+    fn insufficient_array_type_info() {
+        let analyzed = hir("
+def my_function():
+    x = []");
+
+        let result = hir_printer::print_hir(&analyzed.final_mir, &analyzed.type_db);
+        println!("{}", result);
+        assert_eq!(analyzed.type_errors.count(), 1);
+        assert_eq!(analyzed.type_errors.insufficient_array_type_info.len(), 1);
+        assert_eq!(analyzed.type_errors.insufficient_array_type_info[0].on_function, "my_function");
+    }
+
+
+    #[test]
+    fn call_to_non_callable() {
+        let analyzed = hir("
+def my_function():
+    x: array<i32> = []
+    y = x()");
+
+        let result = hir_printer::print_hir(&analyzed.final_mir, &analyzed.type_db);
+        println!("{}", result);
+        assert_eq!(analyzed.type_errors.count(), 1);
+        assert_eq!(analyzed.type_errors.call_non_callable.len(), 1);
+        println!("{:?}", analyzed.type_errors.call_non_callable[0].actual_type);
+
+        assert_eq!(analyzed.type_errors.call_non_callable[0].actual_type.as_string(&analyzed.type_db), "array<i32>");
+    }
+
+
+    #[test]
+    fn visibility_is_recorded_in_globals() {
+        let analyzed = hir("
+struct Point:
+    x: i32
+
+pub def public_function():
+    print(\"hello\")
+
+def private_function():
+    print(\"hello\")");
+
+        assert_eq!(analyzed.globals.get_visibility("public_function"), Some(Visibility::Public));
+        assert_eq!(analyzed.globals.get_visibility("private_function"), Some(Visibility::Private));
+        assert_eq!(analyzed.globals.get_visibility("Point"), Some(Visibility::Private));
+        assert_eq!(analyzed.globals.get_visibility("print"), None);
+    }
+
+
+    fn module(name: &str, source: &str) -> SourceModule {
+        let tokens = crate::ast::lexer::tokenize(source).unwrap();
+        let ast = AST::Root(crate::ast::parser::parse_ast(tokens).unwrap());
+        SourceModule { name: name.into(), ast }
+    }
+
+    #[test]
+    fn call_function_declared_in_other_module() {
+        let other = module("other", "
+pub def half(x: i32) -> i32:
+    return x / 2");
+
+        let main = module("main", "
+import other
+
+def main():
+    y = half(10)");
+
+        let analyzed = do_analysis_modules(&[other, main]);
+
+        let result = hir_printer::print_hir(&analyzed.final_mir, &analyzed.type_db);
+
+        let expected = "
+def half(x: i32) -> i32:
+    return x / 2
+def main() -> Void:
+    y : i32 = half(10)";
+
+        assert_eq!(expected.trim(), result.trim());
+        assert_eq!(analyzed.type_errors.count(), 0);
+    }
+
+    #[test]
+    fn private_names_of_other_module_are_rejected() {
+        let other = module("other", "
+def half(x: i32) -> i32:
+    return x / 2
+struct Point:
+    x: i32
+pub def double(x: i32) -> i32:
+    return x * 2");
+
+        let main = module("main", "
+import other
+
+def take(p: Point) -> i32:
+    return p.x
+def main():
+    y = half(10)
+    z = double(half(4))");
+
+        let analyzed = do_analysis_modules(&[other, main]);
+        let message = TypeErrorPrinter::new(&analyzed.type_errors, &analyzed.type_db).to_string();
+        assert_eq!(message, "Point is private to module other and cannot be used in module main
+half is private to module other and cannot be used in module main
+");
+    }
+
+    #[test]
+    fn name_of_module_not_imported_is_rejected() {
+        let other = module("other", "
+pub def half(x: i32) -> i32:
+    return x / 2
+pub struct Point:
+    x: i32");
+
+        let main = module("main", "
+def take(p: Point) -> i32:
+    return half(p.x)");
+
+        let analyzed = do_analysis_modules(&[other, main]);
+        let message = TypeErrorPrinter::new(&analyzed.type_errors, &analyzed.type_db).to_string();
+        assert_eq!(message, "Point is declared in module other, which is not imported by module main
+half is declared in module other, which is not imported by module main
+");
+    }
+
+    #[test]
+    fn local_named_like_private_name_of_other_module_is_allowed() {
+        let other = module("other", "
+def half(x: i32) -> i32:
+    return x / 2");
+
+        let main = module("main", "
+def main():
+    half = 10
+    y = half + 1");
+
+        let analyzed = do_analysis_modules(&[other, main]);
+        assert_eq!(analyzed.type_errors.private_names_used.len(), 0);
+    }
+
+    #[test]
+    fn name_clash_between_modules() {
+        let other = module("other", "
+def half(x: i32) -> i32:
+    return x / 2");
+
+        let main = module("main", "
+def half(x: i32) -> i32:
+    return x / 2");
+
+        let analyzed = do_analysis_modules(&[other, main]);
+        assert_eq!(analyzed.type_errors.names_declared_in_many_modules.len(), 1);
+        let message = TypeErrorPrinter::new(&analyzed.type_errors, &analyzed.type_db).to_string();
+        assert_eq!(message, "Name half declared in module main is already declared in module other\n");
+    }
+
+    #[test]
+    fn const_and_alias_clash_between_modules() {
+        let other = module("other", "
+const LIMIT: i32 = 10
+type Ints = array<i32>");
+
+        let main = module("main", "
+type LIMIT = i32
+const Ints: i32 = 1");
+
+        let analyzed = do_analysis_modules(&[other, main]);
+        let message = TypeErrorPrinter::new(&analyzed.type_errors, &analyzed.type_db).to_string();
+        assert_eq!(message, "Name LIMIT declared in module main is already declared in module other
+Name Ints declared in module main is already declared in module other
+");
+    }
+
+    #[test]
+    fn import_of_missing_module_is_error() {
+        let main = module("main", "
+import other
+
+def main():
+    y = 1");
+
+        let analyzed = do_analysis_modules(&[main]);
+        let message = TypeErrorPrinter::new(&analyzed.type_errors, &analyzed.type_db).to_string();
+        assert_eq!(message, "Module other imported by main not found\n");
+    }
+
+
+    #[test]
+    fn into_result_ok_when_no_errors() {
+        let analyzed = hir("
+def my_function() -> i32:
+    return 1");
+
+        let result = analyzed.into_result();
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn into_result_err_when_errors_found() {
+        let analyzed = hir("
+def my_function():
+    x = []");
+
+        let Err(errors) = analyzed.into_result() else {
+            panic!("Expected type errors");
+        };
+        assert!(!errors.is_empty());
+        assert_eq!(errors.insufficient_array_type_info.len(), 1);
+    }
+
+
+    #[test]
+    fn infer_generic_method_returning_container_type() {
+        let analyzed = hir("
+def my_function():
+    x: array<i32> = [1]
+    y = x.push(2)");
+
+        let result = hir_printer::print_hir(&analyzed.final_mir, &analyzed.type_db);
+
+        let expected = "
+def my_function() -> Void:
+    x : array<i32> = [1]
+    $0 : fn (i32) -> array<i32> = x.push
+    y : array<i32> = $0(2)";
+
+        assert_eq!(expected.trim(), result.trim());
+        assert_eq!(analyzed.type_errors.count(), 0);
+    }
+
+
+    #[test]
+    fn local_shadowing_function_is_reported() {
+        let analyzed = hir("
+def half(x: i32) -> i32:
+    return x / 2
+
+def my_function():
+    half = 1
+    print(\"hello\")");
+
+        assert_eq!(analyzed.shadowed_globals.len(), 1);
+        assert_eq!(analyzed.shadowed_globals[0].on_function, "my_function");
+        assert_eq!(analyzed.shadowed_globals[0].variable_name, "half");
+    }
+
+
+    #[test]
+    fn empty_return_in_non_void_function() {
+        let analyzed = hir("
+def my_function() -> i32:
+    return");
+
+        assert_eq!(analyzed.type_errors.count(), 1);
+        assert_eq!(analyzed.type_errors.return_type_mismatches.len(), 1);
+        let error = &analyzed.type_errors.return_type_mismatches[0];
+        assert_eq!(error.on_function, "my_function");
+        assert_eq!(error.expected.as_string(&analyzed.type_db), "i32");
+        assert_eq!(error.actual.as_string(&analyzed.type_db), "Void");
+    }
+
+    #[test]
+    fn empty_return_in_void_function() {
+        let analyzed = hir("
+def my_function():
+    return");
+
+        assert_eq!(analyzed.type_errors.count(), 0);
+    }
+
+
+    #[test]
+    fn print_typed_hir() {
+        let analyzed = hir("
+def my_function(a: i32, b: i32) -> i32:
+    c = a + b
+    return -c");
+
+        let result = hir_printer::print_hir_typed(&analyzed.final_mir, &analyzed.type_db);
+
+        let expected = "
+def my_function(a: i32, b: i32) -> i32:
+    c : i32 = (a : i32) + (b : i32) : i32
+    return -(c : i32) : i32";
+
+        assert_eq!(expected.trim(), result.trim());
+    }
+
+
+    #[test]
+    fn sizeof_primitive_is_folded() {
+        let analyzed = hir("
+def my_function() -> u32:
+    return sizeof<i32>()");
+
+        let result = hir_printer::print_hir(&analyzed.final_mir, &analyzed.type_db);
+
+        let expected = "
+def my_function() -> u32:
+    return 4";
+
+        assert_eq!(expected.trim(), result.trim());
+        assert_eq!(analyzed.type_errors.count(), 0);
+    }
+
+    #[test]
+    fn sizeof_struct_is_sum_of_fields() {
+        let analyzed = hir("
+struct SomeStruct:
+    x: i32
+    y: i64
+    z: bool
+
+def my_function():
+    size = sizeof<SomeStruct>()");
+
+        let result = hir_printer::print_hir(&analyzed.final_mir[1..], &analyzed.type_db);
+
+        let expected = "
+def my_function() -> Void:
+    size : u32 = 13";
+
+        assert_eq!(expected.trim(), result.trim());
+        assert_eq!(analyzed.type_errors.count(), 0);
+    }
+
+    #[test]
+    fn sizeof_fixed_size_array_is_item_size_times_length() {
+        let analyzed = hir("
+struct Buffer:
+    items: array<i64, 8>
+    count: u32
+
+def my_function():
+    fixed = sizeof<array<i32, 4>>()
+    dynamic = sizeof<array<i32>>()
+    longer = sizeof<array<i64, 8>>()
+    buffer = sizeof<Buffer>()");
+
+        let result = hir_printer::print_hir(&analyzed.final_mir[1..], &analyzed.type_db);
+
+        let expected = "
+def my_function() -> Void:
+    fixed : u32 = 16
+    dynamic : u32 = 12
+    longer : u32 = 64
+    buffer : u32 = 68";
+
+        assert_eq!(expected.trim(), result.trim());
+        assert_eq!(analyzed.type_errors.count(), 0);
+    }
+
+    #[test]
+    fn sizeof_recursive_struct_through_pointer() {
+        let analyzed = hir("
+struct Node:
+    value: i32
+    next: ptr<Node>
+
+def my_function():
+    size = sizeof<Node>()");
+
+        let result = hir_printer::print_hir(&analyzed.final_mir[1..], &analyzed.type_db);
+
+        let expected = format!("
+def my_function() -> Void:
+    size : u32 = {}", 4 + std::mem::size_of::<usize>());
+
+        assert_eq!(expected.trim(), result.trim());
+        assert_eq!(analyzed.type_errors.count(), 0);
+    }
+
+    #[test]
+    fn sizeof_generic_struct_uses_its_type_arguments() {
+        let analyzed = hir("
+struct box<T>:
+    value: T
+    tag: u8
+
+struct Holder:
+    small: box<i32>
+    large: box<box<i64>>
+
+def my_function():
+    small = sizeof<box<i32>>()
+    nested = sizeof<box<box<i64>>>()
+    holder = sizeof<Holder>()");
+
+        let result = hir_printer::print_hir(&analyzed.final_mir[2..], &analyzed.type_db);
+
+        let expected = "
+def my_function() -> Void:
+    small : u32 = 5
+    nested : u32 = 10
+    holder : u32 = 15";
+
+        assert_eq!(expected.trim(), result.trim());
+        assert_eq!(analyzed.type_errors.count(), 0);
+    }
+
+    #[test]
+    fn generic_struct_containing_itself_by_value_is_error() {
+        let analyzed = hir("
+struct Node<T>:
+    value: T
+    next: Node<T>
+
+struct Wrapper:
+    inner: Node<Wrapper>
+
+def my_function():
+    x = 1");
+
+        let message = TypeErrorPrinter::new(&analyzed.type_errors, &analyzed.type_db).to_string();
+        assert_eq!(message, "Struct Node contains itself by value through field next, use a ptr to break the cycle
+Struct Wrapper contains itself by value through field inner, use a ptr to break the cycle\n");
+    }
+
+    #[test]
+    fn struct_containing_itself_by_value_is_error() {
+        let analyzed = hir("
+struct Node:
+    value: i32
+    next: Node
+
+struct List:
+    head: Node
+
+def my_function():
+    x = 1");
+
+        let message = TypeErrorPrinter::new(&analyzed.type_errors, &analyzed.type_db).to_string();
+        assert_eq!(message, "Struct Node contains itself by value through field next, use a ptr to break the cycle\n");
+    }
+
+    #[test]
+    fn structs_containing_each_other_by_value_is_error() {
+        let analyzed = hir("
+struct A:
+    b: B
+
+struct B:
+    a: A
+
+def my_function():
+    x = 1");
+
+        assert_eq!(analyzed.type_errors.recursive_structs.len(), 1);
+        assert_eq!(analyzed.type_errors.recursive_structs[0].struct_name, "B");
+        assert_eq!(analyzed.type_errors.recursive_structs[0].field_name, "a");
+    }
+
+    #[test]
+    fn struct_with_void_field_is_error() {
+        let analyzed = hir("
+struct Holder:
+    nothing: Void
+
+def my_function():
+    x = 1");
+
+        let message = TypeErrorPrinter::new(&analyzed.type_errors, &analyzed.type_db).to_string();
+        assert_eq!(message, "Struct Holder has field nothing whose type has no size\n");
+    }
+
+    #[test]
+    fn sizeof_unsized_type_is_error() {
+        let analyzed = hir("
+def my_function():
+    size = sizeof<Void>()");
+
+        assert_eq!(analyzed.type_errors.count(), 1);
+        assert_eq!(analyzed.type_errors.unsized_types.len(), 1);
+    }
+
+
+    #[test]
+    fn struct_called_as_function_is_error() {
+        let analyzed = hir("
+struct Point:
+    x: i32
+    y: i32
+
+def my_function():
+    p = Point(1, 2)");
+
+        assert_eq!(analyzed.type_errors.count(), 1);
+        assert_eq!(analyzed.type_errors.struct_called_as_function.len(), 1);
+
+        let message = TypeErrorPrinter::new(&analyzed.type_errors, &analyzed.type_db).to_string();
+        assert_eq!(message, "In function my_function, struct Point is called as if it were a function. Structs have no constructors, Point(...) is not supported\n");
+    }
+
+    #[test]
+    fn extern_function_can_be_called() {
+        let analyzed = hir("
+extern def log(x: str) -> i32
+
+def main():
+    r = log(\"hello\")");
+
+        assert_eq!(analyzed.type_errors.count(), 0);
+        let result = hir_printer::print_hir(&analyzed.final_mir, &analyzed.type_db);
+
+        let expected = "
+extern def log(x: str) -> i32
+def main() -> Void:
+    r : i32 = log(\"hello\")";
+
+        assert_eq!(expected.trim(), result.trim());
+    }
+
+    #[test]
+    fn return_multiple_values_is_error() {
+        let analyzed = hir("
+def my_function(a: i32, b: i32) -> i32:
+    return a, b + 1");
+
+        let result = hir_printer::print_hir(&analyzed.initial_mir, &analyzed.type_db);
+        let expected = "
+def my_function(a: UNRESOLVED! i32, b: UNRESOLVED! i32) -> UNRESOLVED! i32:
+    $0 : UNKNOWN_TYPE = b + 1
+    return a, $0";
+        assert_eq!(expected.trim(), result.trim());
+
+        assert_eq!(analyzed.type_errors.count(), 1);
+        assert_eq!(analyzed.type_errors.multiple_return_values.len(), 1);
+
+        let message = TypeErrorPrinter::new(&analyzed.type_errors, &analyzed.type_db).to_string();
+        assert_eq!(message, "In function my_function, return has 2 values, but function does not return multiple values\n");
+    }
+
+    #[test]
+    fn chained_comparison_lowered_to_if() {
+        let analyzed = hir("
+def my_function(x: i32) -> bool:
+    return 1 < x < 10");
+
+        let result = hir_printer::print_hir(&analyzed.final_mir, &analyzed.type_db);
+        let expected = "
+def my_function(x: i32) -> bool:
+    $0 : bool = 1 < x
+    if $0:
+        $0 = x < 10
+    else:
+        pass
+    return $0";
+        assert_eq!(expected.trim(), result.trim());
+        assert_eq!(analyzed.type_errors.count(), 0);
+    }
+
+    #[test]
+    fn chained_comparison_evaluates_middle_once() {
+        let analyzed = hir("
+def middle() -> i32:
+    return 5
+def my_function() -> bool:
+    return 1 < middle() < 10");
+
+        let result = hir_printer::print_hir(&analyzed.final_mir, &analyzed.type_db);
+        let expected = "
+def middle() -> i32:
+    return 5
+def my_function() -> bool:
+    $0 : i32 = middle()
+    $1 : bool = 1 < $0
+    if $1:
+        $1 = $0 < 10
+    else:
+        pass
+    return $1";
+        assert_eq!(expected.trim(), result.trim());
+        assert_eq!(analyzed.type_errors.count(), 0);
+    }
+
+    #[test]
+    fn chained_comparison_short_circuits_later_operands() {
+        let analyzed = hir("
+def f() -> i32:
+    return 5
+def g() -> i32:
+    return 7
+def h() -> i32:
+    return 9
+def my_function() -> bool:
+    return 1 < f() < g() < h()");
+
+        let result = hir_printer::print_hir(&analyzed.final_mir[3..], &analyzed.type_db);
+        let expected = "
+def my_function() -> bool:
+    $0 : i32 = f()
+    $1 : bool = 1 < $0
+    if $1:
+        $2 : i32 = g()
+        $1 = $0 < $2
+        if $1:
+            $3 : i32 = h()
+            $1 = $2 < $3
+        else:
+            pass
+    else:
+        pass
+    return $1";
+        assert_eq!(expected.trim(), result.trim());
+        assert_eq!(analyzed.type_errors.count(), 0);
+    }
+
+    #[test]
+    fn duplicate_parameter_is_error() {
+        let analyzed = hir("
+def my_function(x: i32, y: i32, x: i32) -> i32:
+    return x");
+
+        assert_eq!(analyzed.type_errors.count(), 1);
+        assert_eq!(analyzed.type_errors.duplicate_parameters.len(), 1);
+
+        let message = TypeErrorPrinter::new(&analyzed.type_errors, &analyzed.type_db).to_string();
+        assert_eq!(message, "In function my_function, parameter x is declared more than once\n");
+    }
+
+    #[test]
+    fn int_plus_float_is_rejected_with_cast_hint() {
+        let analyzed = hir("
+def my_function():
+    x = 1 + 2.0");
+
+        assert_eq!(analyzed.type_errors.count(), 1);
+        assert_eq!(analyzed.type_errors.binary_op_not_found.len(), 1);
+
+        let error = &analyzed.type_errors.binary_op_not_found[0];
+        assert_eq!(error.lhs.as_string(&analyzed.type_db), "i32");
+        assert_eq!(error.rhs.as_string(&analyzed.type_db), "f32");
+        assert!(error.is_int_float_mix(&analyzed.type_db));
+
+        let message = TypeErrorPrinter::new(&analyzed.type_errors, &analyzed.type_db).to_string();
+        assert!(message.contains("cast one of the operands explicitly"));
+    }
+
+    fn declared_type(analyzed: &AnalysisResult, function: &str, variable: &str) -> String {
+        for node in analyzed.final_mir.iter() {
+            if let HIR::DeclareFunction { function_name, body, .. } = node {
+                if function_name != function {
+                    continue;
+                }
+                for statement in body {
+                    if let HIR::Declare { var, typedef, .. } = statement {
+                        if var == variable {
+                            return typedef.expect_resolved().as_string(&analyzed.type_db);
+                        }
+                    }
+                }
+            }
+        }
+        panic!("{variable} not declared in {function}")
+    }
+
+    #[test]
+    fn cast_between_int_and_float_infers_target_type() {
+        let analyzed = hir("
+def my_function():
+    x = cast<f64>(5)
+    y = cast<i32>(3.9)
+    z = cast<f32>(y) * 2.0");
+
+        assert_eq!(analyzed.type_errors.count(), 0);
+        assert_eq!(declared_type(&analyzed, "my_function", "x"), "f64");
+        assert_eq!(declared_type(&analyzed, "my_function", "y"), "i32");
+        assert_eq!(declared_type(&analyzed, "my_function", "z"), "f32");
+    }
+
+    #[test]
+    fn cast_of_non_number_is_rejected() {
+        let analyzed = hir("
+def my_function():
+    x = cast<i32>(\"a\")");
+
+        assert_eq!(analyzed.type_errors.invalid_casts.len(), 1);
+        let message = TypeErrorPrinter::new(&analyzed.type_errors, &analyzed.type_db).to_string();
+        assert_eq!(message, "In function my_function, cannot cast str to i32, casts are only allowed between numeric types\n");
+    }
+
+    #[test]
+    fn cast_between_integer_sizes_infers_target_type() {
+        let analyzed = hir("
+def my_function():
+    small = 5
+    wide = cast<i64>(small)
+    narrow = cast<u32>(wide)
+    back = cast<i32>(narrow)");
+
+        assert_eq!(analyzed.type_errors.count(), 0);
+        assert_eq!(declared_type(&analyzed, "my_function", "wide"), "i64");
+        assert_eq!(declared_type(&analyzed, "my_function", "narrow"), "u32");
+        assert_eq!(declared_type(&analyzed, "my_function", "back"), "i32");
+    }
+
+    #[test]
+    fn cast_to_non_number_is_rejected() {
+        let analyzed = hir("
+def my_function():
+    x = cast<bool>(1)");
+
+        assert_eq!(analyzed.type_errors.invalid_casts.len(), 1);
+        let message = TypeErrorPrinter::new(&analyzed.type_errors, &analyzed.type_db).to_string();
+        assert_eq!(message, "In function my_function, cannot cast i32 to bool, casts are only allowed between numeric types\n");
+    }
+
+    #[test]
+    fn only_first_assignment_becomes_declaration() {
+        let analyzed = hir("
+def my_function():
+    x = 5
+    x = 6");
+
+        let expected = "
+def my_function() -> UNRESOLVED! Void:
+    x : UNKNOWN_TYPE = 5
+    x = 6";
+        assert_eq!(expected.trim(), analyzed.print_after_make_declarations().trim());
+    }
+
+    #[test]
+    fn undeclared_function_called_as_statement_is_error() {
+        let analyzed = hir("
+def my_function():
+    undeclaredFn(1)");
+
+        assert_eq!(analyzed.type_errors.count(), 1);
+        let message = TypeErrorPrinter::new(&analyzed.type_errors, &analyzed.type_db).to_string();
+        assert_eq!(message, "In function my_function, undeclaredFn is not a variable or function in scope\n");
+    }
+
+    #[test]
+    fn function_called_as_statement_is_resolved() {
+        let analyzed = hir("
+def my_function():
+    print(\"x\")");
+
+        let result = hir_printer::print_hir_typed(&analyzed.final_mir, &analyzed.type_db);
+        let expected = "
+def my_function() -> Void:
+    (print : fn (str) -> Void)((\"x\" : str))";
+        assert_eq!(expected.trim(), result.trim());
+    }
+
+    //hir.rs tests that lowering leaves them pending
+    #[test]
+    fn trivial_operands_are_resolved_by_inference() {
+        let analyzed = hir("
+def my_function(a: i32, b: i32):
+    x = a + b");
+
+        let HIR::DeclareFunction { body, .. } = &analyzed.final_mir[0] else {
+            panic!("Expected function, got {:?}", analyzed.final_mir[0]);
+        };
+        let HIR::Declare { expression: HIRExpr::BinaryOperation(lhs, _, rhs, ..), .. } = &body[0] else {
+            panic!("Expected declaration of a binary operation, got {:?}", body[0]);
+        };
+        let i32_type = HIRTypeDef::Resolved(analyzed.type_db.special_types.i32.clone());
+        assert_eq!(*lhs, TypedTrivialHIRExpr(TrivialHIRExpr::Variable("a".into()), i32_type.clone()));
+        assert_eq!(rhs.1, i32_type);
+    }
+
+    #[test]
+    fn cast_to_unknown_type_is_rejected() {
+        let analyzed = hir("
+def my_function():
+    x = cast<Celsius>(5)");
+
+        assert_eq!(analyzed.type_errors.count(), 1);
+        let message = TypeErrorPrinter::new(&analyzed.type_errors, &analyzed.type_db).to_string();
+        assert_eq!(message, "In function my_function, type not found: Celsius\n");
+    }
+
+    #[test]
+    fn const_is_folded_and_used_as_array_length() {
+        let analyzed = hir("
+const LEN: u32 = 1 + 2
+const TOTAL: i64 = LEN * 10
+
+def my_function() -> i64:
+    arr: array<i32, LEN> = [1, 2, 3]
+    return TOTAL");
+
+        let result = hir_printer::print_hir(&analyzed.final_mir, &analyzed.type_db);
+        let expected = "
+const LEN : u32 = 3
+const TOTAL : i64 = 30
+def my_function() -> i64:
+    arr : array<i32, 3> = [1, 2, 3]
+    return 30";
+        assert_eq!(expected.trim(), result.trim());
+        assert_eq!(analyzed.type_errors.count(), 0);
+    }
+
+    #[test]
+    fn const_initialized_from_function_call_is_error() {
+        let analyzed = hir("
+def get_max() -> i32:
+    return 100
+
+const MAX: i32 = get_max() + 1");
+
+        assert_eq!(analyzed.type_errors.count(), 1);
+        let message = TypeErrorPrinter::new(&analyzed.type_errors, &analyzed.type_db).to_string();
+        assert_eq!(message, "In const MAX, get_max() is not a constant expression\n");
+    }
+
+    #[test]
+    fn unary_plus_on_number_keeps_type() {
+        let analyzed = hir("
+def my_function():
+    x = +5
+    y = +x * 2
+    z = +1.5");
+
+        assert_eq!(analyzed.type_errors.count(), 0);
+        assert_eq!(declared_type(&analyzed, "my_function", "x"), "i32");
+        assert_eq!(declared_type(&analyzed, "my_function", "y"), "i32");
+        assert_eq!(declared_type(&analyzed, "my_function", "z"), "f32");
+    }
+
+    #[test]
+    fn unary_plus_on_non_number_is_rejected() {
+        let analyzed = hir("
+def my_function():
+    b = True
+    s = \"a\"
+    x = +b
+    y = +s");
+
+        assert_eq!(analyzed.type_errors.unary_op_not_found.len(), 2);
+    }
+
+    #[test]
+    fn top_level_return_is_error() {
+        let analyzed = hir("
+def my_function() -> i32:
+    return 1
+return 1");
+
+        assert_eq!(analyzed.type_errors.count(), 1);
+        let message = TypeErrorPrinter::new(&analyzed.type_errors, &analyzed.type_db).to_string();
+        assert_eq!(message, "return statement outside of a function\n");
+        assert_eq!(analyzed.final_mir.len(), 1);
+    }
+
+    #[test]
+    fn return_inside_top_level_if_and_while_is_error() {
+        let analyzed = hir("
+x = 1
+if x == 1:
+    return 1
+else:
+    return
+while x == 1:
+    return 2");
+
+        let message = TypeErrorPrinter::new(&analyzed.type_errors, &analyzed.type_db).to_string();
+        assert_eq!(message, "return statement outside of a function\nreturn statement outside of a function\nreturn statement outside of a function\n");
+    }
+
+    #[test]
+    fn generic_array_parameter_unifies_with_argument() {
+        let analyzed = hir("
+def first<T>(xs: array<T>) -> T:
+    return xs[0]
+def my_function():
+    arr = [1, 2]
+    x = first(arr)");
+
+        assert_eq!(analyzed.type_errors.count(), 0);
+        assert_eq!(declared_type(&analyzed, "my_function", "x"), "i32");
+    }
+
+    #[test]
+    fn generic_call_with_non_matching_argument_is_error() {
+        let analyzed = hir("
+def first<T>(xs: array<T>) -> T:
+    return xs[0]
+def my_function():
+    x = first(1)");
+
+        assert_eq!(analyzed.type_errors.count(), 1);
+        assert_eq!(analyzed.type_errors.generic_call_mismatches.len(), 1);
+    }
+
+    #[test]
+    fn type_parameter_shadows_struct_with_the_same_name() {
+        let analyzed = hir("
+struct T:
+    x: i32
+
+def f<T>(a: T) -> T:
+    return a
+
+def my_function():
+    x = f(1)");
+
+        assert_eq!(analyzed.type_errors.count(), 0);
+        assert_eq!(declared_type(&analyzed, "my_function", "x"), "i32");
+    }
+
+    #[test]
+    fn undeclared_type_parameter_is_not_found() {
+        let analyzed = hir("
+def first(xs: array<P>) -> P:
+    return xs[0]");
+
+        assert_eq!(analyzed.type_errors.count(), analyzed.type_errors.type_not_found.len());
+        let message = TypeErrorPrinter::new(&analyzed.type_errors, &analyzed.type_db).to_string();
+        assert!(message.contains("In function first, type not found: array<P>\n"));
+        assert!(message.contains("In function first, type not found: P\n"));
+    }
+
+    #[test]
+    fn compound_assignment_infers_like_binary_operation() {
+        let analyzed = hir("
+def my_function():
+    x = 1
+    x += 2");
+
+        assert_eq!(analyzed.type_errors.count(), 0);
+        assert_eq!(declared_type(&analyzed, "my_function", "x"), "i32");
+    }
+
+    #[test]
+    fn compound_assignment_with_integer_only_operators() {
+        let analyzed = hir("
+def my_function():
+    x = 17
+    x %= 5
+    x <<= 2
+    x >>= 1
+    x ^= 3");
+
+        assert_eq!(analyzed.type_errors.count(), 0);
+        let result = hir_printer::print_hir(&analyzed.final_mir, &analyzed.type_db);
+        let expected = "
+def my_function() -> Void:
+    x : i32 = 17
+    x = x % 5
+    x = x << 2
+    x = x >> 1
+    x = x ^ 3";
+        assert_eq!(expected.trim(), result.trim());
+    }
+
+    #[test]
+    fn remainder_of_float_is_error() {
+        let analyzed = hir("
+def my_function():
+    x = 1.5
+    x %= 2.0");
+
+        assert_eq!(analyzed.type_errors.count(), 1);
+        let message = TypeErrorPrinter::new(&analyzed.type_errors, &analyzed.type_db).to_string();
+        assert_eq!(message, "In function my_function, binary operator % not found for types: f32 % f32\n");
+    }
+
+    #[test]
+    fn compound_assignment_to_undeclared_variable_is_error() {
+        let analyzed = hir("
+def my_function():
+    x += 2");
+
+        assert_eq!(analyzed.type_errors.count(), 1);
+        let message = TypeErrorPrinter::new(&analyzed.type_errors, &analyzed.type_db).to_string();
+        assert_eq!(message, "In function my_function, x is not a variable or function in scope\n");
+    }
+
+    #[test]
+    fn generic_struct_field_with_nested_generic_is_substituted() {
+        let analyzed = hir("
+struct box<T>:
+    contents: array<T>
+    first: T
+
+def my_function(b: box<i32>):
+    x = b.contents
+    y = b.first");
+
+        assert_eq!(analyzed.type_errors.count(), 0);
+        assert_eq!(declared_type(&analyzed, "my_function", "x"), "array<i32>");
+        assert_eq!(declared_type(&analyzed, "my_function", "y"), "i32");
+    }
+
+    #[test]
+    fn cast_is_printed_with_resolved_target() {
+        let analyzed = hir("
+def my_function():
+    y = 3.9
+    x = cast<i32>(y)");
+
+        let result = hir_printer::print_hir_typed(&analyzed.final_mir, &analyzed.type_db);
+
+        let expected = "
+def my_function() -> Void:
+    y : f32 = (3.9 : f32)
+    x : i32 = cast<i32>((y : f32)) : i32";
+        assert_eq!(expected.trim(), result.trim());
+    }
+
+    #[test]
+    fn function_typed_parameter_is_callable() {
+        let analyzed = hir("
+def apply(f: fn(i32) -> i32, x: i32) -> i32:
+    return f(x)
+
+def double(x: i32) -> i32:
+    return x * 2
+
+def main():
+    y = apply(double, 3)");
+
+        assert_eq!(analyzed.type_errors.count(), 0);
+        assert_eq!(declared_type(&analyzed, "main", "y"), "i32");
+    }
+
+    #[test]
+    fn cast_of_literal_is_printed_with_resolved_target() {
+        let analyzed = hir("
+def my_function():
+    x = cast<u32>(2)");
+
+        assert_eq!(analyzed.type_errors.count(), 0);
+        let result = hir_printer::print_hir_typed(&analyzed.final_mir, &analyzed.type_db);
+
+        let expected = "
+def my_function() -> Void:
+    x : u32 = cast<u32>((2 : i32)) : u32";
+        assert_eq!(expected.trim(), result.trim());
+    }
+
+    #[test]
+    fn reinterpret_f32_as_u32_infers_u32() {
+        let analyzed = hir("
+def my_function(some_f32: f32):
+    bits = reinterpret<u32>(some_f32)");
+
+        assert_eq!(analyzed.type_errors.count(), 0);
+        assert_eq!(declared_type(&analyzed, "my_function", "bits"), "u32");
+
+        let result = hir_printer::print_hir_typed(&analyzed.final_mir, &analyzed.type_db);
+        let expected = "
+def my_function(some_f32: f32) -> Void:
+    bits : u32 = reinterpret<u32>((some_f32 : f32)) : u32";
+        assert_eq!(expected.trim(), result.trim());
+    }
+
+    #[test]
+    fn reinterpret_between_different_sizes_is_rejected() {
+        let analyzed = hir("
+def my_function(some_u32: u32):
+    x = reinterpret<u64>(some_u32)");
+
+        assert_eq!(analyzed.type_errors.reinterpret_size_mismatches.len(), 1);
+        let message = TypeErrorPrinter::new(&analyzed.type_errors, &analyzed.type_db).to_string();
+        assert_eq!(message, "In function my_function, cannot reinterpret u32 as u64, both types must have the same size\n");
+    }
+
+    #[test]
+    fn method_declared_in_impl_block_is_called() {
+        let analyzed = hir("
+struct Point:
+    x: i32
+    y: i32
+
+impl Point:
+    def sum(offset: i32) -> i32:
+        total = self.x + self.y
+        return total + offset
+
+def my_function(p: Point):
+    s = p.sum(1)");
+
+        assert_eq!(analyzed.type_errors.count(), 0);
+        assert_eq!(declared_type(&analyzed, "my_function", "s"), "i32");
+
+        let result = hir_printer::print_hir(&analyzed.final_mir[1..2], &analyzed.type_db);
+        let expected = "
+impl Point:
+    def sum(self: Point, offset: i32) -> i32:
+        $0 : i32 = self.x
+        $1 : i32 = self.y
+        total : i32 = $0 + $1
+        return total + offset";
+        assert_eq!(expected.trim(), result.trim());
+    }
+
+    #[test]
+    fn method_declared_in_generic_impl_block_is_resolved_against_receiver() {
+        let analyzed = hir("
+struct box<T>:
+    first: T
+
+impl<U> box<U>:
+    def get() -> U:
+        return self.first
+    def replace(item: U) -> box<U>:
+        return self
+
+def my_function(b: box<i64>):
+    x = b.get()
+    y = b.replace(x)");
+
+        assert_eq!(analyzed.type_errors.count(), 0);
+        assert_eq!(declared_type(&analyzed, "my_function", "x"), "i64");
+        assert_eq!(declared_type(&analyzed, "my_function", "y"), "box<i64>");
+    }
+
+    #[test]
+    fn receiver_fields_are_typed_with_impl_type_parameters() {
+        let analyzed = hir("
+impl<T> array<T>:
+    def items_ptr() -> ptr<T>:
+        items = self.items
+        return items
+
+def my_function(arr: array<i64>):
+    p = arr.items_ptr()");
+
+        assert_eq!(analyzed.type_errors.count(), 0);
+        assert_eq!(declared_type(&analyzed, "my_function", "p"), "ptr<i64>");
+
+        let result = hir_printer::print_hir(&analyzed.final_mir[0..1], &analyzed.type_db);
+        let expected = "
+impl<T> array<T>:
+    def items_ptr(self: array<T>) -> ptr<T>:
+        items : ptr<T> = self.items
+        return items";
+        assert_eq!(expected.trim(), result.trim());
+    }
+
+    #[test]
+    fn errors_in_generic_impl_methods_are_reported() {
+        let analyzed = hir("
+struct box<T>:
+    first: T
+
+impl<U> box<U>:
+    def get() -> U:
+        return self.second");
+
+        assert_eq!(analyzed.type_errors.count(), 1);
+        assert_eq!(analyzed.type_errors.field_or_method_not_found.len(), 1);
+    }
+
+    #[test]
+    fn special_method_with_wrong_signature_is_error() {
+        let analyzed = hir("
+struct Point:
+    x: i32
+
+impl Point:
+    def __index__(i: str) -> i32:
+        return self.x");
+
+        assert_eq!(analyzed.type_errors.count(), 1);
+        let message = TypeErrorPrinter::new(&analyzed.type_errors, &analyzed.type_db).to_string();
+        assert_eq!(message, "Special method Point.__index__ has the wrong signature, expected one integer argument and a non-Void return type\n");
+    }
+
+    #[test]
+    fn impl_on_function_type_is_error() {
+        let analyzed = hir("
+impl fn(i32) -> i32:
+    def twice() -> i32:
+        return 2");
+
+        assert_eq!(analyzed.type_errors.count(), 1);
+        let message = TypeErrorPrinter::new(&analyzed.type_errors, &analyzed.type_db).to_string();
+        assert_eq!(message, "Cannot implement methods on fn(i32) -> i32, impl blocks must target a type name\n");
+    }
+
+    #[test]
+    fn errors_of_a_kind_are_printed_in_source_order() {
+        let analyzed = hir("def first():
+    x = 1.5 % 2.5
+    y = x
+def second():
+    z = True + False");
+
+        //the line 3 error is of another kind, so it comes after both operator errors
+        let message = TypeErrorPrinter::new(&analyzed.type_errors, &analyzed.type_db).to_string();
+        assert_eq!(message, "In function first, binary operator % not found for types: f32 % f32
+In function second, binary operator + not found for types: bool + bool
+In function first, variable x is used but its type could not be inferred
+");
+    }
+
+    #[test]
+    fn custom_prelude_provides_types_and_functions() {
+        let mut type_db = TypeDatabase::new();
+        let f32_type = type_db.special_types.f32.clone();
+        let vec3 = type_db.register_struct("vec3", &[("x", f32_type.clone()), ("y", f32_type.clone()), ("z", f32_type.clone())]);
+        let mut globals = NameRegistry::new();
+        globals.insert("length".to_string(), HIRTypeDef::Resolved(
+            TypeInstance::Function(vec![TypeInstance::Simple(vec3)], Box::new(f32_type))
+        ));
+
+        let tokens = crate::ast::lexer::tokenize("
+def my_function(v: vec3):
+    l = length(v)").unwrap();
+        let ast = AST::Root(crate::ast::parser::parse_ast(tokens).unwrap());
+        let analyzed = do_analysis_with_prelude(&ast, Prelude { type_db, globals });
+
+        assert_eq!(analyzed.type_errors.count(), 0);
+        assert_eq!(declared_type(&analyzed, "my_function", "l"), "f32");
+        //builtins are not added on top of a custom prelude
+        assert!(analyzed.globals.get("print").is_none());
+    }
+
+    #[test]
+    fn type_alias_used_in_declaration() {
+        let analyzed = hir("
+type Ints = array<i32>
+type MoreInts = Ints
+
+def my_function():
+    x: MoreInts = [1, 2]");
+
+        let result = hir_printer::print_hir(&analyzed.final_mir[2..], &analyzed.type_db);
+
+        let expected = "
+def my_function() -> Void:
+    x : array<i32> = [1, 2]";
+
+        assert_eq!(expected.trim(), result.trim());
+        assert_eq!(analyzed.type_errors.count(), 0);
+
+        let ints = analyzed.type_db.find_alias("Ints").unwrap();
+        assert_eq!(ints.as_string_with_alias(&analyzed.type_db, Some("Ints")), "array<i32> (alias Ints)");
+        assert_eq!(ints.as_string_with_alias(&analyzed.type_db, None), "array<i32>");
+    }
+
+    #[test]
+    fn type_alias_of_struct() {
+        let analyzed = hir("
+struct Point:
+    x: i32
+    y: i32
+
+type P = Point
+
+def my_function(p: P) -> i32:
+    return p.x");
+
+        assert_eq!(analyzed.type_errors.count(), 0);
+        let point = analyzed.type_db.find_by_name("Point").unwrap().to_instance();
+        assert_eq!(analyzed.type_db.find_alias("P"), Some(&point));
+    }
+
+    #[test]
+    fn struct_field_uses_alias() {
+        let analyzed = hir("
+type Num = i32
+
+struct S:
+    x: Num
+
+def my_function(s: S) -> i32:
+    return s.x");
+
+        assert_eq!(analyzed.type_errors.count(), 0);
+        let s = analyzed.type_db.find_by_name("S").unwrap();
+        assert_eq!(s.size, 4);
+    }
+
+
+    #[test]
+    fn cyclic_type_alias_is_error() {
+        let analyzed = hir("
+type A = B
+type B = array<A>
+
+def my_function():
+    print(1)");
+
+        assert_eq!(analyzed.type_errors.type_alias_cycles.len(), 1);
+        let cycle = &analyzed.type_errors.type_alias_cycles[0];
+        assert_eq!(cycle.alias_name, "A");
+        assert_eq!(cycle.path, vec!["A", "B", "A"]);
+    }
+
+
+    #[test]
+    fn type_not_found_shows_source_syntax() {
+        let analyzed = hir("
+def my_function():
+    x: lista<i32, str> = 1");
+
+        let message = TypeErrorPrinter::new(&analyzed.type_errors, &analyzed.type_db).to_string();
+        assert_eq!(message, "In function my_function, type not found: lista<i32, str>\n");
     }
 
 }
@@ -0,0 +1,267 @@
+use crate::ast::lexer::Operator;
+use crate::ast::parser::Expr;
+use crate::commons::float::Float;
+use crate::semantic::hir::*;
+use crate::semantic::hir_printer::ast_expr_str;
+use crate::semantic::type_inference::instantiate_type;
+use crate::types::type_db::{TypeDatabase, TypeInstance};
+use crate::types::type_errors::*;
+
+use std::collections::HashSet;
+
+//Evaluates const initializers at compile time. Only literals, previously declared consts
+//and operators over them are constant expressions, anything else (calls, variables, indexing) is an error.
+//After type inference, reads of consts in functions are replaced by their folded values.
+
+//on failure, returns the innermost subexpression that could not be folded
+fn fold_expr(expr: &Expr, type_db: &TypeDatabase) -> Result<TrivialHIRExpr, Expr> {
+    match expr {
+        Expr::IntegerValue(i) => Ok(TrivialHIRExpr::IntegerValue(*i)),
+        Expr::FloatValue(f) => Ok(TrivialHIRExpr::FloatValue(*f)),
+        Expr::StringValue(s) => Ok(TrivialHIRExpr::StringValue(s.clone())),
+        Expr::BooleanValue(b) => Ok(TrivialHIRExpr::BooleanValue(*b)),
+        Expr::Variable(name) => match type_db.find_constant(name) {
+            Some(value) => Ok(value.0.clone()),
+            None => Err(expr.clone()),
+        },
+        Expr::Parenthesized(inner) => fold_expr(inner, type_db),
+        Expr::UnaryExpression(op, operand) => {
+            let folded = match (op, fold_expr(operand, type_db)?) {
+                (Operator::Minus, TrivialHIRExpr::IntegerValue(i)) => Some(TrivialHIRExpr::IntegerValue(-i)),
+                (Operator::Minus, TrivialHIRExpr::FloatValue(f)) => Some(TrivialHIRExpr::FloatValue(Float(-f.0))),
+                (Operator::Plus, value @ (TrivialHIRExpr::IntegerValue(_) | TrivialHIRExpr::FloatValue(_))) => Some(value),
+                (Operator::Not, TrivialHIRExpr::BooleanValue(b)) => Some(TrivialHIRExpr::BooleanValue(!b)),
+                _ => None,
+            };
+            folded.ok_or_else(|| expr.clone())
+        }
+        Expr::BinaryOperation(lhs, op, rhs) => {
+            let lhs = fold_expr(lhs, type_db)?;
+            let rhs = fold_expr(rhs, type_db)?;
+            fold_binary_operation(&lhs, *op, &rhs).ok_or_else(|| expr.clone())
+        }
+        _ => Err(expr.clone()),
+    }
+}
+
+//overflows and divisions by zero are not folded, so they are reported as non-constant
+fn fold_binary_operation(lhs: &TrivialHIRExpr, op: Operator, rhs: &TrivialHIRExpr) -> Option<TrivialHIRExpr> {
+    use TrivialHIRExpr::{BooleanValue, FloatValue, IntegerValue};
+    match (lhs, rhs) {
+        (IntegerValue(l), IntegerValue(r)) => match op {
+            Operator::Plus => l.checked_add(*r).map(IntegerValue),
+            Operator::Minus => l.checked_sub(*r).map(IntegerValue),
+            Operator::Multiply => l.checked_mul(*r).map(IntegerValue),
+            Operator::Divide => l.checked_div(*r).map(IntegerValue),
+            Operator::Mod => l.checked_rem(*r).map(IntegerValue),
+            Operator::Equals => Some(BooleanValue(l == r)),
+            Operator::NotEquals => Some(BooleanValue(l != r)),
+            Operator::Greater => Some(BooleanValue(l > r)),
+            Operator::GreaterEquals => Some(BooleanValue(l >= r)),
+            Operator::Less => Some(BooleanValue(l < r)),
+            Operator::LessEquals => Some(BooleanValue(l <= r)),
+            _ => None,
+        },
+        (FloatValue(l), FloatValue(r)) => match op {
+            Operator::Plus => Some(FloatValue(Float(l.0 + r.0))),
+            Operator::Minus => Some(FloatValue(Float(l.0 - r.0))),
+            Operator::Multiply => Some(FloatValue(Float(l.0 * r.0))),
+            Operator::Divide => Some(FloatValue(Float(l.0 / r.0))),
+            Operator::Greater => Some(BooleanValue(l > r)),
+            Operator::GreaterEquals => Some(BooleanValue(l >= r)),
+            Operator::Less => Some(BooleanValue(l < r)),
+            Operator::LessEquals => Some(BooleanValue(l <= r)),
+            _ => None,
+        },
+        (BooleanValue(l), BooleanValue(r)) => match op {
+            Operator::And => Some(BooleanValue(*l && *r)),
+            Operator::Or => Some(BooleanValue(*l || *r)),
+            Operator::Equals => Some(BooleanValue(l == r)),
+            Operator::NotEquals => Some(BooleanValue(l != r)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+//literals take the declared type like they do in declarations, i.e. an integer literal fits any integer type
+fn literal_fits(value: &TrivialHIRExpr, declared: &TypeInstance, type_db: &TypeDatabase) -> bool {
+    let TypeInstance::Simple(id) = declared else {
+        return false;
+    };
+    let record = type_db.find(*id);
+    match value {
+        TrivialHIRExpr::IntegerValue(_) => record.is_integer(type_db),
+        TrivialHIRExpr::FloatValue(_) => record.is_float(type_db),
+        TrivialHIRExpr::BooleanValue(_) => *declared == type_db.special_types.bool,
+        TrivialHIRExpr::StringValue(_) => record.name == "str",
+        _ => false,
+    }
+}
+
+fn literal_type(value: &TrivialHIRExpr, type_db: &TypeDatabase) -> TypeInstance {
+    match value {
+        TrivialHIRExpr::IntegerValue(_) => type_db.special_types.i32.clone(),
+        TrivialHIRExpr::FloatValue(_) => type_db.special_types.f32.clone(),
+        TrivialHIRExpr::BooleanValue(_) => type_db.special_types.bool.clone(),
+        TrivialHIRExpr::StringValue(_) => type_db.find_by_name("str").unwrap().to_instance(),
+        _ => type_db.special_types.void.clone(),
+    }
+}
+
+//Folds the top-level consts in declaration order, so a const can refer to the ones declared before it.
+//Folded consts are registered in the type database, where array lengths and other const contexts find them.
+pub fn fold_constants(type_db: &mut TypeDatabase, mir: Vec<HIR>, errors: &mut TypeErrors) -> Vec<HIR> {
+    let mut new_mir = vec![];
+    for node in mir {
+        let HIR::DeclareConst { var, typedef, value: None, meta_ast, meta_expr: Some(initializer) } = node else {
+            new_mir.push(node);
+            continue;
+        };
+
+        let declared = instantiate_type(&var, type_db, &typedef.expect_unresolved(), errors);
+        let value = match (fold_expr(&initializer, type_db), declared) {
+            (Err(not_constant), _) => {
+                errors.not_constant_expressions.push(NotAConstantExpression {
+                    const_name: var.clone(),
+                    expression: ast_expr_str(&not_constant),
+                });
+                None
+            }
+            (Ok(folded), Some(declared)) if literal_fits(&folded, &declared, type_db) => {
+                let typed = TypedTrivialHIRExpr(folded, HIRTypeDef::Resolved(declared));
+                type_db.add_constant(&var, typed.clone());
+                Some(typed)
+            }
+            (Ok(folded), Some(declared)) => {
+                errors.assign_mismatches.push(TypeMismatch {
+                    on_function: var.clone(),
+                    context: AssignContext {
+                        target_variable_name: var.clone(),
+                        declared_alias: type_db.alias_written_as(&typedef.expect_unresolved())
+                    },
+                    expected: declared,
+                    actual: literal_type(&folded, type_db),
+                });
+                None
+            }
+            //type not found, already reported
+            (Ok(_), None) => None,
+        };
+
+        let typedef = match &value {
+            Some(TypedTrivialHIRExpr(_, resolved)) => resolved.clone(),
+            None => typedef,
+        };
+        new_mir.push(HIR::DeclareConst { var, typedef, value, meta_ast, meta_expr: Some(initializer) });
+    }
+    new_mir
+}
+
+fn propagate_trivial(trivial: TypedTrivialHIRExpr, type_db: &TypeDatabase, shadowed: &HashSet<String>) -> TypedTrivialHIRExpr {
+    match &trivial.0 {
+        TrivialHIRExpr::Variable(name) if !shadowed.contains(name) => match type_db.find_constant(name) {
+            Some(value) => value.clone(),
+            None => trivial,
+        },
+        _ => trivial,
+    }
+}
+
+fn propagate_expr(expr: HIRExpr, type_db: &TypeDatabase, shadowed: &HashSet<String>) -> HIRExpr {
+    let t = |trivial| propagate_trivial(trivial, type_db, shadowed);
+    match expr {
+        HIRExpr::Trivial(trivial, meta) => HIRExpr::Trivial(t(trivial), meta),
+        HIRExpr::Cast(trivial, typedef, meta) => HIRExpr::Cast(t(trivial), typedef, meta),
+        HIRExpr::Reinterpret(trivial, typedef, meta) => HIRExpr::Reinterpret(t(trivial), typedef, meta),
+        HIRExpr::BinaryOperation(lhs, op, rhs, typedef, meta) => HIRExpr::BinaryOperation(t(lhs), op, t(rhs), typedef, meta),
+        HIRExpr::FunctionCall(function, args, typedef, meta) => {
+            HIRExpr::FunctionCall(function, args.into_iter().map(t).collect(), typedef, meta)
+        }
+        HIRExpr::Tuple(items, typedef, meta) => HIRExpr::Tuple(items.into_iter().map(t).collect(), typedef, meta),
+        HIRExpr::UnaryExpression(op, operand, typedef, meta) => HIRExpr::UnaryExpression(op, t(operand), typedef, meta),
+        HIRExpr::MemberAccess(obj, member, typedef, meta) => HIRExpr::MemberAccess(t(obj), member, typedef, meta),
+        HIRExpr::Array(items, typedef, meta) => HIRExpr::Array(items.into_iter().map(t).collect(), typedef, meta),
+        sizeof @ HIRExpr::SizeOf(..) => sizeof,
+    }
+}
+
+fn collect_local_names(body: &[HIR], names: &mut HashSet<String>) {
+    for node in body {
+        match node {
+            HIR::Declare { var, .. } => {
+                names.insert(var.clone());
+            }
+            HIR::If(_, true_branch, false_branch, _) => {
+                collect_local_names(true_branch, names);
+                collect_local_names(false_branch, names);
+            }
+            HIR::While(_, body, _) => collect_local_names(body, names),
+            _ => {}
+        }
+    }
+}
+
+fn propagate_body(body: Vec<HIR>, type_db: &TypeDatabase, shadowed: &HashSet<String>) -> Vec<HIR> {
+    body.into_iter()
+        .map(|node| match node {
+            HIR::Declare { var, typedef, expression, meta_ast, meta_expr } => HIR::Declare {
+                var,
+                typedef,
+                expression: propagate_expr(expression, type_db, shadowed),
+                meta_ast,
+                meta_expr,
+            },
+            HIR::Assign { path, expression, meta_ast, meta_expr } => HIR::Assign {
+                path,
+                expression: propagate_expr(expression, type_db, shadowed),
+                meta_ast,
+                meta_expr,
+            },
+            HIR::FunctionCall { function, args, meta } => HIR::FunctionCall {
+                function,
+                args: args.into_iter().map(|arg| propagate_trivial(arg, type_db, shadowed)).collect(),
+                meta,
+            },
+            HIR::If(condition, true_branch, false_branch, meta) => HIR::If(
+                propagate_trivial(condition, type_db, shadowed),
+                propagate_body(true_branch, type_db, shadowed),
+                propagate_body(false_branch, type_db, shadowed),
+                meta,
+            ),
+            HIR::While(condition, body, meta) => HIR::While(
+                propagate_trivial(condition, type_db, shadowed),
+                propagate_body(body, type_db, shadowed),
+                meta,
+            ),
+            HIR::Return(expr, typedef, meta) => HIR::Return(propagate_expr(expr, type_db, shadowed), typedef, meta),
+            other => other,
+        })
+        .collect()
+}
+
+//Replaces reads of consts by their values, keeping the type of the const. Runs after type inference,
+//so that a const declared as i64 is still an i64 where it's used. Locals and parameters named like a const shadow it.
+pub fn propagate_constants(type_db: &TypeDatabase, mir: Vec<HIR>) -> Vec<HIR> {
+    if type_db.constants.is_empty() {
+        return mir;
+    }
+    mir.into_iter()
+        .map(|node| match node {
+            HIR::DeclareFunction { function_name, type_parameters, parameters, body, return_type, visibility, meta } => {
+                let mut shadowed = parameters.iter().map(|param| param.name.clone()).collect::<HashSet<_>>();
+                collect_local_names(&body, &mut shadowed);
+                HIR::DeclareFunction {
+                    function_name,
+                    type_parameters,
+                    parameters,
+                    body: propagate_body(body, type_db, &shadowed),
+                    return_type,
+                    visibility,
+                    meta,
+                }
+            }
+            other => other,
+        })
+        .collect()
+}
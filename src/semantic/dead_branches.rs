@@ -0,0 +1,150 @@
+use crate::ast::lexer::Operator;
+use crate::semantic::hir::*;
+
+use std::collections::HashMap;
+
+//Removes the dead side of ifs whose condition is known at compile time, i.e. if True: A else: B becomes just A,
+//and loops whose condition is known to be false, i.e. while False: A is removed entirely.
+//Meant to run after the type checker, on the HIR that is lowered again for code generation,
+//so that the dead branch still gets its errors reported.
+
+fn fold_trivial(expr: &TypedTrivialHIRExpr) -> Option<bool> {
+    match expr.0 {
+        TrivialHIRExpr::BooleanValue(b) => Some(b),
+        _ => None,
+    }
+}
+
+fn fold_expr(expr: &HIRExpr) -> Option<bool> {
+    match expr {
+        HIRExpr::Trivial(trivial, ..) => fold_trivial(trivial),
+        HIRExpr::UnaryExpression(Operator::Not, operand, ..) => fold_trivial(operand).map(|b| !b),
+        HIRExpr::BinaryOperation(lhs, op, rhs, ..) => match (&lhs.0, &rhs.0) {
+            (TrivialHIRExpr::IntegerValue(l), TrivialHIRExpr::IntegerValue(r)) => match op {
+                Operator::Equals => Some(l == r),
+                Operator::NotEquals => Some(l != r),
+                Operator::Greater => Some(l > r),
+                Operator::GreaterEquals => Some(l >= r),
+                Operator::Less => Some(l < r),
+                Operator::LessEquals => Some(l <= r),
+                _ => None,
+            },
+            (TrivialHIRExpr::BooleanValue(l), TrivialHIRExpr::BooleanValue(r)) => match op {
+                Operator::Equals => Some(l == r),
+                Operator::NotEquals => Some(l != r),
+                Operator::And => Some(*l && *r),
+                Operator::Or => Some(*l || *r),
+                _ => None,
+            },
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+//intermediaries are declared once and used once, so the declaration of a folded condition can go away too.
+//user variables are never folded, they can be reassigned.
+fn fold_condition(condition: &TypedTrivialHIRExpr, preceding: &mut Vec<HIR>) -> Option<bool> {
+    let TrivialHIRExpr::Variable(name) = &condition.0 else {
+        return fold_trivial(condition);
+    };
+    if !name.starts_with('$') {
+        return None;
+    }
+    let position = preceding.iter().rposition(|node| matches!(node, HIR::Declare { var, .. } if var == name))?;
+    let HIR::Declare { expression, .. } = &preceding[position] else {
+        unreachable!()
+    };
+    let folded = fold_expr(expression)?;
+    preceding.remove(position);
+    Some(folded)
+}
+
+fn count_declarations(body: &[HIR], counts: &mut HashMap<String, i32>, delta: i32) {
+    for node in body {
+        match node {
+            HIR::Declare { var, .. } => *counts.entry(var.clone()).or_insert(0) += delta,
+            HIR::If(_, true_branch, false_branch, _) => {
+                count_declarations(true_branch, counts, delta);
+                count_declarations(false_branch, counts, delta);
+            }
+            HIR::While(_, body, _) => count_declarations(body, counts, delta),
+            _ => {}
+        }
+    }
+}
+
+//the surviving branch is spliced into the enclosing scope, which is only fine if its
+//declarations don't clash with names declared anywhere else in the function
+fn can_splice(surviving: &[HIR], dead: &[HIR], function_decls: &HashMap<String, i32>) -> bool {
+    let mut remaining = function_decls.clone();
+    count_declarations(surviving, &mut remaining, -1);
+    count_declarations(dead, &mut remaining, -1);
+    surviving.iter().all(|node| match node {
+        HIR::Declare { var, .. } => remaining.get(var).copied().unwrap_or(0) == 0,
+        _ => true,
+    })
+}
+
+fn prune_body(body: Vec<HIR>, function_decls: &HashMap<String, i32>) -> Vec<HIR> {
+    let mut new_body = vec![];
+    for node in body {
+        //a loop that never runs goes away along with its condition, otherwise only the ifs inside it can be pruned
+        if let HIR::While(condition, body, meta) = node {
+            let mut folded_body = new_body.clone();
+            if fold_condition(&condition, &mut folded_body) == Some(false) {
+                new_body = folded_body;
+            } else {
+                new_body.push(HIR::While(condition, prune_body(body, function_decls), meta));
+            }
+            continue;
+        }
+        let HIR::If(condition, true_branch, false_branch, meta) = node else {
+            new_body.push(node);
+            continue;
+        };
+        let true_branch = prune_body(true_branch, function_decls);
+        let false_branch = prune_body(false_branch, function_decls);
+
+        //fold on a copy, the condition declaration has to stay if the if stays
+        let mut folded_body = new_body.clone();
+        let folded = fold_condition(&condition, &mut folded_body);
+        let (surviving, dead) = match folded {
+            Some(true) => (&true_branch, &false_branch),
+            Some(false) => (&false_branch, &true_branch),
+            None => {
+                new_body.push(HIR::If(condition, true_branch, false_branch, meta));
+                continue;
+            }
+        };
+
+        if can_splice(surviving, dead, function_decls) {
+            new_body = folded_body;
+            new_body.extend(surviving.iter().cloned());
+        } else {
+            new_body.push(HIR::If(condition, true_branch, false_branch, meta));
+        }
+    }
+    new_body
+}
+
+pub fn prune_constant_branches(hir: Vec<HIR>) -> Vec<HIR> {
+    hir.into_iter()
+        .map(|node| match node {
+            HIR::DeclareFunction { function_name, type_parameters, parameters, body, return_type, visibility, meta } => {
+                let mut function_decls = HashMap::new();
+                count_declarations(&body, &mut function_decls, 1);
+                HIR::DeclareFunction {
+                    function_name,
+                    type_parameters,
+                    parameters,
+                    body: prune_body(body, &function_decls),
+                    return_type,
+                    visibility,
+                    meta,
+                }
+            }
+            other => other,
+        })
+        .collect()
+}
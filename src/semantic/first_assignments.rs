@@ -38,6 +38,11 @@ fn make_first_assignments_in_body(
                     make_first_assignments_in_body(&false_branch, &mut false_branch_scope);
                 HIR::If(condition.clone(), true_branch_decls, false_branch_decls, meta.clone())
             }
+            HIR::While(condition, body, meta) => {
+                let mut body_scope = declarations_found.clone();
+                let body_decls = make_first_assignments_in_body(&body, &mut body_scope);
+                HIR::While(condition.clone(), body_decls, meta.clone())
+            }
             other => other.clone(),
         };
         new_mir.push(mir_node);
@@ -76,9 +81,11 @@ pub fn transform_first_assignment_into_declaration(mir: Vec<HIR>) -> Vec<HIR> {
         let result = match node {
             HIR::DeclareFunction {
                 function_name,
+                type_parameters,
                 parameters,
                 body,
                 return_type, 
+                visibility,
                 meta
             } => {
                 let new_body = make_assignments_into_declarations_in_function(
@@ -89,12 +96,20 @@ pub fn transform_first_assignment_into_declaration(mir: Vec<HIR>) -> Vec<HIR> {
                 );
                 HIR::DeclareFunction {
                     function_name: function_name.clone(),
+                    type_parameters: type_parameters.clone(),
                     parameters: parameters.clone(),
                     body: new_body,
                     return_type: return_type.clone(),
+                    visibility: *visibility,
                     meta: meta.clone()
                 }
             }
+            HIR::ImplBlock { type_parameters, target, methods, meta } => HIR::ImplBlock {
+                type_parameters: type_parameters.clone(),
+                target: target.clone(),
+                methods: transform_first_assignment_into_declaration(methods.clone()),
+                meta: meta.clone()
+            },
             other => other.clone(),
         };
         new_mir.push(result);
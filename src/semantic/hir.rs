@@ -1,6 +1,7 @@
 use std::borrow::Cow;
 use std::borrow::Cow::Borrowed;
 use std::borrow::Cow::Owned;
+use std::fmt::Display;
 
 use crate::ast::lexer::*;
 use crate::ast::parser::*;
@@ -58,6 +59,10 @@ pub struct TypedTrivialHIRExpr(pub TrivialHIRExpr, pub HIRTypeDef);
 pub enum HIRExpr {
     Trivial(TypedTrivialHIRExpr, HIRExprMetadata),
     Cast(TypedTrivialHIRExpr, HIRTypeDef, HIRExprMetadata),
+    //same bytes, different type: source and target must have the same size
+    Reinterpret(TypedTrivialHIRExpr, HIRTypeDef, HIRExprMetadata),
+    //measured type, result type (always u32), folded into a literal during type inference
+    SizeOf(HIRTypeDef, HIRTypeDef, HIRExprMetadata),
     BinaryOperation(
         TypedTrivialHIRExpr,
         Operator,
@@ -66,6 +71,8 @@ pub enum HIRExpr {
         HIRExprMetadata
     ),
     FunctionCall(TypedTrivialHIRExpr, Vec<TypedTrivialHIRExpr>, HIRTypeDef, HIRExprMetadata),
+    //values of return a, b. There are no tuple types yet, type inference rejects it
+    Tuple(Vec<TypedTrivialHIRExpr>, HIRTypeDef, HIRExprMetadata),
     UnaryExpression(Operator, TypedTrivialHIRExpr, HIRTypeDef, HIRExprMetadata),
     //obj, field, result_type, metadata
     MemberAccess(TypedTrivialHIRExpr, String, HIRTypeDef, HIRExprMetadata),
@@ -81,47 +88,28 @@ pub enum HIRType {
     Simple(String),
     Generic(String, Vec<HIRType>),
     Function(Vec<HIRType>, Box<HIRType>),
+    Const(u32),
 }
 
-impl HIRType {
-
-    fn to_string_internal(result: &mut String, hir_type: &HIRType) {
-        match hir_type {
-            HIRType::Simple(s) => result.push_str(s),
-            HIRType::Generic(s, generics) => {
-                let comma_sep = generics
-                    .iter()
-                    .map(|x| x.to_string())
-                    .collect::<Vec<String>>()
-                    .join(", ");
-
-                result.push_str(s);
-                result.push_str("<");
-                result.push_str(&comma_sep);
-                result.push_str(">");
-            },
+//renders the type as it would be written in source, e.g. array<i32> or fn(i32, str) -> bool
+impl Display for HIRType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let comma_sep = |types: &[HIRType]| {
+            types
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+        match self {
+            HIRType::Simple(s) => write!(f, "{}", s),
+            HIRType::Generic(s, generics) => write!(f, "{}<{}>", s, comma_sep(generics)),
             HIRType::Function(arg_types, return_type) => {
-                let comma_sep_args = arg_types
-                    .iter()
-                    .map(|x| x.to_string())
-                    .collect::<Vec<String>>()
-                    .join(", ");
-
-                let return_type = return_type.to_string();
-
-                result.push_str("fn(");
-                result.push_str(&comma_sep_args);
-                result.push_str(") -> ");
-                result.push_str(&return_type);
-            },
+                write!(f, "fn({}) -> {}", comma_sep(arg_types), return_type)
+            }
+            HIRType::Const(value) => write!(f, "{}", value),
         }
     }
-
-    pub fn to_string(&self) -> String {
-        let mut str = String::new();
-        Self::to_string_internal(&mut str, &self);
-        return str;
-    }
 }
 
 impl HIRExpr {
@@ -136,11 +124,30 @@ impl HIRExpr {
         match self {
             HIRExpr::Trivial(t, ..) => &t.1,
             HIRExpr::Cast(_, t,..) => t,
+            HIRExpr::Reinterpret(_, t, _) => t,
+            HIRExpr::SizeOf(_, t, _) => t,
             HIRExpr::BinaryOperation(.., t, _) => t,
             HIRExpr::FunctionCall(.., t, _) => t,
             HIRExpr::UnaryExpression(.., t, _) => t,
             HIRExpr::MemberAccess(.., t, _) => t,
             HIRExpr::Array(.., t, _) => t,
+            HIRExpr::Tuple(.., t, _) => t,
+        }
+    }
+
+    //the source expression this was lowered from, used to point diagnostics at the user's code
+    pub fn get_expr_meta(&self) -> &HIRExprMetadata {
+        match self {
+            HIRExpr::Trivial(.., meta) => meta,
+            HIRExpr::Cast(.., meta) => meta,
+            HIRExpr::Reinterpret(.., meta) => meta,
+            HIRExpr::SizeOf(.., meta) => meta,
+            HIRExpr::BinaryOperation(.., meta) => meta,
+            HIRExpr::FunctionCall(.., meta) => meta,
+            HIRExpr::UnaryExpression(.., meta) => meta,
+            HIRExpr::MemberAccess(.., meta) => meta,
+            HIRExpr::Array(.., meta) => meta,
+            HIRExpr::Tuple(.., meta) => meta,
         }
     }
 }
@@ -186,7 +193,7 @@ impl HIRTypeDef {
 }
 
 impl HIRType {
-    fn from_ast(typ: &ASTType) -> Self {
+    pub fn from_ast(typ: &ASTType) -> Self {
         match typ {
             ASTType::Simple(name) => Self::Simple(name.clone()),
             ASTType::Generic(name, generics) => {
@@ -196,6 +203,11 @@ impl HIRType {
                     .collect::<Vec<_>>();
                 return HIRType::Generic(name.clone(), hir_generics);
             }
+            ASTType::Const(value) => Self::Const(*value),
+            ASTType::Function(args, return_type) => Self::Function(
+                args.iter().map(|x| Self::from_ast(x)).collect(),
+                Box::new(Self::from_ast(return_type)),
+            ),
         }
     }
 }
@@ -230,14 +242,24 @@ pub enum HIR {
     },
     DeclareFunction {
         function_name: String,
+        type_parameters: Vec<String>,
         parameters: Vec<HIRTypedBoundName>,
         body: Vec<HIR>,
         return_type: HIRTypeDef,
+        visibility: Visibility,
+        meta: HIRAstMetadata
+    },
+    DeclareExternFunction {
+        function_name: String,
+        parameters: Vec<HIRTypedBoundName>,
+        return_type: HIRTypeDef,
         meta: HIRAstMetadata
     },
     StructDeclaration {
         struct_name: String,
+        type_parameters: Vec<String>,
         body: Vec<HIRTypedBoundName>,
+        visibility: Visibility,
         meta: HIRAstMetadata
     },
     FunctionCall {
@@ -248,14 +270,49 @@ pub enum HIR {
     //condition, true branch, false branch
     //this transforms elifs into else: \n\t if ..
     If(TypedTrivialHIRExpr, Vec<HIR>, Vec<HIR>, HIRAstMetadata),
+    //condition, body. The body ends by recomputing the condition's intermediaries
+    While(TypedTrivialHIRExpr, Vec<HIR>, HIRAstMetadata),
     Return(HIRExpr, HIRTypeDef, HIRAstMetadata),
     EmptyReturn,
+    TypeAlias {
+        alias_name: String,
+        target: HIRType,
+        meta: HIRAstMetadata
+    },
+    //value is None until const folding evaluates the initializer, which is kept in meta_expr
+    DeclareConst {
+        var: String,
+        typedef: HIRTypeDef,
+        value: Option<TypedTrivialHIRExpr>,
+        meta_ast: HIRAstMetadata,
+        meta_expr: HIRExprMetadata
+    },
+    //methods are function declarations whose first parameter is self, typed as the target
+    ImplBlock {
+        type_parameters: Vec<String>,
+        target: HIRType,
+        methods: Vec<HIR>,
+        meta: HIRAstMetadata
+    },
 }
 
+//the lexer rejects $ in source, so these never collide with user names
 fn make_intermediary(intermediary: i32) -> String {
     return format!("${}", intermediary);
 }
 
+//middle operands of a comparison chain are read by two comparisons, so they are evaluated once into a variable
+fn reduce_chain_operand(operand: &Expr, is_middle: bool, intermediary: i32, accum: &mut Vec<HIR>) -> (Expr, i32) {
+    if !is_middle || get_trivial_hir_expr(operand).is_some() {
+        return (operand.clone(), 0);
+    }
+    let (reduced, num_interm) = reduce_expr_to_hir_declarations(operand, intermediary, accum, true, operand);
+    let TrivialHIRExpr::Variable(var) = reduced.expect_trivial().0 else {
+        panic!("Comparison chain: middle operand should have been reduced to a variable")
+    };
+    (Expr::Variable(var), num_interm)
+}
+
 //an expression is trivial when it needs basically no effort to
 //check its type. You shouldn't recurse anymore on the expr tree
 fn get_trivial_hir_expr(expr: &Expr) -> Option<TrivialHIRExpr> {
@@ -367,7 +424,7 @@ fn reduce_expr_to_hir_declarations<'a>(
                 let mut args_interm_used = 0;
                 for node in args {
                     let (arg_expr, arg_num_interm) =
-                        reduce_expr_to_hir_declarations(node, intermediary, accum, true, function_expr);
+                        reduce_expr_to_hir_declarations(node, intermediary, accum, true, node);
                     intermediary += arg_num_interm;
                     args_interm_used += arg_num_interm;
 
@@ -424,11 +481,11 @@ fn reduce_expr_to_hir_declarations<'a>(
             let mut total_used_interm = 0;
             let binop = if check_if_reducible(full_binop) {
                 let (lhs_intermediary, lhs_num_intern) =
-                    reduce_expr_to_hir_declarations(lhs, intermediary, accum, true, metadata);
+                    reduce_expr_to_hir_declarations(lhs, intermediary, accum, true, lhs);
                 intermediary += lhs_num_intern;
 
                 let (rhs_intermediary, rhs_num_intern) =
-                    reduce_expr_to_hir_declarations(rhs, intermediary, accum, true, metadata);
+                    reduce_expr_to_hir_declarations(rhs, intermediary, accum, true, rhs);
                 intermediary += rhs_num_intern;
 
                 total_used_interm = lhs_num_intern + rhs_num_intern;
@@ -528,6 +585,103 @@ fn reduce_expr_to_hir_declarations<'a>(
                 return (array, total_used_interm);
             }
         }
+        full_tuple_exp @ Expr::Tuple(tuple_exprs) => {
+            let mut total_used_interm = 0;
+            let mut item_exprs = vec![];
+            for node in tuple_exprs {
+                let (item_expr, item_num_interm) =
+                    reduce_expr_to_hir_declarations(node, intermediary, accum, true, node);
+                intermediary += item_num_interm;
+                total_used_interm += item_num_interm;
+                item_exprs.push(item_expr.expect_trivial());
+            }
+            let tuple = HIRExpr::Tuple(item_exprs, HIRTypeDef::PendingInference, Some(full_tuple_exp.clone()));
+
+            if force_declare_intermediate_on_nonroot_exprs {
+                let declare = HIR::Declare {
+                    var: make_intermediary(intermediary),
+                    typedef: HIRTypeDef::PendingInference,
+                    expression: tuple,
+                    meta_ast: None,
+                    meta_expr: Some(full_tuple_exp.clone())
+                };
+                total_used_interm += 1;
+                accum.push(declare);
+                return (
+                    HIRExpr::Trivial(
+                        TrivialHIRExpr::Variable(make_intermediary(intermediary)).pending_type(),
+                        Some(full_tuple_exp.clone())
+                    ),
+                    total_used_interm,
+                );
+            } else {
+                return (tuple, total_used_interm);
+            }
+        }
+        //a < f() < g() becomes $0 = f(), $1 = a < $0, then if $1: $1 = $0 < g(), so that f() is evaluated only once
+        //and g() is only called when a < $0 holds. Each further comparison is nested in the if of the previous one.
+        Expr::ComparisonChain(operands, ops) => {
+            let first_intermediary = intermediary;
+            let last_index = operands.len() - 1;
+
+            let (lhs, num_interm) = reduce_chain_operand(&operands[0], false, intermediary, accum);
+            intermediary += num_interm;
+            let (mut previous, num_interm) = reduce_chain_operand(&operands[1], 1 < last_index, intermediary, accum);
+            intermediary += num_interm;
+
+            let first_comparison = Expr::BinaryOperation(Box::new(lhs), ops[0], Box::new(previous.clone()));
+            let (reduced, num_interm) =
+                reduce_expr_to_hir_declarations(&first_comparison, intermediary, accum, false, &first_comparison);
+            intermediary += num_interm;
+            let result_var = make_intermediary(intermediary);
+            intermediary += 1;
+            accum.push(HIR::Declare {
+                var: result_var.clone(),
+                typedef: HIRTypeDef::PendingInference,
+                expression: reduced,
+                meta_ast: None,
+                meta_expr: Some(first_comparison.clone())
+            });
+
+            let mut levels = vec![];
+            for (i, op) in ops.iter().enumerate().skip(1) {
+                //operands are reduced inside the if, so they are not evaluated when a previous comparison fails
+                let mut body = vec![];
+                let (next, num_interm) = reduce_chain_operand(&operands[i + 1], i + 1 < last_index, intermediary, &mut body);
+                intermediary += num_interm;
+                let comparison = Expr::BinaryOperation(Box::new(previous), *op, Box::new(next.clone()));
+                let (reduced, num_interm) =
+                    reduce_expr_to_hir_declarations(&comparison, intermediary, &mut body, false, &comparison);
+                intermediary += num_interm;
+                body.push(HIR::Assign {
+                    path: vec![result_var.clone()],
+                    expression: reduced,
+                    meta_ast: None,
+                    meta_expr: Some(comparison)
+                });
+                levels.push(body);
+                previous = next;
+            }
+
+            let nested_ifs = levels.into_iter().rev().fold(None, |inner, mut body| {
+                body.extend(inner);
+                Some(HIR::If(
+                    TrivialHIRExpr::Variable(result_var.clone()).pending_type(),
+                    body,
+                    vec![],
+                    None
+                ))
+            });
+            accum.extend(nested_ifs);
+
+            return (
+                HIRExpr::Trivial(
+                    TrivialHIRExpr::Variable(result_var).pending_type(),
+                    Some(metadata.clone())
+                ),
+                intermediary - first_intermediary,
+            );
+        }
         //transforms an index access into a method call on obj
         //i.e. if obj[0], becomes obj.__index__(0)
         //i.e. if obj.map[0] becomes obj.map.__index__(0)
@@ -547,6 +701,21 @@ fn reduce_expr_to_hir_declarations<'a>(
                 &index_access
             );
         }
+        //same as index access, obj[1:3] becomes obj.__slice__(1, 3)
+        slice_access @ Expr::SliceAccess(obj_expr, start_expr, end_expr) => {
+            let as_fcall = Expr::FunctionCall(
+                Box::new(Expr::MemberAccess(obj_expr.clone(), "__slice__".into())),
+                vec![*start_expr.clone(), *end_expr.clone()],
+            );
+
+            return reduce_expr_to_hir_declarations(
+                &as_fcall,
+                intermediary,
+                accum,
+                force_declare_intermediate_on_nonroot_exprs,
+                &slice_access
+            );
+        }
         unary_expression @ Expr::UnaryExpression(op, expr) => {
             let mut total_used_interm = 0;
             let unaryop = if check_if_reducible(unary_expression) {
@@ -639,6 +808,74 @@ fn reduce_expr_to_hir_declarations<'a>(
                 return (member_access, total_used_interm);
             }
         }
+        Expr::SizeOf(typ) => {
+            let sizeof = HIRExpr::SizeOf(
+                HIRTypeDef::Unresolved(HIRType::from_ast(typ)),
+                HIRTypeDef::PendingInference,
+                Some(expr.clone())
+            );
+
+            if force_declare_intermediate_on_nonroot_exprs {
+                let declare = HIR::Declare {
+                    var: make_intermediary(intermediary),
+                    typedef: HIRTypeDef::PendingInference,
+                    expression: sizeof,
+                    meta_ast: None,
+                    meta_expr: Some(expr.clone())
+                };
+                accum.push(declare);
+
+                return (
+                    HIRExpr::Trivial(
+                        TrivialHIRExpr::Variable(make_intermediary(intermediary)).pending_type(),
+                        Some(expr.clone())
+                    ),
+                    1,
+                );
+            } else {
+                return (sizeof, 0);
+            }
+        }
+        Expr::Cast(typ, casted) | Expr::Reinterpret(typ, casted) => {
+            let mut total_used_interm = 0;
+            let casted_trivial = match get_trivial_hir_expr(casted) {
+                Some(trivial) => trivial.pending_type(),
+                None => {
+                    let (casted_intermediary, num_interm) =
+                        reduce_expr_to_hir_declarations(casted, intermediary, accum, true, expr);
+                    intermediary += num_interm;
+                    total_used_interm = num_interm;
+                    casted_intermediary.expect_trivial()
+                }
+            };
+            let target = HIRTypeDef::Unresolved(HIRType::from_ast(typ));
+            let cast = if let Expr::Reinterpret(..) = expr {
+                HIRExpr::Reinterpret(casted_trivial, target, Some(expr.clone()))
+            } else {
+                HIRExpr::Cast(casted_trivial, target, Some(expr.clone()))
+            };
+
+            if force_declare_intermediate_on_nonroot_exprs {
+                let declare = HIR::Declare {
+                    var: make_intermediary(intermediary),
+                    typedef: HIRTypeDef::PendingInference,
+                    expression: cast,
+                    meta_ast: None,
+                    meta_expr: Some(expr.clone())
+                };
+                accum.push(declare);
+
+                return (
+                    HIRExpr::Trivial(
+                        TrivialHIRExpr::Variable(make_intermediary(intermediary)).pending_type(),
+                        Some(expr.clone())
+                    ),
+                    total_used_interm + 1,
+                );
+            } else {
+                return (cast, total_used_interm);
+            }
+        }
         exprnode => panic!("Expr to HIR not implemented for {:?}", exprnode),
     }
 }
@@ -682,11 +919,30 @@ pub fn ast_to_hir(ast: &AST, mut intermediary: i32, accum: &mut Vec<HIR>) -> i32
             accum.push(decl_hir);
             return num_intermediaries;
         }
+        AST::CompoundAssign { path, operator, expression } => {
+            //x.y += e is lowered as x.y = x.y + e
+            let target = path.iter().skip(1).fold(Expr::Variable(path[0].clone()), |obj, member| {
+                Expr::MemberAccess(Box::new(obj), member.clone())
+            });
+            let desugared = Expr::BinaryOperation(Box::new(target), *operator, Box::new(expression.clone()));
+            let (result_expr, num_intermediaries) =
+                reduce_expr_to_hir_declarations(&desugared, intermediary, accum, false, &desugared);
+
+            accum.push(HIR::Assign {
+                path: path.clone(),
+                expression: result_expr,
+                meta_ast: Some(ast.clone()),
+                meta_expr: Some(desugared)
+            });
+            return num_intermediaries;
+        }
         AST::DeclareFunction {
             function_name,
+            type_parameters,
             parameters,
             body,
             return_type,
+            visibility,
         } => {
             let mut function_body = vec![];
 
@@ -697,6 +953,7 @@ pub fn ast_to_hir(ast: &AST, mut intermediary: i32, accum: &mut Vec<HIR>) -> i32
 
             let decl_hir = HIR::DeclareFunction {
                 function_name: function_name.clone(),
+                type_parameters: type_parameters.clone(),
                 parameters: parameters
                     .iter()
                     .map(|param| {
@@ -712,6 +969,7 @@ pub fn ast_to_hir(ast: &AST, mut intermediary: i32, accum: &mut Vec<HIR>) -> i32
                     Some(x) => HIRTypeDef::Unresolved(HIRType::from_ast(x)),
                     None => HIRTypeDef::Unresolved(HIRType::Simple("Void".into())),
                 },
+                visibility: *visibility,
                 meta: Some(ast.clone())
             };
 
@@ -740,7 +998,7 @@ pub fn ast_to_hir(ast: &AST, mut intermediary: i32, accum: &mut Vec<HIR>) -> i32
                 return num_intermediaries;
             }
         },
-        AST::StructDeclaration { struct_name, body } => {
+        AST::StructDeclaration { struct_name, type_parameters, body, visibility } => {
             let fields = body.iter().map(|field| {
                 return HIRTypedBoundName {
                     name: field.name.clone(),
@@ -749,7 +1007,9 @@ pub fn ast_to_hir(ast: &AST, mut intermediary: i32, accum: &mut Vec<HIR>) -> i32
             });
             accum.push(HIR::StructDeclaration {
                 struct_name: struct_name.clone(),
+                type_parameters: type_parameters.clone(),
                 body: fields.collect(),
+                visibility: *visibility,
                 meta: Some(ast.clone())
             });
             return 0;
@@ -779,6 +1039,8 @@ pub fn ast_to_hir(ast: &AST, mut intermediary: i32, accum: &mut Vec<HIR>) -> i32
             elifs,
             final_else,
         } => {
+            //conditions are declared before the if, so the statements after it can't reuse their names
+            let first_intermediary = intermediary;
             let (true_branch_result_expr, num_intermediaries) =
                 reduce_expr_to_hir_declarations(&true_branch.expression, intermediary, accum, true, &true_branch.expression);
             intermediary += num_intermediaries;
@@ -814,7 +1076,7 @@ pub fn ast_to_hir(ast: &AST, mut intermediary: i32, accum: &mut Vec<HIR>) -> i32
                     Some(ast.clone())
                 ));
 
-                return 0;
+                return intermediary - first_intermediary;
             } else if elifs.len() == 0 && final_else.is_some() {
                 //in this case we have a final else, just generate a false branch
                 let mut false_body_hir = vec![];
@@ -836,7 +1098,7 @@ pub fn ast_to_hir(ast: &AST, mut intermediary: i32, accum: &mut Vec<HIR>) -> i32
                         ));
                     }
                 }
-                return 0;
+                return intermediary - first_intermediary;
             } else {
                 //in this case we have elifs, so we build the "tree"
                 //and we don't actually need to store the false body because we'll connect everything later.
@@ -846,12 +1108,16 @@ pub fn ast_to_hir(ast: &AST, mut intermediary: i32, accum: &mut Vec<HIR>) -> i32
                 let mut nodes = vec![];
 
                 struct IfTreeNode {
+                    //intermediaries of an elif condition go in the else branch of the previous node,
+                    //so they are only evaluated when the previous conditions are false
+                    condition_decls: Vec<HIR>,
                     condition: TypedTrivialHIRExpr,
                     true_body: Vec<HIR>,
                     body_meta: AST
                 }
 
                 let root_node = IfTreeNode {
+                    condition_decls: vec![],
                     condition: trivial_true_branch_expr.clone(),
                     true_body: true_body_hir,
                     body_meta: AST::Root(true_branch.statements.clone())
@@ -860,11 +1126,12 @@ pub fn ast_to_hir(ast: &AST, mut intermediary: i32, accum: &mut Vec<HIR>) -> i32
                 nodes.push(root_node);
 
                 for item in elifs {
+                    let mut condition_decls = vec![];
                     let (elif_true_branch_result_expr, num_intermediaries) =
                         reduce_expr_to_hir_declarations(
                             &item.expression,
                             intermediary,
-                            accum,
+                            &mut condition_decls,
                             true,
                             &item.expression
                         );
@@ -874,6 +1141,7 @@ pub fn ast_to_hir(ast: &AST, mut intermediary: i32, accum: &mut Vec<HIR>) -> i32
                         panic!("Lowering of elif true branch expr returned invalid result: {:?}", elif_true_branch_result_expr);
                     };
                     let mut if_node = IfTreeNode {
+                        condition_decls,
                         condition: elif_trivial_true_branch_result_expr.clone(),
                         true_body: vec![],
                         body_meta: AST::Root(item.statements.clone())
@@ -895,35 +1163,142 @@ pub fn ast_to_hir(ast: &AST, mut intermediary: i32, accum: &mut Vec<HIR>) -> i32
                     }
                 }
 
-                //trivialexpr, vec<hir>, vec<hir>
-                let mut final_if_chain = None;
+                //the condition declarations of a node followed by its if, this becomes the else branch of the previous node
+                let mut final_if_chain: Option<Vec<HIR>> = None;
 
                 if final_else_body.len() > 0 {
                     let first_node = nodes.pop().unwrap(); //there MUST be a node here
-                    final_if_chain = Some(HIR::If(
+                    let mut chain = first_node.condition_decls;
+                    chain.push(HIR::If(
                         first_node.condition,
                         first_node.true_body,
                         final_else_body,
                         Some(ast.clone())
                     ));
+                    final_if_chain = Some(chain);
                 }
 
                 nodes.reverse();
                 //we navigate through the nodes in reverse and build the final HIR tree
                 for node in nodes {
-                    let new_node = match final_if_chain {
-                        None => HIR::If(node.condition, node.true_body, vec![], Some(node.body_meta)),
-                        Some(current_chain) => {
-                            HIR::If(node.condition, node.true_body, vec![current_chain], Some(node.body_meta))
-                        }
-                    };
-                    final_if_chain = Some(new_node);
+                    let mut chain = node.condition_decls;
+                    chain.push(HIR::If(
+                        node.condition,
+                        node.true_body,
+                        final_if_chain.unwrap_or_default(),
+                        Some(node.body_meta)
+                    ));
+                    final_if_chain = Some(chain);
                 }
-                accum.push(final_if_chain.unwrap());
+                //the root condition was already declared before the chain
+                accum.extend(final_if_chain.unwrap());
 
-                return 0;
+                return intermediary - first_intermediary;
             }
         }
+        //imports are resolved by the module driver in analysis, nothing to lower here
+        AST::Import(_) => 0,
+        AST::DeclareExternFunction {
+            function_name,
+            parameters,
+            return_type,
+        } => {
+            accum.push(HIR::DeclareExternFunction {
+                function_name: function_name.clone(),
+                parameters: parameters
+                    .iter()
+                    .map(|param| HIRTypedBoundName {
+                        name: param.name.clone(),
+                        typename: HIRTypeDef::Unresolved(HIRType::from_ast(&param.name_type)),
+                    })
+                    .collect(),
+                return_type: match return_type {
+                    Some(x) => HIRTypeDef::Unresolved(HIRType::from_ast(x)),
+                    None => HIRTypeDef::Unresolved(HIRType::Simple("Void".into())),
+                },
+                meta: Some(ast.clone())
+            });
+            return 0;
+        }
+        AST::TypeAlias { alias_name, target } => {
+            accum.push(HIR::TypeAlias {
+                alias_name: alias_name.clone(),
+                target: HIRType::from_ast(target),
+                meta: Some(ast.clone())
+            });
+            return 0;
+        }
+        AST::DeclareConst { var, expression } => {
+            accum.push(HIR::DeclareConst {
+                var: var.name.clone(),
+                typedef: HIRTypeDef::Unresolved(HIRType::from_ast(&var.name_type)),
+                value: None,
+                meta_ast: Some(ast.clone()),
+                meta_expr: Some(expression.clone())
+            });
+            return 0;
+        }
+        AST::ImplBlock { type_parameters, target, methods } => {
+            let target = HIRType::from_ast(target);
+            let mut methods_hir = vec![];
+            for method in methods {
+                intermediary += ast_to_hir(method, intermediary, &mut methods_hir);
+            }
+            let self_param = HIRTypedBoundName {
+                name: "self".into(),
+                typename: HIRTypeDef::Unresolved(target.clone()),
+            };
+            for method in methods_hir.iter_mut() {
+                if let HIR::DeclareFunction { parameters, .. } = method {
+                    parameters.insert(0, self_param.clone());
+                }
+            }
+            accum.push(HIR::ImplBlock {
+                type_parameters: type_parameters.clone(),
+                target,
+                methods: methods_hir,
+                meta: Some(ast.clone())
+            });
+            return 0;
+        }
+        AST::WhileStatement { expression, body } => {
+            //like in ifs, the condition is declared before the loop
+            let first_intermediary = intermediary;
+            let condition_start = accum.len();
+            let (condition_result_expr, num_intermediaries) =
+                reduce_expr_to_hir_declarations(expression, intermediary, accum, true, expression);
+            intermediary += num_intermediaries;
+            let HIRExpr::Trivial(trivial_condition_expr, _) = &condition_result_expr else {
+                panic!("Lowering of while condition returned invalid result: {:?}", condition_result_expr);
+            };
+
+            let mut body_hir = vec![];
+            for node in body.iter() {
+                intermediary += ast_to_hir(node, intermediary, &mut body_hir);
+            }
+
+            //the intermediaries are assigned again at the end of each iteration, so the condition sees the updated values.
+            //ifs from comparison chains only assign to those intermediaries, they are repeated as they are
+            for node in accum[condition_start..].iter() {
+                match node {
+                    HIR::Declare { var, expression, meta_ast, meta_expr, .. } => body_hir.push(HIR::Assign {
+                        path: vec![var.clone()],
+                        expression: expression.clone(),
+                        meta_ast: meta_ast.clone(),
+                        meta_expr: meta_expr.clone()
+                    }),
+                    if_node @ HIR::If(..) => body_hir.push(if_node.clone()),
+                    other => panic!("Lowering of while condition emitted unexpected node: {:?}", other),
+                }
+            }
+
+            accum.push(HIR::While(
+                trivial_condition_expr.clone(),
+                body_hir,
+                Some(ast.clone())
+            ));
+            return intermediary - first_intermediary;
+        }
         ast => panic!("Not implemented HIR for {:?}", ast),
     }
 }
@@ -942,7 +1317,7 @@ mod tests {
     fn parse(source: &str) -> Vec<HIR> {
         let tokens = crate::ast::lexer::tokenize(source);
         //println!("Tokens: {:?}", tokens);
-        let ast = crate::ast::parser::parse_ast(tokens.unwrap());
+        let ast = crate::ast::parser::parse_ast(tokens.unwrap()).unwrap();
 
         let root = crate::ast::parser::AST::Root(ast);
         let mut result = vec![];
@@ -950,6 +1325,163 @@ mod tests {
         return result;
     }
 
+    //codegen emits the body in order, so every intermediary must be declared right before it's needed
+    #[test]
+    fn intermediaries_are_declared_in_evaluation_order() {
+        let result = parse(
+            "
+def main():
+    x = f(a + b * c, arr[i + 1]) - -h(e)
+    print(x)
+",
+        );
+        let result = print_hir(&result, &TypeDatabase::new());
+
+        let expected = "
+def main() -> UNRESOLVED! Void:
+    $0 : UNKNOWN_TYPE = b * c
+    $1 : UNKNOWN_TYPE = a + $0
+    $2 : UNKNOWN_TYPE = arr.__index__
+    $3 : UNKNOWN_TYPE = i + 1
+    $4 : UNKNOWN_TYPE = $2($3)
+    $5 : UNKNOWN_TYPE = f($1, $4)
+    $6 : UNKNOWN_TYPE = h(e)
+    $7 : UNKNOWN_TYPE = -$6
+    x = $5 - $7
+    print(x)";
+        assert_eq!(expected.trim(), result.trim());
+
+        let mut declared: Vec<String> = vec![];
+        for line in result.lines().skip(1) {
+            let (target, value) = line.split_once('=').unwrap_or(("", line));
+            for used in value.split(|c: char| !(c == '$' || c.is_ascii_digit())).filter(|t| t.starts_with('$')) {
+                assert!(declared.iter().any(|d| d == used), "{used} used before its declaration in: {line}");
+            }
+            let target = target.split(':').next().unwrap().trim();
+            if target.starts_with('$') {
+                assert_eq!(target, format!("${}", declared.len()), "intermediaries must be numbered in order");
+                declared.push(target.to_string());
+            }
+        }
+    }
+
+    #[test]
+    fn compound_assignment_is_lowered_to_binary_operation() {
+        let result = parse(
+            "
+def main():
+    x = 1
+    x += 2 * 3
+    p.y -= 1",
+        );
+        let result = print_hir(&result, &TypeDatabase::new());
+
+        let expected = "
+def main() -> UNRESOLVED! Void:
+    x = 1
+    $0 : UNKNOWN_TYPE = 2 * 3
+    x = x + $0
+    $1 : UNKNOWN_TYPE = p.y
+    p.y = $1 - 1";
+        assert_eq!(expected.trim(), result.trim());
+    }
+
+    #[test]
+    fn elif_condition_is_only_evaluated_in_else_branch() {
+        let result = parse(
+            "
+def main():
+    if x == 1:
+        y = 1
+    elif f(x) == 2:
+        y = 2
+    elif g(x) == 3:
+        y = 3
+    else:
+        y = 4",
+        );
+        let result = print_hir(&result, &TypeDatabase::new());
+
+        let expected = "
+def main() -> UNRESOLVED! Void:
+    $0 : UNKNOWN_TYPE = x == 1
+    if $0:
+        y = 1
+    else:
+        $1 : UNKNOWN_TYPE = f(x)
+        $2 : UNKNOWN_TYPE = $1 == 2
+        if $2:
+            y = 2
+        else:
+            $3 : UNKNOWN_TYPE = g(x)
+            $4 : UNKNOWN_TYPE = $3 == 3
+            if $4:
+                y = 3
+            else:
+                y = 4";
+        assert_eq!(expected.trim(), result.trim());
+    }
+
+    #[test]
+    fn cast_is_lowered_and_printed() {
+        let result = parse(
+            "
+def main():
+    x = cast<u32>(y + 1)",
+        );
+        let result = print_hir(&result, &TypeDatabase::new());
+
+        let expected = "
+def main() -> UNRESOLVED! Void:
+    $0 : UNKNOWN_TYPE = y + 1
+    x = cast<u32>($0)";
+        assert_eq!(expected.trim(), result.trim());
+    }
+
+    #[test]
+    fn reduced_expressions_keep_their_source_expression() {
+        let result = parse(
+            "
+def main():
+    x = f(a + b * c, -d, arr[0])",
+        );
+        let HIR::DeclareFunction { body, .. } = &result[0] else {
+            panic!("Expected function, got {:?}", result[0]);
+        };
+        let sources = body.iter().map(|node| match node {
+            HIR::Declare { expression, .. } | HIR::Assign { expression, .. } => {
+                hir_printer::ast_expr_str(expression.get_expr_meta().as_ref().unwrap())
+            }
+            other => panic!("Unexpected node {:?}", other),
+        }).collect::<Vec<_>>();
+
+        //the method lookup is introduced by the index lowering, so it has no better source than itself
+        assert_eq!(sources, vec!["b * c", "a + b * c", "-d", "arr.__index__", "arr[0]", "f(a + b * c, -d, arr[0])"]);
+    }
+
+    //Cast is (casted value, target type, meta) everywhere, this fails to compile if the shape changes
+    #[test]
+    fn cast_is_lowered_to_value_target_and_meta() {
+        let result = parse(
+            "
+def main():
+    x = cast<u32>(y)",
+        );
+        let HIR::DeclareFunction { body, .. } = &result[0] else {
+            panic!("Expected function, got {:?}", result[0]);
+        };
+        let HIR::Assign { expression, .. } = &body[0] else {
+            panic!("Expected assign, got {:?}", body[0]);
+        };
+        let HIRExpr::Cast(TypedTrivialHIRExpr(casted, casted_type), target, meta) = expression else {
+            panic!("Expected cast, got {:?}", expression);
+        };
+        assert_eq!(casted, &TrivialHIRExpr::Variable("y".into()));
+        assert_eq!(casted_type, &HIRTypeDef::PendingInference);
+        assert_eq!(target, &HIRTypeDef::Unresolved(HIRType::Simple("u32".into())));
+        assert!(matches!(meta, Some(Expr::Cast(..))));
+    }
+
     #[test]
     fn complex_code() {
         let result = parse(
@@ -1060,8 +1592,8 @@ def main(args: UNRESOLVED List<UNRESOLVED! String>) -> UNRESOLVED! Void:
         else:
             pass
     else:
-        $2 : UNKNOWN_TYPE = arg == 2
-        if $2:
+        $3 : UNKNOWN_TYPE = arg == 2
+        if $3:
             print(40)
         else:
             pass        
@@ -1122,4 +1654,46 @@ def main() -> UNRESOLVED! i32:
 
         assert_eq!(expected.trim(), result.trim());
     }
+
+    #[test]
+    fn hir_type_display_simple() {
+        assert_eq!(HIRType::Simple("i32".into()).to_string(), "i32");
+    }
+
+    #[test]
+    fn hir_type_display_nested_generic() {
+        let typ = HIRType::Generic("map".into(), vec![
+            HIRType::Simple("str".into()),
+            HIRType::Generic("array".into(), vec![HIRType::Simple("i32".into())])
+        ]);
+        assert_eq!(typ.to_string(), "map<str, array<i32>>");
+    }
+
+    #[test]
+    fn hir_type_display_function() {
+        let typ = HIRType::Function(
+            vec![HIRType::Simple("i32".into()), HIRType::Generic("array".into(), vec![HIRType::Simple("str".into())])],
+            Box::new(HIRType::Simple("bool".into()))
+        );
+        assert_eq!(typ.to_string(), "fn(i32, array<str>) -> bool");
+
+        let no_args = HIRType::Function(vec![], Box::new(HIRType::Simple("Void".into())));
+        assert_eq!(no_args.to_string(), "fn() -> Void");
+    }
+
+    #[test]
+    fn trivial_operands_are_pending_after_lowering() {
+        let result = parse("
+def my_function(a: i32, b: i32):
+    x = a + b");
+
+        let HIR::DeclareFunction { body, .. } = &result[0] else {
+            panic!("Expected function, got {:?}", result[0]);
+        };
+        let HIR::Assign { expression: HIRExpr::BinaryOperation(lhs, _, rhs, ..), .. } = &body[0] else {
+            panic!("Expected assignment of a binary operation, got {:?}", body[0]);
+        };
+        assert_eq!(*lhs, TypedTrivialHIRExpr(TrivialHIRExpr::Variable("a".into()), HIRTypeDef::PendingInference));
+        assert_eq!(rhs.1, HIRTypeDef::PendingInference);
+    }
 }
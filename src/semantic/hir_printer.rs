@@ -12,16 +12,61 @@ pub fn operator_str(op: lexer::Operator) -> String {
         Operator::Minus => "-".into(),
         Operator::Multiply => "*".into(),
         Operator::Divide => "/".into(),
+        Operator::Mod => "%".into(),
+        Operator::BitShiftLeft => "<<".into(),
+        Operator::BitShiftRight => ">>".into(),
+        Operator::Xor => "^".into(),
         Operator::Equals => "==".into(),
         Operator::NotEquals => "!=".into(),
         Operator::Greater => ">".into(),
         Operator::GreaterEquals => ">=".into(),
         Operator::LessEquals => "<=".into(),
         Operator::Less => "<".into(),
+        Operator::And => "and".into(),
+        Operator::Or => "or".into(),
         _ => "operator_str doesn't implement this operator".into(),
     }
 }
 
+//renders a parsed expression back as source, used to show the user expression behind lowered code
+pub fn ast_expr_str(expr: &parser::Expr) -> String {
+    use parser::Expr;
+    let comma_sep = |exprs: &[Expr]| exprs.iter().map(ast_expr_str).collect::<Vec<_>>().join(", ");
+    match expr {
+        Expr::IntegerValue(i) => format!("{}", i),
+        Expr::FloatValue(f) => format!("{:?}", f.0),
+        Expr::StringValue(s) => format!("\"{}\"", s),
+        Expr::BooleanValue(true) => "True".into(),
+        Expr::BooleanValue(false) => "False".into(),
+        Expr::None => "None".into(),
+        Expr::Variable(name) => name.clone(),
+        Expr::FunctionCall(f, args) => format!("{}({})", ast_expr_str(f), comma_sep(args)),
+        Expr::IndexAccess(obj, index) => format!("{}[{}]", ast_expr_str(obj), ast_expr_str(index)),
+        Expr::SliceAccess(obj, start, end) => {
+            format!("{}[{}:{}]", ast_expr_str(obj), ast_expr_str(start), ast_expr_str(end))
+        }
+        Expr::BinaryOperation(lhs, op, rhs) => {
+            format!("{} {} {}", ast_expr_str(lhs), operator_str(*op), ast_expr_str(rhs))
+        }
+        Expr::Parenthesized(inner) => format!("({})", ast_expr_str(inner)),
+        Expr::UnaryExpression(Operator::Not, operand) => format!("not {}", ast_expr_str(operand)),
+        Expr::UnaryExpression(op, operand) => format!("{}{}", operator_str(*op), ast_expr_str(operand)),
+        Expr::MemberAccess(obj, member) => format!("{}.{}", ast_expr_str(obj), member),
+        Expr::Array(items) => format!("[{}]", comma_sep(items)),
+        Expr::SizeOf(typ) => format!("sizeof<{}>()", HIRType::from_ast(typ)),
+        Expr::Cast(typ, casted) => format!("cast<{}>({})", HIRType::from_ast(typ), ast_expr_str(casted)),
+        Expr::Reinterpret(typ, value) => format!("reinterpret<{}>({})", HIRType::from_ast(typ), ast_expr_str(value)),
+        Expr::Tuple(items) => comma_sep(items),
+        Expr::ComparisonChain(operands, operators) => {
+            let mut result = ast_expr_str(&operands[0]);
+            for (operand, op) in operands[1..].iter().zip(operators.iter()) {
+                result = format!("{} {} {}", result, operator_str(*op), ast_expr_str(operand));
+            }
+            result
+        }
+    }
+}
+
 pub fn trivial_expr_str(expr: &TypedTrivialHIRExpr) -> String {
     match &expr.0 {
         TrivialHIRExpr::Variable(s) => s.clone(),
@@ -34,7 +79,16 @@ pub fn trivial_expr_str(expr: &TypedTrivialHIRExpr) -> String {
     }
 }
 
-pub fn expr_str(expr: &HIRExpr) -> String {
+//type arguments are printed as written in the source, or by name once resolved
+fn type_arg_str(typ: &HIRTypeDef, type_db: &TypeDatabase) -> String {
+    match typ {
+        HIRTypeDef::PendingInference => "UNKNOWN_TYPE".into(),
+        HIRTypeDef::Unresolved(typ) => typ.to_string(),
+        HIRTypeDef::Resolved(instance) => instance.as_string(type_db),
+    }
+}
+
+pub fn expr_str(expr: &HIRExpr, type_db: &TypeDatabase) -> String {
     match expr {
         HIRExpr::Trivial(trivial, ..) => trivial_expr_str(trivial),
         HIRExpr::FunctionCall(f, params, ..) => {
@@ -66,10 +120,76 @@ pub fn expr_str(expr: &HIRExpr) -> String {
         HIRExpr::MemberAccess(obj, elem, ..) => {
             format!("{}.{}", trivial_expr_str(obj), elem)
         }
+        HIRExpr::SizeOf(HIRTypeDef::Unresolved(typ), ..) => format!("sizeof<{}>()", typ.to_string()),
+        HIRExpr::Cast(casted, target, ..) => format!("cast<{}>({})", type_arg_str(target, type_db), trivial_expr_str(casted)),
+        HIRExpr::Reinterpret(value, target, ..) => format!("reinterpret<{}>({})", type_arg_str(target, type_db), trivial_expr_str(value)),
+        HIRExpr::Tuple(items, ..) => items
+            .iter()
+            .map(|x| trivial_expr_str(x))
+            .collect::<Vec<_>>()
+            .join(", "),
         e => format!("not added to expr_str: {:?}", e),
     }
 }
 
+pub fn typed_trivial_expr_str(expr: &TypedTrivialHIRExpr, type_db: &TypeDatabase) -> String {
+    format!("({} : {})", trivial_expr_str(expr), hir_type_str(&expr.1, type_db))
+}
+
+//Same as expr_str, but every subexpression is annotated with its type, e.g. (a : i32) + (b : i32) : i32
+pub fn typed_expr_str(expr: &HIRExpr, type_db: &TypeDatabase) -> String {
+    let trivials_str = |items: &[TypedTrivialHIRExpr]| {
+        items
+            .iter()
+            .map(|x| typed_trivial_expr_str(x, type_db))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let expr_type = hir_type_str(expr.get_expr_type(), type_db);
+    match expr {
+        HIRExpr::Trivial(trivial, ..) => typed_trivial_expr_str(trivial, type_db),
+        HIRExpr::FunctionCall(f, params, ..) => format!(
+            "{}({}) : {}",
+            typed_trivial_expr_str(f, type_db),
+            trivials_str(params),
+            expr_type
+        ),
+        HIRExpr::BinaryOperation(var, op, var2, ..) => format!(
+            "{} {} {} : {}",
+            typed_trivial_expr_str(var, type_db),
+            operator_str(*op),
+            typed_trivial_expr_str(var2, type_db),
+            expr_type
+        ),
+        HIRExpr::Array(items, ..) => format!("[{}] : {}", trivials_str(items), expr_type),
+        HIRExpr::UnaryExpression(op, expr, ..) => format!(
+            "{}{} : {}",
+            operator_str(*op),
+            typed_trivial_expr_str(expr, type_db),
+            expr_type
+        ),
+        HIRExpr::MemberAccess(obj, elem, ..) => format!(
+            "{}.{} : {}",
+            typed_trivial_expr_str(obj, type_db),
+            elem,
+            expr_type
+        ),
+        HIRExpr::Cast(casted, target, ..) => format!(
+            "cast<{}>({}) : {}",
+            type_arg_str(target, type_db),
+            typed_trivial_expr_str(casted, type_db),
+            expr_type
+        ),
+        HIRExpr::Reinterpret(value, target, ..) => format!(
+            "reinterpret<{}>({}) : {}",
+            type_arg_str(target, type_db),
+            typed_trivial_expr_str(value, type_db),
+            expr_type
+        ),
+        e => format!("not added to typed_expr_str: {:?}", e),
+    }
+}
+
 pub fn hir_type_str(typ: &HIRTypeDef, type_db: &TypeDatabase) -> String {
     fn slice_types_str(types: &[HIRType], type_db: &TypeDatabase) -> String {
         types
@@ -86,15 +206,30 @@ pub fn hir_type_str(typ: &HIRTypeDef, type_db: &TypeDatabase) -> String {
             format!("UNRESOLVED {}<{}>", s, slice_types_str(g, type_db))
         }
         HIRTypeDef::Unresolved(HIRType::Function(args, return_type)) => format!(
-            "UNRESOLVED ({}) -> {}",
+            "UNRESOLVED fn ({}) -> {}",
             slice_types_str(args, type_db),
             hir_type_str(&HIRTypeDef::Unresolved(*return_type.clone()), type_db)
         ),
+        HIRTypeDef::Unresolved(HIRType::Const(value)) => value.to_string(),
         HIRTypeDef::Resolved(instance) => instance.as_string(type_db),
     }
 }
 
-fn print_hir_str(node: &HIR, indent: &str, type_db: &TypeDatabase) -> String {
+fn print_hir_str(node: &HIR, indent: &str, type_db: &TypeDatabase, typed: bool) -> String {
+    let expr_str = |expr: &HIRExpr| {
+        if typed {
+            typed_expr_str(expr, type_db)
+        } else {
+            expr_str(expr, type_db)
+        }
+    };
+    let trivial_expr_str = |expr: &TypedTrivialHIRExpr| {
+        if typed {
+            typed_trivial_expr_str(expr, type_db)
+        } else {
+            trivial_expr_str(expr)
+        }
+    };
     match node {
         HIR::Assign {
             path, expression, ..
@@ -116,6 +251,7 @@ fn print_hir_str(node: &HIR, indent: &str, type_db: &TypeDatabase) -> String {
         }
         HIR::DeclareFunction {
             function_name,
+            type_parameters,
             parameters,
             body,
             return_type, ..
@@ -124,8 +260,7 @@ fn print_hir_str(node: &HIR, indent: &str, type_db: &TypeDatabase) -> String {
                 .iter()
                 .map(|param| {
                     return format!(
-                        "{}{}: {}",
-                        indent,
+                        "{}: {}",
                         param.name,
                         hir_type_str(&param.typename, type_db)
                     );
@@ -133,27 +268,76 @@ fn print_hir_str(node: &HIR, indent: &str, type_db: &TypeDatabase) -> String {
                 .collect::<Vec<_>>()
                 .join(", ");
 
+            let type_parameters = match type_parameters.as_slice() {
+                [] => String::new(),
+                params => format!("<{}>", params.join(", ")),
+            };
             let mut function = format!(
-                "{}def {}({}) -> {}:\n",
+                "{}def {}{}({}) -> {}:\n",
                 indent,
                 function_name,
+                type_parameters,
                 parameters,
                 hir_type_str(return_type, type_db)
             );
             let indent_block = format!("{}    ", indent);
             for n in body {
-                function.push_str(&print_hir_str(n, &indent_block, type_db));
+                function.push_str(&print_hir_str(n, &indent_block, type_db, typed));
             }
             return function;
         }
+        HIR::DeclareExternFunction {
+            function_name,
+            parameters,
+            return_type, ..
+        } => {
+            let parameters = parameters
+                .iter()
+                .map(|param| format!("{}: {}", param.name, hir_type_str(&param.typename, type_db)))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            format!(
+                "{}extern def {}({}) -> {}\n",
+                indent,
+                function_name,
+                parameters,
+                hir_type_str(return_type, type_db)
+            )
+        }
         HIR::Return(expr, ..) => {
             format!("{}return {}\n", indent, expr_str(expr))
         }
         HIR::EmptyReturn => {
             format!("{}return\n", indent)
         }
-        HIR::StructDeclaration { struct_name, body, .. } => {
-            let mut structdecl = format!("{}struct {}:\n", indent, struct_name);
+        HIR::ImplBlock { type_parameters, target, methods, .. } => {
+            let mut impl_block = match type_parameters.as_slice() {
+                [] => format!("{}impl {}:\n", indent, target.to_string()),
+                params => format!("{}impl<{}> {}:\n", indent, params.join(", "), target.to_string()),
+            };
+            let indent_block = format!("{}    ", indent);
+            for method in methods {
+                impl_block.push_str(&print_hir_str(method, &indent_block, type_db, typed));
+            }
+            impl_block
+        }
+        HIR::TypeAlias { alias_name, target, .. } => {
+            format!("{}type {} = {}\n", indent, alias_name, target.to_string())
+        }
+        HIR::DeclareConst { var, typedef, value, meta_expr, .. } => {
+            let value_str = match (value, meta_expr) {
+                (Some(value), _) => trivial_expr_str(value),
+                (None, Some(initializer)) => ast_expr_str(initializer),
+                (None, None) => "?".to_string(),
+            };
+            format!("{}const {} : {} = {}\n", indent, var, hir_type_str(typedef, type_db), value_str)
+        }
+        HIR::StructDeclaration { struct_name, type_parameters, body, .. } => {
+            let mut structdecl = match type_parameters.as_slice() {
+                [] => format!("{}struct {}:\n", indent, struct_name),
+                params => format!("{}struct {}<{}>:\n", indent, struct_name, params.join(", ")),
+            };
 
             for field in body {
                 structdecl.push_str(&format!(
@@ -180,12 +364,12 @@ fn print_hir_str(node: &HIR, indent: &str, type_db: &TypeDatabase) -> String {
             let mut ifdecl = format!("{}if {}:\n", indent, condition_str);
             let indent_block = format!("{}    ", indent);
             for statement in true_body {
-                let statement_str = print_hir_str(statement, &indent_block, type_db);
+                let statement_str = print_hir_str(statement, &indent_block, type_db, typed);
                 ifdecl.push_str(&statement_str);
             }
             ifdecl.push_str(&format!("{}else:\n", indent));
             for statement in false_body {
-                let statement_str = print_hir_str(statement, &indent_block, type_db);
+                let statement_str = print_hir_str(statement, &indent_block, type_db, typed);
                 ifdecl.push_str(&statement_str);
             }
 
@@ -194,6 +378,16 @@ fn print_hir_str(node: &HIR, indent: &str, type_db: &TypeDatabase) -> String {
             }
             return ifdecl;
         }
+        HIR::While(condition, body, ..) => {
+            let condition_str = trivial_expr_str(condition);
+            let mut whiledecl = format!("{}while {}:\n", indent, condition_str);
+            let indent_block = format!("{}    ", indent);
+            for statement in body {
+                let statement_str = print_hir_str(statement, &indent_block, type_db, typed);
+                whiledecl.push_str(&statement_str);
+            }
+            return whiledecl;
+        }
         
         e => panic!("Code format not implemented for node {:?}", e),
     }
@@ -202,7 +396,16 @@ fn print_hir_str(node: &HIR, indent: &str, type_db: &TypeDatabase) -> String {
 pub fn print_hir(mir: &[HIR], type_db: &TypeDatabase) -> String {
     let mut buffer = String::new();
     for node in mir {
-        buffer.push_str(&print_hir_str(&node, "".into(), type_db));
+        buffer.push_str(&print_hir_str(&node, "".into(), type_db, false));
+    }
+    return buffer;
+}
+
+//Verbose version of print_hir that shows the type of every subexpression, useful to debug type inference.
+pub fn print_hir_typed(mir: &[HIR], type_db: &TypeDatabase) -> String {
+    let mut buffer = String::new();
+    for node in mir {
+        buffer.push_str(&print_hir_str(&node, "".into(), type_db, true));
     }
     return buffer;
 }
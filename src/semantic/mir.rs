@@ -27,10 +27,14 @@ but represent the main parts of the program.
 pub enum MIRTopLevelNode {
     DeclareFunction {
         function_name: String,
+        //generic functions are type checked like any other, but not compiled until they are monomorphized
+        type_parameters: Vec<String>,
         parameters: Vec<MIRTypedBoundName>,
         body: Vec<MIRBlock>,
         scopes: Vec<MIRScope>,
         return_type: TypeInstance,
+        //the alias the return type was declared with, only used in error messages
+        return_type_alias: Option<String>,
     },
     StructDeclaration {
         struct_name: String,
@@ -244,6 +248,18 @@ fn process_body(emitter: &mut MIRFunctionEmitter, body: &[HIR], type_db: &TypeDa
             HIR::StructDeclaration { .. } => {
                 panic!("Cannot declare struct inside a function yet!")
             }
+            HIR::TypeAlias { .. } => {
+                panic!("Cannot declare type alias inside a function yet!")
+            }
+            HIR::ImplBlock { .. } => {
+                panic!("Cannot declare impl block inside a function yet!")
+            }
+            HIR::DeclareConst { .. } => {
+                panic!("Cannot declare const inside a function yet!")
+            }
+            HIR::DeclareExternFunction { .. } => {
+                panic!("Cannot declare extern function inside a function!")
+            }
             HIR::Assign { path, expression, meta_ast, meta_expr } => {
                 emitter.emit(MIRBlockNode::Assign {
                     path: path.clone(),
@@ -412,6 +428,38 @@ fn process_body(emitter: &mut MIRFunctionEmitter, body: &[HIR], type_db: &TypeDa
                     emitter.set_current_block(fallback_block);
                 }
             }
+            HIR::While(condition, body_hir, ast) => {
+                let HIRTypeDef::Resolved(_) = &condition.1 else {
+                    panic!("Unresolved condition type reached MIR, this might be a type inference bug");
+                };
+
+                //the condition gets its own block so that the end of the body can jump back to it,
+                //and the body comes right after it, so the branch only needs to jump when the loop ends
+                let current_scope = emitter.current_scope;
+
+                let condition_scope = emitter.create_scope(current_scope);
+                let condition_block = emitter.new_block(condition_scope);
+                emitter.finish_with_goto_block(condition_block);
+
+                let body_scope = emitter.create_scope(current_scope);
+                let body_block = emitter.new_block(body_scope);
+                emitter.set_current_block(body_block);
+                process_body(emitter, body_hir, type_db);
+
+                //the body may have moved on to other blocks, the last one loops back unless it returned
+                if !emitter.check_if_block_is_finished(emitter.current_block) {
+                    emitter.finish_with_goto_block(condition_block);
+                }
+
+                let after_scope = emitter.create_scope(current_scope);
+                let after_block = emitter.new_block(after_scope);
+
+                emitter.set_current_block(condition_block);
+                emitter.finish_with_branch(condition.clone(), body_block, after_block, ast.clone());
+
+                emitter.set_current_block(after_block);
+                emitter.set_current_scope(after_scope);
+            }
             HIR::Return(expr, typedef, meta_ast) => {
                 let HIRTypeDef::Resolved(resolved_type) = typedef else {
                     panic!("Unresolved return type reached MIR, this might be a bug in type inference");
@@ -427,9 +475,11 @@ fn process_body(emitter: &mut MIRFunctionEmitter, body: &[HIR], type_db: &TypeDa
 
 pub fn process_hir_funcdecl(
     function_name: &str,
+    type_parameters: &[String],
     parameters: &[HIRTypedBoundName],
     body: &[HIR],
     return_type: &HIRTypeDef,
+    return_type_alias: Option<String>,
     type_db: &TypeDatabase,
 ) -> MIRTopLevelNode {
     let mut emitter = MIRFunctionEmitter::new();
@@ -462,6 +512,7 @@ pub fn process_hir_funcdecl(
 
     return MIRTopLevelNode::DeclareFunction {
         function_name: function_name.to_string(),
+        type_parameters: type_parameters.to_vec(),
         parameters: parameters
             .iter()
             .map(|x| MIRTypedBoundName {
@@ -473,6 +524,7 @@ pub fn process_hir_funcdecl(
         body,
         scopes,
         return_type: type_def,
+        return_type_alias,
     };
 }
 
@@ -480,17 +532,35 @@ pub fn hir_to_mir(hir_nodes: &[HIR], type_db: &TypeDatabase) -> Vec<MIRTopLevelN
     let mut top_levels = vec![];
     for hir in hir_nodes {
         match hir {
+            //functions with errors in their signature are left uninferred, analysis already reported them
+            HIR::DeclareFunction { return_type: HIRTypeDef::Unresolved(_), .. } => {}
             HIR::DeclareFunction {
                 function_name,
+                type_parameters,
                 parameters,
                 body,
                 return_type,
-                meta
+                meta,
+                ..
             } => {
+                let return_type_alias = match meta {
+                    Some(AST::DeclareFunction { return_type: Some(annotation), .. }) => {
+                        type_db.alias_written_as(&HIRType::from_ast(annotation))
+                    }
+                    _ => None
+                };
                 let fdecl =
-                    process_hir_funcdecl(function_name, parameters, body, return_type, type_db);
+                    process_hir_funcdecl(function_name, type_parameters, parameters, body, return_type, return_type_alias, type_db);
                 top_levels.push(fdecl);
             }
+            //aliases are expanded during type inference, nothing left to lower
+            HIR::TypeAlias { .. } => {}
+            //reads of consts were replaced by their values after type inference
+            HIR::DeclareConst { .. } => {}
+            //methods are type checked during analysis, but not compiled yet
+            HIR::ImplBlock { .. } => {}
+            //extern functions have no body, calls to them are resolved by the backend
+            HIR::DeclareExternFunction { .. } => {}
             _ => {
                 panic!("Top-level HIR unsupported: {:?}", hir)
             }
@@ -1356,7 +1426,7 @@ def main() -> i32:
         inheritscope 9
     defscope 11:
         inheritscope 10
-        $0 : bool
+        $1 : bool
     defscope 12:
         inheritscope 11
     defscope 13:
@@ -1409,11 +1479,11 @@ def main() -> i32:
         gotoblock 11
     defblock 11:
         usescope 11
-        $0 = 2 == 2
+        $1 = 2 == 2
         gotoblock 12
     defblock 12:
         usescope 12
-        if $0:
+        if $1:
             gotoblock 13
         else:
             gotoblock 14
@@ -1482,7 +1552,7 @@ def main() -> i32:
         inheritscope 10
     defscope 12:
         inheritscope 11
-        $0 : bool
+        $1 : bool
     defscope 13:
         inheritscope 12
     defscope 14:
@@ -1542,11 +1612,11 @@ def main() -> i32:
         gotoblock 12
     defblock 12:
         usescope 12
-        $0 = 2 == 2
+        $1 = 2 == 2
         gotoblock 13
     defblock 13:
         usescope 13
-        if $0:
+        if $1:
             gotoblock 14
         else:
             gotoblock 16
@@ -21,7 +21,7 @@ fn print_mir_block(block: &MIRBlock, type_db: &TypeDatabase) -> String {
                 buffer.push_str(&format!(
                     "        {} = {}\n",
                     path.join("."),
-                    expr_str(&expression)
+                    expr_str(&expression, type_db)
                 ));
             }
             MIRBlockNode::FunctionCall { function, args, .. } => {
@@ -52,7 +52,7 @@ fn print_mir_block(block: &MIRBlock, type_db: &TypeDatabase) -> String {
             buffer.push_str(&format!("        gotoblock {}\n", block.0));
         }
         super::mir::MIRBlockFinal::Return(expr, ..) => {
-            buffer.push_str(&format!("        return {}\n", expr_str(&expr)));
+            buffer.push_str(&format!("        return {}\n", expr_str(&expr, type_db)));
         }
         super::mir::MIRBlockFinal::EmptyReturn => {
             buffer.push_str(&format!("        return\n"));
@@ -83,10 +83,12 @@ fn print_mir_str(node: &MIRTopLevelNode, type_db: &TypeDatabase) -> String {
     match node {
         MIRTopLevelNode::DeclareFunction {
             function_name,
+            type_parameters,
             parameters,
             body,
             scopes,
             return_type,
+            ..
         } => {
             let parameters = parameters
                 .iter()
@@ -95,9 +97,14 @@ fn print_mir_str(node: &MIRTopLevelNode, type_db: &TypeDatabase) -> String {
                 })
                 .collect::<Vec<_>>()
                 .join(", ");
+            let type_parameters = match type_parameters.as_slice() {
+                [] => String::new(),
+                params => format!("<{}>", params.join(", ")),
+            };
             let mut function = format!(
-                "def {}({}) -> {}:\n",
+                "def {}{}({}) -> {}:\n",
                 function_name,
+                type_parameters,
                 parameters,
                 &return_type.as_string(type_db)
             );
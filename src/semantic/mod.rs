@@ -8,3 +8,5 @@ pub mod hir;
 pub mod analysis;
 pub mod mir;
 pub mod type_checker;
+pub mod dead_branches;
+mod const_fold;
@@ -1,6 +1,7 @@
-use crate::{semantic::hir::*, types::type_db::{TypeDatabase, TypeInstance}};
+use crate::{semantic::hir::*, types::type_db::{TypeDatabase, TypeInstance}, ast::parser::Visibility};
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
 
 #[derive(Debug, Clone)]
 pub struct PartiallyResolvedFunctionSignature {
@@ -9,22 +10,44 @@ pub struct PartiallyResolvedFunctionSignature {
 }
 
 
+//the maps are shared between clones and only copied when a clone is written to,
+//so cloning a scope for each branch doesn't copy every name in the enclosing scopes
 #[derive(Debug, Clone)]
 pub struct NameRegistry {
-    names: HashMap<String, HIRTypeDef>,
-    partially_resolved_function_sigs: HashMap<String, PartiallyResolvedFunctionSignature>
+    names: Rc<HashMap<String, HIRTypeDef>>,
+    partially_resolved_function_sigs: Rc<HashMap<String, PartiallyResolvedFunctionSignature>>,
+    //visibility of user-declared top-level symbols (functions and structs).
+    //builtins have no visibility recorded.
+    visibilities: Rc<HashMap<String, Visibility>>,
+    //type parameters of generic functions, their signatures stay unresolved and are instantiated on each call
+    type_parameters: Rc<HashMap<String, Vec<String>>>,
+    //declarations whose type inference failed and was already reported, they stay pending
+    failed_inference: Rc<HashSet<String>>
 }
 
 impl NameRegistry {
     pub fn new() -> Self {
         NameRegistry {
-            names: HashMap::new(),
-            partially_resolved_function_sigs: HashMap::new(),
+            names: Rc::new(HashMap::new()),
+            partially_resolved_function_sigs: Rc::new(HashMap::new()),
+            visibilities: Rc::new(HashMap::new()),
+            type_parameters: Rc::new(HashMap::new()),
+            failed_inference: Rc::new(HashSet::new()),
         }
     }
 
+    //O(1), names inserted in the new scope are not visible in this one
+    pub fn new_scope(&self) -> NameRegistry {
+        self.clone()
+    }
+
+    //whether both registries still point to the same names, i.e. neither was written to since the clone
+    pub fn shares_names_with(&self, other: &NameRegistry) -> bool {
+        Rc::ptr_eq(&self.names, &other.names)
+    }
+
     pub fn insert_partially_resolved_signature(&mut self, name: String, sig: PartiallyResolvedFunctionSignature) {
-        self.partially_resolved_function_sigs.insert(name, sig);
+        Rc::make_mut(&mut self.partially_resolved_function_sigs).insert(name, sig);
     }
 
     pub fn find_partially_resolved_sig(&mut self, name: &str) ->  Option<&PartiallyResolvedFunctionSignature> {
@@ -32,21 +55,53 @@ impl NameRegistry {
     }
 
     pub fn insert(&mut self, name: String, type_instance: HIRTypeDef) {
-        self.names.insert(name, type_instance);
+        Rc::make_mut(&mut self.names).insert(name, type_instance);
+    }
+
+    pub fn set_visibility(&mut self, name: String, visibility: Visibility) {
+        Rc::make_mut(&mut self.visibilities).insert(name, visibility);
+    }
+
+    pub fn get_visibility(&self, name: &str) -> Option<Visibility> {
+        self.visibilities.get(name).copied()
+    }
+
+    pub fn set_type_parameters(&mut self, name: String, type_parameters: Vec<String>) {
+        Rc::make_mut(&mut self.type_parameters).insert(name, type_parameters);
+    }
+
+    //None if the name is not a generic function
+    pub fn get_type_parameters(&self, name: &str) -> Option<&[String]> {
+        self.type_parameters.get(name).map(|params| params.as_slice())
+    }
+
+    pub fn mark_inference_failed(&mut self, name: String) {
+        Rc::make_mut(&mut self.failed_inference).insert(name);
+    }
+
+    pub fn inference_failed(&self, name: &str) -> bool {
+        self.failed_inference.contains(name)
     }
 
     pub fn include(&mut self, outer: &NameRegistry) {
         for (k, v) in outer.names.iter() {
             self.insert(k.clone(), v.clone())
         }
+        for (k, v) in outer.type_parameters.iter() {
+            self.set_type_parameters(k.clone(), v.clone())
+        }
+        for name in outer.failed_inference.iter() {
+            self.mark_inference_failed(name.clone())
+        }
     }
 
-    pub fn get(&self, name: &str) -> HIRTypeDef {
-        return self
-            .names
-            .get(name)
-            .expect(&format!("Could not find a name for {}", name))
-            .clone();
+    //None if the name is not in scope at all, as opposed to being in scope with a pending type
+    pub fn get(&self, name: &str) -> Option<HIRTypeDef> {
+        return self.names.get(name).cloned();
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.names.contains_key(name)
     }
 
     pub fn get_ref(&self, name: &str) -> &HIRTypeDef {
@@ -141,17 +196,26 @@ fn register_builtins(type_db: &TypeDatabase, registry: &mut NameRegistry) {
     );
 }
 
-pub fn build_name_registry(type_db: &TypeDatabase, mir: &[HIR]) -> NameRegistry {
+//the functions every program can call without declaring them
+pub fn builtin_names(type_db: &TypeDatabase) -> NameRegistry {
     let mut registry = NameRegistry::new();
     register_builtins(type_db, &mut registry);
+    registry
+}
+
+//prelude holds the names available before any declaration, usually builtin_names
+pub fn build_name_registry(prelude: &NameRegistry, mir: &[HIR]) -> NameRegistry {
+    let mut registry = prelude.new_scope();
 
     //first collect all globals by navigating through all functions and assigns
     for node in mir.iter() {
         match node {
             HIR::DeclareFunction {
                 function_name,
+                type_parameters,
                 parameters,
                 return_type,
+                visibility,
                 ..
             } => {
                 let param_types = parameters
@@ -164,9 +228,76 @@ pub fn build_name_registry(type_db: &TypeDatabase, mir: &[HIR]) -> NameRegistry
                 let function_type =
                     HIRType::Function(param_types, Box::new(return_type.expect_unresolved()));
                 registry.insert(function_name.clone(), HIRTypeDef::Unresolved(function_type));
+                registry.set_visibility(function_name.clone(), *visibility);
+                if !type_parameters.is_empty() {
+                    registry.set_type_parameters(function_name.clone(), type_parameters.clone());
+                }
+            }
+            HIR::DeclareExternFunction {
+                function_name,
+                parameters,
+                return_type,
+                ..
+            } => {
+                let param_types = parameters
+                    .iter()
+                    .map(|x| x.typename.expect_unresolved())
+                    .collect::<Vec<_>>();
+                let function_type =
+                    HIRType::Function(param_types, Box::new(return_type.expect_unresolved()));
+                registry.insert(function_name.clone(), HIRTypeDef::Unresolved(function_type));
+            }
+            HIR::StructDeclaration {
+                struct_name,
+                visibility,
+                ..
+            } => {
+                registry.set_visibility(struct_name.clone(), *visibility);
+            }
+            HIR::DeclareConst { var, typedef, .. } => {
+                registry.insert(var.clone(), typedef.clone());
             }
             _ => {}
         };
     }
     return registry;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn branch_scopes_share_names_until_written() {
+        let type_db = TypeDatabase::new();
+        let i32_type = HIRTypeDef::Resolved(type_db.expect_find_by_name("i32").to_instance());
+        let mut outer = NameRegistry::new();
+        for i in 0..1000 {
+            outer.insert(format!("x{i}"), i32_type.clone());
+        }
+
+        let branches = (0..1000).map(|_| outer.new_scope()).collect::<Vec<_>>();
+        assert!(branches.iter().all(|branch| branch.shares_names_with(&outer)));
+
+        let mut branch = outer.new_scope();
+        branch.insert("only_in_branch".into(), i32_type.clone());
+        assert!(!branch.shares_names_with(&outer));
+        assert!(branch.contains("x999"));
+        assert!(branch.contains("only_in_branch"));
+        assert!(!outer.contains("only_in_branch"));
+    }
+
+    #[test]
+    fn registry_starts_from_the_prelude_only() {
+        let type_db = TypeDatabase::new();
+        let mut prelude = NameRegistry::new();
+        prelude.insert("length".into(), HIRTypeDef::Resolved(
+            TypeInstance::Function(vec![], Box::new(type_db.special_types.f32.clone()))
+        ));
+
+        let registry = build_name_registry(&prelude, &[]);
+        assert!(registry.contains("length"));
+        assert!(!registry.contains("print"));
+        assert!(build_name_registry(&builtin_names(&type_db), &[]).contains("print"));
+    }
+}
@@ -1,6 +1,8 @@
 use std::borrow::Cow;
+use std::collections::HashMap;
 
 use super::hir::*;
+use super::hir_printer;
 use crate::ast::parser::AST;
 use crate::types::type_db::TypeDatabase;
 use crate::types::type_db::TypeInstance;
@@ -51,6 +53,7 @@ fn expect_find_variable<'block, 'scope>(
 fn check_function_arguments<'callargs>(
     on_function: &str,
     function_called: &FunctionName,
+    lowered_from: Option<LoweredCall>,
     function_parameters: &[TypeInstance],
     arguments_passed: &[TypeInstance],
     type_db: &TypeDatabase,
@@ -79,6 +82,7 @@ fn check_function_arguments<'callargs>(
                 context: FunctionCallContext {
                     called_function_name: function_called.clone(),
                     argument_position: number,
+                    lowered_from: lowered_from.clone(),
                 },
             });
         }
@@ -89,6 +93,7 @@ fn all_paths_return_values_of_correct_type(
     function_name: &str,
     body: &[MIRBlock],
     return_type: &TypeInstance,
+    return_type_alias: &Option<String>,
     type_db: &TypeDatabase,
     errors: &mut TypeErrors,
 ) {
@@ -97,7 +102,7 @@ fn all_paths_return_values_of_correct_type(
             let expr_type = return_expr.get_expr_type().expect_resolved();
             if !return_type.is_compatible(&return_expr.get_expr_type().expect_resolved(), type_db) {
                 errors.return_type_mismatches.push(TypeMismatch {
-                    context: ReturnTypeContext(),
+                    context: ReturnTypeContext(return_type_alias.clone()),
                     on_function: function_name.to_string(),
                     expected: return_type.clone(),
                     actual: expr_type.clone(),
@@ -107,7 +112,7 @@ fn all_paths_return_values_of_correct_type(
         if let MIRBlockFinal::EmptyReturn = &body_node.finish {
             if return_type != &type_db.special_types.void {
                 errors.return_type_mismatches.push(TypeMismatch {
-                    context: ReturnTypeContext(),
+                    context: ReturnTypeContext(return_type_alias.clone()),
                     on_function: function_name.to_string(),
                     expected: return_type.clone(),
                     actual: type_db.special_types.void.clone(),
@@ -125,6 +130,18 @@ fn all_assignments_correct_type(
     type_db: &TypeDatabase,
     type_errors: &mut TypeErrors,
 ) {
+    //variables declared with an alias annotation, like x: Number = ..., mention it in errors
+    let mut declared_aliases = HashMap::new();
+    for body_node in body {
+        for block_node in body_node.block.iter() {
+            if let MIRBlockNode::Assign { meta_ast: Some(AST::Declare { var, .. }), .. } = block_node {
+                if let Some(alias) = type_db.alias_written_as(&HIRType::from_ast(&var.name_type)) {
+                    declared_aliases.insert(var.name.clone(), alias);
+                }
+            }
+        }
+    }
+
     for body_node in body {
         for block_node in body_node.block.iter() {
             match block_node {
@@ -143,6 +160,7 @@ fn all_assignments_correct_type(
                                     on_function: function_name.to_string(),
                                     context: AssignContext {
                                         target_variable_name: var.to_string(),
+                                        declared_alias: declared_aliases.get(var).cloned(),
                                     },
                                     expected: variable_found_type.clone(),
                                     actual: expr_type.clone(),
@@ -167,6 +185,7 @@ fn all_assignments_correct_type(
 pub enum FunctionName {
     Function(String),
     IndexAccess,
+    SliceAccess,
     Method {
         function_name: String,
         type_name: String,
@@ -202,12 +221,38 @@ fn get_actual_function_name_with_details(
             };
         }
         crate::ast::parser::Expr::IndexAccess(_, _) => return FunctionName::IndexAccess,
+        crate::ast::parser::Expr::SliceAccess(..) => return FunctionName::SliceAccess,
         _ => {}
     };
 
     return FunctionName::Function(function_name.to_string());
 }
 
+//index and slice accesses are lowered to method calls, errors on those calls mention both forms
+fn lowered_call(meta_ast: &HIRAstMetadata, meta_expr: &HIRExprMetadata) -> Option<LoweredCall> {
+    use crate::ast::parser::Expr;
+    let expr = match (meta_ast.as_ref(), meta_expr.as_ref()) {
+        (Some(AST::StandaloneExpr(expr)), _) => expr,
+        (_, Some(expr)) => expr,
+        _ => return None,
+    };
+    let lowered = match expr {
+        Expr::IndexAccess(obj, index) => Expr::FunctionCall(
+            Box::new(Expr::MemberAccess(obj.clone(), "__index__".into())),
+            vec![*index.clone()],
+        ),
+        Expr::SliceAccess(obj, start, end) => Expr::FunctionCall(
+            Box::new(Expr::MemberAccess(obj.clone(), "__slice__".into())),
+            vec![*start.clone(), *end.clone()],
+        ),
+        _ => return None,
+    };
+    Some(LoweredCall {
+        user_expr: hir_printer::ast_expr_str(expr),
+        lowered_call: hir_printer::ast_expr_str(&lowered),
+    })
+}
+
 fn function_calls_are_actually_callable_and_parameters_are_correct_type(
     body: &[MIRBlock],
     scopes: &[MIRScope],
@@ -264,6 +309,7 @@ fn function_calls_are_actually_callable_and_parameters_are_correct_type(
                     check_function_arguments(
                         &function_name,
                         &actual_function_name,
+                        lowered_call(meta_ast, expr_metadata),
                         &func_args_types,
                         &passed_types,
                         type_db,
@@ -287,6 +333,7 @@ fn function_calls_are_actually_callable_and_parameters_are_correct_type(
                             check_function_arguments(
                                 &function_name,
                                 &actual_function_name,
+                                lowered_call(meta_ast, &None),
                                 &argument_types,
                                 &passed,
                                 type_db,
@@ -311,12 +358,13 @@ fn type_check_function(
     body: &[MIRBlock],
     scopes: &[MIRScope],
     return_type: &TypeInstance,
+    return_type_alias: &Option<String>,
     globals: &NameRegistry,
     type_db: &TypeDatabase,
     type_errors: &mut TypeErrors,
 ) {
     
-    all_paths_return_values_of_correct_type(function_name, body, return_type, type_db, type_errors);
+    all_paths_return_values_of_correct_type(function_name, body, return_type, return_type_alias, type_db, type_errors);
     all_assignments_correct_type(function_name, body, scopes, globals, type_db, type_errors);
     function_calls_are_actually_callable_and_parameters_are_correct_type(
         body,
@@ -343,16 +391,19 @@ pub fn check_type(
         match node {
             MIRTopLevelNode::DeclareFunction {
                 function_name,
+                type_parameters: _,
                 parameters: _,
                 body,
                 scopes,
                 return_type,
+                return_type_alias,
             } => {
                 type_check_function(
                     function_name,
                     body,
                     scopes,
                     return_type,
+                    return_type_alias,
                     names,
                     type_db,
                     &mut type_errors,
@@ -413,6 +464,21 @@ mod tests {
         return (errors, &ctx.database);
     }
 
+    #[test]
+    fn statement_call_with_wrong_argument_count_is_error() {
+        let ctx = prepare(
+            "
+def main():
+    print(\"x\", \"y\")
+",
+        );
+        let (err, db) = run_test(&ctx);
+        let printer = TypeErrorPrinter::new(&err, &db);
+        let error_msg = format!("{}", printer);
+        let expected = "Argument count mismatch: In function main, call to function print expects 1 arguments, but 2 were passed\n";
+        assert_eq!(error_msg, expected);
+    }
+
     #[test]
     fn return_from_void_func_is_correct() {
         let ctx = prepare(
@@ -461,6 +527,50 @@ def main() -> i32:
         assert_eq!(0, err.count());
     }
 
+    #[test]
+    fn nested_ifs_where_all_leaves_return_are_correct() {
+        let ctx = prepare(
+            "
+def main(x: i32) -> i32:
+    if x > 0:
+        if x > 10:
+            return 2
+        else:
+            return 1
+    else:
+        if x < -10:
+            return -2
+        else:
+            return -1
+",
+        );
+        let (err, _) = run_test(&ctx);
+        assert_eq!(0, err.count());
+    }
+
+    #[test]
+    fn nested_ifs_where_a_leaf_falls_through_are_not_correct() {
+        let ctx = prepare(
+            "
+def main(x: i32) -> i32:
+    if x > 0:
+        if x > 10:
+            return 2
+        else:
+            y = 1
+    else:
+        if x < -10:
+            return -2
+        else:
+            return -1
+",
+        );
+        let (err, db) = run_test(&ctx);
+        assert_eq!(1, err.count());
+        assert_eq!(1, err.return_type_mismatches.len());
+        assert_eq!(err.return_type_mismatches[0].actual, db.special_types.void);
+    }
+
     #[test]
     fn return_void_from_int_func_is_not_correct() {
         let ctx = prepare(
@@ -479,6 +589,24 @@ def main() -> i32:
         );
     }
 
+    #[test]
+    fn return_generic_of_wrong_item_type_is_not_correct() {
+        let ctx = prepare(
+            "
+def main() -> array<i32>:
+    return [\"a\", \"b\"]
+",
+        );
+        let (err, db) = run_test(&ctx);
+        assert_eq!(1, err.count());
+        assert_eq!(1, err.return_type_mismatches.len());
+        let message = TypeErrorPrinter::new(&err, db).to_string();
+        assert_eq!(
+            message,
+            "Return type mismatch: Function main returns array<i32> but expression returns array<str>\n"
+        );
+    }
+
     #[test]
     fn assign_incorrect_type_literal() {
         let ctx = prepare(
@@ -501,6 +629,59 @@ def main():
         );
     }
 
+    #[test]
+    fn errors_in_constant_false_branch_are_reported() {
+        let ctx = prepare(
+            "
+def main():
+    if False:
+        x: i32 = \"str\"
+",
+        );
+
+        let (err, _) = run_test(&ctx);
+        assert_eq!(1, err.count());
+        assert_eq!(1, err.assign_mismatches.len());
+    }
+
+    #[test]
+    fn return_in_constant_false_else_is_type_checked() {
+        let ctx = prepare(
+            "
+def main() -> i32:
+    if True:
+        return 1
+    else:
+        return \"a\"
+",
+        );
+
+        let (err, _) = run_test(&ctx);
+        assert_eq!(1, err.count());
+        assert_eq!(1, err.return_type_mismatches.len());
+    }
+
+    #[test]
+    fn generic_function_body_is_type_checked() {
+        let ctx = prepare(
+            "
+def first<T>(xs: array<T>) -> T:
+    y: i32 = \"str\"
+    return xs[0]
+def main():
+    x = first([1, 2])
+",
+        );
+
+        let (err, db) = run_test(&ctx);
+        assert_eq!(1, err.count());
+        assert_eq!(1, err.assign_mismatches.len());
+        assert_eq!(
+            err.assign_mismatches[0].actual,
+            TypeInstance::Simple(db.expect_find_by_name("str").id)
+        );
+    }
+
     #[test]
     fn type_check_function_call_no_args_correct_types() {
         let ctx = prepare(
@@ -646,6 +827,31 @@ def main():
         );
     }
 
+    #[test]
+    fn pass_wrong_type_to_extern_function() {
+        let ctx = prepare(
+            "
+extern def log(x: str)
+
+def main():
+    log(1)
+",
+        );
+
+        let (err, db) = run_test(&ctx);
+
+        assert_eq!(1, err.count());
+        assert_eq!(1, err.function_call_mismatches.len());
+        assert_eq!(
+            err.function_call_mismatches[0].actual,
+            TypeInstance::Simple(db.expect_find_by_name("i32").id)
+        );
+        assert_eq!(
+            err.function_call_mismatches[0].expected,
+            TypeInstance::Simple(db.expect_find_by_name("str").id)
+        );
+    }
+
     #[test]
     fn pass_wrong_type_to_function_two_args_both_wrong() {
         let ctx = prepare(
@@ -725,7 +931,86 @@ def main(args: array<str>):
         let (err, db) = run_test(&ctx);
         let printer = TypeErrorPrinter::new(&err, &db);
         let error_msg = format!("{}", printer);
-        let expected = "Function argument type mismatch: In function main, on index operator, parameter on position 0 has incorrect type: Expected u32 but passed str\n";
+        let expected = "Function argument type mismatch: In function main, on index operator, parameter on position 0 has incorrect type: Expected u32 but passed str (in args[\"lol\"], lowered to args.__index__(\"lol\"))\n";
+        assert_eq!(error_msg, expected);
+    }
+
+    #[test]
+    fn nested_index_error_shows_user_expression_and_lowered_call() {
+        let ctx = prepare(
+            "
+def main(args: array<str>):
+    i : str = args[args[0]]
+",
+        );
+        let (err, db) = run_test(&ctx);
+        let printer = TypeErrorPrinter::new(&err, &db);
+        let error_msg = format!("{}", printer);
+        let expected = "Function argument type mismatch: In function main, on index operator, parameter on position 0 has incorrect type: Expected u32 but passed str (in args[args[0]], lowered to args.__index__(args[0]))\n";
+        assert_eq!(error_msg, expected);
+    }
+
+    #[test]
+    fn slice_with_string_bound_refers_to_slice_operator() {
+        let ctx = prepare(
+            "
+def main(args: array<str>):
+    s = args[\"a\":3]
+",
+        );
+        let (err, db) = run_test(&ctx);
+        let printer = TypeErrorPrinter::new(&err, &db);
+        let error_msg = format!("{}", printer);
+        let expected = "Function argument type mismatch: In function main, on slice operator, parameter on position 0 has incorrect type: Expected u32 but passed str (in args[\"a\":3], lowered to args.__slice__(\"a\", 3))\n";
+        assert_eq!(error_msg, expected);
+    }
+
+    #[test]
+    fn assign_incorrect_type_to_alias_errormsg() {
+        let ctx = prepare(
+            "
+type Number = i32
+
+def main():
+    x: Number = \"some str\"
+",
+        );
+        let (err, db) = run_test(&ctx);
+        let printer = TypeErrorPrinter::new(&err, &db);
+        let error_msg = format!("{}", printer);
+        let expected = "Assigned type mismatch: In function main, assignment to variable x: variable has type i32 (alias Number) but got assigned a value of type str\n";
+        assert_eq!(error_msg, expected);
+    }
+
+    #[test]
+    fn alias_is_only_mentioned_where_the_annotation_used_it() {
+        let ctx = prepare(
+            "
+type Number = i32
+
+def main():
+    x: i32 = \"some str\"
+",
+        );
+        let (err, db) = run_test(&ctx);
+        let error_msg = format!("{}", TypeErrorPrinter::new(&err, &db));
+        let expected = "Assigned type mismatch: In function main, assignment to variable x: variable has type i32 but got assigned a value of type str\n";
+        assert_eq!(error_msg, expected);
+    }
+
+    #[test]
+    fn return_incorrect_type_to_alias_errormsg() {
+        let ctx = prepare(
+            "
+type Number = i32
+
+def main() -> Number:
+    return \"some str\"
+",
+        );
+        let (err, db) = run_test(&ctx);
+        let error_msg = format!("{}", TypeErrorPrinter::new(&err, &db));
+        let expected = "Return type mismatch: Function main returns i32 (alias Number) but expression returns str\n";
         assert_eq!(error_msg, expected);
     }
 }
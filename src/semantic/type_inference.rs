@@ -1,8 +1,10 @@
+use crate::ast::parser::{AST, Parser};
 use crate::semantic::hir::*;
 use crate::semantic::name_registry::NameRegistry;
-use crate::types::type_db::{TypeInstance, TypeDatabase, TypeId, Type, FunctionSignature};
+use crate::types::type_db::{TypeInstance, TypeDatabase, Type, FunctionSignature, TypeRecord, TypeKind, TypeResolution, GenericParameter, resolve_type, StructSizeError};
 use crate::types::type_errors::*;
 use either::Either;
+use std::collections::HashMap;
 
 use super::name_registry::PartiallyResolvedFunctionSignature;
 
@@ -16,6 +18,7 @@ pub fn instantiate_type(on_function: &str, type_db: &TypeDatabase, typedef: &HIR
             Type::Simple(Either::Left(generic)) => panic!("as_type() method shouldn't return a generic parameter {}", generic.0),
             Type::Generic(_, _) => panic!("Type in MIR is {:?} but type is generic {:?}, bug in the compiler or code is just wrong?", typedef, type_data),
             Type::Function(_, _) => panic!("Function types shouldn't be recorded in the database, either this is a bug, or this decision was revised and needs adjusting. {:?}", type_data),
+            Type::Const(_) => panic!("Constant generic arguments are not types {:?}", typedef),
         }
     }
 
@@ -27,6 +30,9 @@ pub fn instantiate_type(on_function: &str, type_db: &TypeDatabase, typedef: &HIR
                     let as_type = found_type.as_type();
                     Some(type_to_instance(&as_type, typedef))
                 },
+                None if type_db.find_alias(type_name).is_some() => {
+                    type_db.find_alias(type_name).cloned()
+                },
                 None => {
                     errors.type_not_found.push(TypeNotFound { on_function: on_function.to_string(), type_name: typedef.clone() });
                     None
@@ -38,7 +44,29 @@ pub fn instantiate_type(on_function: &str, type_db: &TypeDatabase, typedef: &HIR
             let base_type_record = type_db.find_by_name(type_name);
             let mut resolved_args = vec![];
 
+            //consts can be used as the length too: array<i32, MAX>
+            let args = args.iter().map(|arg| match arg {
+                HIRType::Simple(name) => match type_db.find_constant(name) {
+                    Some(TypedTrivialHIRExpr(TrivialHIRExpr::IntegerValue(length), _)) if u32::try_from(*length).is_ok() => HIRType::Const(*length as u32),
+                    _ => arg.clone(),
+                },
+                _ => arg.clone(),
+            }).collect::<Vec<_>>();
+            //only arrays take a constant argument, as their fixed length: array<i32, 4>
+            let const_args = args.iter().filter(|arg| matches!(arg, HIRType::Const(_))).count();
+            let is_fixed_array = type_name == "array" && const_args == 1 && matches!(args.last(), Some(HIRType::Const(_)));
+            let type_args_count = if is_fixed_array { args.len() - 1 } else { args.len() };
+
             let actual_base_type = match base_type_record {
+                Some(found_type) if found_type.type_args.len() != type_args_count || (const_args > 0 && !is_fixed_array) => {
+                    errors.generic_argument_count.push(GenericArgumentCountMismatch {
+                        on_function: on_function.to_string(),
+                        type_name: type_name.to_string(),
+                        expected_count: found_type.type_args.len(),
+                        actual_count: args.len()
+                    });
+                    None
+                },
                 Some(found_type) => {
                     Some(found_type)
                 },
@@ -86,116 +114,9 @@ pub fn instantiate_type(on_function: &str, type_db: &TypeDatabase, typedef: &HIR
                 args_instances.iter().map(|x| x.clone().unwrap()).collect(), 
                 Box::new(return_type_instance.unwrap())))
         },
+        HIRType::Const(value) => Some(TypeInstance::Const(*value)),
     }
 }
-#[derive(Debug, Clone, PartialEq, Eq)]
-struct TypeResolution<'a> {
-    object_type_id: Option<TypeId>,
-    object_instance_generic_args: &'a [TypeInstance]
-}
-
-impl<'a> TypeResolution<'a> {
-    pub fn new(object_type_id: Option<TypeId>,
-        object_instance_generic_args: &'a [TypeInstance]) -> Self {
-            Self {
-                object_type_id, object_instance_generic_args
-            }
-        }
-}
-
-
-fn resolve_type<'a>(type_partially_filled: &Type, type_db: &TypeDatabase, type_resolution: TypeResolution<'a>) -> TypeInstance {
-    /*
-     We are continuing the resolution of a generic method call. 
-     Recall that type_partially_filled is named like that because the Type may still have unresolved generics.
-     Also, type_partially_filled is an element of a function signature (either a param, or a return type)
-     This is the case here: type_partially_filled is Type::Simple(Either::Left(GenericParameter("TItem")))
-    */
-
-     let type_instance: TypeInstance = match type_partially_filled {
-        Type::Simple(Either::Right(type_id)) => TypeInstance::Simple(*type_id),
-        Type::Simple(Either::Left(gen_param)) => {
-            /*
-            Finally we have gen_param, which will have a type called TItem.
-            It's a generic parameter, and we can't look it up in the type database.
-            It's a parameter we need to do substitution.
-            
-            We can either look in:
-             - The call site itself, which currently doesnt hold any type info, so it's not an option
-             - Inferred from arguments, which we currently don't have argument information... so we can't do that
-             - The struct type arguments, which are positional, so we can match it by position
-
-            We will do the 3rd option.
-
-            This is equivalent to checking the object type ID onto which we are calling the method.
-            Recall:
-                        fn(u32) -> TItem    
-                        vvvvvvvv  
-                [1,2,3].__index__(0)
-                ^^^^^^^
-               array<TItem>  
-            
-            We already determined in a previous step that the array is typed as array<i32>.
-            */
- 
-            //So first let's get the array<TItem> type data
-            let type_data = type_db.find(type_resolution.object_type_id.unwrap());
-
-            /*
-            
-            Now we have type_data.type_args, which will be &[GenericParameter("TItem")]
-            
-            Recall the gen_param in this match guard:
-            Type::Simple(Either::Left(gen_param))
-            Scroll the code back to the pattern match and re-read the first comment in this function.
-            If you don't understand, recall: we are matching on an element of the function signature:
-
-                fn __index__(at: u32) -> TItem
-
-            And in this example we are talking about the return type, TItem.
-            So gen__param is &GenericParameter("TItem")    
-
-            The question is: What is TItem?
-
-            The parameter struct_instance_generic_args will contain the positional arguments 
-            in the declaration of array<TItem>. If we have 
-            x = [1,2,3]
-            then typeof(x) = array<i32>, and struct_instance_generic_args will be [TypeInstance::Simple(i32)]
-            
-            Then, what's the index of the TItem parameter? 
-            */
-
-            let index_of = type_data.type_args.iter().position(|p| *p == *gen_param).unwrap(); 
-            
-            //It will be 0, so we return the 0th value of [TypeInstance::Simple(i32)]. Type is i32. 
-            return type_resolution.object_instance_generic_args.get(index_of).unwrap().clone();
-        },
-        Type::Generic(type_id, type_args) => {
-            let all_args_resolved = type_args.iter().map(|type_arg| 
-                resolve_type(type_arg, type_db, 
-                TypeResolution::new(Some(*type_id), type_resolution.object_instance_generic_args)))
-                .collect::<Vec<_>>();
-            
-            return TypeInstance::Generic(*type_id, all_args_resolved);
-        },
-        Type::Function(fun_arg_types, return_type) => {
-            let all_args_resolved = fun_arg_types.iter().map(|type_arg| 
-                resolve_type(
-                    type_arg,
-                    type_db, 
-                    type_resolution.clone())).collect::<Vec<_>>();
-            
-            let return_type_resolved = resolve_type(
-                &return_type,
-                type_db, 
-                type_resolution);
-            
-            return TypeInstance::Function(all_args_resolved, Box::new(return_type_resolved));
-        },
-    };
-
-    return type_instance;
-}
 
 fn resolve_function_signature(type_db: &TypeDatabase, signature: FunctionSignature, generics: &[TypeInstance]) -> (Vec<TypeInstance>, TypeInstance) {
     //if function signature has type parameters
@@ -228,12 +149,170 @@ fn make_resolved_or_unresolved_typedef(original_expr: &HIRType, instanced: &Opti
     }
 }
 
+//Returns false (and reports it) when a Void value is used where a value is required
+fn check_not_void(on_function: &str, type_db: &TypeDatabase, typ: Option<&TypeInstance>, context: ValueContext, errors: &mut TypeErrors) -> bool {
+    if typ == Some(&type_db.special_types.void) {
+        errors.void_used_as_value.push(VoidUsedAsValue {
+            on_function: on_function.to_string(),
+            context
+        });
+        return false;
+    }
+    true
+}
+
+//arr[5] is lowered to $0 = arr.__index__ followed by $0(5), so the receiver is found in the preceding declarations
+fn check_fixed_array_bounds(on_function: &str, type_db: &TypeDatabase, preceding: &[HIR], expression: &HIRExpr, errors: &mut TypeErrors) {
+    let HIRExpr::FunctionCall(TypedTrivialHIRExpr(TrivialHIRExpr::Variable(callee), _), args, ..) = expression else {
+        return;
+    };
+    let [TypedTrivialHIRExpr(TrivialHIRExpr::IntegerValue(index), _)] = args.as_slice() else {
+        return;
+    };
+    let receiver = preceding.iter().rev().find_map(|node| match node {
+        HIR::Declare { var, expression: HIRExpr::MemberAccess(obj, method, ..), .. } if var == callee && method == "__index__" => Some(&obj.1),
+        _ => None
+    });
+    let Some(HIRTypeDef::Resolved(receiver_type)) = receiver else {
+        return;
+    };
+    let Some(length) = type_db.fixed_array_length(receiver_type) else {
+        return;
+    };
+    if *index < 0 || *index >= length as i128 {
+        errors.index_out_of_bounds.push(IndexOutOfBounds {
+            on_function: on_function.to_string(),
+            index: *index,
+            length
+        });
+    }
+}
+
 //maybe add a type hint here for empty arrays in assigns
+
+//binds the type parameters found in the parameter type to the matching parts of the argument type,
+//i.e. array<T> against array<i32> binds T to i32. Returns false if the shapes don't match.
+fn unify_type_parameters(type_db: &TypeDatabase, param: &HIRType, arg: &TypeInstance, type_params: &[String], bindings: &mut HashMap<String, TypeInstance>) -> bool {
+    match (param, arg) {
+        (HIRType::Simple(name), _) if type_params.contains(name) => match bindings.get(name) {
+            Some(bound) => bound == arg,
+            None => {
+                bindings.insert(name.clone(), arg.clone());
+                true
+            }
+        },
+        (HIRType::Generic(name, params), TypeInstance::Generic(id, args)) => {
+            type_db.find(*id).name == *name && params.len() == args.len()
+                && params.iter().zip(args.iter()).all(|(p, a)| unify_type_parameters(type_db, p, a, type_params, bindings))
+        }
+        (HIRType::Function(params, param_return), TypeInstance::Function(args, arg_return)) => {
+            params.len() == args.len()
+                && params.iter().zip(args.iter()).all(|(p, a)| unify_type_parameters(type_db, p, a, type_params, bindings))
+                && unify_type_parameters(type_db, param_return, arg_return, type_params, bindings)
+        }
+        (HIRType::Const(length), TypeInstance::Const(arg_length)) => length == arg_length,
+        _ => instantiate_type("", type_db, param, &mut TypeErrors::new()).as_ref() == Some(arg),
+    }
+}
+
+fn substitute_type_parameters(type_db: &TypeDatabase, typ: &HIRType, bindings: &HashMap<String, TypeInstance>) -> Option<TypeInstance> {
+    match typ {
+        HIRType::Simple(name) if bindings.contains_key(name) => bindings.get(name).cloned(),
+        HIRType::Generic(name, args) => {
+            let id = type_db.find_by_name(name)?.id;
+            let args = args.iter().map(|arg| substitute_type_parameters(type_db, arg, bindings)).collect::<Option<Vec<_>>>()?;
+            Some(TypeInstance::Generic(id, args))
+        }
+        HIRType::Function(args, return_type) => {
+            let args = args.iter().map(|arg| substitute_type_parameters(type_db, arg, bindings)).collect::<Option<Vec<_>>>()?;
+            let return_type = substitute_type_parameters(type_db, return_type, bindings)?;
+            Some(TypeInstance::Function(args, Box::new(return_type)))
+        }
+        _ => instantiate_type("", type_db, typ, &mut TypeErrors::new()),
+    }
+}
+
+//the concrete parameter and return types of a generic function for the given argument types, None if they don't fit
+fn instantiate_generic_call(type_db: &TypeDatabase, params: &[HIRType], return_type: &HIRType, type_params: &[String], arg_types: &[Option<TypeInstance>]) -> Option<(Vec<TypeInstance>, TypeInstance)> {
+    if params.len() != arg_types.len() {
+        return None;
+    }
+    let mut bindings = HashMap::new();
+    for (param, arg) in params.iter().zip(arg_types.iter()) {
+        if !unify_type_parameters(type_db, param, arg.as_ref()?, type_params, &mut bindings) {
+            return None;
+        }
+    }
+    let args = params.iter().map(|param| substitute_type_parameters(type_db, param, &bindings)).collect::<Option<Vec<_>>>()?;
+    let return_type = substitute_type_parameters(type_db, return_type, &bindings)?;
+    Some((args, return_type))
+}
+
+//HIR reduction leaves only trivial arguments in calls, but any expression is accepted here,
+//i.e. a nested call passed directly as an argument is inferred to its return type
+pub fn infer_call_arguments(on_function: &str, type_db: &TypeDatabase, decls_in_scope: &NameRegistry, args: &[HIRExpr], param_hints: &[TypeInstance], errors: &mut TypeErrors) -> Vec<(HIRExpr, Option<TypeInstance>)> {
+    args.iter().enumerate().map(|(i, arg)| {
+        let (arg_expr, arg_type) = compute_and_infer_expr_type(
+            on_function, type_db, decls_in_scope, arg, param_hints.get(i).cloned(), errors);
+        check_not_void(on_function, type_db, arg_type.as_ref(), ValueContext::FunctionArgument, errors);
+        (arg_expr, arg_type)
+    }).collect()
+}
+
+//Infers the type of a single expression against an existing scope, for tooling like a REPL or hovers.
+//Parsing and type errors are returned as the printed diagnostic.
+pub fn infer_expression_type(source: &str, type_db: &TypeDatabase, scope: &NameRegistry) -> Result<TypeInstance, String> {
+    let tokens = crate::ast::lexer::tokenize(source)?;
+    let expr = Parser::new(tokens).parse_expr().map_err(|e| format!("{:?}", e))?.resulting_expr;
+
+    //lowered like the right side of an assignment, so subexpressions become intermediaries declared before it
+    let mut hir = vec![];
+    ast_to_hir(&AST::Assign { path: vec!["$result".into()], expression: expr }, 0, &mut hir);
+
+    let mut scope = scope.new_scope();
+    let mut errors = TypeErrors::new();
+    let mut result = None;
+    for node in hir.iter() {
+        let (var, expression) = match node {
+            HIR::Declare { var, expression, .. } => (var, expression),
+            HIR::Assign { path, expression, .. } => (&path[0], expression),
+            _ => panic!("Unexpected node when lowering expression: {:?}", node),
+        };
+        let (_, typ) = compute_and_infer_expr_type("<expr>", type_db, &scope, expression, None, &mut errors);
+        let Some(typ) = typ else {
+            break;
+        };
+        scope.insert(var.clone(), HIRTypeDef::Resolved(typ.clone()));
+        result = Some(typ);
+    }
+
+    if !errors.is_empty() {
+        return Err(TypeErrorPrinter::new(&errors, type_db).to_string());
+    }
+    result.ok_or_else(|| format!("Could not infer the type of {}", source))
+}
+
 pub fn compute_and_infer_expr_type(on_function: &str, type_db: &TypeDatabase, decls_in_scope: &NameRegistry, expression: &HIRExpr, type_hint: Option<TypeInstance>, errors: &mut TypeErrors) -> (HIRExpr, Option<TypeInstance>) {
     match expression {
         HIRExpr::Trivial(TypedTrivialHIRExpr(TrivialHIRExpr::Variable(var), _), meta) => {
-            match decls_in_scope.get(&var) {
-                HIRTypeDef::PendingInference => panic!("Expr type inference bug: tried to resolve a type of variable {} in expression, but variable still needs type inference. If the variable was declared before, it should have been inferred before.", &var),
+            let Some(typedef) = decls_in_scope.get(&var) else {
+                //i.e. a struct name used as a value, undeclared_vars lets struct names through
+                errors.names_not_in_scope.push(NameNotInScope {
+                    on_function: on_function.to_string(),
+                    name: var.clone()
+                });
+                return (expression.clone(), None);
+            };
+            match typedef {
+                //the declaration was visited, but inference failed on it
+                HIRTypeDef::PendingInference if decls_in_scope.inference_failed(&var) => {
+                    errors.variable_types_unknown.push(VariableTypeUnknown {
+                        on_function: on_function.to_string(),
+                        variable_name: var.clone()
+                    });
+                    (expression.clone(), None)
+                },
+                HIRTypeDef::PendingInference => panic!("Expr type inference bug: in function {on_function}, tried to resolve the type of variable {var}, but it still needs type inference. Declarations are inferred in order, so it should have been inferred or reported as failed before."),
                 HIRTypeDef::Unresolved(mir_type) => {
                     let instantiated_type = instantiate_type(on_function, type_db, &mir_type, errors);
                     
@@ -259,6 +338,26 @@ pub fn compute_and_infer_expr_type(on_function: &str, type_db: &TypeDatabase, de
             }
         }
         HIRExpr::Trivial(trivial_expr, meta) => {
+            //integer literals take the declared integer type when there is one, so x: i64 = 70000 is an i64 literal
+            if let (TrivialHIRExpr::IntegerValue(_), Some(hint @ TypeInstance::Simple(hint_id))) = (&trivial_expr.0, &type_hint) {
+                if type_db.find(*hint_id).is_integer(type_db) {
+                    let expr = HIRExpr::Trivial(TypedTrivialHIRExpr(
+                        trivial_expr.0.clone(),
+                        HIRTypeDef::Resolved(hint.clone())
+                    ), meta.clone());
+                    return (expr, Some(hint.clone()));
+                }
+            }
+            //None has no type on its own, it takes the declared Option type
+            if let (TrivialHIRExpr::None, Some(hint @ TypeInstance::Generic(hint_id, _))) = (&trivial_expr.0, &type_hint) {
+                if type_db.find(*hint_id).name == "Option" {
+                    let expr = HIRExpr::Trivial(TypedTrivialHIRExpr(
+                        trivial_expr.0.clone(),
+                        HIRTypeDef::Resolved(hint.clone())
+                    ), meta.clone());
+                    return (expr, Some(hint.clone()));
+                }
+            }
             //@TODO maybe use a type hint here to resolve to u32, u64, etc whenever needed, as in index accessors
             let typename = match trivial_expr.0 {
                 TrivialHIRExpr::IntegerValue(_) => "i32",
@@ -277,12 +376,43 @@ pub fn compute_and_infer_expr_type(on_function: &str, type_db: &TypeDatabase, de
 
             (expr, Some(type_instance))
         }
+        HIRExpr::Tuple(items, _, meta) => {
+            let typed_items = items.iter().map(|item| {
+                let (item_expr, _) = compute_and_infer_expr_type(
+                    on_function, type_db, decls_in_scope,
+                    &HIRExpr::Trivial(item.clone(), meta.clone()), None, errors);
+                item_expr.expect_trivial()
+            }).collect::<Vec<_>>();
+            (HIRExpr::Tuple(typed_items, HIRTypeDef::PendingInference, meta.clone()), None)
+        }
+        HIRExpr::SizeOf(typedef, _, meta) => {
+            //sizeof is always known at compile time, so it gets folded into a u32 literal right here
+            let Some(instance) = instantiate_type(on_function, type_db, &typedef.expect_unresolved(), errors) else {
+                return (expression.clone(), None);
+            };
+            let Some(size) = instance.size_in_bytes(type_db) else {
+                errors.unsized_types.push(UnsizedType {
+                    on_function: on_function.to_string(),
+                    type_def: instance
+                });
+                return (expression.clone(), None);
+            };
+            let u32_type = type_db.special_types.u32.clone();
+            let expr = HIRExpr::Trivial(TypedTrivialHIRExpr(
+                TrivialHIRExpr::IntegerValue(size as i128),
+                HIRTypeDef::Resolved(u32_type.clone())
+            ), meta.clone());
+
+            (expr, Some(u32_type))
+        }
         HIRExpr::BinaryOperation(lhs, op, rhs, _, meta) => {
             let (lhs_expr, lhs_type) = compute_and_infer_expr_type(on_function, type_db, decls_in_scope, &HIRExpr::Trivial(lhs.clone(), meta.clone()), None, errors);
             let (rhs_expr, rhs_type) = compute_and_infer_expr_type(on_function, type_db, decls_in_scope, &HIRExpr::Trivial(rhs.clone(), meta.clone()), None, errors);
             
             {
-                let mut type_error_found = false;
+                let lhs_not_void = check_not_void(on_function, type_db, lhs_type.as_ref(), ValueContext::Operand, errors);
+                let rhs_not_void = check_not_void(on_function, type_db, rhs_type.as_ref(), ValueContext::Operand, errors);
+                let mut type_error_found = !lhs_not_void || !rhs_not_void;
 
                 if let Some(function @ TypeInstance::Function(..)) = &lhs_type {
                     errors.unexpected_types.push(UnexpectedTypeFound { 
@@ -361,32 +491,74 @@ pub fn compute_and_infer_expr_type(on_function: &str, type_db: &TypeDatabase, de
         //no function polymorphism supported 
         HIRExpr::FunctionCall(fun_expr, fun_params, _, meta) => {
             let TrivialHIRExpr::Variable(var) = &fun_expr.0 else {
-                panic!("Functions should be bound to a name! This is a bug in the type inference phase or HIR expression reduction phase.");
+                panic!("Expr type inference bug: in function {on_function}, called {:?}, but functions should be bound to a name by the HIR expression reduction phase", fun_expr.0);
+            };
+
+            //calling a struct type, i.e. Point(1, 2), unless a variable shadows the struct name
+            if !decls_in_scope.contains(var) {
+                if let Some(TypeRecord { kind: TypeKind::Struct, .. }) = type_db.find_by_name(var) {
+                    errors.struct_called_as_function.push(StructCalledAsFunction {
+                        on_function: on_function.to_string(),
+                        struct_name: var.clone(),
+                    });
+                    return (expression.clone(), None);
+                }
+            }
+
+            //the parameter types are used as hints, so that arr[1] passes the literal as u32
+            let param_hints = match decls_in_scope.get(&var) {
+                Some(HIRTypeDef::Resolved(TypeInstance::Function(params, _))) => params.clone(),
+                _ => vec![]
             };
 
             //infer parameter types
-            let fun_params = fun_params.iter().map(|x| {
-                let (fun_p_expr, _) = compute_and_infer_expr_type(
-                    on_function, type_db, decls_in_scope, 
-                    &HIRExpr::Trivial(x.clone(), meta.clone()), None, errors);
-                let as_trivial = fun_p_expr.expect_trivial();
-                as_trivial
-            }).collect::<Vec<_>>();
+            let args = fun_params.iter().map(|x| HIRExpr::Trivial(x.clone(), meta.clone())).collect::<Vec<_>>();
+            let (fun_params, arg_types): (Vec<_>, Vec<_>) = infer_call_arguments(on_function, type_db, decls_in_scope, &args, &param_hints, errors)
+                .into_iter()
+                .map(|(fun_p_expr, fun_p_type)| (fun_p_expr.expect_trivial(), fun_p_type))
+                .unzip();
 
             //we have to find the function declaration
-            return match decls_in_scope.get(&var) {
+            let Some(function_typedef) = decls_in_scope.get(&var) else {
+                errors.names_not_in_scope.push(NameNotInScope {
+                    on_function: on_function.to_string(),
+                    name: var.clone()
+                });
+                return (expression.clone(), None);
+            };
+            return match function_typedef {
                 HIRTypeDef::PendingInference => {
                     //previous type inference failed for this variable, just continue
                     return (expression.clone(), None);
                 },
-                HIRTypeDef::Unresolved(mir_type) => {
-                    /*match mir_type {
-                        HIRType::Function(_, return_type) => {
-                            instantiate_type(type_db, &return_type)
-                        },
-                        _ => panic!("Expr type inference bug: tried to find a function decl, found, but the returned type is not a function... type is {:?}", mir_type)
-                    }*/
-                    panic!("Expr type inference bug: Variable {var} still has unresolved type {mir_type:#?}")
+                HIRTypeDef::Unresolved(HIRType::Function(params, return_type)) if decls_in_scope.get_type_parameters(&var).is_some() => {
+                    let type_params = decls_in_scope.get_type_parameters(&var).unwrap();
+                    let Some((concrete_params, concrete_return)) = instantiate_generic_call(type_db, &params, &return_type, type_params, &arg_types) else {
+                        errors.generic_call_mismatches.push(GenericCallMismatch {
+                            on_function: on_function.to_string(),
+                            function_name: var.clone(),
+                            signature: HIRType::Function(params, return_type)
+                        });
+                        return (expression.clone(), None);
+                    };
+                    (HIRExpr::FunctionCall(
+                        TypedTrivialHIRExpr(
+                            TrivialHIRExpr::Variable(var.clone()),
+                            HIRTypeDef::Resolved(TypeInstance::Function(concrete_params, Box::new(concrete_return.clone())))
+                        ),
+                        fun_params,
+                        HIRTypeDef::Resolved(concrete_return.clone()),
+                        meta.clone()
+                    ), Some(concrete_return))
+                },
+                HIRTypeDef::Unresolved(_) => {
+                    //signatures are registered before any body is inferred, so this is a function
+                    //(possibly declared later) whose signature has errors of its own
+                    errors.unresolved_functions_called.push(UnresolvedFunctionCalled {
+                        on_function: on_function.to_string(),
+                        function_name: var.clone()
+                    });
+                    return (expression.clone(), None);
                 },
                 HIRTypeDef::Resolved(resolved) => match &resolved {
                     type_instance @ TypeInstance::Function(_params, return_type) => {
@@ -424,12 +596,15 @@ pub fn compute_and_infer_expr_type(on_function: &str, type_db: &TypeDatabase, de
             let (rhs_expr, rhs_type)  = compute_and_infer_expr_type(on_function, type_db, decls_in_scope, &HIRExpr::Trivial(rhs.clone(), meta.clone()), None, errors);
             
             //multiplying, subtracting, etc functions not supported... what does that even mean?
-            
-            if let Some(function @ TypeInstance::Function(..)) = rhs_type {
+            let rhs_not_void = check_not_void(on_function, type_db, rhs_type.as_ref(), ValueContext::Operand, errors);
+            let rhs_is_function = matches!(rhs_type, Some(TypeInstance::Function(..)));
+            if let Some(function @ TypeInstance::Function(..)) = &rhs_type {
                 errors.unexpected_types.push(UnexpectedTypeFound {
                     on_function: on_function.to_string(),
                     type_def: function.clone(),
                 });
+            }
+            if !rhs_not_void || rhs_is_function {
                 let expr = HIRExpr::UnaryExpression(
                     op.clone(),
                     rhs_expr.expect_trivial(),
@@ -512,42 +687,24 @@ pub fn compute_and_infer_expr_type(on_function: &str, type_db: &TypeDatabase, de
                     let (type_id, generics) = match &found_type_obj {
                         TypeInstance::Generic(type_id, generics) => (type_id, generics.clone()),
                         TypeInstance::Simple(type_id) => (type_id, vec![]),
-                        TypeInstance::Function(..) => panic!("Member access on functions isn't defined, maybe we could have cool things in the future, like some metaprogramming/run time type info stuff")
+                        TypeInstance::Function(..) => panic!("Member access on functions isn't defined, maybe we could have cool things in the future, like some metaprogramming/run time type info stuff"),
+                        TypeInstance::Const(_) => unreachable!("Constant generic arguments only appear inside generic types")
                     };
         
                     let type_data = type_db.find(type_id.clone()); 
+
+                    //the length of a fixed-size array is known at compile time
+                    if let (Some(length), "length") = (type_db.fixed_array_length(&found_type_obj), name.as_str()) {
+                        let u32_type = type_db.special_types.u32.clone();
+                        let expr = HIRExpr::Trivial(TypedTrivialHIRExpr(
+                            TrivialHIRExpr::IntegerValue(length as i128),
+                            HIRTypeDef::Resolved(u32_type.clone())
+                        ), meta.clone());
+                        return (expr, Some(u32_type));
+                    }
                             
-                    //we'll find the method call here by name
-                    let method = type_data.methods
-                        .iter()
-                        .find(|signature| signature.name == *name);
-                
-                    if let Some(signature) = method {
-                        //if function signature has type parameters
-                        //we have to replace them but for now forget about it
-                        //we don't have syntax to call functions with their own type params
-                        if signature.type_args.len() != 0 {
-                            panic!("Function type args not supported yet")
-                        }
-                       
-                        //Now we have to resolve each element in the type signature. 
-                        
-                        //Remember that &generics will contain an i32 if we have a __index__(u32): TItem call on arr<i32>
-                        //arg is a simple type
-                        let results = signature.args.iter().map(|arg| {
-                            return resolve_type(
-                                arg, 
-                                type_db, 
-                                TypeResolution::new(Some(type_id.clone()), &generics) );
-                        }).collect::<Vec<_>>();
-        
-                        //In this case, return_type is generic, specifically Type::Simple(Either::Left(GenericParam("TItem")))
-                        let return_type = resolve_type(
-                            &signature.return_type, //this will be  Type::Simple(Either::Left(GenericParam("TItem")))
-                            type_db, //just the type database
-                            TypeResolution::new(Some(type_id.clone()),&generics) //typeof array, and i32
-                        );
-        
+                    //we'll find the method call here by name, resolved against the receiver generics
+                    if let Some((results, return_type)) = type_db.resolve_method(&found_type_obj, name) {
                         let member_access_expr = HIRExpr::MemberAccess(
                             obj_expr.expect_trivial(),
                             name.clone(), 
@@ -555,7 +712,6 @@ pub fn compute_and_infer_expr_type(on_function: &str, type_db: &TypeDatabase, de
                             meta.clone()
                         );
         
-                        //Continue reading the comments on resolve_type.
                         return (member_access_expr, Some(TypeInstance::Function(results, Box::new(return_type))));
                     }
         
@@ -605,18 +761,38 @@ pub fn compute_and_infer_expr_type(on_function: &str, type_db: &TypeDatabase, de
 
             
             if array_items.len() > 0 {
+                //items take the element type of the declared array, so x: array<i64> = [1, 2] has i64 items
+                let item_hint = match &type_hint {
+                    Some(TypeInstance::Generic(hint_id, type_args)) if *hint_id == array_type.id => type_args.first().cloned(),
+                    _ => None
+                };
                 let items_typed = array_items.iter().map(|x| {
-                    let (expr, type_def) = compute_and_infer_expr_type(on_function, type_db, decls_in_scope, &HIRExpr::Trivial(x.clone(), meta.clone()), None, errors);
+                    let (expr, type_def) = compute_and_infer_expr_type(on_function, type_db, decls_in_scope, &HIRExpr::Trivial(x.clone(), meta.clone()), item_hint.clone(), errors);
                     return (expr.expect_trivial(), type_def)
                 }).collect::<Vec<_>>();
 
+                let all_items_not_void = items_typed.iter().fold(true, |acc, (_, typedef)| {
+                    check_not_void(on_function, type_db, typedef.as_ref(), ValueContext::ArrayItem, errors) && acc
+                });
+                if !all_items_not_void {
+                    return (expression.clone(), None);
+                }
+
                 let all_exprs = items_typed.iter().map(|(expr, _)| expr.clone()).collect::<Vec<_>>();
                 
                 let first_typed_item = items_typed.iter().find(|(expr, typedef)| typedef.is_some());
 
                 match first_typed_item {
                     Some((expr, first_item_type)) => {
-                        let array_type_generic_replaced = TypeInstance::Generic(array_type.id, vec![first_item_type.clone().unwrap()]);
+                        let mut array_type_generic_replaced = TypeInstance::Generic(array_type.id, vec![first_item_type.clone().unwrap()]);
+
+                        //a literal with as many items as the declared fixed-size array has its type
+                        if let Some(hint @ TypeInstance::Generic(_, type_args)) = &type_hint {
+                            let item_type_matches = type_args.first() == first_item_type.as_ref();
+                            if item_type_matches && type_db.fixed_array_length(hint) == Some(array_items.len() as u32) {
+                                array_type_generic_replaced = hint.clone();
+                            }
+                        }
 
                         return (HIRExpr::Array(all_exprs, HIRTypeDef::Resolved(array_type_generic_replaced.clone()), meta.clone()), Some(array_type_generic_replaced));
                     },
@@ -637,7 +813,62 @@ pub fn compute_and_infer_expr_type(on_function: &str, type_db: &TypeDatabase, de
 
       
         },
-        HIRExpr::Cast(..) => todo!("Casts haven't been figured out yet"),
+        //integer <-> float casts change the representation, float to integer truncates toward zero
+        HIRExpr::Cast(casted, target, meta) => {
+            let (casted_expr, casted_type) = compute_and_infer_expr_type(on_function, type_db, decls_in_scope, &HIRExpr::Trivial(casted.clone(), meta.clone()), None, errors);
+            let target_type = match target {
+                HIRTypeDef::Unresolved(typ) => instantiate_type(on_function, type_db, typ, errors),
+                HIRTypeDef::Resolved(typ) => Some(typ.clone()),
+                HIRTypeDef::PendingInference => None,
+            };
+            let (Some(casted_type), Some(target_type)) = (casted_type, target_type) else {
+                return (HIRExpr::Cast(casted_expr.expect_trivial(), target.clone(), meta.clone()), None);
+            };
+
+            let is_number = |typ: &TypeInstance| match typ {
+                TypeInstance::Simple(id) => {
+                    let record = type_db.find(*id);
+                    record.is_integer(type_db) || record.is_float(type_db)
+                }
+                _ => false
+            };
+            if !is_number(&casted_type) || !is_number(&target_type) {
+                errors.invalid_casts.push(InvalidCast {
+                    on_function: on_function.to_string(),
+                    from: casted_type,
+                    to: target_type
+                });
+                return (HIRExpr::Cast(casted_expr.expect_trivial(), target.clone(), meta.clone()), None);
+            }
+
+            let expr = HIRExpr::Cast(casted_expr.expect_trivial(), HIRTypeDef::Resolved(target_type.clone()), meta.clone());
+            (expr, Some(target_type))
+        }
+        //no conversion happens, so the source and target sizes must match exactly
+        HIRExpr::Reinterpret(value, target, meta) => {
+            let (value_expr, value_type) = compute_and_infer_expr_type(on_function, type_db, decls_in_scope, &HIRExpr::Trivial(value.clone(), meta.clone()), None, errors);
+            let target_type = match target {
+                HIRTypeDef::Unresolved(typ) => instantiate_type(on_function, type_db, typ, errors),
+                HIRTypeDef::Resolved(typ) => Some(typ.clone()),
+                HIRTypeDef::PendingInference => None,
+            };
+            let (Some(value_type), Some(target_type)) = (value_type, target_type) else {
+                return (HIRExpr::Reinterpret(value_expr.expect_trivial(), target.clone(), meta.clone()), None);
+            };
+
+            let value_size = value_type.size_in_bytes(type_db);
+            if value_size.is_none() || value_size != target_type.size_in_bytes(type_db) {
+                errors.reinterpret_size_mismatches.push(ReinterpretSizeMismatch {
+                    on_function: on_function.to_string(),
+                    from: value_type,
+                    to: target_type
+                });
+                return (HIRExpr::Reinterpret(value_expr.expect_trivial(), target.clone(), meta.clone()), None);
+            }
+
+            let expr = HIRExpr::Reinterpret(value_expr.expect_trivial(), HIRTypeDef::Resolved(target_type.clone()), meta.clone());
+            (expr, Some(target_type))
+        }
 
         
     }
@@ -666,6 +897,12 @@ fn infer_types_in_body(
                 };
 
                 let (typed_expr, typedef) = compute_and_infer_expr_type(on_function, type_db, &decls_in_scope, expression, hint.clone(), errors);
+                check_fixed_array_bounds(on_function, type_db, &new_mir, &typed_expr, errors);
+
+                //intermediaries hold the call result until the operation that uses it is checked
+                if !var.starts_with('$') {
+                    check_not_void(on_function, type_db, typedef.as_ref(), ValueContext::Assignment, errors);
+                }
                 
                 match &typedef {
                     Some(found_type) => {
@@ -675,6 +912,7 @@ fn infer_types_in_body(
                     None => {
                         //HIRExpr type is probably pending or unresolved                        
                         decls_in_scope.insert(var.clone(),  typed_expr.get_expr_type().clone());
+                        decls_in_scope.mark_inference_failed(var.clone());
                     }
                 }
 
@@ -693,7 +931,9 @@ fn infer_types_in_body(
             },
             HIR::Assign { path, expression, meta_ast, meta_expr } => {
 
-                let (typed_expr, _) = compute_and_infer_expr_type(on_function, type_db, &decls_in_scope, expression, None, errors);
+                let (typed_expr, typedef) = compute_and_infer_expr_type(on_function, type_db, &decls_in_scope, expression, None, errors);
+                check_fixed_array_bounds(on_function, type_db, &new_mir, &typed_expr, errors);
+                check_not_void(on_function, type_db, typedef.as_ref(), ValueContext::Assignment, errors);
 
                 HIR::Assign { 
                     path: path.clone(), 
@@ -702,24 +942,46 @@ fn infer_types_in_body(
                     meta_expr: meta_expr.clone()
                 }
             },
+            //inferred like a call in an expression, so the function is resolved the same way
             HIR::FunctionCall { function , args, meta } => {
-                HIR::FunctionCall { 
-                    function: function.clone(), 
-                    args: args.iter().map(|expr| {
-                        let (typed_expr, _) = compute_and_infer_expr_type(on_function, type_db, decls_in_scope, &HIRExpr::Trivial(expr.clone(), None), None, errors);
-                            typed_expr.expect_trivial()
-                        },
-                    ).collect::<Vec<_>>(),
-                    meta: meta.clone()
-                }
+                let meta_expr = match meta {
+                    Some(AST::StandaloneExpr(expr)) => Some(expr.clone()),
+                    _ => None
+                };
+                let call = HIRExpr::FunctionCall(function.clone(), args.clone(), HIRTypeDef::PendingInference, meta_expr);
+                let (typed_call, _) = compute_and_infer_expr_type(on_function, type_db, decls_in_scope, &call, None, errors);
+                let HIRExpr::FunctionCall(function, args, ..) = typed_call else {
+                    panic!("Type inference of a function call returned {:?}", typed_call);
+                };
+                HIR::FunctionCall { function, args, meta: meta.clone() }
             },
             HIR::If(condition, true_branch, false_branch, meta) => {
-                let true_branch_inferred = infer_types_in_body(on_function, type_db,  &mut decls_in_scope.clone(), true_branch, errors);
-                let false_branch_inferred = infer_types_in_body(on_function, type_db, &mut decls_in_scope.clone(),  false_branch, errors);
+                let true_branch_inferred = infer_types_in_body(on_function, type_db,  &mut decls_in_scope.new_scope(), true_branch, errors);
+                let false_branch_inferred = infer_types_in_body(on_function, type_db, &mut decls_in_scope.new_scope(), false_branch, errors);
                 let (condition_expr, _) = compute_and_infer_expr_type(on_function, type_db, decls_in_scope, &HIRExpr::Trivial(condition.clone(), None), None, errors);
                 HIR::If(condition_expr.expect_trivial(), true_branch_inferred, false_branch_inferred, meta.clone())
             },
+            HIR::While(condition, body, meta) => {
+                let (condition_expr, condition_type) = compute_and_infer_expr_type(on_function, type_db, decls_in_scope, &HIRExpr::Trivial(condition.clone(), None), None, errors);
+                if let Some(actual) = condition_type {
+                    if actual != type_db.special_types.bool {
+                        errors.non_bool_conditions.push(ConditionNotBool {
+                            on_function: on_function.to_string(),
+                            actual
+                        });
+                    }
+                }
+                let body_inferred = infer_types_in_body(on_function, type_db, &mut decls_in_scope.new_scope(), body, errors);
+                HIR::While(condition_expr.expect_trivial(), body_inferred, meta.clone())
+            },
             HIR::Return(expr, _, meta) => {
+                //there are no tuple types yet, so no function can return multiple values
+                if let HIRExpr::Tuple(values, ..) = expr {
+                    errors.multiple_return_values.push(MultipleReturnValues {
+                        on_function: on_function.to_string(),
+                        value_count: values.len()
+                    });
+                }
                 let (typed_expr, type_def) = compute_and_infer_expr_type(on_function, type_db, decls_in_scope, expr, None, errors);
                 
                 let hir_type_def = type_def.map_or_else(|| HIRTypeDef::PendingInference, |x| HIRTypeDef::Resolved(x)); 
@@ -734,6 +996,41 @@ fn infer_types_in_body(
 }
 
 
+//A bare return is only allowed on Void functions
+fn check_empty_returns(
+    on_function: &str,
+    type_db: &TypeDatabase,
+    return_type: &TypeInstance,
+    return_alias: &Option<String>,
+    body: &[HIR],
+    errors: &mut TypeErrors) {
+
+    if *return_type == type_db.special_types.void {
+        return;
+    }
+
+    for node in body {
+        match node {
+            HIR::EmptyReturn => {
+                errors.return_type_mismatches.push(TypeMismatch {
+                    on_function: on_function.to_string(),
+                    context: ReturnTypeContext(return_alias.clone()),
+                    expected: return_type.clone(),
+                    actual: type_db.special_types.void.clone()
+                });
+            },
+            HIR::If(_, true_branch, false_branch, _) => {
+                check_empty_returns(on_function, type_db, return_type, return_alias, true_branch, errors);
+                check_empty_returns(on_function, type_db, return_type, return_alias, false_branch, errors);
+            }
+            HIR::While(_, body, _) => {
+                check_empty_returns(on_function, type_db, return_type, return_alias, body, errors);
+            }
+            _ => {}
+        }
+    }
+}
+
 fn infer_variable_types_in_functions(
     type_db: &TypeDatabase,
     globals: &NameRegistry,
@@ -761,8 +1058,14 @@ fn infer_function_parameter_types_and_return(
     type_db: &TypeDatabase,
     parameters: &[HIRTypedBoundName], return_type: &HIRTypeDef, errors: &mut TypeErrors) -> (Vec<HIRTypedBoundName>, Option<TypeInstance>) {
     
-    let mut new_args = vec![];
+    let mut new_args: Vec<HIRTypedBoundName> = vec![];
     for node in parameters.iter() {
+        if new_args.iter().any(|arg| arg.name == node.name) {
+            errors.duplicate_parameters.push(DuplicateParameter {
+                on_function: on_function.to_string(),
+                parameter_name: node.name.clone()
+            });
+        }
         match &node.typename {
             HIRTypeDef::PendingInference => panic!("Function parameters cannot have type inference"),
             HIRTypeDef::Unresolved(mir_type) => {
@@ -807,64 +1110,393 @@ fn infer_function_parameter_types_and_return(
 
 
 
-pub fn infer_types(globals: &mut NameRegistry, type_db: &TypeDatabase, mir: Vec<HIR>, errors: &mut TypeErrors) -> Vec<HIR> {
+//Replaces alias names in the type by the types they alias, recursively.
+//Returns Err with the aliases visited when a cycle is found.
+fn expand_aliases(typ: &HIRType, declared: &[(&String, &HIRType)], visiting: &mut Vec<String>) -> Result<HIRType, Vec<String>> {
+    match typ {
+        HIRType::Simple(name) => {
+            let Some((_, target)) = declared.iter().find(|(alias, _)| *alias == name) else {
+                return Ok(typ.clone());
+            };
+            if visiting.contains(name) {
+                let mut path = visiting.clone();
+                path.push(name.clone());
+                return Err(path);
+            }
+            visiting.push(name.clone());
+            let expanded = expand_aliases(target, declared, visiting);
+            visiting.pop();
+            expanded
+        }
+        HIRType::Generic(name, args) => {
+            let args = args.iter().map(|x| expand_aliases(x, declared, visiting)).collect::<Result<Vec<_>, _>>()?;
+            Ok(HIRType::Generic(name.clone(), args))
+        }
+        HIRType::Function(args, return_type) => {
+            let args = args.iter().map(|x| expand_aliases(x, declared, visiting)).collect::<Result<Vec<_>, _>>()?;
+            let return_type = expand_aliases(return_type, declared, visiting)?;
+            Ok(HIRType::Function(args, Box::new(return_type)))
+        }
+        HIRType::Const(_) => Ok(typ.clone()),
+    }
+}
 
-    let mut new_mir = vec![];
+//Adds type aliases to the type database, each one already expanded to the type it ultimately refers to.
+//Aliases in a cycle are reported once and not registered.
+pub fn register_type_aliases(type_db: &mut TypeDatabase, mir: &[HIR], errors: &mut TypeErrors) {
+    let declared = mir.iter().filter_map(|node| match node {
+        HIR::TypeAlias { alias_name, target, .. } => Some((alias_name, target)),
+        _ => None
+    }).collect::<Vec<_>>();
 
-    for node in mir.iter() {
-        let result = match node {
-            HIR::DeclareFunction{ function_name, parameters, body, return_type, meta} => {
-                let (parameters_resolved, return_type_inferred) = infer_function_parameter_types_and_return(function_name, type_db, parameters, return_type, errors);
-            
-                let mut parameter_types = vec![];
-                let mut found_type_errors = return_type_inferred.is_none();
-                for f in parameters_resolved.iter() {
-                    match &f.typename {
-                        HIRTypeDef::Resolved(r) => parameter_types.push(r.clone()),
-                        HIRTypeDef::Unresolved(unresolved) => {
-                            errors.type_not_found.push(TypeNotFound {
-                                on_function: function_name.to_string(),
-                                type_name: unresolved.clone()
-                            });
-                            found_type_errors = true;
-                        },
-                        HIRTypeDef::PendingInference => {
-                            panic!("Compiler bug: Pending type after inference, should at least be unresolved!");
-                        }
-                    }
+    let mut in_cycle: Vec<String> = vec![];
+
+    for (alias_name, _) in declared.iter() {
+        if in_cycle.contains(alias_name) {
+            continue;
+        }
+        match expand_aliases(&HIRType::Simple(alias_name.to_string()), &declared, &mut vec![]) {
+            Ok(expanded) => {
+                if let Some(instance) = instantiate_type(alias_name, type_db, &expanded, errors) {
+                    type_db.add_alias(alias_name, instance);
                 }
+            }
+            Err(path) => {
+                in_cycle.extend(path.iter().cloned());
+                errors.type_alias_cycles.push(TypeAliasCycle {
+                    alias_name: alias_name.to_string(),
+                    path
+                });
+            }
+        }
+    }
+}
 
-                if found_type_errors {
+//Adds user-declared structs to the type database, so that they can be used in type annotations, sizeof, etc.
+//The struct size is the sum of the sizes of its fields, no padding for now.
+//fields and methods of generic structs keep their type parameters, i.e. contents: array<T> in box<T>,
+//they are substituted by the receiver's type args when the member is accessed
+fn struct_member_type(struct_name: &str, type_db: &TypeDatabase, typ: &HIRType, type_parameters: &[String], errors: &mut TypeErrors) -> Option<Type> {
+    fn mentions_type_parameter(typ: &HIRType, type_parameters: &[String]) -> bool {
+        match typ {
+            HIRType::Simple(name) => type_parameters.contains(name),
+            HIRType::Generic(_, args) => args.iter().any(|arg| mentions_type_parameter(arg, type_parameters)),
+            HIRType::Function(args, return_type) => args.iter().chain(std::iter::once(return_type.as_ref()))
+                .any(|arg| mentions_type_parameter(arg, type_parameters)),
+            HIRType::Const(_) => false,
+        }
+    }
+    if !mentions_type_parameter(typ, type_parameters) {
+        return instantiate_type(struct_name, type_db, typ, errors).map(|instance| instance.as_type());
+    }
+    match typ {
+        HIRType::Simple(name) => Some(Type::Simple(Either::Left(GenericParameter(name.clone())))),
+        HIRType::Generic(name, args) => {
+            let Some(base) = type_db.find_by_name(name) else {
+                errors.type_not_found.push(TypeNotFound { on_function: struct_name.to_string(), type_name: typ.clone() });
+                return None;
+            };
+            let args = args.iter()
+                .map(|arg| struct_member_type(struct_name, type_db, arg, type_parameters, errors))
+                .collect::<Option<Vec<_>>>()?;
+            Some(Type::Generic(base.id, args))
+        }
+        HIRType::Function(args, return_type) => {
+            let args = args.iter()
+                .map(|arg| struct_member_type(struct_name, type_db, arg, type_parameters, errors))
+                .collect::<Option<Vec<_>>>()?;
+            let return_type = struct_member_type(struct_name, type_db, return_type, type_parameters, errors)?;
+            Some(Type::Function(args, Box::new(return_type)))
+        }
+        HIRType::Const(length) => Some(Type::Const(*length)),
+    }
+}
 
-                    let partial_solve = PartiallyResolvedFunctionSignature {
-                        args: parameters.iter().map(|x| x.typename.clone()).collect(),
-                        return_type: match return_type_inferred {
-                            Some(x) => HIRTypeDef::Resolved(x),
-                            None => return_type.clone()
-                        }
-                    };
+//Only the names, so that aliases and fields can refer to any struct, including the one being declared
+pub fn declare_struct_types(type_db: &mut TypeDatabase, mir: &[HIR]) {
+    for node in mir {
+        let HIR::StructDeclaration { struct_name, type_parameters, .. } = node else {
+            continue;
+        };
+        match type_parameters.as_slice() {
+            [] => type_db.declare_struct(struct_name),
+            params => type_db.declare_generic_struct(struct_name, params.iter().map(|p| GenericParameter(p.clone())).collect()),
+        };
+    }
+}
+
+pub fn register_struct_types(type_db: &mut TypeDatabase, mir: &[HIR], errors: &mut TypeErrors) {
+    let structs = mir.iter().filter_map(|node| match node {
+        HIR::StructDeclaration { struct_name, type_parameters, body, .. } => Some((struct_name, type_parameters, body)),
+        _ => None
+    }).collect::<Vec<_>>();
 
-                    globals.insert_partially_resolved_signature(function_name.clone(), partial_solve);
+    let ids = structs.iter()
+        .map(|(struct_name, _, _)| type_db.expect_find_by_name(struct_name).id)
+        .collect::<Vec<_>>();
+
+    for ((struct_name, type_parameters, body), id) in structs.iter().zip(ids.iter()) {
+        for field in body.iter() {
+            let Some(field_type) = struct_member_type(struct_name, type_db, &field.typename.expect_unresolved(), type_parameters, errors) else {
+                continue;
+            };
+            type_db.add_struct_field(*id, &field.name, field_type);
+        }
+    }
 
-                    node.clone()
-                } else {
+    for error in type_db.compute_struct_sizes(&ids) {
+        match error {
+            StructSizeError::ContainsItself { struct_id, field_name } => errors.recursive_structs.push(RecursiveStruct {
+                struct_name: type_db.get_name(struct_id).to_string(),
+                field_name
+            }),
+            StructSizeError::UnsizedField { struct_id, field_name } => errors.unsized_struct_fields.push(UnsizedStructField {
+                struct_name: type_db.get_name(struct_id).to_string(),
+                field_name
+            }),
+        }
+    }
+}
 
-                    //Allow calls from other functions and allow recursion
+//impl<U> box<U> may name its type parameters differently from struct box<T>, they are matched by position
+fn rename_type_parameters(typ: &HIRType, renames: &HashMap<String, String>) -> HIRType {
+    match typ {
+        HIRType::Simple(name) => HIRType::Simple(renames.get(name).unwrap_or(name).clone()),
+        HIRType::Generic(name, args) => HIRType::Generic(name.clone(), args.iter().map(|arg| rename_type_parameters(arg, renames)).collect()),
+        HIRType::Function(args, return_type) => HIRType::Function(
+            args.iter().map(|arg| rename_type_parameters(arg, renames)).collect(),
+            Box::new(rename_type_parameters(return_type, renames))
+        ),
+        HIRType::Const(length) => HIRType::Const(*length),
+    }
+}
+
+//Adds a placeholder type for each type parameter of generic functions and impls,
+//they are only found by name while inferring the body that declares them
+pub fn register_type_parameters(type_db: &mut TypeDatabase, mir: &[HIR]) {
+    for node in mir {
+        match node {
+            HIR::DeclareFunction { type_parameters, .. } => {
+                for name in type_parameters {
+                    type_db.add_type_parameter(name);
+                }
+            }
+            HIR::ImplBlock { type_parameters, methods, .. } => {
+                for name in type_parameters {
+                    type_db.add_type_parameter(name);
+                }
+                register_type_parameters(type_db, methods);
+            }
+            _ => {}
+        }
+    }
+}
+
+//Adds the methods of impl blocks to their types, without the self parameter, like the builtin methods of array
+pub fn register_impl_methods(type_db: &mut TypeDatabase, mir: &[HIR], errors: &mut TypeErrors) {
+    for node in mir {
+        let HIR::ImplBlock { type_parameters, target, methods, .. } = node else {
+            continue;
+        };
+        let (type_name, target_args) = match target {
+            HIRType::Generic(name, args) => (name, args.as_slice()),
+            HIRType::Simple(name) => (name, &[][..]),
+            _ => {
+                errors.invalid_impl_targets.push(InvalidImplTarget { target: target.clone() });
+                continue;
+            }
+        };
+        let Some(record) = type_db.find_by_name(type_name) else {
+            errors.type_not_found.push(TypeNotFound { on_function: format!("impl {}", target), type_name: target.clone() });
+            continue;
+        };
+        let type_id = record.id;
+        let renames = target_args.iter().zip(record.type_args.iter())
+            .filter_map(|(arg, param)| match arg {
+                HIRType::Simple(name) if type_parameters.contains(name) => Some((name.clone(), param.0.clone())),
+                _ => None
+            })
+            .collect::<HashMap<_, _>>();
+        let struct_type_parameters = renames.values().cloned().collect::<Vec<_>>();
+
+        for method in methods {
+            let HIR::DeclareFunction { function_name, parameters, return_type, .. } = method else {
+                continue;
+            };
+            let args = parameters.iter().skip(1)
+                .map(|param| struct_member_type(function_name, type_db, &rename_type_parameters(&param.typename.expect_unresolved(), &renames), &struct_type_parameters, errors))
+                .collect::<Option<Vec<_>>>();
+            let return_type = struct_member_type(function_name, type_db, &rename_type_parameters(&return_type.expect_unresolved(), &renames), &struct_type_parameters, errors);
+            let (Some(args), Some(return_type)) = (args, return_type) else {
+                continue;
+            };
+            let signature = FunctionSignature {
+                name: function_name.clone(),
+                type_args: vec![],
+                args,
+                return_type
+            };
+            if let Err(error) = type_db.try_add_method(type_id, signature) {
+                errors.invalid_special_methods.push(error);
+            }
+        }
+    }
+}
+
+//extern functions only have a signature, they are resolved before everything else so they can be called anywhere
+fn infer_extern_function_types(globals: &mut NameRegistry, type_db: &TypeDatabase, mir: Vec<HIR>, errors: &mut TypeErrors) -> Vec<HIR> {
+    mir.into_iter().map(|node| match node {
+        HIR::DeclareExternFunction { function_name, parameters, return_type, meta } => {
+            let (parameters_resolved, return_type_inferred) = infer_function_parameter_types_and_return(&function_name, type_db, &parameters, &return_type, errors);
+
+            let parameter_types = parameters_resolved.iter().filter_map(|param| match &param.typename {
+                HIRTypeDef::Resolved(r) => Some(r.clone()),
+                HIRTypeDef::Unresolved(unresolved) => {
+                    errors.type_not_found.push(TypeNotFound {
+                        on_function: function_name.to_string(),
+                        type_name: unresolved.clone()
+                    });
+                    None
+                },
+                HIRTypeDef::PendingInference => {
+                    panic!("Compiler bug: parameter {} of function {function_name} is pending after inference, should at least be unresolved!", param.name);
+                }
+            }).collect::<Vec<_>>();
+
+            match return_type_inferred {
+                Some(return_type_inferred) if parameter_types.len() == parameters.len() => {
                     globals.insert(function_name.clone(), HIRTypeDef::Resolved(
-                        TypeInstance::Function(parameter_types, Box::new(return_type_inferred.clone().unwrap()))
+                        TypeInstance::Function(parameter_types, Box::new(return_type_inferred.clone()))
                     ));
-
-                    let new_body = infer_variable_types_in_functions( type_db, globals, function_name, parameters, body, errors);
-                    HIR::DeclareFunction {
-                        function_name: function_name.clone(), 
-                        parameters: parameters_resolved, 
-                        body: new_body, 
-                        return_type: HIRTypeDef::Resolved(return_type_inferred.unwrap()) ,
-                        meta: meta.clone()
+                    HIR::DeclareExternFunction {
+                        function_name,
+                        parameters: parameters_resolved,
+                        return_type: HIRTypeDef::Resolved(return_type_inferred),
+                        meta
                     }
                 }
-               
+                _ => HIR::DeclareExternFunction { function_name, parameters, return_type, meta }
             }
+        }
+        other => other
+    }).collect()
+}
+
+//Registers the signatures of all functions before any body is inferred, so that a function can call
+//another one declared after it. Errors are reported later, when the function itself is inferred.
+fn register_function_signatures(globals: &mut NameRegistry, type_db: &TypeDatabase, mir: &[HIR]) {
+    for node in mir {
+        let HIR::DeclareFunction { function_name, type_parameters, parameters, return_type, .. } = node else {
+            continue;
+        };
+        //generic signatures stay unresolved, each call instantiates them
+        if !type_parameters.is_empty() {
+            continue;
+        }
+        let (parameters_resolved, return_type_inferred) = infer_function_parameter_types_and_return(
+            function_name, type_db, parameters, return_type, &mut TypeErrors::new());
+        let parameter_types = parameters_resolved.iter().filter_map(|param| match &param.typename {
+            HIRTypeDef::Resolved(r) => Some(r.clone()),
+            _ => None
+        }).collect::<Vec<_>>();
+
+        match return_type_inferred {
+            Some(return_type_inferred) if parameter_types.len() == parameters.len() => {
+                globals.insert(function_name.clone(), HIRTypeDef::Resolved(
+                    TypeInstance::Function(parameter_types, Box::new(return_type_inferred))
+                ));
+            }
+            _ => {}
+        }
+    }
+}
+
+//infers the parameters, return type and body of a function, and adds its signature to the globals
+fn infer_function_declaration(globals: &mut NameRegistry, type_db: &TypeDatabase, node: &HIR, errors: &mut TypeErrors) -> HIR {
+    let HIR::DeclareFunction { function_name, type_parameters, parameters, body, return_type, visibility, meta } = node else {
+        panic!("Expected a function declaration, got {:?}", node);
+    };
+    let (parameters_resolved, return_type_inferred) = infer_function_parameter_types_and_return(function_name, type_db, parameters, return_type, errors);
+
+    let mut parameter_types = vec![];
+    let mut found_type_errors = return_type_inferred.is_none();
+    for f in parameters_resolved.iter() {
+        match &f.typename {
+            HIRTypeDef::Resolved(r) => parameter_types.push(r.clone()),
+            HIRTypeDef::Unresolved(unresolved) => {
+                errors.type_not_found.push(TypeNotFound {
+                    on_function: function_name.to_string(),
+                    type_name: unresolved.clone()
+                });
+                found_type_errors = true;
+            },
+            HIRTypeDef::PendingInference => {
+                panic!("Compiler bug: parameter {} of function {function_name} is pending after inference, should at least be unresolved!", f.name);
+            }
+        }
+    }
+
+    if found_type_errors {
+
+        let partial_solve = PartiallyResolvedFunctionSignature {
+            args: parameters.iter().map(|x| x.typename.clone()).collect(),
+            return_type: match return_type_inferred {
+                Some(x) => HIRTypeDef::Resolved(x),
+                None => return_type.clone()
+            }
+        };
+
+        globals.insert_partially_resolved_signature(function_name.clone(), partial_solve);
+
+        node.clone()
+    } else {
+
+        //Allow calls from other functions and allow recursion
+        globals.insert(function_name.clone(), HIRTypeDef::Resolved(
+            TypeInstance::Function(parameter_types, Box::new(return_type_inferred.clone().unwrap()))
+        ));
+
+        let new_body = infer_variable_types_in_functions( type_db, globals, function_name, &parameters_resolved, body, errors);
+        let return_alias = match return_type {
+            HIRTypeDef::Unresolved(annotation) => type_db.alias_written_as(annotation),
+            _ => None
+        };
+        check_empty_returns(function_name, type_db, return_type_inferred.as_ref().unwrap(), &return_alias, &new_body, errors);
+        HIR::DeclareFunction {
+            function_name: function_name.clone(), 
+            type_parameters: type_parameters.clone(),
+            parameters: parameters_resolved, 
+            body: new_body, 
+            return_type: HIRTypeDef::Resolved(return_type_inferred.unwrap()) ,
+            visibility: *visibility,
+            meta: meta.clone()
+        }
+    }
+}
+
+pub fn infer_types(globals: &mut NameRegistry, type_db: &TypeDatabase, mir: Vec<HIR>, errors: &mut TypeErrors) -> Vec<HIR> {
+
+    let mir = infer_extern_function_types(globals, type_db, mir, errors);
+    register_function_signatures(globals, type_db, &mir);
+    let mut new_mir = vec![];
+
+    for node in mir.iter() {
+        let result = match node {
+            //the body is inferred with each type parameter standing for an unknown type, so errors in it are reported.
+            //the signature stays out of the globals, calls instantiate it with the types of their arguments
+            HIR::DeclareFunction { type_parameters, .. } if !type_parameters.is_empty() => {
+                let generic_db = type_db.with_type_parameters(type_parameters);
+                infer_function_declaration(&mut globals.new_scope(), &generic_db, node, errors)
+            }
+            HIR::DeclareFunction { .. } => infer_function_declaration(globals, type_db, node, errors),
+            //methods see the globals, but are not added to them. In generic impls, the type parameters
+            //stand for unknown types while the methods are inferred
+            HIR::ImplBlock { type_parameters, target, methods, meta } => HIR::ImplBlock {
+                type_parameters: type_parameters.clone(),
+                target: target.clone(),
+                methods: infer_types(&mut globals.new_scope(), &type_db.with_type_parameters(type_parameters), methods.clone(), errors),
+                meta: meta.clone()
+            },
             other => other.clone()
         };
         new_mir.push(result);
@@ -875,5 +1507,113 @@ pub fn infer_types(globals: &mut NameRegistry, type_db: &TypeDatabase, mir: Vec<
 } 
 
 
-//Why no tests?
-//This is actually tested in the file analysis.rs!
\ No newline at end of file
+//Most of this is tested end to end in the file analysis.rs,
+//the tests here cover what a program cannot reach.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic::name_registry::builtin_names;
+
+    #[test]
+    #[should_panic(expected = "Expr type inference bug: in function main, tried to resolve the type of variable x, but it still needs type inference")]
+    fn pending_variable_that_did_not_fail_is_internal_error() {
+        let type_db = TypeDatabase::new();
+        let mut decls_in_scope = NameRegistry::new();
+        decls_in_scope.insert("x".to_string(), HIRTypeDef::PendingInference);
+        let expression = HIRExpr::Trivial(
+            TypedTrivialHIRExpr(TrivialHIRExpr::Variable("x".to_string()), HIRTypeDef::PendingInference),
+            None,
+        );
+
+        compute_and_infer_expr_type("main", &type_db, &decls_in_scope, &expression, None, &mut TypeErrors::new());
+    }
+
+    #[test]
+    fn resolve_method_on_minimal_type_db() {
+        use crate::types::type_db::TypeSign;
+
+        let mut type_db = TypeDatabase::minimal();
+        let i32_type = type_db.register_primitive("i32", 4, TypeSign::Signed);
+        let point_type = type_db.register_struct("Point", &[
+            ("x", TypeInstance::Simple(i32_type)),
+            ("y", TypeInstance::Simple(i32_type)),
+        ]);
+        type_db.add_method(point_type, FunctionSignature {
+            name: "dot".into(),
+            type_args: vec![],
+            args: vec![Type::Simple(Either::Right(point_type))],
+            return_type: Type::Simple(Either::Right(i32_type)),
+        });
+
+        assert_eq!(type_db.types.len(), 2);
+        assert_eq!(type_db.find(point_type).size, 8);
+
+        let mut names = NameRegistry::new();
+        names.insert("p".into(), HIRTypeDef::Resolved(TypeInstance::Simple(point_type)));
+        let member_access = HIRExpr::MemberAccess(
+            TrivialHIRExpr::Variable("p".into()).pending_type(),
+            "dot".into(),
+            HIRTypeDef::PendingInference,
+            None
+        );
+
+        let mut errors = TypeErrors::new();
+        let (_, resolved) = compute_and_infer_expr_type(
+            "test", &type_db, &names, &member_access, None, &mut errors);
+
+        assert_eq!(errors.count(), 0);
+        assert_eq!(resolved.unwrap().as_string(&type_db), "fn (Point) -> i32");
+    }
+
+    #[test]
+    fn nested_call_as_argument_is_inferred() {
+        let type_db = TypeDatabase::new();
+        let i32_type = type_db.special_types.i32.clone();
+
+        let mut names = NameRegistry::new();
+        names.insert("double".into(), HIRTypeDef::Resolved(
+            TypeInstance::Function(vec![i32_type.clone()], Box::new(i32_type.clone()))));
+
+        //double(double(2)), without reducing the inner call to an intermediary
+        let inner_call = HIRExpr::FunctionCall(
+            TrivialHIRExpr::Variable("double".into()).pending_type(),
+            vec![TrivialHIRExpr::IntegerValue(2).pending_type()],
+            HIRTypeDef::PendingInference,
+            None
+        );
+
+        let mut errors = TypeErrors::new();
+        let inferred = infer_call_arguments(
+            "test", &type_db, &names, &[inner_call], &[i32_type.clone()], &mut errors);
+
+        assert_eq!(errors.count(), 0);
+        assert_eq!(inferred.len(), 1);
+        let (arg_expr, arg_type) = &inferred[0];
+        assert_eq!(arg_type.as_ref(), Some(&i32_type));
+        assert_eq!(arg_expr.get_expr_type(), &HIRTypeDef::Resolved(i32_type));
+    }
+
+    #[test]
+    fn infer_standalone_expression_type() {
+        let type_db = TypeDatabase::new();
+        let globals = builtin_names(&type_db);
+
+        let typ = infer_expression_type("1 + 2", &type_db, &globals).unwrap();
+        assert_eq!(typ.as_string(&type_db), "i32");
+    }
+
+    #[test]
+    fn infer_standalone_expression_type_from_scope() {
+        let type_db = TypeDatabase::new();
+        let mut scope = builtin_names(&type_db).new_scope();
+        let array_id = type_db.find_by_name("array").unwrap().id;
+        let str_type = type_db.find_by_name("str").unwrap().to_instance();
+        scope.insert("someArr".to_string(), HIRTypeDef::Resolved(TypeInstance::Generic(array_id, vec![str_type])));
+
+        let typ = infer_expression_type("someArr[0]", &type_db, &scope).unwrap();
+        assert_eq!(typ.as_string(&type_db), "str");
+
+        let error = infer_expression_type("someArr.missing", &type_db, &scope).unwrap_err();
+        assert_eq!(error, "In function <expr>, tried to access field/method missing on type array<str> but no such field or method exists.\n");
+    }
+}
@@ -4,6 +4,13 @@ use std::collections::HashSet;
 
 use super::name_registry::NameRegistry;
 
+//A local variable (or parameter) with the same name as a global or function.
+//The local is used for the rest of its scope, which is usually a mistake.
+pub struct ShadowsGlobal {
+    pub on_function: String,
+    pub variable_name: String,
+}
+
 fn check_trivial_expr(
     declarations_found: &HashSet<String>,
     function_name: &str,
@@ -28,8 +35,8 @@ fn check_expr(declarations_found: &HashSet<String>, function_name: &str, expr: &
             check_trivial_expr(declarations_found, function_name, lhs);
             check_trivial_expr(declarations_found, function_name, rhs);
         }
-        HIRExpr::FunctionCall(func_expr, args, ..) => {
-            check_trivial_expr(declarations_found, function_name, func_expr);
+        //callees are resolved by type inference, which reports unknown functions
+        HIRExpr::FunctionCall(_, args, ..) => {
             for fun_arg in args {
                 check_trivial_expr(declarations_found, function_name, fun_arg);
             }
@@ -40,28 +47,38 @@ fn check_expr(declarations_found: &HashSet<String>, function_name: &str, expr: &
         HIRExpr::MemberAccess(member_expr, ..) => {
             check_trivial_expr(declarations_found, function_name, member_expr);
         }
-        HIRExpr::Array(item_exprs, ..) => {
+        HIRExpr::Array(item_exprs, ..) | HIRExpr::Tuple(item_exprs, ..) => {
             for array_item in item_exprs {
                 check_trivial_expr(&declarations_found, function_name, array_item);
             }
         }
-        HIRExpr::Cast(expr, typedef, ..) => {
+        //the target type is resolved by type inference, which reports unknown type names
+        HIRExpr::Cast(expr, _, ..) | HIRExpr::Reinterpret(expr, _, ..) => {
             check_trivial_expr(&declarations_found, function_name, expr)
         }
+        HIRExpr::SizeOf(..) => {}
     }
 }
 
+//globals_not_shadowed contains the globals that weren't shadowed yet in the current scope
 fn detect_decl_errors_in_body(
     declarations_found: &mut HashSet<String>,
+    globals_not_shadowed: &mut HashSet<String>,
     function_name: &str,
     body: &[HIR],
+    warnings: &mut Vec<ShadowsGlobal>,
 ) {
     for node in body {
         match node {
             HIR::Declare {
                 var, expression, ..
             } => {
-                if declarations_found.contains(var) {
+                if globals_not_shadowed.remove(var) {
+                    warnings.push(ShadowsGlobal {
+                        on_function: function_name.to_string(),
+                        variable_name: var.clone(),
+                    });
+                } else if declarations_found.contains(var) {
                     panic!("Variable {} declared more than once", var);
                 }
                 declarations_found.insert(var.clone());
@@ -75,19 +92,12 @@ fn detect_decl_errors_in_body(
                 }
                 check_expr(&declarations_found, function_name, expression);
             }
-            HIR::FunctionCall { function, args,.. } => {
+            HIR::FunctionCall { function, args, .. } => {
                 check_expr(
                     &declarations_found,
                     function_name,
-                    &HIRExpr::Trivial(function.clone(), None),
+                    &HIRExpr::FunctionCall(function.clone(), args.clone(), HIRTypeDef::PendingInference, None),
                 );
-                for fun_arg in args {
-                    check_expr(
-                        &declarations_found,
-                        function_name,
-                        &HIRExpr::Trivial(fun_arg.clone(), None),
-                    );
-                }
             }
             HIR::Return(expr, ..) => {
                 check_expr(&declarations_found, function_name, expr);
@@ -96,13 +106,27 @@ fn detect_decl_errors_in_body(
                 //we clone the decls so that the scopes are different
                 detect_decl_errors_in_body(
                     &mut declarations_found.clone(),
+                    &mut globals_not_shadowed.clone(),
                     function_name,
                     &true_branch,
+                    warnings,
                 );
                 detect_decl_errors_in_body(
                     &mut declarations_found.clone(),
+                    &mut globals_not_shadowed.clone(),
                     function_name,
                     &false_branch,
+                    warnings,
+                );
+            }
+            HIR::While(condition, body, ..) => {
+                check_trivial_expr(&declarations_found, function_name, condition);
+                detect_decl_errors_in_body(
+                    &mut declarations_found.clone(),
+                    &mut globals_not_shadowed.clone(),
+                    function_name,
+                    &body,
+                    warnings,
                 );
             }
 
@@ -117,16 +141,34 @@ fn detect_declaration_errors_in_function(
     parameters: &[HIRTypedBoundName],
     body: &[HIR],
     return_type: &HIRTypeDef,
+    warnings: &mut Vec<ShadowsGlobal>,
 ) {
+    let mut globals_not_shadowed = declarations_found.clone();
     for p in parameters {
+        if globals_not_shadowed.remove(&p.name) {
+            warnings.push(ShadowsGlobal {
+                on_function: function_name.to_string(),
+                variable_name: p.name.clone(),
+            });
+        }
         declarations_found.insert(p.name.clone());
     }
 
-    detect_decl_errors_in_body(&mut declarations_found, function_name, body);
+    detect_decl_errors_in_body(
+        &mut declarations_found,
+        &mut globals_not_shadowed,
+        function_name,
+        body,
+        warnings,
+    );
 }
 
-pub fn detect_undeclared_vars_and_redeclarations(globals: &NameRegistry, mir: &[HIR]) {
+pub fn detect_undeclared_vars_and_redeclarations(
+    globals: &NameRegistry,
+    mir: &[HIR],
+) -> Vec<ShadowsGlobal> {
     let mut declarations_found = HashSet::<String>::new();
+    let mut warnings = vec![];
 
     for name in globals.get_names() {
         declarations_found.insert(name.to_string());
@@ -135,9 +177,16 @@ pub fn detect_undeclared_vars_and_redeclarations(globals: &NameRegistry, mir: &[
     //first collect all globals
     for node in mir.iter() {
         let result = match node {
-            HIR::DeclareFunction { function_name, .. } => {
+            HIR::DeclareFunction { function_name, .. } | HIR::DeclareExternFunction { function_name, .. } => {
                 declarations_found.insert(function_name.clone());
             }
+            //struct names are globals too, type inference reports misuses like calling them
+            HIR::StructDeclaration { struct_name, .. } => {
+                declarations_found.insert(struct_name.clone());
+            }
+            HIR::DeclareConst { var, .. } => {
+                declarations_found.insert(var.clone());
+            }
             _ => {}
         };
     }
@@ -158,9 +207,26 @@ pub fn detect_undeclared_vars_and_redeclarations(globals: &NameRegistry, mir: &[
                     parameters,
                     body,
                     return_type,
+                    &mut warnings,
                 );
             }
+            //methods are not globals, they are only reachable through their receiver
+            HIR::ImplBlock { methods, .. } => {
+                for method in methods {
+                    if let HIR::DeclareFunction { function_name, parameters, body, return_type, .. } = method {
+                        detect_declaration_errors_in_function(
+                            declarations_found.clone(),
+                            function_name,
+                            parameters,
+                            body,
+                            return_type,
+                            &mut warnings,
+                        );
+                    }
+                }
+            }
             _ => {}
         };
     }
+    return warnings;
 }
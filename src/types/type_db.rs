@@ -4,7 +4,9 @@ use either::*;
 
 use std::{
     any::Any,
+    cell::RefCell,
     collections::{HashMap, HashSet},
+    ops::Deref,
 };
 
 
@@ -15,6 +17,7 @@ pub enum TypeInstance {
     Generic(TypeId, Vec<TypeInstance>), //each TypeId in the vec is a type parameter used in this specific usage of the type, this is positional.
     //parameters, return type
     Function(Vec<TypeInstance>, Box<TypeInstance>), //In this case there is not even a base type like in generics, functions are functions
+    Const(u32), //constant generic argument, only valid inside Generic, i.e. the 4 in array<i32, 4>
 }
 
 impl TypeInstance {
@@ -23,6 +26,7 @@ impl TypeInstance {
             TypeInstance::Simple(id) => *id,
             TypeInstance::Generic(_, _) => panic!("Not a simple type"),
             TypeInstance::Function(_, _) => panic!("Not a simple type"),
+            TypeInstance::Const(_) => panic!("Not a simple type"),
         }
     }
     pub fn as_string(&self, type_db: &TypeDatabase) -> String {
@@ -46,6 +50,72 @@ impl TypeInstance {
                 let return_type_str = return_type.as_string(type_db);
                 format!("fn ({}) -> {}", args_str, return_type_str)
             }
+            TypeInstance::Const(value) => value.to_string(),
+        }
+    }
+
+    //Returns None for types that have no size, like Void, None and functions.
+    //Struct sizes are computed once when the struct is registered, so this never recurses.
+    pub fn size_in_bytes(&self, type_db: &TypeDatabase) -> Option<usize> {
+        self.size_in_bytes_visiting(type_db, &mut vec![])
+    }
+
+    //visiting holds the generic structs whose fields are being sized. Only a field declared as one of them is a cycle,
+    //box<box<i64>> is fine since the inner box comes from a type argument
+    fn size_in_bytes_visiting(&self, type_db: &TypeDatabase, visiting: &mut Vec<TypeId>) -> Option<usize> {
+        //fixed-size arrays hold their items inline, the others are a pointer and a length
+        if let (Some(length), TypeInstance::Generic(_, args)) = (type_db.fixed_array_length(self), self) {
+            return args[0].size_in_bytes_visiting(type_db, visiting).map(|item_size| item_size * length as usize);
+        }
+        match self {
+            //the record of a generic struct can't know the size of its type parameters, the instance does
+            TypeInstance::Generic(id, args) if type_db.sized_by_instance(*id) => {
+                let entered = !visiting.contains(id);
+                if entered {
+                    visiting.push(*id);
+                }
+                let size = type_db.find(*id).fields.iter()
+                    .map(|field| match &field.field_type {
+                        Type::Generic(field_id, _) if visiting.contains(field_id) => None,
+                        field_type => resolve_type(field_type, type_db, TypeResolution::new(Some(*id), args))
+                            .size_in_bytes_visiting(type_db, visiting)
+                    })
+                    .sum();
+                if entered {
+                    visiting.pop();
+                }
+                size
+            }
+            TypeInstance::Simple(id) | TypeInstance::Generic(id, _) => {
+                match type_db.find(*id).size {
+                    0 => None,
+                    size => Some(size)
+                }
+            }
+            TypeInstance::Function(_, _) | TypeInstance::Const(_) => None,
+        }
+    }
+
+    pub fn as_type(&self) -> Type {
+        match self {
+            TypeInstance::Simple(id) => Type::Simple(Either::Right(*id)),
+            TypeInstance::Generic(id, args) => Type::Generic(*id, args.iter().map(|x| x.as_type()).collect()),
+            TypeInstance::Function(args, return_type) => Type::Function(
+                args.iter().map(|x| x.as_type()).collect(),
+                Box::new(return_type.as_type())
+            ),
+            TypeInstance::Const(value) => Type::Const(*value),
+        }
+    }
+
+    //same as as_string, but mentions the alias the source annotation used, e.g. array<i32> (alias Ints),
+    //or the whole annotation when an alias is only part of it, e.g. array<i32> (written as array<Num>)
+    pub fn as_string_with_alias(&self, type_db: &TypeDatabase, written_as: Option<&str>) -> String {
+        let type_str = self.as_string(type_db);
+        match written_as {
+            Some(alias) if type_db.find_alias(alias) == Some(self) => format!("{} (alias {})", type_str, alias),
+            Some(written) if type_db.find_alias(written).is_none() => format!("{} (written as {})", type_str, written),
+            _ => type_str
         }
     }
 
@@ -53,10 +123,28 @@ impl TypeInstance {
         //for now we just compare by equality
         return self == other;
     }
+
+    //True if a generic type is used without all of its type arguments, like a bare array or array<> somewhere inside.
+    //A fully resolved type never contains one, so this should only be true if substitution has a bug.
+    pub fn contains_unresolved_generic(&self, type_db: &TypeDatabase) -> bool {
+        match self {
+            TypeInstance::Simple(id) => !type_db.find(*id).type_args.is_empty(),
+            TypeInstance::Generic(id, args) => {
+                let type_args = args.iter().filter(|arg| !matches!(arg, TypeInstance::Const(_))).count();
+                type_db.find(*id).type_args.len() != type_args
+                    || args.iter().any(|arg| arg.contains_unresolved_generic(type_db))
+            }
+            TypeInstance::Function(args, return_type) => {
+                args.iter().any(|arg| arg.contains_unresolved_generic(type_db))
+                    || return_type.contains_unresolved_generic(type_db)
+            }
+            TypeInstance::Const(_) => false,
+        }
+    }
 }
 
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct TypeId(pub usize);
 
 //Types arent simple, generic, function.... but rather primitive, struct and trait.
@@ -64,6 +152,8 @@ pub struct TypeId(pub usize);
 pub enum TypeKind {
     Primitive,
     Struct,
+    //T in def first<T> or impl<T> box<T>, stands for an unknown type while the generic body is inferred
+    TypeParameter,
 }
 
 //Whether a type is signed or unsigned
@@ -82,6 +172,22 @@ pub enum Type {
     Simple(Either<GenericParameter, TypeId>),
     Generic(TypeId, Vec<Type>), //on generics, the base root type has to be known
     Function(Vec<Type>, Box<Type>), //on functions, both return or args can use generics
+    Const(u32), //constant generic argument, i.e. the length of a fixed-size array
+}
+
+impl Type {
+    //True if any generic parameter (like TItem) still needs to be substituted
+    pub fn contains_generic_parameter(&self) -> bool {
+        match self {
+            Type::Simple(Either::Left(_)) => true,
+            Type::Simple(Either::Right(_)) => false,
+            Type::Generic(_, args) => args.iter().any(|arg| arg.contains_generic_parameter()),
+            Type::Function(args, return_type) => {
+                args.iter().any(|arg| arg.contains_generic_parameter()) || return_type.contains_generic_parameter()
+            }
+            Type::Const(_) => false,
+        }
+    }
 }
 
 //@TODO must implement a way to perform generic substitution on every type instance...
@@ -176,6 +282,51 @@ impl Default for TypeRecord {
     }
 }
 
+//One operator registered on a type, with types already rendered as strings. rhs is None for unary operators.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperatorEntry {
+    pub lhs: String,
+    pub operator: Operator,
+    pub rhs: Option<String>,
+    pub result: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StructSizeError {
+    //like struct Node: next: Node, which would need infinite space
+    ContainsItself { struct_id: TypeId, field_name: String },
+    //a field whose type has no size, like Void
+    UnsizedField { struct_id: TypeId, field_name: String },
+}
+
+//special methods are called by lowerings like arr[i] -> arr.__index__(i), so their shape is fixed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidSpecialMethod {
+    pub type_name: String,
+    pub method_name: String,
+    pub expected: &'static str,
+}
+
+impl std::fmt::Display for InvalidSpecialMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Special method {}.{} has the wrong signature, expected {}",
+            self.type_name, self.method_name, self.expected
+        )
+    }
+}
+
+impl std::fmt::Display for OperatorEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let operator = crate::semantic::hir_printer::operator_str(self.operator);
+        match &self.rhs {
+            Some(rhs) => write!(f, "{} {} {} -> {}", self.lhs, operator, rhs, self.result),
+            None => write!(f, "{}{} -> {}", operator, self.lhs, self.result),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SpecialTypes {
     pub void: TypeInstance,
@@ -191,12 +342,49 @@ pub struct SpecialTypes {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TypeDatabase {
     pub types: Vec<TypeRecord>,
-    pub special_types: SpecialTypes
+    pub special_types: SpecialTypes,
+    //alias name, aliased type (already expanded)
+    pub aliases: Vec<(String, TypeInstance)>,
+    //const name, folded value typed with the declared type
+    pub constants: Vec<(String, TypedTrivialHIRExpr)>,
+    //type parameters that can be found by name, only set while inferring a generic body
+    pub type_parameters_in_scope: RefCell<Vec<String>>,
+}
+
+//Keeps type parameters in scope until dropped, derefs to the database they were put in scope on
+pub struct TypeParameterScope<'db> {
+    type_db: &'db TypeDatabase,
+    count: usize,
+}
+
+impl Deref for TypeParameterScope<'_> {
+    type Target = TypeDatabase;
+
+    fn deref(&self) -> &TypeDatabase {
+        self.type_db
+    }
+}
+
+impl Drop for TypeParameterScope<'_> {
+    fn drop(&mut self) {
+        let mut in_scope = self.type_db.type_parameters_in_scope.borrow_mut();
+        let outer_len = in_scope.len() - self.count;
+        in_scope.truncate(outer_len);
+    }
 }
 
 impl TypeDatabase {
+    //Type database with all builtin types (numbers, str, array, etc)
     pub fn new() -> Self {
-        let mut item = Self { types: vec![], special_types: SpecialTypes { 
+        let mut item = Self::minimal();
+        item.init_builtin();
+        return item;
+    }
+
+    //Empty type database, useful to build a focused type environment in tests.
+    //Special types point to TypeId(0) until the builtins are registered.
+    pub fn minimal() -> Self {
+        Self { types: vec![], special_types: SpecialTypes { 
             void: TypeInstance::Simple(TypeId(0)),
             i32: TypeInstance::Simple(TypeId(0)),
             i64: TypeInstance::Simple(TypeId(0)),
@@ -205,9 +393,149 @@ impl TypeDatabase {
             bool: TypeInstance::Simple(TypeId(0)),
             f32: TypeInstance::Simple(TypeId(0)),
             f64: TypeInstance::Simple(TypeId(0)),
-        }};
-        item.init_builtin();
-        return item;
+        }, aliases: vec![], constants: vec![], type_parameters_in_scope: RefCell::new(vec![])}
+    }
+
+    //Registers a primitive type without any operators
+    pub fn register_primitive(&mut self, name: &str, size: usize, sign: TypeSign) -> TypeId {
+        self.add(TypeKind::Primitive, sign, name, size)
+    }
+
+    //Registers a struct whose size is the sum of its fields' sizes
+    pub fn register_struct(&mut self, name: &str, fields: &[(&str, TypeInstance)]) -> TypeId {
+        let type_id = self.declare_struct(name);
+        for (field_name, field_type) in fields {
+            self.add_struct_field(type_id, field_name, field_type.as_type());
+        }
+        if let Some(error) = self.compute_struct_sizes(&[type_id]).first() {
+            panic!("Cannot register struct {}: {:?}", name, error);
+        }
+        type_id
+    }
+
+    //Adds a struct without fields, so that fields can refer to it (i.e. ptr<Node> inside Node).
+    //Call compute_struct_sizes after adding all fields.
+    pub fn declare_struct(&mut self, name: &str) -> TypeId {
+        self.add(TypeKind::Struct, TypeSign::Unsigned, name, 0)
+    }
+
+    //Same as declare_struct, for structs like box<T> whose fields refer to type parameters
+    pub fn declare_generic_struct(&mut self, name: &str, type_args: Vec<GenericParameter>) -> TypeId {
+        self.add_generic(TypeKind::Struct, name, type_args, 0)
+    }
+
+    //Returns the fields that prevented a struct from having a size, the struct is then left with size 0
+    pub fn compute_struct_sizes(&mut self, structs: &[TypeId]) -> Vec<StructSizeError> {
+        let mut memo = HashMap::new();
+        let mut errors = vec![];
+        for id in structs {
+            let size = self.compute_struct_size(*id, &mut memo, &mut HashSet::new(), &mut errors).unwrap_or(0);
+            self.types[id.0].size = size;
+        }
+        errors
+    }
+
+    //visiting holds the structs being computed: reaching one of them again means it contains itself by value.
+    //generic types like ptr<T> and array<T> have a fixed size, so T is never visited.
+    //Each error is reported once, on the field where it happens, not on every struct that contains it.
+    fn compute_struct_size(&self, id: TypeId, memo: &mut HashMap<TypeId, Option<usize>>, visiting: &mut HashSet<TypeId>, errors: &mut Vec<StructSizeError>) -> Option<usize> {
+        if let Some(size) = memo.get(&id) {
+            return *size;
+        }
+        let record = self.find(id);
+        if record.kind != TypeKind::Struct || record.fields.is_empty() {
+            return match record.size {
+                0 => None,
+                size => Some(size)
+            };
+        }
+        visiting.insert(id);
+        let mut size = Some(0);
+        for field in record.fields.iter() {
+            let field_size = match &field.field_type {
+                Type::Simple(Either::Right(field_id)) if visiting.contains(field_id) => {
+                    errors.push(StructSizeError::ContainsItself { struct_id: id, field_name: field.name.clone() });
+                    None
+                }
+                Type::Simple(Either::Right(field_id)) => {
+                    let field_size = self.compute_struct_size(*field_id, memo, visiting, errors);
+                    if field_size.is_none() && self.find(*field_id).kind != TypeKind::Struct {
+                        errors.push(StructSizeError::UnsizedField { struct_id: id, field_name: field.name.clone() });
+                    }
+                    field_size
+                }
+                Type::Generic(generic_id, args) => match args.as_slice() {
+                    //fixed-size arrays hold their items inline
+                    [Type::Simple(Either::Right(item_id)), Type::Const(_)] if visiting.contains(item_id) => {
+                        errors.push(StructSizeError::ContainsItself { struct_id: id, field_name: field.name.clone() });
+                        None
+                    }
+                    [Type::Simple(Either::Right(item_id)), Type::Const(length)] if self.find(*generic_id).name == "array" => {
+                        self.compute_struct_size(*item_id, memo, visiting, errors).map(|item_size| item_size * *length as usize)
+                    }
+                    _ if self.sized_by_instance(*generic_id) && visiting.contains(generic_id) => {
+                        errors.push(StructSizeError::ContainsItself { struct_id: id, field_name: field.name.clone() });
+                        None
+                    }
+                    //generic structs are sized once their type parameters are known
+                    _ if self.sized_by_instance(*generic_id) => match field.field_type.contains_generic_parameter() {
+                        true => Some(0),
+                        false => {
+                            let instance = resolve_type(&field.field_type, self, TypeResolution::new(None, &[]));
+                            self.compute_instance_size(&instance, (id, &field.name), memo, visiting, errors)
+                        }
+                    },
+                    _ => Some(self.find(*generic_id).size),
+                },
+                //the size of a type parameter is only known once the struct is instantiated
+                Type::Simple(Either::Left(_)) => Some(0),
+                Type::Function(..) | Type::Const(_) => Some(0),
+            };
+            size = match (size, field_size) {
+                (Some(size), Some(field_size)) => Some(size + field_size),
+                _ => None
+            };
+        }
+        visiting.remove(&id);
+        memo.insert(id, size);
+        size
+    }
+
+    //generic structs whose fields use their type parameters, like box<T>, only have a size once instantiated
+    fn sized_by_instance(&self, id: TypeId) -> bool {
+        let record = self.find(id);
+        record.kind == TypeKind::Struct && record.fields.iter().any(|field| field.field_type.contains_generic_parameter())
+    }
+
+    //like compute_struct_size, but for an instance of a generic struct, whose fields get the instance type arguments.
+    //owner is the struct and field the instance is in, where a type argument that contains the owner gets reported.
+    //A generic struct declaring a field of itself was already reported when its own size was computed
+    fn compute_instance_size(&self, instance: &TypeInstance, owner: (TypeId, &str), memo: &mut HashMap<TypeId, Option<usize>>, visiting: &mut HashSet<TypeId>, errors: &mut Vec<StructSizeError>) -> Option<usize> {
+        let (struct_id, field_name) = owner;
+        match instance {
+            TypeInstance::Simple(id) if visiting.contains(id) => {
+                errors.push(StructSizeError::ContainsItself { struct_id, field_name: field_name.to_string() });
+                None
+            }
+            TypeInstance::Simple(id) => self.compute_struct_size(*id, memo, visiting, errors),
+            TypeInstance::Generic(id, args) if self.sized_by_instance(*id) => {
+                let entered = visiting.insert(*id);
+                let size = self.find(*id).fields.iter()
+                    .map(|field| match &field.field_type {
+                        Type::Generic(field_id, _) if visiting.contains(field_id) => None,
+                        field_type => {
+                            let field_instance = resolve_type(field_type, self, TypeResolution::new(Some(*id), args));
+                            self.compute_instance_size(&field_instance, owner, memo, visiting, errors)
+                        }
+                    })
+                    .sum();
+                if entered {
+                    visiting.remove(id);
+                }
+                size
+            }
+            other => other.size_in_bytes(self),
+        }
     }
 
     pub fn add(&mut self, kind: TypeKind, sign: TypeSign, name: &str, size: usize) -> TypeId {
@@ -280,12 +608,130 @@ impl TypeDatabase {
             .expect(&format!("Type ID not found: {}", id.0))
     }
 
+    //All binary and unary operators, ordered by type registration and then operator registration
+    pub fn operator_table(&self) -> Vec<OperatorEntry> {
+        let mut table = vec![];
+        for record in self.types.iter() {
+            let lhs = record.to_instance().as_string(self);
+            for (operator, rhs, result) in record.rhs_binary_ops.iter() {
+                table.push(OperatorEntry {
+                    lhs: lhs.clone(),
+                    operator: *operator,
+                    rhs: Some(rhs.as_string(self)),
+                    result: result.as_string(self),
+                });
+            }
+            for (operator, result) in record.unary_ops.iter() {
+                table.push(OperatorEntry {
+                    lhs: lhs.clone(),
+                    operator: *operator,
+                    rhs: None,
+                    result: result.as_string(self),
+                });
+            }
+        }
+        table
+    }
+
+    //type parameters in scope shadow any other type with the same name
     pub fn find_by_name(&self, name: &str) -> Option<&TypeRecord> {
-        self.types.iter().find(|t| t.name == name)
+        let is_type_parameter = self.type_parameters_in_scope.borrow().iter().any(|p| p == name);
+        self.types.iter().find(|t| t.name == name && (t.kind == TypeKind::TypeParameter) == is_type_parameter)
+    }
+
+    //type parameters with the same name share a record, since only one of them can be in scope at a time
+    pub fn add_type_parameter(&mut self, name: &str) -> TypeId {
+        match self.types.iter().find(|t| t.name == name && t.kind == TypeKind::TypeParameter) {
+            Some(record) => record.id,
+            None => self.add(TypeKind::TypeParameter, TypeSign::Unsigned, name, 0),
+        }
+    }
+
+    //the given type parameters can be found by name until the scope is dropped, for inferring the body that declares them.
+    //They have to be added with add_type_parameter first.
+    pub fn with_type_parameters(&self, names: &[String]) -> TypeParameterScope<'_> {
+        self.type_parameters_in_scope.borrow_mut().extend(names.iter().cloned());
+        TypeParameterScope { type_db: self, count: names.len() }
+    }
+
+    //the length of array<TItem, N>, None for arrays without a fixed length and any other type
+    pub fn fixed_array_length(&self, typ: &TypeInstance) -> Option<u32> {
+        match typ {
+            TypeInstance::Generic(id, args) if self.find(*id).name == "array" => match args.as_slice() {
+                [_, TypeInstance::Const(length)] => Some(*length),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    //Finds a method on the receiver type and binds its signature to the receiver's generic arguments,
+    //i.e. __index__ on array<i32> returns ([u32], i32). Returns the argument types and the return type.
+    pub fn resolve_method(&self, receiver: &TypeInstance, name: &str) -> Option<(Vec<TypeInstance>, TypeInstance)> {
+        let (type_id, generics) = match receiver {
+            TypeInstance::Generic(type_id, generics) => (*type_id, generics.as_slice()),
+            TypeInstance::Simple(type_id) => (*type_id, &[][..]),
+            TypeInstance::Function(..) | TypeInstance::Const(_) => return None,
+        };
+
+        let signature = self.find(type_id).methods
+            .iter()
+            .find(|signature| signature.name == name)?;
+
+        //if function signature has type parameters
+        //we have to replace them but for now forget about it
+        //we don't have syntax to call functions with their own type params
+        if signature.type_args.len() != 0 {
+            panic!("Function type args not supported yet")
+        }
+
+        //Remember that generics will contain an i32 if we have a __index__(u32): TItem call on arr<i32>
+        let args = signature.args.iter().map(|arg| {
+            resolve_type(arg, self, TypeResolution::new(Some(type_id), generics))
+        }).collect::<Vec<_>>();
+
+        //In this case, return_type is generic, specifically Type::Simple(Either::Left(GenericParam("TItem")))
+        //Continue reading the comments on resolve_type.
+        let return_type = resolve_type(&signature.return_type, self, TypeResolution::new(Some(type_id), generics));
+
+        Some((args, return_type))
+    }
+
+    pub fn add_alias(&mut self, name: &str, aliased: TypeInstance) {
+        self.aliases.push((name.to_string(), aliased));
+    }
+
+    pub fn find_alias(&self, name: &str) -> Option<&TypeInstance> {
+        self.aliases.iter().find(|(alias, _)| alias == name).map(|(_, aliased)| aliased)
+    }
+
+    pub fn add_constant(&mut self, name: &str, value: TypedTrivialHIRExpr) {
+        self.constants.push((name.to_string(), value));
+    }
+
+    pub fn find_constant(&self, name: &str) -> Option<&TypedTrivialHIRExpr> {
+        self.constants.iter().find(|(constant, _)| constant == name).map(|(_, value)| value)
+    }
+
+    //how the annotation was written if it uses an alias anywhere, like Ints in x: Ints or array<Num> in x: array<Num>
+    pub fn alias_written_as(&self, annotation: &HIRType) -> Option<String> {
+        fn mentions_alias(type_db: &TypeDatabase, typ: &HIRType) -> bool {
+            match typ {
+                HIRType::Simple(name) => type_db.find_alias(name).is_some(),
+                HIRType::Generic(_, args) => args.iter().any(|arg| mentions_alias(type_db, arg)),
+                HIRType::Function(args, return_type) => args.iter().chain(std::iter::once(return_type.as_ref()))
+                    .any(|arg| mentions_alias(type_db, arg)),
+                HIRType::Const(_) => false,
+            }
+        }
+        match mentions_alias(self, annotation) {
+            true => Some(annotation.to_string()),
+            false => None
+        }
     }
 
     pub fn expect_find_by_name(&self, name: &str) -> &TypeRecord {
-        match self.types.iter().find(|t| t.name == name){
+        match self.find_by_name(name) {
             Some(r) => r,
             None => panic!("Could not find type by name {}", name)
         }
@@ -306,6 +752,7 @@ impl TypeDatabase {
             TypeInstance::Function(_, _) => {
                 panic!("Binary operations on functions are not supported")
             }
+            TypeInstance::Const(_) => panic!("Constant generic arguments are not values"),
         }
     }
 
@@ -324,6 +771,7 @@ impl TypeDatabase {
             TypeInstance::Function(_, _) => {
                 panic!("Unary operations on functions are not supported")
             }
+            TypeInstance::Const(_) => panic!("Constant generic arguments are not values"),
         }
     }
 
@@ -367,6 +815,14 @@ impl TypeDatabase {
             TypeInstance::Simple(type_id),
             TypeInstance::Simple(bool_id),
         );
+        for operator in [Operator::Less, Operator::LessEquals, Operator::Greater, Operator::GreaterEquals] {
+            self.add_binary_operator(
+                type_id,
+                operator,
+                TypeInstance::Simple(type_id),
+                TypeInstance::Simple(bool_id),
+            );
+        }
 
         self.add_unary_operator(type_id, Operator::Plus, TypeInstance::Simple(type_id));
         self.add_unary_operator(type_id, Operator::Minus, TypeInstance::Simple(type_id));
@@ -374,9 +830,56 @@ impl TypeDatabase {
         return type_id;
     }
 
+    //remainder, shifts and xor only make sense on integers
+    fn register_primitive_integer(&mut self, name: &str, size: usize, sign: TypeSign) -> TypeId {
+        let type_id = self.register_primitive_number(name, size, sign);
+        for operator in [Operator::Mod, Operator::BitShiftLeft, Operator::BitShiftRight, Operator::Xor] {
+            self.add_binary_operator(
+                type_id,
+                operator,
+                TypeInstance::Simple(type_id),
+                TypeInstance::Simple(type_id),
+            );
+        }
+        return type_id;
+    }
+
+    //builtin methods are known to be well formed, a malformed one is a bug in the compiler
     pub fn add_method(&mut self, type_id: TypeId, signature: FunctionSignature) {
+        if let Err(error) = self.try_add_method(type_id, signature) {
+            panic!("{error}");
+        }
+    }
+
+    pub fn try_add_method(&mut self, type_id: TypeId, signature: FunctionSignature) -> Result<(), InvalidSpecialMethod> {
+        self.check_special_method(type_id, &signature)?;
         let record = self.types.get_mut(type_id.0).unwrap();
-        record.methods.push(signature)
+        record.methods.push(signature);
+        Ok(())
+    }
+
+    fn check_special_method(&self, type_id: TypeId, signature: &FunctionSignature) -> Result<(), InvalidSpecialMethod> {
+        let (integer_args, expected) = match signature.name.as_str() {
+            "__index__" => (1, "one integer argument and a non-Void return type"),
+            "__slice__" => (2, "two integer arguments and a non-Void return type"),
+            _ => return Ok(()),
+        };
+        let is_integer = |typ: &Type| match typ {
+            Type::Simple(Either::Right(id)) => self.find(*id).is_integer(self),
+            _ => false,
+        };
+        let returns_value = match &signature.return_type {
+            Type::Simple(Either::Right(id)) => TypeInstance::Simple(*id) != self.special_types.void,
+            _ => true,
+        };
+        if signature.args.len() == integer_args && signature.args.iter().all(is_integer) && returns_value {
+            return Ok(());
+        }
+        Err(InvalidSpecialMethod {
+            type_name: self.find(type_id).name.clone(),
+            method_name: signature.name.clone(),
+            expected,
+        })
     }
 
     pub fn add_field(&mut self, type_id: TypeId, name: &str, field_type: TypeId) {
@@ -384,6 +887,11 @@ impl TypeDatabase {
         record.fields.push(TypeField { name: name.to_string(), field_type: Type::Simple(Either::Right(field_type)) })
     }
 
+    pub fn add_struct_field(&mut self, type_id: TypeId, name: &str, field_type: Type) {
+        let record = self.types.get_mut(type_id.0).unwrap();
+        record.fields.push(TypeField { name: name.to_string(), field_type })
+    }
+
     fn init_builtin(&mut self) {
         use std::mem;
 
@@ -391,27 +899,47 @@ impl TypeDatabase {
         self.special_types.void = TypeInstance::Simple(void_type);
 
         self.add(TypeKind::Primitive, TypeSign::Unsigned, "None", mem::size_of::<()>());
-        self.special_types.bool = TypeInstance::Simple(self.add(TypeKind::Primitive, TypeSign::Unsigned, "bool", mem::size_of::<bool>()));
+        let bool_type = self.add(TypeKind::Primitive, TypeSign::Unsigned, "bool", mem::size_of::<bool>());
+        self.special_types.bool = TypeInstance::Simple(bool_type);
+        //and/or are not short-circuiting, both sides are always evaluated
+        for operator in [Operator::And, Operator::Or] {
+            self.add_binary_operator(
+                bool_type,
+                operator,
+                TypeInstance::Simple(bool_type),
+                TypeInstance::Simple(bool_type),
+            );
+        }
 
-        let i32_type = self.register_primitive_number("i32", mem::size_of::<i32>(), TypeSign::Signed);
-        let u32_type = self.register_primitive_number("u32", mem::size_of::<u32>(), TypeSign::Unsigned);
+        let i32_type = self.register_primitive_integer("i32", mem::size_of::<i32>(), TypeSign::Signed);
+        let u32_type = self.register_primitive_integer("u32", mem::size_of::<u32>(), TypeSign::Unsigned);
         self.special_types.i32 = TypeInstance::Simple(i32_type);
         self.special_types.u32 = TypeInstance::Simple(u32_type);
 
 
-        self.special_types.i64 = TypeInstance::Simple(self.register_primitive_number("i64", mem::size_of::<i64>(), TypeSign::Signed));
-        self.special_types.u64 = TypeInstance::Simple(self.register_primitive_number("u64", mem::size_of::<u64>(), TypeSign::Unsigned));
+        self.special_types.i64 = TypeInstance::Simple(self.register_primitive_integer("i64", mem::size_of::<i64>(), TypeSign::Signed));
+        self.special_types.u64 = TypeInstance::Simple(self.register_primitive_integer("u64", mem::size_of::<u64>(), TypeSign::Unsigned));
         self.special_types.f32 = TypeInstance::Simple(self.register_primitive_number("f32", mem::size_of::<f32>(), TypeSign::Signed));
         self.special_types.f64 = TypeInstance::Simple(self.register_primitive_number("f64", mem::size_of::<f64>(), TypeSign::Signed));
+        //strings are indexed byte by byte, there is no dedicated char type
+        let u8_type = self.register_primitive_integer("u8", mem::size_of::<u8>(), TypeSign::Unsigned);
 
         //internal type for pointers, ptr<i32> points to a buffer of i32, and so on
-        self.add_generic(
+        let ptr_type = self.add_generic(
             TypeKind::Primitive,
             "ptr",
             vec![GenericParameter("TPtr".into())],
             mem::size_of::<usize>(),
         );
 
+        //a nullable pointer to the value, None is the null pointer
+        self.add_generic(
+            TypeKind::Struct,
+            "Option",
+            vec![GenericParameter("TValue".into())],
+            mem::size_of::<usize>(),
+        );
+
         //ptr + len
         let str_type = self.add(
             TypeKind::Struct,
@@ -428,6 +956,15 @@ impl TypeDatabase {
                 return_type: Type::Simple(Either::Right(i32_type)),
             },
         );
+        self.add_method(
+            str_type,
+            FunctionSignature {
+                name: "__index__".to_string(),
+                type_args: vec![],
+                args: vec![Type::Simple(Either::Right(u32_type))],
+                return_type: Type::Simple(Either::Right(u8_type)),
+            },
+        );
 
         //ptr + num items
         let arr_type = self.add_generic(
@@ -447,7 +984,263 @@ impl TypeDatabase {
             },
         );
 
+        //arr[start:end], returns a new array with the items in [start, end)
+        self.add_method(
+            arr_type,
+            FunctionSignature {
+                name: "__slice__".to_string(),
+                type_args: vec![],
+                args: vec![Type::Simple(Either::Right(u32_type)), Type::Simple(Either::Right(u32_type))],
+                return_type: Type::Generic(arr_type, vec![Type::Simple(Either::Left(GenericParameter("TItem".into())))]),
+            },
+        );
+
+        self.add_method(
+            arr_type,
+            FunctionSignature {
+                name: "push".to_string(),
+                type_args: vec![],
+                args: vec![Type::Simple(Either::Left(GenericParameter("TItem".into())))],
+                return_type: Type::Generic(arr_type, vec![Type::Simple(Either::Left(GenericParameter("TItem".into())))]),
+            },
+        );
+
+        //resolved against the receiver, so arr.items is a ptr<i32> when arr is an array<i32>
+        self.add_struct_field(
+            arr_type,
+            "items",
+            Type::Generic(ptr_type, vec![Type::Simple(Either::Left(GenericParameter("TItem".into())))]),
+        );
         //u32_type
         self.add_field(arr_type, "length", u32_type);
     }
 }
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeResolution<'a> {
+    pub object_type_id: Option<TypeId>,
+    pub object_instance_generic_args: &'a [TypeInstance]
+}
+
+impl<'a> TypeResolution<'a> {
+    pub fn new(object_type_id: Option<TypeId>,
+        object_instance_generic_args: &'a [TypeInstance]) -> Self {
+            Self {
+                object_type_id, object_instance_generic_args
+            }
+        }
+}
+
+
+pub fn resolve_type<'a>(type_partially_filled: &Type, type_db: &TypeDatabase, type_resolution: TypeResolution<'a>) -> TypeInstance {
+    /*
+     We are continuing the resolution of a generic method call. 
+     Recall that type_partially_filled is named like that because the Type may still have unresolved generics.
+     Also, type_partially_filled is an element of a function signature (either a param, or a return type)
+     This is the case here: type_partially_filled is Type::Simple(Either::Left(GenericParameter("TItem")))
+    */
+
+     let type_instance: TypeInstance = match type_partially_filled {
+        Type::Simple(Either::Right(type_id)) => TypeInstance::Simple(*type_id),
+        Type::Simple(Either::Left(gen_param)) => {
+            /*
+            Finally we have gen_param, which will have a type called TItem.
+            It's a generic parameter, and we can't look it up in the type database.
+            It's a parameter we need to do substitution.
+            
+            We can either look in:
+             - The call site itself, which currently doesnt hold any type info, so it's not an option
+             - Inferred from arguments, which we currently don't have argument information... so we can't do that
+             - The struct type arguments, which are positional, so we can match it by position
+
+            We will do the 3rd option.
+
+            This is equivalent to checking the object type ID onto which we are calling the method.
+            Recall:
+                        fn(u32) -> TItem    
+                        vvvvvvvv  
+                [1,2,3].__index__(0)
+                ^^^^^^^
+               array<TItem>  
+            
+            We already determined in a previous step that the array is typed as array<i32>.
+            */
+ 
+            //So first let's get the array<TItem> type data
+            let type_data = type_db.find(type_resolution.object_type_id.unwrap());
+
+            /*
+            
+            Now we have type_data.type_args, which will be &[GenericParameter("TItem")]
+            
+            Recall the gen_param in this match guard:
+            Type::Simple(Either::Left(gen_param))
+            Scroll the code back to the pattern match and re-read the first comment in this function.
+            If you don't understand, recall: we are matching on an element of the function signature:
+
+                fn __index__(at: u32) -> TItem
+
+            And in this example we are talking about the return type, TItem.
+            So gen__param is &GenericParameter("TItem")    
+
+            The question is: What is TItem?
+
+            The parameter struct_instance_generic_args will contain the positional arguments 
+            in the declaration of array<TItem>. If we have 
+            x = [1,2,3]
+            then typeof(x) = array<i32>, and struct_instance_generic_args will be [TypeInstance::Simple(i32)]
+            
+            Then, what's the index of the TItem parameter? 
+            */
+
+            let index_of = type_data.type_args.iter().position(|p| *p == *gen_param).unwrap(); 
+            
+            //It will be 0, so we return the 0th value of [TypeInstance::Simple(i32)]. Type is i32. 
+            return type_resolution.object_instance_generic_args.get(index_of).unwrap().clone();
+        },
+        Type::Generic(type_id, type_args) => {
+            /*
+            The generic parameters used in the type args refer to the object the method is called on,
+            not to the generic type being built here. For instance, in:
+
+                def push(item: TItem) -> array<TItem>
+
+            TItem is the type arg of the receiver array, so we keep resolving against the receiver.
+            */
+            let all_args_resolved = type_args.iter().map(|type_arg| 
+                resolve_type(type_arg, type_db, type_resolution.clone()))
+                .collect::<Vec<_>>();
+            
+            return TypeInstance::Generic(*type_id, all_args_resolved);
+        },
+        Type::Function(fun_arg_types, return_type) => {
+            let all_args_resolved = fun_arg_types.iter().map(|type_arg| 
+                resolve_type(
+                    type_arg,
+                    type_db, 
+                    type_resolution.clone())).collect::<Vec<_>>();
+            
+            let return_type_resolved = resolve_type(
+                &return_type,
+                type_db, 
+                type_resolution);
+            
+            return TypeInstance::Function(all_args_resolved, Box::new(return_type_resolved));
+        },
+        Type::Const(value) => TypeInstance::Const(*value),
+    };
+
+    //substitution bugs would otherwise show up much later as wrong sizes or bogus type errors
+    assert!(
+        !type_instance.contains_unresolved_generic(type_db),
+        "Internal error: resolving {:?} left an unresolved generic in {}",
+        type_partially_filled,
+        type_instance.as_string(type_db)
+    );
+
+    return type_instance;
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn alias_inside_generic_annotation_is_shown_as_written() {
+        let mut type_db = TypeDatabase::new();
+        let i32_type = type_db.special_types.i32.clone();
+        type_db.add_alias("Num", i32_type.clone());
+
+        let annotation = HIRType::Generic("array".into(), vec![HIRType::Simple("Num".into())]);
+        let written_as = type_db.alias_written_as(&annotation);
+        assert_eq!(written_as.as_deref(), Some("array<Num>"));
+
+        let array = TypeInstance::Generic(type_db.expect_find_by_name("array").id, vec![i32_type]);
+        assert_eq!(array.as_string_with_alias(&type_db, written_as.as_deref()), "array<i32> (written as array<Num>)");
+
+        let function = HIRType::Function(vec![HIRType::Simple("Num".into())], Box::new(HIRType::Simple("bool".into())));
+        assert_eq!(type_db.alias_written_as(&function).as_deref(), Some("fn(Num) -> bool"));
+        assert_eq!(type_db.alias_written_as(&HIRType::Simple("i32".into())), None);
+    }
+
+    #[test]
+    fn operator_table_lists_builtin_operators() {
+        let type_db = TypeDatabase::new();
+        let table = type_db.operator_table()
+            .iter()
+            .map(|entry| entry.to_string())
+            .collect::<Vec<_>>();
+
+        assert!(table.contains(&"i32 + i32 -> i32".to_string()));
+        assert!(table.contains(&"i32 < i32 -> bool".to_string()));
+        assert!(table.contains(&"-i32 -> i32".to_string()));
+    }
+
+    #[test]
+    fn resolve_method_binds_receiver_generics() {
+        let type_db = TypeDatabase::new();
+        let i32_type = type_db.special_types.i32.clone();
+        let u32_type = type_db.special_types.u32.clone();
+        let array_id = type_db.find_by_name("array").unwrap().id;
+        let receiver = TypeInstance::Generic(array_id, vec![i32_type.clone()]);
+
+        let (args, return_type) = type_db.resolve_method(&receiver, "__index__").unwrap();
+        assert_eq!(args, vec![u32_type]);
+        assert_eq!(return_type, i32_type);
+
+        let signature = TypeInstance::Function(args, Box::new(return_type));
+        assert_eq!(signature.as_string(&type_db), "fn (u32) -> i32");
+    }
+
+    #[test]
+    fn resolve_method_not_found() {
+        let type_db = TypeDatabase::new();
+        let array_id = type_db.find_by_name("array").unwrap().id;
+        let receiver = TypeInstance::Generic(array_id, vec![type_db.special_types.i32.clone()]);
+
+        assert!(type_db.resolve_method(&receiver, "does_not_exist").is_none());
+        assert!(type_db.resolve_method(&type_db.special_types.i32, "__index__").is_none());
+    }
+
+    #[test]
+    fn resolved_type_has_no_unresolved_generic() {
+        let type_db = TypeDatabase::new();
+        let array_id = type_db.find_by_name("array").unwrap().id;
+        let resolved = TypeInstance::Generic(array_id, vec![type_db.special_types.i32.clone()]);
+        assert!(!resolved.contains_unresolved_generic(&type_db));
+        assert!(!resolved.as_type().contains_generic_parameter());
+
+        let (args, return_type) = type_db.resolve_method(&resolved, "__index__").unwrap();
+        let method = TypeInstance::Function(args, Box::new(return_type));
+        assert!(!method.contains_unresolved_generic(&type_db));
+
+        //array without its type argument
+        let bare = TypeInstance::Simple(array_id);
+        assert!(bare.contains_unresolved_generic(&type_db));
+        let nested = TypeInstance::Generic(array_id, vec![bare]);
+        assert!(nested.contains_unresolved_generic(&type_db));
+    }
+
+    #[test]
+    fn malformed_index_method_is_rejected() {
+        let mut type_db = TypeDatabase::new();
+        let str_type = type_db.expect_find_by_name("str").id;
+        let point_type = type_db.register_struct("Point", &[
+            ("x", type_db.special_types.i32.clone()),
+        ]);
+        let error = type_db.try_add_method(point_type, FunctionSignature {
+            name: "__index__".into(),
+            type_args: vec![],
+            args: vec![Type::Simple(Either::Right(str_type))],
+            return_type: Type::Simple(Either::Right(str_type)),
+        }).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Special method Point.__index__ has the wrong signature, expected one integer argument and a non-Void return type"
+        );
+        assert!(type_db.resolve_method(&TypeInstance::Simple(point_type), "__index__").is_none());
+    }
+}
@@ -2,7 +2,7 @@ use std::fmt::Display;
 
 use crate::{semantic::{type_checker::FunctionName, hir::HIRType, hir_printer::operator_str}, ast::lexer::Operator};
 
-use super::type_db::{TypeDatabase, TypeInstance};
+use super::type_db::{TypeDatabase, TypeInstance, InvalidSpecialMethod};
 
 
 pub trait TypeErrorDisplay {
@@ -18,11 +18,13 @@ pub struct TypeMismatch<TContext> {
 
 pub struct AssignContext {
     pub target_variable_name: String,
+    //the alias the variable was declared with, if any
+    pub declared_alias: Option<String>,
 }
 
 impl TypeErrorDisplay for TypeMismatch<AssignContext> {
     fn fmt_err(&self, type_db: &TypeDatabase, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let var_type_str = self.expected.as_string(type_db);
+        let var_type_str = self.expected.as_string_with_alias(type_db, self.context.declared_alias.as_deref());
         let expr_type_str = self.actual.as_string(type_db);
 
         write!(f,  "Assigned type mismatch: In function {on_function}, assignment to variable {var}: variable has type {var_type_str} but got assigned a value of type {expr_type_str}",
@@ -32,12 +34,13 @@ impl TypeErrorDisplay for TypeMismatch<AssignContext> {
     }
 }
 
-pub struct ReturnTypeContext();
+//the alias the return type was declared with, if any
+pub struct ReturnTypeContext(pub Option<String>);
 
 impl TypeErrorDisplay for TypeMismatch<ReturnTypeContext> {
     fn fmt_err(&self, type_db: &TypeDatabase, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let passed_name = self.actual.as_string(type_db);
-        let expected_name = self.expected.as_string(type_db);
+        let expected_name = self.expected.as_string_with_alias(type_db, self.context.0.as_deref());
         write!(f,  "Return type mismatch: Function {on_function} returns {return_type_name} but expression returns {expr_return_type_name}",
             on_function = self.on_function,
             return_type_name = expected_name,
@@ -46,9 +49,27 @@ impl TypeErrorDisplay for TypeMismatch<ReturnTypeContext> {
     }
 }
 
+//a call synthesized by lowering, i.e. args["a"] became args.__index__("a")
+#[derive(Clone)]
+pub struct LoweredCall {
+    pub user_expr: String,
+    pub lowered_call: String,
+}
+
 pub struct FunctionCallContext {
     pub called_function_name: FunctionName,
     pub argument_position: usize,
+    //None for calls written by the user
+    pub lowered_from: Option<LoweredCall>,
+}
+
+impl FunctionCallContext {
+    fn lowered_from_str(&self) -> String {
+        match &self.lowered_from {
+            Some(LoweredCall { user_expr, lowered_call }) => format!(" (in {user_expr}, lowered to {lowered_call})"),
+            None => String::new(),
+        }
+    }
 }
 
 impl<'a> TypeErrorDisplay for TypeMismatch<FunctionCallContext> {
@@ -57,16 +78,25 @@ impl<'a> TypeErrorDisplay for TypeMismatch<FunctionCallContext> {
         let expected_name = self.expected.as_string(type_db);
         match &self.context.called_function_name {
             FunctionName::Function(function_name) => {
-                write!(f,  "Function argument type mismatch: In function {on_function}, call to function {function_called} parameter on position {position} has incorrect type: Expected {expected_name} but passed {passed_name}",
+                write!(f,  "Function argument type mismatch: In function {on_function}, call to function {function_called} parameter on position {position} has incorrect type: Expected {expected_name} but passed {passed_name}{lowered_from}",
                     on_function = self.on_function,
                     function_called = function_name,
-                    position = self.context.argument_position
+                    position = self.context.argument_position,
+                    lowered_from = self.context.lowered_from_str()
                 )
             }
             FunctionName::IndexAccess =>  {
-                write!(f,  "Function argument type mismatch: In function {on_function}, on index operator, parameter on position {position} has incorrect type: Expected {expected_name} but passed {passed_name}",
+                write!(f,  "Function argument type mismatch: In function {on_function}, on index operator, parameter on position {position} has incorrect type: Expected {expected_name} but passed {passed_name}{lowered_from}",
+                    on_function = self.on_function,
+                    position = self.context.argument_position,
+                    lowered_from = self.context.lowered_from_str()
+                )
+            },
+            FunctionName::SliceAccess =>  {
+                write!(f,  "Function argument type mismatch: In function {on_function}, on slice operator, parameter on position {position} has incorrect type: Expected {expected_name} but passed {passed_name}{lowered_from}",
                     on_function = self.on_function,
-                    position = self.context.argument_position
+                    position = self.context.argument_position,
+                    lowered_from = self.context.lowered_from_str()
                 )
             },
             FunctionName::Method { function_name, type_name } => todo!("method calls not fully implemented"),
@@ -102,6 +132,13 @@ impl<'a> TypeErrorDisplay for FunctionCallArgumentCountMismatch {
                     passed_args = self.passed_count,
                 )  
             },
+            FunctionName::SliceAccess => {
+                write!(f,  "Argument count mismatch: In function {on_function}, slice operator expects {expected_args} arguments, but {passed_args} were passed",
+                    on_function = self.on_function,
+                    expected_args = self.expected_count,
+                    passed_args = self.passed_count,
+                )  
+            },
             FunctionName::Method { function_name, type_name } => todo!("method calls not fully implemented"),
         }
 
@@ -166,6 +203,21 @@ pub struct BinaryOperatorNotFound {
     pub operator: Operator
 }
 
+impl BinaryOperatorNotFound {
+    //there is no implicit promotion between integers and floats, one side has to be casted explicitly
+    pub fn is_int_float_mix(&self, type_db: &TypeDatabase) -> bool {
+        let is_int = |t: &TypeInstance| match t {
+            TypeInstance::Simple(id) => type_db.find(*id).is_integer(type_db),
+            _ => false
+        };
+        let is_float = |t: &TypeInstance| match t {
+            TypeInstance::Simple(id) => type_db.find(*id).is_float(type_db),
+            _ => false
+        };
+        (is_int(&self.lhs) && is_float(&self.rhs)) || (is_float(&self.lhs) && is_int(&self.rhs))
+    }
+}
+
 impl TypeErrorDisplay for BinaryOperatorNotFound {
     fn fmt_err(&self, type_db: &TypeDatabase, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -175,7 +227,11 @@ impl TypeErrorDisplay for BinaryOperatorNotFound {
             operator = operator_str(self.operator),
             lhs_type = self.lhs.as_string(type_db),
             rhs_type = self.rhs.as_string(type_db)
-        )
+        )?;
+        if self.is_int_float_mix(type_db) {
+            write!(f, ". Integers and floats cannot be mixed implicitly, cast one of the operands explicitly")?;
+        }
+        Ok(())
     }
 }
 
@@ -231,6 +287,412 @@ impl TypeErrorDisplay for InsufficientTypeInformationForArray {
     }
 }
 
+pub struct TypeAliasCycle {
+    pub alias_name: String,
+    //aliases visited until the cycle was found, starting on alias_name
+    pub path: Vec<String>
+}
+
+impl TypeErrorDisplay for TypeAliasCycle {
+    fn fmt_err(&self, _type_db: &TypeDatabase, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Type alias {alias_name} is cyclic: {path}",
+            alias_name = self.alias_name,
+            path = self.path.join(" -> ")
+        )
+    }
+}
+
+pub struct UnsizedType {
+    pub on_function: String,
+    pub type_def: TypeInstance
+}
+
+impl TypeErrorDisplay for UnsizedType {
+    fn fmt_err(&self, type_db: &TypeDatabase, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "In function {on_function}, sizeof used on type {type_name}, but it has no size",
+            on_function = self.on_function,
+            type_name = self.type_def.as_string(type_db)
+        )
+    }
+}
+
+//a struct that contains itself by value would need infinite space, it has to go through a ptr
+pub struct RecursiveStruct {
+    pub struct_name: String,
+    pub field_name: String
+}
+
+impl TypeErrorDisplay for RecursiveStruct {
+    fn fmt_err(&self, _type_db: &TypeDatabase, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Struct {struct_name} contains itself by value through field {field_name}, use a ptr to break the cycle",
+            struct_name = self.struct_name,
+            field_name = self.field_name
+        )
+    }
+}
+
+pub struct UnsizedStructField {
+    pub struct_name: String,
+    pub field_name: String
+}
+
+impl TypeErrorDisplay for UnsizedStructField {
+    fn fmt_err(&self, _type_db: &TypeDatabase, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Struct {struct_name} has field {field_name} whose type has no size",
+            struct_name = self.struct_name,
+            field_name = self.field_name
+        )
+    }
+}
+
+impl TypeErrorDisplay for InvalidSpecialMethod {
+    fn fmt_err(&self, _type_db: &TypeDatabase, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+pub struct InvalidImplTarget {
+    pub target: HIRType
+}
+
+impl TypeErrorDisplay for InvalidImplTarget {
+    fn fmt_err(&self, _type_db: &TypeDatabase, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Cannot implement methods on {target}, impl blocks must target a type name",
+            target = self.target
+        )
+    }
+}
+
+pub struct StructCalledAsFunction {
+    pub on_function: String,
+    pub struct_name: String
+}
+
+impl TypeErrorDisplay for StructCalledAsFunction {
+    fn fmt_err(&self, _type_db: &TypeDatabase, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "In function {on_function}, struct {struct_name} is called as if it were a function. Structs have no constructors, {struct_name}(...) is not supported",
+            on_function = self.on_function,
+            struct_name = self.struct_name
+        )
+    }
+}
+
+pub struct MultipleReturnValues {
+    pub on_function: String,
+    pub value_count: usize
+}
+
+impl TypeErrorDisplay for MultipleReturnValues {
+    fn fmt_err(&self, _type_db: &TypeDatabase, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "In function {on_function}, return has {value_count} values, but function does not return multiple values",
+            on_function = self.on_function,
+            value_count = self.value_count
+        )
+    }
+}
+
+pub struct DuplicateParameter {
+    pub on_function: String,
+    pub parameter_name: String
+}
+
+impl TypeErrorDisplay for DuplicateParameter {
+    fn fmt_err(&self, _type_db: &TypeDatabase, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "In function {on_function}, parameter {parameter_name} is declared more than once",
+            on_function = self.on_function,
+            parameter_name = self.parameter_name
+        )
+    }
+}
+
+pub struct NameNotInScope {
+    pub on_function: String,
+    pub name: String
+}
+
+impl TypeErrorDisplay for NameNotInScope {
+    fn fmt_err(&self, _type_db: &TypeDatabase, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "In function {on_function}, {name} is not a variable or function in scope",
+            on_function = self.on_function,
+            name = self.name
+        )
+    }
+}
+
+pub struct VariableTypeUnknown {
+    pub on_function: String,
+    pub variable_name: String
+}
+
+impl TypeErrorDisplay for VariableTypeUnknown {
+    fn fmt_err(&self, _type_db: &TypeDatabase, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "In function {on_function}, variable {variable_name} is used but its type could not be inferred",
+            on_function = self.on_function,
+            variable_name = self.variable_name
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueContext {
+    Operand,
+    FunctionArgument,
+    ArrayItem,
+    Assignment,
+}
+
+//only numbers can be cast, to other numeric types
+pub struct InvalidCast {
+    pub on_function: String,
+    pub from: TypeInstance,
+    pub to: TypeInstance
+}
+
+impl TypeErrorDisplay for InvalidCast {
+    fn fmt_err(&self, type_db: &TypeDatabase, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "In function {on_function}, cannot cast {from} to {to}, casts are only allowed between numeric types",
+            on_function = self.on_function,
+            from = self.from.as_string(type_db),
+            to = self.to.as_string(type_db)
+        )
+    }
+}
+
+pub struct ReinterpretSizeMismatch {
+    pub on_function: String,
+    pub from: TypeInstance,
+    pub to: TypeInstance
+}
+
+impl TypeErrorDisplay for ReinterpretSizeMismatch {
+    fn fmt_err(&self, type_db: &TypeDatabase, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "In function {on_function}, cannot reinterpret {from} as {to}, both types must have the same size",
+            on_function = self.on_function,
+            from = self.from.as_string(type_db),
+            to = self.to.as_string(type_db)
+        )
+    }
+}
+
+//literal index past the end of a fixed-size array
+pub struct NotAConstantExpression {
+    pub const_name: String,
+    pub expression: String
+}
+
+impl TypeErrorDisplay for NotAConstantExpression {
+    fn fmt_err(&self, _type_db: &TypeDatabase, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "In const {const_name}, {expression} is not a constant expression",
+            const_name = self.const_name,
+            expression = self.expression
+        )
+    }
+}
+
+pub struct GenericCallMismatch {
+    pub on_function: String,
+    pub function_name: String,
+    pub signature: HIRType
+}
+
+impl TypeErrorDisplay for GenericCallMismatch {
+    fn fmt_err(&self, _type_db: &TypeDatabase, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "In function {on_function}, the arguments passed to {function_name} don't match its generic signature {signature}",
+            on_function = self.on_function,
+            function_name = self.function_name,
+            signature = self.signature
+        )
+    }
+}
+
+pub struct ReturnOutsideFunction {}
+
+impl TypeErrorDisplay for ReturnOutsideFunction {
+    fn fmt_err(&self, _type_db: &TypeDatabase, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "return statement outside of a function")
+    }
+}
+
+pub struct ModuleNotFound {
+    pub module_name: String,
+    pub imported_by: String
+}
+
+impl TypeErrorDisplay for ModuleNotFound {
+    fn fmt_err(&self, _type_db: &TypeDatabase, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Module {} imported by {} not found", self.module_name, self.imported_by)
+    }
+}
+
+//a module only sees names of the modules it imports
+pub struct NameNotImported {
+    pub name: String,
+    pub used_in: String,
+    pub declared_in: String
+}
+
+impl TypeErrorDisplay for NameNotImported {
+    fn fmt_err(&self, _type_db: &TypeDatabase, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} is declared in module {}, which is not imported by module {}",
+            self.name, self.declared_in, self.used_in
+        )
+    }
+}
+
+//names declared without pub can only be used by the module that declares them
+pub struct PrivateNameUsed {
+    pub name: String,
+    pub used_in: String,
+    pub declared_in: String
+}
+
+impl TypeErrorDisplay for PrivateNameUsed {
+    fn fmt_err(&self, _type_db: &TypeDatabase, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} is private to module {} and cannot be used in module {}",
+            self.name, self.declared_in, self.used_in
+        )
+    }
+}
+
+//top-level names of all modules share the same name registry
+pub struct NameDeclaredInManyModules {
+    pub name: String,
+    pub module_name: String,
+    pub declared_in: String
+}
+
+impl TypeErrorDisplay for NameDeclaredInManyModules {
+    fn fmt_err(&self, _type_db: &TypeDatabase, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Name {} declared in module {} is already declared in module {}",
+            self.name, self.module_name, self.declared_in
+        )
+    }
+}
+
+pub struct IndexOutOfBounds {
+    pub on_function: String,
+    pub index: i128,
+    pub length: u32
+}
+
+impl TypeErrorDisplay for IndexOutOfBounds {
+    fn fmt_err(&self, _type_db: &TypeDatabase, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "In function {on_function}, index {index} is out of bounds for an array of length {length}",
+            on_function = self.on_function,
+            index = self.index,
+            length = self.length
+        )
+    }
+}
+
+//calls to Void functions are only allowed as statements
+pub struct VoidUsedAsValue {
+    pub on_function: String,
+    pub context: ValueContext
+}
+
+impl TypeErrorDisplay for VoidUsedAsValue {
+    fn fmt_err(&self, _type_db: &TypeDatabase, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let context = match self.context {
+            ValueContext::Operand => "an operand",
+            ValueContext::FunctionArgument => "a function argument",
+            ValueContext::ArrayItem => "an array item",
+            ValueContext::Assignment => "the value of an assignment",
+        };
+        write!(
+            f,
+            "In function {on_function}, the result of a Void function call cannot be used as {context}",
+            on_function = self.on_function
+        )
+    }
+}
+
+//the callee is in scope, but its signature could not be resolved, i.e. one of its parameters has an unknown type
+pub struct UnresolvedFunctionCalled {
+    pub on_function: String,
+    pub function_name: String
+}
+
+impl TypeErrorDisplay for UnresolvedFunctionCalled {
+    fn fmt_err(&self, _type_db: &TypeDatabase, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "In function {on_function}, function {function_name} is called but its signature could not be resolved, check the errors reported on {function_name}",
+            on_function = self.on_function,
+            function_name = self.function_name
+        )
+    }
+}
+
+pub struct GenericArgumentCountMismatch {
+    pub on_function: String,
+    pub type_name: String,
+    pub expected_count: usize,
+    pub actual_count: usize
+}
+
+impl TypeErrorDisplay for GenericArgumentCountMismatch {
+    fn fmt_err(&self, _type_db: &TypeDatabase, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "In function {on_function}, type {type_name} expects {expected_count} generic arguments, but {actual_count} were passed",
+            on_function = self.on_function,
+            type_name = self.type_name,
+            expected_count = self.expected_count,
+            actual_count = self.actual_count
+        )
+    }
+}
+
+//loop conditions have to be bool, there are no implicit truthiness conversions
+pub struct ConditionNotBool {
+    pub on_function: String,
+    pub actual: TypeInstance
+}
+
+impl TypeErrorDisplay for ConditionNotBool {
+    fn fmt_err(&self, type_db: &TypeDatabase, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "In function {on_function}, while condition must be bool, but has type {actual}",
+            on_function = self.on_function,
+            actual = self.actual.as_string(type_db)
+        )
+    }
+}
+
 macro_rules! make_type_errors {
     ($($field:ident : $typename:ty), *) => {
        
@@ -253,6 +715,15 @@ macro_rules! make_type_errors {
                     self.$field.len() +
                 )* 0  
             }
+            pub fn is_empty(&self) -> bool {
+                self.count() == 0
+            }
+            //appends the errors found by another pass, keeping the order within each category
+            pub fn merge(&mut self, other: TypeErrors) {
+                $(
+                    self.$field.extend(other.$field);
+                )*
+            }
         }
 
         impl<'errors, 'callargs, 'type_db> Display for TypeErrorPrinter<'errors, 'type_db> {
@@ -261,6 +732,8 @@ macro_rules! make_type_errors {
                 if self.errors.count() == 0 {
                     return Ok(());
                 }
+                //the AST has no positions to sort by, so errors are grouped by kind.
+                //each pass walks the program top to bottom, so errors of a kind are in source order
                 $(
                     for err in self.errors.$field.iter() {
                         err.fmt_err(self.type_db,f)?;
@@ -303,5 +776,61 @@ make_type_errors!(
     binary_op_not_found: Vec<BinaryOperatorNotFound>,
     unary_op_not_found: Vec<UnaryOperatorNotFound>,
     field_or_method_not_found: Vec<FieldOrMethodNotFound>,
-    insufficient_array_type_info: Vec<InsufficientTypeInformationForArray>
+    insufficient_array_type_info: Vec<InsufficientTypeInformationForArray>,
+    unsized_types: Vec<UnsizedType>,
+    type_alias_cycles: Vec<TypeAliasCycle>,
+    struct_called_as_function: Vec<StructCalledAsFunction>,
+    multiple_return_values: Vec<MultipleReturnValues>,
+    duplicate_parameters: Vec<DuplicateParameter>,
+    generic_argument_count: Vec<GenericArgumentCountMismatch>,
+    names_not_in_scope: Vec<NameNotInScope>,
+    variable_types_unknown: Vec<VariableTypeUnknown>,
+    unresolved_functions_called: Vec<UnresolvedFunctionCalled>,
+    void_used_as_value: Vec<VoidUsedAsValue>,
+    index_out_of_bounds: Vec<IndexOutOfBounds>,
+    invalid_casts: Vec<InvalidCast>,
+    not_constant_expressions: Vec<NotAConstantExpression>,
+    returns_outside_function: Vec<ReturnOutsideFunction>,
+    generic_call_mismatches: Vec<GenericCallMismatch>,
+    reinterpret_size_mismatches: Vec<ReinterpretSizeMismatch>,
+    non_bool_conditions: Vec<ConditionNotBool>,
+    modules_not_found: Vec<ModuleNotFound>,
+    names_declared_in_many_modules: Vec<NameDeclaredInManyModules>,
+    names_not_imported: Vec<NameNotImported>,
+    private_names_used: Vec<PrivateNameUsed>,
+    recursive_structs: Vec<RecursiveStruct>,
+    unsized_struct_fields: Vec<UnsizedStructField>,
+    invalid_special_methods: Vec<InvalidSpecialMethod>,
+    invalid_impl_targets: Vec<InvalidImplTarget>
 );
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_type_errors_from_two_passes() {
+        let mut first = TypeErrors::new();
+        first.duplicate_parameters.push(DuplicateParameter {
+            on_function: "f".into(),
+            parameter_name: "x".into(),
+        });
+        let mut second = TypeErrors::new();
+        second.duplicate_parameters.push(DuplicateParameter {
+            on_function: "g".into(),
+            parameter_name: "y".into(),
+        });
+        second.index_out_of_bounds.push(IndexOutOfBounds {
+            on_function: "g".into(),
+            index: 3,
+            length: 2,
+        });
+
+        first.merge(second);
+
+        assert_eq!(first.count(), 3);
+        assert_eq!(first.duplicate_parameters[0].on_function, "f");
+        assert_eq!(first.duplicate_parameters[1].on_function, "g");
+        assert_eq!(first.index_out_of_bounds.len(), 1);
+    }
+}